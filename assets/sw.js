@@ -0,0 +1,46 @@
+// Keeps the static assets available offline. Pages and data always come
+// from the server, only these files are cached
+const CACHE = "ytm-static-v1";
+const ASSETS = ["/style.css", "/alpine.js", "/chart.js", "/manifest.json", "/favicon.ico", "/icons/icon-192.png"];
+
+self.addEventListener("install", (event) => {
+    event.waitUntil(
+        caches.open(CACHE)
+            .then((cache) => cache.addAll(ASSETS))
+            .then(() => self.skipWaiting()),
+    );
+});
+
+self.addEventListener("activate", (event) => {
+    event.waitUntil(
+        caches.keys()
+            .then((keys) => Promise.all(keys.filter((k) => k !== CACHE).map((k) => caches.delete(k))))
+            .then(() => self.clients.claim()),
+    );
+});
+
+// Stale while revalidate, an upgraded server is picked up on the next load
+self.addEventListener("fetch", (event) => {
+    const url = new URL(event.request.url);
+
+    if (event.request.method !== "GET" || url.origin !== self.location.origin || !ASSETS.includes(url.pathname)) {
+        return;
+    }
+
+    event.respondWith(
+        caches.open(CACHE).then(async (cache) => {
+            const cached = await cache.match(event.request);
+            const fetched = fetch(event.request)
+                .then((res) => {
+                    if (res.ok) {
+                        cache.put(event.request, res.clone());
+                    }
+
+                    return res;
+                })
+                .catch(() => cached);
+
+            return cached ?? fetched;
+        }),
+    );
+});
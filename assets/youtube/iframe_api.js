@@ -0,0 +1,8 @@
+// Offline stand-in for YouTube `iframe_api`, loads the local widget API stub
+var scriptUrl = '\/www-widgetapi.js';
+(function () {
+    var script = document.createElement('script');
+    script.src = scriptUrl;
+    script.async = true;
+    document.head.appendChild(script);
+})();
@@ -0,0 +1,23 @@
+// Offline stand-in for YouTube `www-widgetapi.js`, the player renders nothing
+(function () {
+    function Player(id, options) {
+        this.id = id;
+        this.options = options || {};
+    }
+
+    Player.prototype.getPlayerState = function () {
+        return -1;
+    };
+    Player.prototype.loadVideoById = function () {};
+    Player.prototype.cueVideoById = function () {};
+    Player.prototype.playVideo = function () {};
+    Player.prototype.pauseVideo = function () {};
+    Player.prototype.stopVideo = function () {};
+    Player.prototype.destroy = function () {};
+
+    window.YT = { Player: Player, loaded: 1 };
+
+    if (typeof window.onYouTubeIframeAPIReady === 'function') {
+        window.onYouTubeIframeAPIReady();
+    }
+})();
@@ -0,0 +1,78 @@
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use ytm::{
+    demo::{DemoOptions, generate},
+    schema::{MetadataTable, ValidatedFilter},
+};
+
+const SIZES: [usize; 3] = [10_000, 100_000, 1_000_000];
+
+/// Generated table, channel count grows with the size so the amount of
+/// distinct videos does too
+fn table(entries: usize) -> MetadataTable {
+    let options = DemoOptions {
+        entries,
+        channels: entries / 50,
+        seed: Some(1),
+        ..Default::default()
+    };
+
+    MetadataTable::from_entries(generate(&options))
+}
+
+fn filter(query: &str) -> ValidatedFilter {
+    ValidatedFilter::parse(query).unwrap()
+}
+
+fn collection(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_collection");
+    group.sample_size(10);
+
+    for entries in SIZES {
+        let table = table(entries);
+
+        for (name, query) in [
+            ("unfiltered", ""),
+            ("oldest", "order=oldest"),
+            ("most_watched", "order=most_watched"),
+            ("title", "title=episode 1"),
+            ("channel", "channel_name=demo channel 1"),
+            ("range", "from=2024-01-01T00:00:00Z&to=2024-07-01T00:00:00Z"),
+        ] {
+            let filter = filter(query);
+
+            group.bench_function(BenchmarkId::new(name, entries), |b| {
+                b.iter(|| table.get_collection(black_box(&filter)))
+            });
+        }
+    }
+
+    group.finish();
+}
+
+fn pagination(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pagination");
+    group.sample_size(10);
+
+    for entries in SIZES {
+        let table = table(entries);
+        let last_page = table.total_count().div_ceil(100);
+
+        // latest order is sliced directly, oldest goes through filtering
+        for order in ["latest", "oldest"] {
+            for (name, page) in [("first", 1), ("middle", last_page / 2), ("last", last_page)] {
+                let filter = filter(&format!("page={page}&limit=100&order={order}"));
+
+                group.bench_function(BenchmarkId::new(format!("{order}/{name}"), entries), |b| {
+                    b.iter(|| table.get_collection(black_box(&filter)))
+                });
+            }
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, collection, pagination);
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+use std::{hint::black_box, io::Cursor};
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use ytm::{
+    demo::{DemoOptions, generate, to_v1_json},
+    schema::load_metadata_from_reader,
+};
+
+/// Version 1 JSON of a generated history
+fn v1_bytes(entries: usize, channels: usize) -> Vec<u8> {
+    let options = DemoOptions {
+        entries,
+        channels,
+        seed: Some(1),
+        ..Default::default()
+    };
+
+    serde_json::to_vec(&to_v1_json(&generate(&options))).unwrap()
+}
+
+fn load(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load_v1");
+    group.sample_size(10);
+
+    // few channels means few videos, i.e. heavily rewatched timelines
+    for (name, entries, channels) in [
+        ("varied", 10_000, 200),
+        ("rewatched", 10_000, 3),
+        ("rewatched", 100_000, 3),
+    ] {
+        let bytes = v1_bytes(entries, channels);
+
+        group.throughput(Throughput::Elements(entries as u64));
+        group.bench_with_input(BenchmarkId::new(name, entries), &bytes, |b, bytes| {
+            b.iter(|| load_metadata_from_reader(Cursor::new(black_box(bytes))).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, load);
+criterion_main!(benches);
@@ -0,0 +1,98 @@
+# German
+
+order-latest = Neueste
+order-oldest = Älteste
+order-most_watched = Am häufigsten angesehen
+order-least_watched = Am seltensten angesehen
+order-recently_watched = Zuletzt angesehen
+order-least_recently_watched = Am längsten nicht angesehen
+order-relevance = Relevanz
+order-alphabetical = Titel A-Z
+order-reverse_alphabetical = Titel Z-A
+
+nav-reports = Berichte
+nav-quality = Qualität
+nav-sessions = Sitzungen
+nav-years = Jahre
+nav-milestones = Meilensteine
+nav-resurface = Wiederentdecken
+nav-duplicates = Mögliche Duplikate
+nav-about = Info
+nav-logs = Protokoll
+nav-diff = Vergleich
+nav-profile = Profil
+nav-logout = Abmelden
+nav-theme = Design
+theme-dark = Dunkel
+theme-light = Hell
+
+overview = Übersicht
+overview-raw-total = Rohsumme
+overview-raw-total-desc = Anzahl der ursprünglichen Einträge
+overview-total = Summe
+overview-total-desc = Anzahl der eindeutigen Einträge
+overview-ads = Werbung
+overview-ads-desc = Nur als Werbung gezeigte Videos, aus den Ergebnissen ausgenommen
+overview-all-time-stats = Gesamtstatistik
+overview-product-desc = Aufrufe über { $n } Videos
+
+calendar = Kalender
+calendar-watches = Aufrufe
+
+filter = Filter
+filter-search = Titel oder Kanal
+filter-id = Video-ID
+filter-title = Titel
+filter-channel-name = Kanalname
+filter-product = Produkt
+filter-product-all = Alle
+filter-ads = Werbung
+filter-ads-hidden = Ausgeblendet
+filter-ads-listed = Angezeigt
+filter-from = Von
+filter-to = Bis
+filter-last-from = Zuletzt angesehen von
+filter-last-to = Zuletzt angesehen bis
+filter-boundaries = Grenzen
+filter-boundaries-inclusive = Eingeschlossen
+filter-boundaries-exclusive = Ausgeschlossen
+filter-boundaries-half-open = Nur Von
+filter-watch = Zeitraum für
+filter-watch-first = Erste Wiedergabe
+filter-watch-last = Letzte Wiedergabe
+filter-watch-any = Jede Wiedergabe
+filter-view = Ansicht
+filter-view-videos = Videos
+filter-view-events = Wiedergaben
+filter-expr = Ausdruck
+filter-match = Abgleich
+filter-match-exact = Genau
+filter-match-fuzzy = Tippfehlertolerant
+filter-case = Groß-/Kleinschreibung
+filter-case-ignore = Ignorieren
+filter-case-match = Beachten
+filter-words = Wörter
+filter-words-partial = Auch Teile
+filter-words-whole = Nur ganze
+filter-order = Sortierung
+filter-limit = Anzahl
+filter-matches = Treffer
+filter-share = Teilen
+filter-share-title = Kurzlink zum angewendeten Filter
+filter-print = Drucken
+filter-export-md = Markdown
+filter-reset = Zurücksetzen
+filter-submit = Filtern
+
+video-channel = Kanal
+video-first-watched = Zuerst angesehen
+video-last-watched = Zuletzt angesehen
+video-watch-count = Anzahl Aufrufe
+video-recent-watches = Letzte Aufrufe
+video-instances = Öffnen in
+video-details = Details
+video-thumbnail = Vorschaubild
+video-stats = Statistik
+event-watched-at = Angesehen am
+event-title = Titel
+zero-result = Keine Ergebnisse.
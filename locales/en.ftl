@@ -0,0 +1,98 @@
+# English, the fallback of every other catalog
+
+order-latest = Latest
+order-oldest = Oldest
+order-most_watched = Most Watched
+order-least_watched = Least Watched
+order-recently_watched = Recently Watched
+order-least_recently_watched = Least Recently Watched
+order-relevance = Relevance
+order-alphabetical = Title A-Z
+order-reverse_alphabetical = Title Z-A
+
+nav-reports = Reports
+nav-quality = Quality
+nav-sessions = Sessions
+nav-years = Years
+nav-milestones = Milestones
+nav-resurface = Resurface
+nav-duplicates = Possible Duplicates
+nav-about = About
+nav-logs = Logs
+nav-diff = Diff
+nav-profile = Profile
+nav-logout = Logout
+nav-theme = Theme
+theme-dark = Dark
+theme-light = Light
+
+overview = Overview
+overview-raw-total = Raw Total
+overview-raw-total-desc = Total count of original metadata
+overview-total = Total
+overview-total-desc = Total count of unique metadata
+overview-ads = Ads
+overview-ads-desc = Videos only ever shown as ads, left out of results
+overview-all-time-stats = All Time Stats
+overview-product-desc = Watch count across { $n } videos
+
+calendar = Calendar
+calendar-watches = watches
+
+filter = Filter
+filter-search = Title or Channel
+filter-id = Video ID
+filter-title = Title
+filter-channel-name = Channel Name
+filter-product = Product
+filter-product-all = All
+filter-ads = Ads
+filter-ads-hidden = Hidden
+filter-ads-listed = Listed
+filter-from = From
+filter-to = To
+filter-last-from = Last Watched From
+filter-last-to = Last Watched To
+filter-boundaries = Bounds
+filter-boundaries-inclusive = Included
+filter-boundaries-exclusive = Excluded
+filter-boundaries-half-open = From Only
+filter-watch = Period Of
+filter-watch-first = First Watch
+filter-watch-last = Last Watch
+filter-watch-any = Any Watch
+filter-view = View
+filter-view-videos = Videos
+filter-view-events = Watches
+filter-expr = Expression
+filter-match = Matching
+filter-match-exact = Exact
+filter-match-fuzzy = Typo Tolerant
+filter-case = Letter Case
+filter-case-ignore = Ignored
+filter-case-match = Matched
+filter-words = Words
+filter-words-partial = Partial
+filter-words-whole = Whole Only
+filter-order = Order
+filter-limit = Limit
+filter-matches = matches
+filter-share = Share
+filter-share-title = Short link to the applied filter
+filter-print = Print
+filter-export-md = Markdown
+filter-reset = Reset
+filter-submit = Filter
+
+video-channel = Channel
+video-first-watched = First Watched
+video-last-watched = Last Watched
+video-watch-count = Watched Count
+video-recent-watches = Recent Watches
+video-instances = Instances
+video-details = Details
+video-thumbnail = Thumbnail
+video-stats = Stats
+event-watched-at = Watched At
+event-title = Title
+zero-result = zero result.
@@ -0,0 +1,113 @@
+use chrono::{Datelike, Local, NaiveDate};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::Period;
+use crate::schema::MetadataTable;
+
+/// CalendarDay
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct CalendarDay {
+    pub date: NaiveDate,
+    /// Watches during the day
+    pub count: usize,
+    /// Videos watched for the first time during the day, what a day filter
+    /// on `from`/`to` returns
+    pub first_count: usize,
+}
+
+/// MonthCalendar
+///
+/// Per-day counts of a local calendar month
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct MonthCalendar {
+    pub year: i32,
+    pub month: u32,
+    pub total: usize,
+    /// Every day of the month, empty days included
+    pub days: Vec<CalendarDay>,
+}
+
+/// Count watches per day of a month, `None` when the month doesn't exist
+pub fn month_calendar(
+    metadata_table: &MetadataTable,
+    year: i32,
+    month: u32,
+) -> Option<MonthCalendar> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let period = Period::month(year, month);
+
+    let mut days = first
+        .iter_days()
+        .take_while(|d| d.month() == month)
+        .map(|date| CalendarDay {
+            date,
+            count: 0,
+            first_count: 0,
+        })
+        .collect::<Vec<CalendarDay>>();
+
+    for m in metadata_table.data() {
+        for t in m.watch_timeline.iter().filter(|t| period.contains(t)) {
+            let day = &mut days[t.with_timezone(&Local).day0() as usize];
+
+            day.count += 1;
+
+            if *t == m.watched_at {
+                day.first_count += 1;
+            }
+        }
+    }
+
+    Some(MonthCalendar {
+        year,
+        month,
+        total: days.iter().map(|d| d.count).sum(),
+        days,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::watch;
+
+    // noon keeps every watch on the same local day in most timezones
+    #[test]
+    fn month_days() {
+        let table = MetadataTable::from_entries([
+            watch("a", "2024-01-31T12:00:00Z"),
+            watch("a", "2024-02-03T12:00:00Z"),
+            watch("b", "2024-02-03T13:00:00Z"),
+            watch("c", "2024-02-29T12:00:00Z"),
+            watch("c", "2024-03-01T12:00:00Z"),
+        ]);
+        let calendar = month_calendar(&table, 2024, 2).unwrap();
+
+        assert_eq!((calendar.year, calendar.month), (2024, 2));
+        assert_eq!(calendar.days.len(), 29);
+        assert_eq!(calendar.total, 3);
+
+        // `a` was first watched in January
+        let day = &calendar.days[2];
+        assert_eq!(day.date, NaiveDate::from_ymd_opt(2024, 2, 3).unwrap());
+        assert_eq!((day.count, day.first_count), (2, 1));
+
+        let day = &calendar.days[28];
+        assert_eq!((day.count, day.first_count), (1, 1));
+
+        assert!(calendar.days[..2].iter().all(|d| d.count == 0));
+    }
+
+    #[test]
+    fn empty_and_invalid_months() {
+        let table = MetadataTable::from_entries([watch("a", "2024-02-03T12:00:00Z")]);
+
+        let calendar = month_calendar(&table, 2023, 2).unwrap();
+        assert_eq!(calendar.days.len(), 28);
+        assert_eq!(calendar.total, 0);
+
+        assert!(month_calendar(&table, 2024, 0).is_none());
+        assert!(month_calendar(&table, 2024, 13).is_none());
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::Utc;
+use serde::Serialize;
+
+use super::{
+    Coverage,
+    quality::{DEFAULT_GAP_DAYS, quality_report},
+};
+use crate::schema::MetadataTable;
+
+/// CheckReport
+///
+/// Sanity check of a freshly loaded dataset
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckReport {
+    pub schema_version: Option<u32>,
+    pub total_count_raw: usize,
+    pub total_count: usize,
+    pub coverage: Coverage,
+    /// Videos watched more than once
+    pub rewatched_videos: usize,
+    /// Watches recorded twice with the exact same video and time
+    pub duplicate_entries: usize,
+    /// Watches without a video ID, e.g. removed videos
+    pub missing_id_entries: usize,
+    /// Watches without a channel, e.g. ads or removed videos
+    pub missing_channel_entries: usize,
+    /// Watches timestamped after now
+    pub future_entries: usize,
+    /// Gaps longer than `DEFAULT_GAP_DAYS`
+    pub gap_count: usize,
+    pub warnings: Vec<String>,
+}
+
+/// Collect counts and warnings worth knowing before serving a dataset
+pub fn check_report(metadata_table: &MetadataTable, schema_version: Option<u32>) -> CheckReport {
+    let now = Utc::now();
+    let data = metadata_table.data();
+
+    let rewatched_videos = data.iter().filter(|m| m.watch_count > 1).count();
+    let duplicate_entries = data
+        .iter()
+        .map(|m| m.watch_timeline.windows(2).filter(|w| w[0] == w[1]).count())
+        .sum::<usize>();
+    let missing_id_entries = data
+        .iter()
+        .filter(|m| m.id.is_empty())
+        .map(|m| m.watch_count)
+        .sum::<usize>();
+    let missing_channel_entries = data
+        .iter()
+        .filter(|m| &*m.channel.id == "-")
+        .map(|m| m.watch_count)
+        .sum::<usize>();
+    let future_entries = metadata_table
+        .watch_timeline_ref()
+        .iter()
+        .filter(|t| **t > now)
+        .count();
+    let gap_count = quality_report(metadata_table, DEFAULT_GAP_DAYS).gaps.len();
+
+    let mut warnings = Vec::new();
+
+    if metadata_table.total_count_raw() == 0 {
+        warnings.push(String::from("history is empty"));
+    }
+
+    if duplicate_entries > 0 {
+        warnings.push(format!(
+            "{} entries are recorded twice with the same time",
+            duplicate_entries
+        ));
+    }
+
+    if missing_id_entries > 0 {
+        warnings.push(format!(
+            "{} entries have no video ID and are grouped together",
+            missing_id_entries
+        ));
+    }
+
+    if missing_channel_entries > 0 {
+        warnings.push(format!(
+            "{} entries have no channel",
+            missing_channel_entries
+        ));
+    }
+
+    if future_entries > 0 {
+        warnings.push(format!(
+            "{} entries are dated in the future",
+            future_entries
+        ));
+    }
+
+    if gap_count > 0 {
+        warnings.push(format!(
+            "{} gaps longer than {} days, see `/quality` for details",
+            gap_count, DEFAULT_GAP_DAYS
+        ));
+    }
+
+    CheckReport {
+        schema_version,
+        total_count_raw: metadata_table.total_count_raw(),
+        total_count: metadata_table.total_count(),
+        coverage: Coverage::of(metadata_table),
+        rewatched_videos,
+        duplicate_entries,
+        missing_id_entries,
+        missing_channel_entries,
+        future_entries,
+        gap_count,
+        warnings,
+    }
+}
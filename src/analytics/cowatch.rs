@@ -0,0 +1,173 @@
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::{Local, NaiveDate};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::sessions::watch_sessions;
+use crate::schema::{Channel, MetadataTable};
+
+/// Sessions or days two channels need to share to be reported
+pub const DEFAULT_MIN_SHARED: usize = 3;
+
+/// CoWatchUnit
+///
+/// What counts as watching two channels together
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoWatchUnit {
+    #[default]
+    Session,
+    Day,
+}
+
+/// CoWatchPair
+///
+/// Two channels watched in the same sessions or days
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct CoWatchPair {
+    pub a: Channel,
+    pub b: Channel,
+    /// Sessions or days containing both channels
+    pub shared: usize,
+    /// `shared` over the sessions or days containing either channel
+    pub jaccard: f64,
+}
+
+/// CoWatchPartner
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct CoWatchPartner {
+    pub channel: Channel,
+    pub shared: usize,
+    pub jaccard: f64,
+}
+
+/// CoWatchNode
+///
+/// Channel with its strongest pairings, a network listing of the pairs
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct CoWatchNode {
+    pub channel: Channel,
+    pub partners: Vec<CoWatchPartner>,
+}
+
+/// Channels of each session or local day, groups with a single channel are
+/// dropped since they can't pair anything
+fn channel_groups(
+    metadata_table: &MetadataTable,
+    unit: CoWatchUnit,
+    gap_minutes: i64,
+) -> Vec<BTreeSet<Channel>> {
+    let groups = match unit {
+        CoWatchUnit::Session => watch_sessions(metadata_table, gap_minutes)
+            .sessions
+            .into_iter()
+            .map(|s| s.videos.into_iter().map(|v| v.channel).collect())
+            .collect::<Vec<BTreeSet<Channel>>>(),
+        CoWatchUnit::Day => {
+            let mut days: HashMap<NaiveDate, BTreeSet<Channel>> = HashMap::new();
+
+            for m in metadata_table.data() {
+                for t in m.watch_timeline.iter() {
+                    days.entry(t.with_timezone(&Local).date_naive())
+                        .or_default()
+                        .insert(m.channel.clone());
+                }
+            }
+
+            days.into_values().collect()
+        }
+    };
+
+    groups.into_iter().filter(|g| g.len() > 1).collect()
+}
+
+/// Channel pairs sharing at least `min_shared` sessions or days, most shared
+/// first
+pub fn co_watch_pairs(
+    metadata_table: &MetadataTable,
+    unit: CoWatchUnit,
+    gap_minutes: i64,
+    min_shared: usize,
+) -> Vec<CoWatchPair> {
+    let groups = channel_groups(metadata_table, unit, gap_minutes);
+
+    let mut single: HashMap<&Channel, usize> = HashMap::new();
+    let mut pairs: HashMap<(&Channel, &Channel), usize> = HashMap::new();
+
+    for g in groups.iter() {
+        for (i, a) in g.iter().enumerate() {
+            *single.entry(a).or_default() += 1;
+
+            for b in g.iter().skip(i + 1) {
+                *pairs.entry((a, b)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut list = pairs
+        .into_iter()
+        .filter(|(_, shared)| *shared >= min_shared.max(1))
+        .map(|((a, b), shared)| {
+            let union = single[a] + single[b] - shared;
+
+            CoWatchPair {
+                a: a.clone(),
+                b: b.clone(),
+                shared,
+                jaccard: shared as f64 / union as f64,
+            }
+        })
+        .collect::<Vec<CoWatchPair>>();
+
+    list.sort_by(|x, y| {
+        y.shared
+            .cmp(&x.shared)
+            .then_with(|| y.jaccard.total_cmp(&x.jaccard))
+            .then_with(|| x.a.name.cmp(&y.a.name))
+    });
+
+    list
+}
+
+/// Pairs listed per channel, channels with the most shared sessions or days
+/// first and at most `n` partners each
+pub fn co_watch_network(pairs: &[CoWatchPair], n: usize) -> Vec<CoWatchNode> {
+    let mut map: HashMap<&Channel, Vec<CoWatchPartner>> = HashMap::new();
+
+    for p in pairs {
+        for (c, other) in [(&p.a, &p.b), (&p.b, &p.a)] {
+            map.entry(c).or_default().push(CoWatchPartner {
+                channel: other.clone(),
+                shared: p.shared,
+                jaccard: p.jaccard,
+            });
+        }
+    }
+
+    let mut list = map
+        .into_iter()
+        .map(|(channel, mut partners)| {
+            partners.sort_by(|x, y| {
+                y.shared
+                    .cmp(&x.shared)
+                    .then_with(|| x.channel.name.cmp(&y.channel.name))
+            });
+            partners.truncate(n);
+
+            CoWatchNode {
+                channel: channel.clone(),
+                partners,
+            }
+        })
+        .collect::<Vec<CoWatchNode>>();
+
+    list.sort_by_cached_key(|v| {
+        (
+            std::cmp::Reverse(v.partners.iter().map(|p| p.shared).sum::<usize>()),
+            v.channel.name.clone(),
+        )
+    });
+
+    list
+}
@@ -0,0 +1,175 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use super::Coverage;
+use crate::{schema::MetadataTable, utils::DateTimeUtility};
+
+/// DiffEntry
+///
+/// Single watch event which only exists on one side of the diff
+#[derive(Clone, Debug, Serialize)]
+pub struct DiffEntry {
+    pub id: String,
+    pub title: Arc<str>,
+    pub channel_name: Arc<str>,
+    pub watched_at: DateTime<Utc>,
+}
+
+impl DiffEntry {
+    pub fn to_datetime_local(&self) -> String {
+        self.watched_at.to_datetime_string()
+    }
+}
+
+/// HistoryDiff
+#[derive(Clone, Debug, Serialize)]
+pub struct HistoryDiff {
+    pub old_coverage: Coverage,
+    pub new_coverage: Coverage,
+    /// Watch events only present in the new export
+    pub added: Vec<DiffEntry>,
+    /// Watch events only present in the old export, usually trimmed by Google
+    pub removed: Vec<DiffEntry>,
+    /// Videos that never appeared in the old export
+    pub added_videos: usize,
+    /// Videos that no longer appear in the new export
+    pub removed_videos: usize,
+}
+
+/// Watch events keyed by `(id, time)`, with how often each one was logged
+type Events<'a> = HashMap<(&'a str, DateTime<Utc>), (DiffEntry, usize)>;
+
+/// Collect watch events keyed by `(id, time)`, counting repeats so logged
+/// duplicates aren't merged into one
+fn collect_events(metadata_table: &MetadataTable) -> Events<'_> {
+    let mut events = Events::new();
+
+    for m in metadata_table.data() {
+        for t in &m.watch_timeline {
+            events
+                .entry((m.id.as_str(), *t))
+                .or_insert_with(|| {
+                    let entry = DiffEntry {
+                        id: m.id.clone(),
+                        title: m.title.clone(),
+                        channel_name: m.channel.name.clone(),
+                        watched_at: *t,
+                    };
+
+                    (entry, 0)
+                })
+                .1 += 1;
+        }
+    }
+
+    events
+}
+
+/// Events of `a` which are missing from `b`, once per missing repeat
+fn missing_from(a: &Events, b: &Events) -> Vec<DiffEntry> {
+    let mut missing = a
+        .iter()
+        .flat_map(|(k, (entry, count))| {
+            let other = b.get(k).map_or(0, |(_, v)| *v);
+
+            std::iter::repeat_n(entry, count.saturating_sub(other))
+        })
+        .cloned()
+        .collect::<Vec<DiffEntry>>();
+
+    missing.sort_by_key(|v| std::cmp::Reverse(v.watched_at));
+
+    missing
+}
+
+/// Compare two datasets on watch event level
+pub fn diff(old: &MetadataTable, new: &MetadataTable) -> HistoryDiff {
+    let old_events = collect_events(old);
+    let new_events = collect_events(new);
+
+    let added = missing_from(&new_events, &old_events);
+    let removed = missing_from(&old_events, &new_events);
+
+    let old_ids = old
+        .data()
+        .iter()
+        .map(|v| v.id.as_str())
+        .collect::<HashSet<&str>>();
+    let new_ids = new
+        .data()
+        .iter()
+        .map(|v| v.id.as_str())
+        .collect::<HashSet<&str>>();
+
+    HistoryDiff {
+        old_coverage: Coverage::of(old),
+        new_coverage: Coverage::of(new),
+        added,
+        removed,
+        added_videos: new_ids.difference(&old_ids).count(),
+        removed_videos: old_ids.difference(&new_ids).count(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::{at, watch};
+
+    fn times(entries: &[DiffEntry]) -> Vec<(&str, DateTime<Utc>)> {
+        entries
+            .iter()
+            .map(|v| (v.id.as_str(), v.watched_at))
+            .collect()
+    }
+
+    #[test]
+    fn added_and_removed() {
+        let old = MetadataTable::from_entries([
+            watch("a", "2024-01-01T12:00:00Z"),
+            watch("b", "2024-01-02T12:00:00Z"),
+        ]);
+        let new = MetadataTable::from_entries([
+            watch("b", "2024-01-02T12:00:00Z"),
+            watch("b", "2024-01-04T12:00:00Z"),
+            watch("c", "2024-01-03T12:00:00Z"),
+        ]);
+        let diff = diff(&old, &new);
+
+        assert_eq!(
+            times(&diff.added),
+            [
+                ("b", at("2024-01-04T12:00:00Z")),
+                ("c", at("2024-01-03T12:00:00Z")),
+            ]
+        );
+        assert_eq!(times(&diff.removed), [("a", at("2024-01-01T12:00:00Z"))]);
+        assert_eq!(diff.added_videos, 1);
+        assert_eq!(diff.removed_videos, 1);
+    }
+
+    #[test]
+    fn repeated_events() {
+        let old = MetadataTable::from_entries([watch("a", "2024-01-01T12:00:00Z")]);
+        let new = MetadataTable::from_entries([
+            watch("a", "2024-01-01T12:00:00Z"),
+            watch("a", "2024-01-01T12:00:00Z"),
+        ]);
+
+        assert_eq!(
+            times(&diff(&old, &new).added),
+            [("a", at("2024-01-01T12:00:00Z"))]
+        );
+        assert!(diff(&old, &new).removed.is_empty());
+        assert_eq!(
+            times(&diff(&new, &old).removed),
+            [("a", at("2024-01-01T12:00:00Z"))]
+        );
+        assert!(diff(&new, &new).added.is_empty());
+    }
+}
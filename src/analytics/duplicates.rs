@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::schema::{Channel, Metadata, MetadataTable, title_key};
+
+/// DuplicateVideo
+///
+/// One of the ids of a possible duplicate
+#[derive(Clone, Debug, Serialize)]
+pub struct DuplicateVideo {
+    pub id: String,
+    pub title: Arc<str>,
+    pub channel: Channel,
+    pub watch_count: usize,
+    pub first_watched_at: DateTime<Utc>,
+    pub last_watched_at: DateTime<Utc>,
+}
+
+impl From<&Metadata> for DuplicateVideo {
+    fn from(m: &Metadata) -> Self {
+        Self {
+            id: m.id.clone(),
+            title: m.title.clone(),
+            channel: m.channel.clone(),
+            watch_count: m.watch_count,
+            first_watched_at: m.watched_at,
+            last_watched_at: m.last_watched_at,
+        }
+    }
+}
+
+/// DuplicateGroup
+///
+/// Videos whose titles only differ by case, accents, punctuation or
+/// bracketed parts, likely re-uploads or mirrors of one video
+#[derive(Clone, Debug, Serialize)]
+pub struct DuplicateGroup {
+    pub key: String,
+    /// Stats of the videos merged
+    pub watch_count: usize,
+    pub first_watched_at: DateTime<Utc>,
+    pub last_watched_at: DateTime<Utc>,
+    /// Most watched first, the one kept when merging
+    pub videos: Vec<DuplicateVideo>,
+}
+
+/// Possible duplicates, most watched groups first
+pub fn duplicate_groups(metadata_table: &MetadataTable) -> Vec<DuplicateGroup> {
+    metadata_table
+        .title_groups()
+        .into_iter()
+        .map(|v| DuplicateGroup {
+            key: title_key(&v[0].title),
+            watch_count: v.iter().map(|m| m.watch_count).sum(),
+            first_watched_at: v.iter().map(|m| m.watched_at).min().unwrap_or_default(),
+            last_watched_at: v
+                .iter()
+                .map(|m| m.last_watched_at)
+                .max()
+                .unwrap_or_default(),
+            videos: v.into_iter().map(DuplicateVideo::from).collect(),
+        })
+        .collect()
+}
@@ -0,0 +1,181 @@
+use std::{collections::HashSet, sync::Arc};
+
+use chrono::{DateTime, Datelike, Local, Utc};
+use serde::Serialize;
+
+use super::{Period, top::top_channels};
+use crate::schema::{Channel, Metadata, MetadataTable};
+
+/// Watch counts reported as milestones, besides the very first watch
+const COUNT_MILESTONES: [usize; 5] = [100, 1_000, 10_000, 100_000, 1_000_000];
+
+/// Top channels whose first video is reported
+const MILESTONE_CHANNELS: usize = 10;
+
+/// Milestone
+///
+/// Single notable watch
+#[derive(Clone, Debug, Serialize)]
+pub struct Milestone {
+    pub label: String,
+    /// 1-based position of the watch in the whole history
+    pub nth: usize,
+    pub time: DateTime<Utc>,
+    pub id: String,
+    pub title: Arc<str>,
+    pub channel: Channel,
+}
+
+impl Milestone {
+    fn new(label: String, nth: usize, time: DateTime<Utc>, m: &Metadata) -> Self {
+        Self {
+            label,
+            nth,
+            time,
+            id: m.id.clone(),
+            title: m.title.clone(),
+            channel: m.channel.clone(),
+        }
+    }
+}
+
+/// Milestones
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Milestones {
+    /// First watch ever, then every reached count milestone
+    pub counts: Vec<Milestone>,
+    /// First watch of each local year, oldest first
+    pub years: Vec<Milestone>,
+    /// First watch of each most watched channel, in ranking order
+    pub channels: Vec<Milestone>,
+}
+
+/// Collect milestones over the whole history
+pub fn milestones(metadata_table: &MetadataTable) -> Milestones {
+    let events = metadata_table.watch_events();
+
+    let mut counts = Vec::new();
+
+    if let Some((t, m)) = events.first() {
+        counts.push(Milestone::new(String::from("First video"), 1, *t, m));
+    }
+
+    for n in COUNT_MILESTONES {
+        if let Some((t, m)) = events.get(n - 1) {
+            counts.push(Milestone::new(format!("{}th video", n), n, *t, m));
+        }
+    }
+
+    let mut seen_years: HashSet<i32> = HashSet::new();
+    let years = events
+        .iter()
+        .enumerate()
+        .filter(|(_, (t, _))| seen_years.insert(t.with_timezone(&Local).year()))
+        .map(|(i, (t, m))| {
+            let label = format!("First video of {}", t.with_timezone(&Local).year());
+
+            Milestone::new(label, i + 1, *t, m)
+        })
+        .collect();
+
+    let channels = top_channels(metadata_table, &Period::default(), MILESTONE_CHANNELS)
+        .into_iter()
+        .filter_map(|c| {
+            let channel = Channel {
+                id: c.id,
+                name: c.name,
+            };
+
+            events
+                .iter()
+                .position(|(_, m)| m.channel == channel)
+                .map(|i| {
+                    let (t, m) = events[i];
+                    let label = format!("First video from {}", channel.name);
+
+                    Milestone::new(label, i + 1, t, m)
+                })
+        })
+        .collect();
+
+    Milestones {
+        counts,
+        years,
+        channels,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::schema::testing::{at, watch_on};
+
+    fn labels(list: &[Milestone]) -> Vec<(&str, usize, &str)> {
+        list.iter()
+            .map(|v| (v.label.as_str(), v.nth, v.id.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn history_milestones() {
+        let start = at("2023-11-01T12:00:00Z");
+        let table = MetadataTable::from_entries((0..120).map(|i| {
+            let channel = if i % 3 == 0 { "X" } else { "Y" };
+            let time = (start + Duration::days(i)).to_rfc3339();
+
+            watch_on(&format!("v{i:03}"), channel, &time)
+        }));
+        let milestones = milestones(&table);
+
+        assert_eq!(
+            labels(&milestones.counts),
+            [("First video", 1, "v000"), ("100th video", 100, "v099")]
+        );
+        assert_eq!(milestones.counts[1].time, start + Duration::days(99));
+
+        assert_eq!(
+            labels(&milestones.years),
+            [
+                ("First video of 2023", 1, "v000"),
+                ("First video of 2024", 62, "v061"),
+            ]
+        );
+
+        // most watched channel first
+        assert_eq!(
+            labels(&milestones.channels),
+            [
+                ("First video from Y", 2, "v001"),
+                ("First video from X", 1, "v000"),
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_video() {
+        let table = MetadataTable::from_entries([
+            watch_on("a", "X", "2024-01-01T12:00:00Z"),
+            watch_on("b", "X", "2024-01-02T12:00:00Z"),
+            watch_on("a", "X", "2024-01-03T12:00:00Z"),
+        ]);
+        let milestones = milestones(&table);
+
+        assert_eq!(labels(&milestones.counts), [("First video", 1, "a")]);
+        assert_eq!(labels(&milestones.years), [("First video of 2024", 1, "a")]);
+        assert_eq!(
+            labels(&milestones.channels),
+            [("First video from X", 1, "a")]
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let milestones = milestones(&MetadataTable::from_entries([]));
+
+        assert!(milestones.counts.is_empty());
+        assert!(milestones.years.is_empty());
+        assert!(milestones.channels.is_empty());
+    }
+}
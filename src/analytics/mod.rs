@@ -0,0 +1,134 @@
+//! Analytics
+//!
+//! Aggregations and reports computed over loaded `MetadataTable`s, shared by
+//! the web service and the command line
+
+pub mod calendar;
+pub mod check;
+pub mod cowatch;
+pub mod diff;
+pub mod duplicates;
+pub mod milestones;
+pub mod products;
+pub mod quality;
+pub mod rates;
+pub mod resurface;
+pub mod sessions;
+pub mod stopped;
+pub mod top;
+pub mod years;
+pub mod zoom;
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use serde::Serialize;
+
+use crate::{schema::MetadataTable, utils::DateTimeUtility};
+
+/// Coverage
+///
+/// Date range covered by a dataset
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Coverage {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub count: usize,
+}
+
+impl Coverage {
+    pub fn of(metadata_table: &MetadataTable) -> Self {
+        let timeline = metadata_table.watch_timeline_ref();
+
+        Self {
+            start: timeline.first().copied(),
+            end: timeline.last().copied(),
+            count: timeline.len(),
+        }
+    }
+
+    pub fn to_range_string(&self) -> String {
+        match (self.start, self.end) {
+            (Some(s), Some(e)) => {
+                format!("{} .. {}", s.to_datetime_string(), e.to_datetime_string())
+            }
+            _ => String::from("-"),
+        }
+    }
+}
+
+/// Period
+///
+/// Half-open time range `[from, to)`, unbounded on `None` sides
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct Period {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl Period {
+    /// Whole calendar year in local time, `None` past the years chrono
+    /// supports
+    pub fn year(year: i32) -> Option<Self> {
+        Some(Self {
+            from: Some(local_midnight(year, 1, 1)?),
+            to: year.checked_add(1).and_then(|v| local_midnight(v, 1, 1)),
+        })
+    }
+
+    /// Whole calendar month in local time
+    pub fn month(year: i32, month: u32) -> Self {
+        let (next_year, next_month) = if month == 12 {
+            (year.checked_add(1), 1)
+        } else {
+            (Some(year), month + 1)
+        };
+
+        Self {
+            from: local_midnight(year, month, 1),
+            to: next_year.and_then(|v| local_midnight(v, next_month, 1)),
+        }
+    }
+
+    pub fn contains(&self, t: &DateTime<Utc>) -> bool {
+        self.from.is_none_or(|v| *t >= v) && self.to.is_none_or(|v| *t < v)
+    }
+}
+
+fn local_midnight(year: i32, month: u32, day: u32) -> Option<DateTime<Utc>> {
+    let date = NaiveDate::from_ymd_opt(year, month, day)?;
+
+    Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()
+        .map(|v| v.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Datelike;
+
+    use super::*;
+
+    #[test]
+    fn year_periods() {
+        let period = Period::year(2024).unwrap();
+        assert_eq!(period.from, local_midnight(2024, 1, 1));
+        assert_eq!(period.to, local_midnight(2025, 1, 1));
+
+        // the last supported year is open ended
+        let last = NaiveDate::MAX.year();
+        assert!(Period::year(last).unwrap().to.is_none());
+
+        assert!(Period::year(last + 1).is_none());
+        assert!(Period::year(i32::MAX).is_none());
+        assert!(Period::year(i32::MIN).is_none());
+    }
+
+    #[test]
+    fn month_periods() {
+        let period = Period::month(2024, 12);
+        assert_eq!(period.from, local_midnight(2024, 12, 1));
+        assert_eq!(period.to, local_midnight(2025, 1, 1));
+
+        assert!(Period::month(i32::MAX, 12).to.is_none());
+    }
+}
@@ -0,0 +1,39 @@
+use std::{collections::HashMap, sync::Arc};
+
+use serde::Serialize;
+
+use crate::schema::MetadataTable;
+
+/// ProductStats
+///
+/// Videos and watches per product, videos watched on several products are
+/// counted in each of them
+#[derive(Clone, Debug, Serialize)]
+pub struct ProductStats {
+    pub name: Arc<str>,
+    pub video_count: usize,
+    pub watch_count: usize,
+}
+
+/// Product breakdown, most watched first
+pub fn product_stats(metadata_table: &MetadataTable) -> Vec<ProductStats> {
+    let mut map: HashMap<&str, ProductStats> = HashMap::new();
+
+    for m in metadata_table.data() {
+        for p in &m.products {
+            let entry = map.entry(p).or_insert_with(|| ProductStats {
+                name: p.clone(),
+                video_count: 0,
+                watch_count: 0,
+            });
+
+            entry.video_count += 1;
+            entry.watch_count += m.watch_count;
+        }
+    }
+
+    let mut stats = map.into_values().collect::<Vec<ProductStats>>();
+    stats.sort_by(|a, b| b.watch_count.cmp(&a.watch_count).then(a.name.cmp(&b.name)));
+
+    stats
+}
@@ -0,0 +1,272 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use serde::Serialize;
+
+use super::Coverage;
+use crate::{schema::MetadataTable, utils::DateTimeUtility};
+
+/// Minimum gap between two consecutive watches to be reported
+pub const DEFAULT_GAP_DAYS: i64 = 7;
+
+/// Longest gap threshold accepted, about a century
+pub const MAX_GAP_DAYS: i64 = 36500;
+
+/// Empty days a gap needs at least to be suspected as a recording pause,
+/// whatever the usual watching rhythm is
+const MIN_PAUSE_DAYS: i64 = 3;
+
+/// Interquartile ranges above the third quartile of day gaps for a gap to
+/// count as anomalous
+const PAUSE_IQR_FACTOR: f64 = 3.0;
+
+/// Gap
+///
+/// Period without any recorded watch
+#[derive(Clone, Debug, Serialize)]
+pub struct Gap {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub days: i64,
+}
+
+impl Gap {
+    pub fn to_range_string(&self) -> String {
+        format!(
+            "{} .. {}",
+            self.start.to_datetime_string(),
+            self.end.to_datetime_string()
+        )
+    }
+}
+
+/// QualityReport
+///
+/// How much of the history is actually present, Google silently truncates
+/// older entries and pauses recording when watch history is turned off
+#[derive(Clone, Debug, Serialize)]
+pub struct QualityReport {
+    pub coverage: Coverage,
+    /// Calendar days between the first and the last watch
+    pub span_days: i64,
+    /// Calendar days with at least one watch
+    pub active_days: usize,
+    pub gap_days: i64,
+    /// Gaps longer than `gap_days`, longest first
+    pub gaps: Vec<Gap>,
+    /// Pauses, see [`suspected_pauses`]
+    pub pauses: PauseReport,
+}
+
+/// PauseReport
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PauseReport {
+    /// Empty days from which a gap is anomalous for this dataset
+    pub fence_days: i64,
+    /// Anomalous gaps, oldest first
+    pub pauses: Vec<Gap>,
+}
+
+impl QualityReport {
+    /// Share of active days over the covered span, in percent
+    pub fn active_ratio(&self) -> f64 {
+        if self.span_days == 0 {
+            return 0.0;
+        }
+
+        self.active_days as f64 / self.span_days as f64 * 100.0
+    }
+
+    /// Total days inside reported gaps
+    pub fn gap_total_days(&self) -> i64 {
+        self.gaps.iter().map(|g| g.days).sum()
+    }
+}
+
+/// Coverage, gaps and suspected pauses, `gap_days` is clamped to
+/// `1..=MAX_GAP_DAYS`
+pub fn quality_report(metadata_table: &MetadataTable, gap_days: i64) -> QualityReport {
+    let gap_days = gap_days.clamp(1, MAX_GAP_DAYS);
+    let timeline = metadata_table.watch_timeline_ref();
+    let coverage = Coverage::of(metadata_table);
+
+    let span_days = match (coverage.start, coverage.end) {
+        (Some(s), Some(e)) => (local_date(e) - local_date(s)).num_days() + 1,
+        _ => 0,
+    };
+
+    let active_days = timeline
+        .iter()
+        .map(|t| local_date(*t))
+        .collect::<HashSet<NaiveDate>>()
+        .len();
+
+    let threshold = Duration::days(gap_days);
+    let mut gaps = timeline
+        .windows(2)
+        .filter(|w| w[1] - w[0] >= threshold)
+        .map(|w| Gap {
+            start: w[0],
+            end: w[1],
+            days: (w[1] - w[0]).num_days(),
+        })
+        .collect::<Vec<Gap>>();
+
+    gaps.sort_by_key(|g| std::cmp::Reverse(g.end - g.start));
+
+    QualityReport {
+        coverage,
+        span_days,
+        active_days,
+        gap_days,
+        gaps,
+        pauses: suspected_pauses(metadata_table),
+    }
+}
+
+/// Find gaps statistically out of the usual watching rhythm, most likely
+/// watch history being turned off rather than nothing being watched
+///
+/// Gaps are counted in empty calendar days between two active days, a gap is
+/// anomalous above the third quartile plus three interquartile ranges of
+/// every gap, and never below `MIN_PAUSE_DAYS`
+pub fn suspected_pauses(metadata_table: &MetadataTable) -> PauseReport {
+    let timeline = metadata_table.watch_timeline_ref();
+
+    // watches on the same day are no gap
+    let gaps = timeline
+        .windows(2)
+        .map(|w| (w, (local_date(w[1]) - local_date(w[0])).num_days() - 1))
+        .filter(|(_, empty)| *empty >= 0)
+        .collect::<Vec<_>>();
+
+    if gaps.is_empty() {
+        return PauseReport::default();
+    }
+
+    let mut sorted = gaps.iter().map(|(_, empty)| *empty).collect::<Vec<i64>>();
+    sorted.sort_unstable();
+
+    let n = sorted.len();
+    let (q1, q3) = (sorted[n / 4] as f64, sorted[n * 3 / 4] as f64);
+    let fence_days = ((q3 + PAUSE_IQR_FACTOR * (q3 - q1)).ceil() as i64).max(MIN_PAUSE_DAYS);
+
+    let pauses = gaps
+        .into_iter()
+        .filter(|(_, empty)| *empty >= fence_days)
+        .map(|(w, _)| Gap {
+            start: w[0],
+            end: w[1],
+            days: (w[1] - w[0]).num_days(),
+        })
+        .collect();
+
+    PauseReport { fence_days, pauses }
+}
+
+fn local_date(t: DateTime<Utc>) -> NaiveDate {
+    t.with_timezone(&Local).date_naive()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::{at, watch};
+
+    fn table() -> MetadataTable {
+        MetadataTable::from_entries([
+            watch("a", "2024-01-01T12:00:00Z"),
+            watch("b", "2024-01-03T12:00:00Z"),
+            watch("c", "2024-01-13T12:00:00Z"),
+            watch("d", "2024-01-14T12:00:00Z"),
+        ])
+    }
+
+    #[test]
+    fn gaps() {
+        let report = quality_report(&table(), 2);
+
+        assert_eq!(report.gap_days, 2);
+        assert_eq!(
+            report
+                .gaps
+                .iter()
+                .map(|g| (g.start, g.days))
+                .collect::<Vec<_>>(),
+            [
+                (at("2024-01-03T12:00:00Z"), 10),
+                (at("2024-01-01T12:00:00Z"), 2)
+            ]
+        );
+        assert_eq!(report.gap_total_days(), 12);
+        assert_eq!(report.span_days, 14);
+        assert_eq!(report.active_days, 4);
+    }
+
+    #[test]
+    fn clamped_threshold() {
+        assert_eq!(quality_report(&table(), 0).gaps.len(), 3);
+        assert_eq!(quality_report(&table(), -5).gap_days, 1);
+
+        let report = quality_report(&table(), i64::MAX);
+        assert_eq!(report.gap_days, MAX_GAP_DAYS);
+        assert!(report.gaps.is_empty());
+    }
+
+    /// Noon watches separated by `empty` calendar days each
+    fn rhythm(empty: &[i64]) -> MetadataTable {
+        let mut time = at("2024-01-01T12:00:00Z");
+        let mut entries = vec![watch("v0", &time.to_rfc3339())];
+
+        for (i, n) in empty.iter().enumerate() {
+            time += Duration::days(n + 1);
+            entries.push(watch(&format!("v{}", i + 1), &time.to_rfc3339()));
+        }
+
+        MetadataTable::from_entries(entries)
+    }
+
+    #[test]
+    fn daily_pause() {
+        let mut empty = vec![0; 20];
+        empty.extend([20, 0, 2, 0]);
+        let report = suspected_pauses(&rhythm(&empty));
+
+        // short gaps stay under the minimum even for a daily rhythm
+        assert_eq!(report.fence_days, MIN_PAUSE_DAYS);
+        assert_eq!(
+            report
+                .pauses
+                .iter()
+                .map(|g| (g.start, g.days))
+                .collect::<Vec<_>>(),
+            [(at("2024-01-21T12:00:00Z"), 21)]
+        );
+        assert_eq!(quality_report(&rhythm(&empty), 7).pauses.pauses.len(), 1);
+    }
+
+    #[test]
+    fn irregular_rhythm() {
+        let report = suspected_pauses(&rhythm(&[2, 2, 4, 4, 6, 6, 8, 19, 25]));
+
+        // third quartile 8, interquartile range 4
+        assert_eq!(report.fence_days, 20);
+        assert_eq!(report.pauses.len(), 1);
+        assert_eq!(report.pauses[0].days, 26);
+    }
+
+    #[test]
+    fn same_day_watches() {
+        let table = MetadataTable::from_entries([
+            watch("a", "2024-01-01T12:00:00Z"),
+            watch("b", "2024-01-01T13:00:00Z"),
+        ]);
+
+        // no gap at all, so no fence either
+        assert_eq!(suspected_pauses(&table).fence_days, 0);
+        assert_eq!(
+            suspected_pauses(&MetadataTable::from_entries([])).fence_days,
+            0
+        );
+    }
+}
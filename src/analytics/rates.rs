@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Days, Local, NaiveDate};
+use serde::Serialize;
+
+use crate::schema::MetadataTable;
+
+/// Tendency
+///
+/// Central tendency of watches per bucket, empty buckets included
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct Tendency {
+    pub average: f64,
+    pub median: f64,
+}
+
+impl Tendency {
+    fn of(mut values: Vec<usize>) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+
+        values.sort_unstable();
+
+        let mid = values.len() / 2;
+        let median = if values.len().is_multiple_of(2) {
+            (values[mid - 1] + values[mid]) as f64 / 2.0
+        } else {
+            values[mid] as f64
+        };
+
+        Self {
+            average: values.iter().sum::<usize>() as f64 / values.len() as f64,
+            median,
+        }
+    }
+}
+
+/// WatchRates
+///
+/// Watches per day, week and month of a period
+#[derive(Clone, Debug, Serialize)]
+pub struct WatchRates {
+    /// `None` for the whole dataset
+    pub year: Option<i32>,
+    /// Calendar days of the period covered by the dataset
+    pub days: usize,
+    pub per_day: Tendency,
+    pub per_week: Tendency,
+    pub per_month: Tendency,
+}
+
+impl WatchRates {
+    pub fn label(&self) -> String {
+        self.year
+            .map(|v| v.to_string())
+            .unwrap_or_else(|| String::from("Overall"))
+    }
+}
+
+/// Watch rates over the whole dataset, followed by each year oldest first
+///
+/// Periods are bounded by the first and the last watch, partial weeks and
+/// months at the edges count as their own bucket
+pub fn watch_rates(metadata_table: &MetadataTable) -> Vec<WatchRates> {
+    let days = daily_counts(metadata_table);
+
+    let (Some(first), Some(last)) = (
+        days.keys().next().copied(),
+        days.keys().next_back().copied(),
+    ) else {
+        return Vec::new();
+    };
+
+    let mut list = vec![rates_between(&days, None, first, last)];
+
+    for year in first.year()..=last.year() {
+        let start = NaiveDate::from_ymd_opt(year, 1, 1)
+            .unwrap_or(first)
+            .max(first);
+        let end = NaiveDate::from_ymd_opt(year, 12, 31)
+            .unwrap_or(last)
+            .min(last);
+
+        list.push(rates_between(&days, Some(year), start, end));
+    }
+
+    list
+}
+
+fn daily_counts(metadata_table: &MetadataTable) -> BTreeMap<NaiveDate, usize> {
+    let mut days: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+
+    for t in metadata_table.watch_timeline_ref() {
+        *days
+            .entry(t.with_timezone(&Local).date_naive())
+            .or_default() += 1;
+    }
+
+    days
+}
+
+fn rates_between(
+    days: &BTreeMap<NaiveDate, usize>,
+    year: Option<i32>,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> WatchRates {
+    let mut per_day = Vec::new();
+    let mut per_week: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    let mut per_month: BTreeMap<(i32, u32), usize> = BTreeMap::new();
+
+    let mut date = start;
+
+    while date <= end {
+        let n = days.get(&date).copied().unwrap_or_default();
+        let monday = date - Days::new(date.weekday().num_days_from_monday() as u64);
+
+        per_day.push(n);
+        *per_week.entry(monday).or_default() += n;
+        *per_month.entry((date.year(), date.month())).or_default() += n;
+
+        let Some(next) = date.succ_opt() else {
+            break;
+        };
+        date = next;
+    }
+
+    WatchRates {
+        year,
+        days: per_day.len(),
+        per_day: Tendency::of(per_day),
+        per_week: Tendency::of(per_week.into_values().collect()),
+        per_month: Tendency::of(per_month.into_values().collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::watch;
+
+    #[test]
+    fn tendency() {
+        let v = Tendency::of(vec![3, 1, 2]);
+        assert_eq!((v.average, v.median), (2.0, 2.0));
+
+        let v = Tendency::of(vec![4, 0, 1, 3]);
+        assert_eq!((v.average, v.median), (2.0, 2.0));
+
+        let v = Tendency::of(Vec::new());
+        assert_eq!((v.average, v.median), (0.0, 0.0));
+    }
+
+    // noon keeps every watch on the same local day in most timezones
+    #[test]
+    fn rates() {
+        let table = MetadataTable::from_entries([
+            watch("a", "2024-01-01T12:00:00Z"),
+            watch("b", "2024-01-01T12:30:00Z"),
+            watch("c", "2024-01-03T12:00:00Z"),
+            watch("d", "2024-01-10T12:00:00Z"),
+            watch("e", "2024-01-10T12:30:00Z"),
+            watch("f", "2024-01-10T13:00:00Z"),
+            watch("g", "2025-02-01T12:00:00Z"),
+        ]);
+        let list = watch_rates(&table);
+
+        assert_eq!(
+            list.iter().map(|v| v.label()).collect::<Vec<_>>(),
+            ["Overall", "2024", "2025"]
+        );
+
+        // first to last watch, empty days and months included
+        let overall = &list[0];
+        assert_eq!(overall.days, 366 + 31 + 1);
+        assert_eq!(overall.per_month.average, 0.5);
+        assert_eq!(overall.per_day.median, 0.0);
+
+        let year = &list[1];
+        assert_eq!(year.days, 366);
+        assert_eq!(year.per_day.average, 6.0 / 366.0);
+        assert_eq!(year.per_week.median, 0.0);
+        assert_eq!(year.per_month.average, 0.5);
+
+        // cut at the last watch
+        let year = &list[2];
+        assert_eq!(year.days, 32);
+        assert_eq!((year.per_month.average, year.per_month.median), (0.5, 0.5));
+    }
+
+    #[test]
+    fn partial_year() {
+        let table = MetadataTable::from_entries([
+            watch("a", "2024-01-01T12:00:00Z"),
+            watch("b", "2024-01-01T12:30:00Z"),
+            watch("c", "2024-01-03T12:00:00Z"),
+            watch("d", "2024-01-10T12:00:00Z"),
+            watch("e", "2024-01-10T12:30:00Z"),
+            watch("f", "2024-01-10T13:00:00Z"),
+        ]);
+        let list = watch_rates(&table);
+        let year = &list[1];
+
+        assert_eq!(year.days, 10);
+        assert_eq!((year.per_day.average, year.per_day.median), (0.6, 0.0));
+        // 2024 starts with a Monday, so both weeks hold 3
+        assert_eq!((year.per_week.average, year.per_week.median), (3.0, 3.0));
+        assert_eq!((year.per_month.average, year.per_month.median), (6.0, 6.0));
+    }
+
+    #[test]
+    fn empty() {
+        assert!(watch_rates(&MetadataTable::from_entries([])).is_empty());
+    }
+}
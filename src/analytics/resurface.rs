@@ -0,0 +1,115 @@
+use std::{collections::HashSet, sync::Arc};
+
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use serde::Serialize;
+
+use crate::schema::{Channel, MetadataTable};
+
+/// Days without any watch before a video resurfaces
+pub const DEFAULT_RESURFACE_IDLE_DAYS: i64 = 365;
+
+/// Longest idle time accepted, about a century
+pub const MAX_RESURFACE_IDLE_DAYS: i64 = 36500;
+
+/// Different days a video needs to have been watched on to resurface
+pub const DEFAULT_RESURFACE_MIN_DAYS: usize = 3;
+
+/// Resurfaced
+///
+/// Video returned to often in the past, untouched since
+#[derive(Clone, Debug, Serialize)]
+pub struct Resurfaced {
+    pub id: String,
+    pub title: Arc<str>,
+    pub channel: Channel,
+    pub watch_count: usize,
+    /// Different local days the video was watched on, a night on repeat
+    /// counts once
+    pub watch_days: usize,
+    pub first_watched_at: DateTime<Utc>,
+    pub last_watched_at: DateTime<Utc>,
+    /// Days between the last watch and the latest watch of the dataset
+    pub idle_days: i64,
+}
+
+/// Videos watched on at least `min_days` different days and not in the last
+/// `idle_days` days, the most returned to first
+///
+/// Days are counted back from the latest watch of the dataset rather than
+/// today, so an old export still reports meaningful results. `idle_days` is
+/// clamped to `0..=MAX_RESURFACE_IDLE_DAYS`
+pub fn resurface(
+    metadata_table: &MetadataTable,
+    idle_days: i64,
+    min_days: usize,
+    n: usize,
+) -> Vec<Resurfaced> {
+    let Some(latest) = metadata_table.watch_timeline_ref().last() else {
+        return Vec::new();
+    };
+    let idle = Duration::days(idle_days.clamp(0, MAX_RESURFACE_IDLE_DAYS));
+    let Some(cutoff) = latest.checked_sub_signed(idle) else {
+        return Vec::new();
+    };
+
+    let mut list = metadata_table
+        .data()
+        .iter()
+        .filter(|m| m.last_watched_at < cutoff && m.watch_count >= min_days)
+        .filter_map(|m| {
+            let watch_days = m
+                .watch_timeline
+                .iter()
+                .map(|t| t.with_timezone(&Local).date_naive())
+                .collect::<HashSet<NaiveDate>>()
+                .len();
+
+            (watch_days >= min_days).then(|| Resurfaced {
+                id: m.id.clone(),
+                title: m.title.clone(),
+                channel: m.channel.clone(),
+                watch_count: m.watch_count,
+                watch_days,
+                first_watched_at: m.watched_at,
+                last_watched_at: m.last_watched_at,
+                idle_days: (*latest - m.last_watched_at).num_days(),
+            })
+        })
+        .collect::<Vec<Resurfaced>>();
+
+    list.sort_by(|a, b| {
+        b.watch_days
+            .cmp(&a.watch_days)
+            .then_with(|| b.watch_count.cmp(&a.watch_count))
+            .then_with(|| a.last_watched_at.cmp(&b.last_watched_at))
+    });
+    list.truncate(n);
+
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::watch;
+
+    #[test]
+    fn clamped_idle_days() {
+        let table = MetadataTable::from_entries([
+            watch("a", "2020-01-01T12:00:00Z"),
+            watch("a", "2020-02-01T12:00:00Z"),
+            watch("b", "2024-01-01T12:00:00Z"),
+        ]);
+        let ids = |idle_days| {
+            resurface(&table, idle_days, 2, 10)
+                .into_iter()
+                .map(|v| v.id)
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(ids(365), ["a"]);
+        assert_eq!(ids(-1), ["a"]);
+        assert!(ids(i64::MAX).is_empty());
+        assert_eq!(ids(i64::MIN), ["a"]);
+    }
+}
@@ -0,0 +1,187 @@
+use std::{collections::HashSet, sync::Arc};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::{
+    schema::{Channel, MetadataTable},
+    utils::DateTimeUtility,
+};
+
+/// Longest pause between two watches still counted as the same session
+pub const DEFAULT_SESSION_GAP_MINUTES: i64 = 30;
+
+/// Longest session gap accepted, a year
+pub const MAX_SESSION_GAP_MINUTES: i64 = 525_600;
+
+/// SessionVideo
+///
+/// Single watch inside a session
+#[derive(Clone, Debug, Serialize)]
+pub struct SessionVideo {
+    pub id: String,
+    pub title: Arc<str>,
+    pub channel: Channel,
+    pub time: DateTime<Utc>,
+}
+
+/// Session
+///
+/// Consecutive watches with no pause longer than the gap threshold
+#[derive(Clone, Debug, Serialize)]
+pub struct Session {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Watches in chronological order
+    pub videos: Vec<SessionVideo>,
+}
+
+impl Session {
+    /// Minutes between the first and the last watch, the length of the last
+    /// video is unknown
+    pub fn duration_minutes(&self) -> i64 {
+        (self.end - self.start).num_minutes()
+    }
+
+    pub fn watch_count(&self) -> usize {
+        self.videos.len()
+    }
+
+    pub fn channel_count(&self) -> usize {
+        self.videos
+            .iter()
+            .map(|v| v.channel.key())
+            .collect::<HashSet<&str>>()
+            .len()
+    }
+
+    pub fn to_range_string(&self) -> String {
+        format!(
+            "{} .. {}",
+            self.start.to_datetime_string(),
+            self.end.to_datetime_string()
+        )
+    }
+}
+
+/// SessionReport
+#[derive(Clone, Debug, Serialize)]
+pub struct SessionReport {
+    pub gap_minutes: i64,
+    /// Sessions, latest first
+    pub sessions: Vec<Session>,
+}
+
+impl SessionReport {
+    /// Average watches per session
+    pub fn average_watch_count(&self) -> f64 {
+        if self.sessions.is_empty() {
+            return 0.0;
+        }
+
+        let total = self.sessions.iter().map(|s| s.watch_count()).sum::<usize>();
+
+        total as f64 / self.sessions.len() as f64
+    }
+
+    /// Average session duration in minutes
+    pub fn average_duration_minutes(&self) -> f64 {
+        if self.sessions.is_empty() {
+            return 0.0;
+        }
+
+        let total = self
+            .sessions
+            .iter()
+            .map(|s| s.duration_minutes())
+            .sum::<i64>();
+
+        total as f64 / self.sessions.len() as f64
+    }
+
+    pub fn longest(&self) -> Option<&Session> {
+        self.sessions.iter().max_by_key(|s| s.end - s.start)
+    }
+}
+
+/// Group every watch into sessions split on pauses longer than `gap_minutes`,
+/// clamped to `1..=MAX_SESSION_GAP_MINUTES`
+pub fn watch_sessions(metadata_table: &MetadataTable, gap_minutes: i64) -> SessionReport {
+    let gap_minutes = gap_minutes.clamp(1, MAX_SESSION_GAP_MINUTES);
+    let events = metadata_table
+        .watch_events()
+        .into_iter()
+        .map(|(t, m)| SessionVideo {
+            id: m.id.clone(),
+            title: m.title.clone(),
+            channel: m.channel.clone(),
+            time: t,
+        });
+
+    let threshold = Duration::minutes(gap_minutes);
+    let mut sessions: Vec<Session> = Vec::new();
+
+    for v in events {
+        match sessions.last_mut() {
+            Some(s) if v.time - s.end <= threshold => {
+                s.end = v.time;
+                s.videos.push(v);
+            }
+            _ => sessions.push(Session {
+                start: v.time,
+                end: v.time,
+                videos: vec![v],
+            }),
+        }
+    }
+
+    sessions.reverse();
+
+    SessionReport {
+        gap_minutes,
+        sessions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::watch;
+
+    fn table() -> MetadataTable {
+        MetadataTable::from_entries([
+            watch("a", "2024-01-01T20:00:00Z"),
+            watch("b", "2024-01-01T20:30:00Z"),
+            watch("a", "2024-01-01T20:45:00Z"),
+            watch("c", "2024-01-01T22:00:00Z"),
+            watch("d", "2024-01-02T22:00:00Z"),
+        ])
+    }
+
+    #[test]
+    fn split_on_gaps() {
+        let report = watch_sessions(&table(), 30);
+        let sessions = report
+            .sessions
+            .iter()
+            .map(|s| (s.watch_count(), s.channel_count(), s.duration_minutes()))
+            .collect::<Vec<_>>();
+
+        // latest first, a pause of exactly the gap still joins
+        assert_eq!(sessions, [(1, 1, 0), (1, 1, 0), (3, 2, 45)]);
+        assert_eq!(report.average_watch_count(), 5.0 / 3.0);
+        assert_eq!(report.average_duration_minutes(), 15.0);
+        assert_eq!(watch_sessions(&table(), 74).sessions.len(), 3);
+        assert_eq!(watch_sessions(&table(), 75).sessions.len(), 2);
+    }
+
+    #[test]
+    fn clamped_gap() {
+        assert_eq!(watch_sessions(&table(), 0).gap_minutes, 1);
+        assert_eq!(watch_sessions(&table(), -1).sessions.len(), 5);
+
+        let report = watch_sessions(&table(), i64::MAX);
+        assert_eq!(report.gap_minutes, MAX_SESSION_GAP_MINUTES);
+        assert_eq!(report.sessions.len(), 1);
+    }
+}
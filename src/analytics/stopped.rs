@@ -0,0 +1,101 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{DateTime, Duration, Months, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::schema::{Channel, MetadataTable};
+
+/// Months without any watch before a channel counts as stopped
+pub const DEFAULT_STOPPED_MONTHS: u32 = 6;
+
+/// Watches a channel needs over its lifetime to be reported
+pub const DEFAULT_STOPPED_MIN_WATCHES: usize = 20;
+
+/// Days before the last watch compared against the lifetime rate
+const TAIL_DAYS: i64 = 30;
+
+/// StoppedChannel
+///
+/// Channel watched a lot in the past, silent since
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct StoppedChannel {
+    pub id: Arc<str>,
+    pub name: Arc<str>,
+    pub watch_count: usize,
+    pub first_watched_at: DateTime<Utc>,
+    pub last_watched_at: DateTime<Utc>,
+    /// Watches in the last 30 days of activity
+    pub tail_count: usize,
+    /// Watch rate of the last 30 days of activity over the lifetime rate,
+    /// above 1 means it stopped while being watched more than usual
+    pub drop_off: f64,
+}
+
+/// Channels with at least `min_watches` watches and none in the last
+/// `months` months, most abrupt drop off first
+///
+/// Months are counted back from the latest watch of the dataset rather than
+/// today, so an old export still reports meaningful results
+pub fn stopped_channels(
+    metadata_table: &MetadataTable,
+    months: u32,
+    min_watches: usize,
+    n: usize,
+) -> Vec<StoppedChannel> {
+    let Some(latest) = metadata_table.watch_timeline_ref().last() else {
+        return Vec::new();
+    };
+    let cutoff = latest
+        .checked_sub_months(Months::new(months))
+        .unwrap_or(*latest);
+
+    let mut map: HashMap<&str, (&Channel, Vec<DateTime<Utc>>)> = HashMap::new();
+
+    for m in metadata_table.data() {
+        map.entry(m.channel.key())
+            .or_insert_with(|| (&m.channel, Vec::new()))
+            .1
+            .extend_from_slice(&m.watch_timeline);
+    }
+
+    let mut list = map
+        .into_values()
+        .filter_map(|(channel, mut timeline)| {
+            timeline.sort();
+
+            let first = *timeline.first()?;
+            let last = *timeline.last()?;
+
+            if timeline.len() < min_watches || last >= cutoff {
+                return None;
+            }
+
+            let tail_start = last - Duration::days(TAIL_DAYS);
+            let tail_count = timeline.iter().filter(|t| **t > tail_start).count();
+
+            let lifetime_days = (last - first).num_days().max(TAIL_DAYS);
+            let lifetime_rate = timeline.len() as f64 / lifetime_days as f64;
+            let tail_rate = tail_count as f64 / TAIL_DAYS as f64;
+
+            Some(StoppedChannel {
+                id: channel.id.clone(),
+                name: channel.name.clone(),
+                watch_count: timeline.len(),
+                first_watched_at: first,
+                last_watched_at: last,
+                tail_count,
+                drop_off: tail_rate / lifetime_rate,
+            })
+        })
+        .collect::<Vec<StoppedChannel>>();
+
+    list.sort_by(|a, b| {
+        b.drop_off
+            .total_cmp(&a.drop_off)
+            .then_with(|| b.watch_count.cmp(&a.watch_count))
+    });
+    list.truncate(n);
+
+    list
+}
@@ -0,0 +1,165 @@
+use std::{collections::HashMap, sync::Arc};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::Period;
+use crate::schema::{Metadata, MetadataTable};
+
+/// TopChannel
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct TopChannel {
+    pub rank: usize,
+    pub id: Arc<str>,
+    pub name: Arc<str>,
+    pub watch_count: usize,
+    pub video_count: usize,
+}
+
+/// TopVideo
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct TopVideo {
+    pub rank: usize,
+    pub id: String,
+    pub title: Arc<str>,
+    pub channel_name: Arc<str>,
+    pub watch_count: usize,
+}
+
+/// Watches of the metadata inside the period
+fn count_in(m: &Metadata, period: &Period) -> usize {
+    m.watch_timeline
+        .iter()
+        .filter(|t| period.contains(t))
+        .count()
+}
+
+/// Most watched channels inside the period
+pub fn top_channels(metadata_table: &MetadataTable, period: &Period, n: usize) -> Vec<TopChannel> {
+    let mut map: HashMap<&str, TopChannel> = HashMap::new();
+
+    for m in metadata_table.data() {
+        let count = count_in(m, period);
+
+        if count == 0 {
+            continue;
+        }
+
+        let entry = map.entry(m.channel.key()).or_insert_with(|| TopChannel {
+            rank: 0,
+            id: m.channel.id.clone(),
+            name: m.channel.name.clone(),
+            watch_count: 0,
+            video_count: 0,
+        });
+
+        entry.watch_count += count;
+        entry.video_count += 1;
+    }
+
+    let mut list = map.into_values().collect::<Vec<TopChannel>>();
+    list.sort_by(|a, b| {
+        b.watch_count
+            .cmp(&a.watch_count)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    list.truncate(n);
+
+    for (i, v) in list.iter_mut().enumerate() {
+        v.rank = i + 1;
+    }
+
+    list
+}
+
+/// Most watched videos inside the period
+pub fn top_videos(metadata_table: &MetadataTable, period: &Period, n: usize) -> Vec<TopVideo> {
+    let mut list = metadata_table
+        .data()
+        .iter()
+        .map(|m| (m, count_in(m, period)))
+        .filter(|(_, count)| *count > 0)
+        .map(|(m, count)| TopVideo {
+            rank: 0,
+            id: m.id.clone(),
+            title: m.title.clone(),
+            channel_name: m.channel.name.clone(),
+            watch_count: count,
+        })
+        .collect::<Vec<TopVideo>>();
+
+    list.sort_by(|a, b| {
+        b.watch_count
+            .cmp(&a.watch_count)
+            .then_with(|| a.title.cmp(&b.title))
+    });
+    list.truncate(n);
+
+    for (i, v) in list.iter_mut().enumerate() {
+        v.rank = i + 1;
+    }
+
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::{at, watch_on};
+
+    fn table() -> MetadataTable {
+        MetadataTable::from_entries([
+            watch_on("a", "X", "2023-06-15T12:00:00Z"),
+            watch_on("a", "X", "2024-06-15T12:00:00Z"),
+            watch_on("a", "X", "2024-06-16T12:00:00Z"),
+            watch_on("b", "Y", "2024-06-17T12:00:00Z"),
+            watch_on("c", "Y", "2024-06-18T12:00:00Z"),
+            watch_on("d", "Z", "2024-06-19T12:00:00Z"),
+        ])
+    }
+
+    #[test]
+    fn ranked_channels() {
+        let list = top_channels(&table(), &Period::default(), 10);
+
+        assert_eq!(
+            list.iter()
+                .map(|v| (v.rank, &*v.name, v.watch_count, v.video_count))
+                .collect::<Vec<_>>(),
+            [(1, "X", 3, 1), (2, "Y", 2, 2), (3, "Z", 1, 1)]
+        );
+    }
+
+    #[test]
+    fn ranked_videos() {
+        let list = top_videos(&table(), &Period::default(), 10);
+
+        // ties go to the first title
+        assert_eq!(
+            list.iter()
+                .map(|v| (v.rank, v.id.as_str(), v.watch_count))
+                .collect::<Vec<_>>(),
+            [(1, "a", 3), (2, "b", 1), (3, "c", 1), (4, "d", 1)]
+        );
+        assert_eq!(&*list[0].channel_name, "X");
+    }
+
+    #[test]
+    fn period_and_limit() {
+        let period = Period::year(2023).unwrap();
+        let list = top_videos(&table(), &period, 10);
+        assert_eq!(list.len(), 1);
+        assert_eq!((list[0].id.as_str(), list[0].watch_count), ("a", 1));
+
+        // `to` is exclusive
+        let period = Period {
+            from: Some(at("2024-06-16T12:00:00Z")),
+            to: Some(at("2024-06-19T12:00:00Z")),
+        };
+        let list = top_channels(&table(), &period, 1);
+        assert_eq!(list.len(), 1);
+        assert_eq!((&*list[0].name, list[0].watch_count), ("Y", 2));
+
+        assert!(top_channels(&table(), &Period::year(2020).unwrap(), 10).is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{Datelike, Local, Month};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::schema::{Channel, MetadataTable};
+
+/// YearChannel
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct YearChannel {
+    pub channel: Channel,
+    pub watch_count: usize,
+}
+
+/// YearMonth
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct YearMonth {
+    /// 1 to 12
+    pub month: u32,
+    pub watch_count: usize,
+}
+
+impl YearMonth {
+    pub fn name(&self) -> &'static str {
+        Month::try_from(self.month as u8)
+            .map(|v| v.name())
+            .unwrap_or("-")
+    }
+}
+
+/// YearSummary
+///
+/// Watching activity of a single local calendar year
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct YearSummary {
+    pub year: i32,
+    pub watch_count: usize,
+    /// Distinct videos watched during the year
+    pub video_count: usize,
+    pub channel_count: usize,
+    pub top_channel: Option<YearChannel>,
+    pub busiest_month: Option<YearMonth>,
+    /// Change of `watch_count` from the previous year in percent, `None` for
+    /// the first year
+    pub change: Option<f64>,
+}
+
+#[derive(Default)]
+struct YearAcc<'a> {
+    watch_count: usize,
+    video_count: usize,
+    channels: HashMap<&'a Channel, usize>,
+    months: [usize; 12],
+}
+
+/// Summarize every year of the dataset, oldest first
+pub fn year_summaries(metadata_table: &MetadataTable) -> Vec<YearSummary> {
+    let mut years: BTreeMap<i32, YearAcc> = BTreeMap::new();
+
+    for m in metadata_table.data() {
+        let mut seen: HashSet<i32> = HashSet::new();
+
+        for t in m.watch_timeline.iter() {
+            let local = t.with_timezone(&Local);
+            let acc = years.entry(local.year()).or_default();
+
+            acc.watch_count += 1;
+            acc.months[local.month0() as usize] += 1;
+            *acc.channels.entry(&m.channel).or_default() += 1;
+
+            if seen.insert(local.year()) {
+                acc.video_count += 1;
+            }
+        }
+    }
+
+    let mut list: Vec<YearSummary> = Vec::with_capacity(years.len());
+
+    for (year, acc) in years {
+        let top_channel = acc
+            .channels
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.name.cmp(&a.0.name)))
+            .map(|(c, n)| YearChannel {
+                channel: (*c).clone(),
+                watch_count: *n,
+            });
+
+        let busiest_month = acc
+            .months
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| **n > 0)
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(&a.0)))
+            .map(|(i, n)| YearMonth {
+                month: i as u32 + 1,
+                watch_count: *n,
+            });
+
+        let change = list
+            .last()
+            .filter(|p| p.year == year - 1 && p.watch_count > 0)
+            .map(|p| (acc.watch_count as f64 / p.watch_count as f64 - 1.0) * 100.0);
+
+        list.push(YearSummary {
+            year,
+            watch_count: acc.watch_count,
+            video_count: acc.video_count,
+            channel_count: acc.channels.len(),
+            top_channel,
+            busiest_month,
+            change,
+        });
+    }
+
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::watch_on;
+
+    // mid-month noon keeps every watch in the same local month in any timezone
+    #[test]
+    fn summaries() {
+        let table = MetadataTable::from_entries([
+            watch_on("a", "X", "2022-03-15T12:00:00Z"),
+            watch_on("a", "X", "2022-03-16T12:00:00Z"),
+            watch_on("b", "Y", "2022-06-15T12:00:00Z"),
+            watch_on("b", "Y", "2023-06-15T12:00:00Z"),
+            watch_on("c", "Y", "2023-07-15T12:00:00Z"),
+            watch_on("c", "Y", "2023-07-20T12:00:00Z"),
+            watch_on("d", "X", "2023-07-16T12:00:00Z"),
+            watch_on("e", "Z", "2023-08-15T12:00:00Z"),
+            watch_on("e", "Z", "2023-08-20T12:00:00Z"),
+            watch_on("a", "X", "2025-05-15T12:00:00Z"),
+            watch_on("f", "Z", "2025-09-15T12:00:00Z"),
+        ]);
+        let years = year_summaries(&table);
+
+        assert_eq!(
+            years.iter().map(|v| v.year).collect::<Vec<_>>(),
+            [2022, 2023, 2025]
+        );
+
+        let first = &years[0];
+        assert_eq!(first.watch_count, 3);
+        assert_eq!(first.video_count, 2);
+        assert_eq!(first.channel_count, 2);
+        let top = first.top_channel.as_ref().unwrap();
+        assert_eq!((&*top.channel.name, top.watch_count), ("X", 2));
+        let month = first.busiest_month.as_ref().unwrap();
+        assert_eq!((month.name(), month.watch_count), ("March", 2));
+        assert_eq!(first.change, None);
+
+        // a video watched in two years counts in both
+        let second = &years[1];
+        assert_eq!(second.watch_count, 6);
+        assert_eq!(second.video_count, 4);
+        assert_eq!(second.channel_count, 3);
+        let top = second.top_channel.as_ref().unwrap();
+        assert_eq!((&*top.channel.name, top.watch_count), ("Y", 3));
+        let month = second.busiest_month.as_ref().unwrap();
+        assert_eq!((month.month, month.watch_count), (7, 3));
+        assert_eq!(second.change, Some(100.0));
+
+        // no change across a missing year, ties go to the first name and month
+        let third = &years[2];
+        assert_eq!(third.change, None);
+        assert_eq!(
+            third.top_channel.as_ref().unwrap().channel.name.as_ref(),
+            "X"
+        );
+        assert_eq!(third.busiest_month.as_ref().unwrap().month, 5);
+    }
+
+    #[test]
+    fn empty() {
+        assert!(year_summaries(&MetadataTable::from_entries([])).is_empty());
+    }
+}
@@ -0,0 +1,105 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+/// Chart width assumed when the client doesn't tell, in pixels
+pub const DEFAULT_ZOOM_WIDTH: usize = 800;
+
+/// Pixels a bucket gets at least, thinner bars can't be told apart
+pub const PIXELS_PER_BUCKET: usize = 4;
+
+/// Most buckets returned whatever the width
+pub const MAX_ZOOM_BUCKETS: usize = 2000;
+
+/// Bucket sizes the zoom snaps to, in seconds. Buckets start at multiples
+/// of their size since the epoch, so panning keeps their edges in place
+const BUCKET_STEPS: [u64; 13] = [
+    60,
+    5 * 60,
+    15 * 60,
+    60 * 60,
+    3 * 60 * 60,
+    6 * 60 * 60,
+    12 * 60 * 60,
+    24 * 60 * 60,
+    7 * 24 * 60 * 60,
+    14 * 24 * 60 * 60,
+    30 * 24 * 60 * 60,
+    91 * 24 * 60 * 60,
+    365 * 24 * 60 * 60,
+];
+
+/// ZoomBucket
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct ZoomBucket {
+    pub start: DateTime<Utc>,
+    /// Watches from `start` until the next bucket
+    pub count: usize,
+}
+
+/// ZoomedTimeline
+///
+/// Watch counts over a visible range, binned to what a chart of the given
+/// width can show
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct ZoomedTimeline {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub bucket_seconds: u64,
+    /// Watches inside the range
+    pub total: usize,
+    /// Every bucket covering the range, empty ones included
+    pub buckets: Vec<ZoomBucket>,
+}
+
+/// Bin a sorted timeline between `from` and `to`, the whole timeline when
+/// unset, into at most one bucket per `PIXELS_PER_BUCKET` of `width`
+pub fn zoom_timeline(
+    timeline: &[DateTime<Utc>],
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    width: usize,
+) -> ZoomedTimeline {
+    let from = from
+        .or_else(|| timeline.first().copied())
+        .unwrap_or_else(Utc::now);
+    let to = to
+        .or_else(|| timeline.last().map(|t| *t + TimeDelta::seconds(1)))
+        .unwrap_or(from)
+        .max(from);
+
+    let max_buckets = (width / PIXELS_PER_BUCKET).clamp(1, MAX_ZOOM_BUCKETS) as u64;
+    let range = (to - from).num_seconds().max(1) as u64;
+    let step = BUCKET_STEPS
+        .into_iter()
+        .find(|s| range.div_ceil(*s) <= max_buckets)
+        .unwrap_or_else(|| {
+            let year = BUCKET_STEPS[BUCKET_STEPS.len() - 1];
+
+            range.div_ceil(max_buckets).div_ceil(year) * year
+        });
+
+    let size = step as i64;
+    let mut start = from.timestamp().div_euclid(size) * size;
+    let mut buckets = Vec::new();
+
+    while start < to.timestamp() {
+        let left = DateTime::from_timestamp(start, 0).unwrap_or(from);
+        let right = DateTime::from_timestamp(start + size, 0).unwrap_or(to);
+
+        buckets.push(ZoomBucket {
+            start: left,
+            count: timeline.partition_point(|t| *t < right)
+                - timeline.partition_point(|t| *t < left),
+        });
+        start += size;
+    }
+
+    ZoomedTimeline {
+        from,
+        to,
+        bucket_seconds: step,
+        total: timeline.partition_point(|t| *t < to) - timeline.partition_point(|t| *t < from),
+        buckets,
+    }
+}
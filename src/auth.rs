@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+use argon2::{
+    Argon2,
+    password_hash::{PasswordHasher, PasswordVerifier, phc::PasswordHash},
+};
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::Lazy;
+use rand::Rng;
+
+use crate::utils::to_hex_string;
+
+/// Session cookie name
+pub const SESSION_COOKIE: &str = "ytm_session";
+
+/// How long a session stays valid after login
+pub const SESSION_TTL_DAYS: i64 = 7;
+
+/// Hash a password into an argon2id PHC string, `$argon2id$v=19$...`
+pub fn hash_password(password: &str) -> Result<String> {
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes())
+        .map_err(|e| anyhow!("failed to hash password. {}", e))?;
+
+    Ok(hash.to_string())
+}
+
+/// Hash checked for unknown user names, so a failed login takes as long
+/// whether the name exists or not
+static DUMMY_HASH: Lazy<String> = Lazy::new(|| hash_password("").unwrap_or_default());
+
+/// Verify `password` against an argon2 PHC string, or against a throwaway
+/// hash for `None` so unknown users can't be told apart by timing. Slow on
+/// purpose, keep it off the async runtime and outside of locks
+pub fn verify_password_hash(password_hash: Option<&str>, password: &str) -> bool {
+    let verify = |hash: &str| {
+        PasswordHash::new(hash).is_ok_and(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+    };
+
+    match password_hash {
+        Some(v) => verify(v),
+        None => {
+            verify(&DUMMY_HASH);
+            false
+        }
+    }
+}
+
+/// Whether a configured password is an argon2 PHC string rather than clear
+/// text
+pub fn is_password_hash(password: &str) -> bool {
+    password.starts_with("$argon2")
+}
+
+/// User
+#[derive(Clone, Debug)]
+pub struct User {
+    pub name: String,
+    /// argon2 PHC string, the password itself is never kept
+    password_hash: String,
+    profiles: Option<Vec<String>>,
+}
+
+impl User {
+    /// Create user from a hash made by [`hash_password`], `None` profiles
+    /// means every profile is visible
+    pub fn new(name: String, password_hash: String, profiles: Option<Vec<String>>) -> Result<Self> {
+        PasswordHash::new(&password_hash)
+            .map_err(|e| anyhow!("invalid password hash of user `{}`. {}", name, e))?;
+
+        Ok(Self {
+            name,
+            password_hash,
+            profiles,
+        })
+    }
+
+    pub fn password_hash(&self) -> &str {
+        &self.password_hash
+    }
+
+    pub fn verify_password(&self, password: &str) -> bool {
+        verify_password_hash(Some(&self.password_hash), password)
+    }
+
+    /// User allowed to see every profile
+    pub fn is_unrestricted(&self) -> bool {
+        self.profiles.is_none()
+    }
+
+    pub fn can_access(&self, profile: &str) -> bool {
+        match &self.profiles {
+            Some(v) => v.iter().any(|p| p == profile),
+            None => true,
+        }
+    }
+}
+
+/// Session
+#[derive(Clone, Debug)]
+struct Session {
+    user: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// Auth
+///
+/// User accounts and their login sessions. Authentication is disabled when no
+/// user is registered
+#[derive(Debug, Default)]
+pub struct Auth {
+    users: HashMap<String, User>,
+    sessions: HashMap<String, Session>,
+}
+
+impl Auth {
+    pub fn new(users: Vec<User>) -> Self {
+        Self {
+            users: users.into_iter().map(|u| (u.name.clone(), u)).collect(),
+            sessions: HashMap::new(),
+        }
+    }
+
+    /// Replace user accounts, sessions of removed users or changed passwords
+    /// are dropped
+    pub fn set_users(&mut self, users: Vec<User>) {
+        let users: HashMap<String, User> = users.into_iter().map(|u| (u.name.clone(), u)).collect();
+
+        self.sessions
+            .retain(|_, s| match (self.users.get(&s.user), users.get(&s.user)) {
+                (Some(old), Some(new)) => old.password_hash == new.password_hash,
+                _ => false,
+            });
+        self.users = users;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.users.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&User> {
+        self.users.get(name)
+    }
+
+    /// Verify credentials and open a new session, returning its token.
+    /// Hashes while borrowing `self`, the server checks the password with
+    /// [`verify_password_hash`] and only then calls [`Self::open_session`]
+    pub fn login(&mut self, name: &str, password: &str) -> Option<String> {
+        let password_hash = self.users.get(name).map(|u| u.password_hash.clone());
+
+        if !verify_password_hash(password_hash.as_deref(), password) {
+            return None;
+        }
+
+        self.open_session(name, password_hash.as_deref()?)
+    }
+
+    /// Open a session for a user whose password matched `password_hash`,
+    /// `None` when the user was removed or the password changed since.
+    /// Expired sessions are swept meanwhile, so tokens which are never
+    /// presented again don't pile up
+    pub fn open_session(&mut self, name: &str, password_hash: &str) -> Option<String> {
+        let user = self
+            .users
+            .get(name)
+            .filter(|u| u.password_hash == password_hash)?;
+
+        let now = Utc::now();
+        self.sessions.retain(|_, s| s.expires_at >= now);
+
+        let token = to_hex_string(&rand::rng().random::<[u8; 32]>());
+        let session = Session {
+            user: user.name.clone(),
+            expires_at: now + Duration::days(SESSION_TTL_DAYS),
+        };
+
+        self.sessions.insert(token.clone(), session);
+
+        Some(token)
+    }
+
+    pub fn logout(&mut self, token: &str) {
+        self.sessions.remove(token);
+    }
+
+    /// Resolve session token into its user, expired session is removed
+    pub fn user(&mut self, token: &str) -> Option<&User> {
+        let session = self.sessions.get(token)?;
+
+        if session.expires_at < Utc::now() {
+            self.sessions.remove(token);
+            return None;
+        }
+
+        self.users.get(&session.user)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(name: &str, password: &str) -> User {
+        User::new(name.to_owned(), hash_password(password).unwrap(), None).unwrap()
+    }
+
+    #[test]
+    fn password_hash() {
+        let hash = hash_password("secret").unwrap();
+        assert!(is_password_hash(&hash));
+        assert!(!hash.contains("secret"));
+
+        let user = User::new("alice".to_owned(), hash, None).unwrap();
+        assert!(user.verify_password("secret"));
+        assert!(!user.verify_password("Secret"));
+        assert!(!user.verify_password(""));
+
+        assert!(User::new("alice".to_owned(), "secret".to_owned(), None).is_err());
+        assert!(User::new("alice".to_owned(), "$argon2id$nonsense".to_owned(), None).is_err());
+    }
+
+    #[test]
+    fn profile_access() {
+        let hash = hash_password("secret").unwrap();
+        let user = User::new("alice".to_owned(), hash, Some(vec!["alice".to_owned()])).unwrap();
+
+        assert!(!user.is_unrestricted());
+        assert!(user.can_access("alice"));
+        assert!(!user.can_access("bob"));
+        assert!(self::user("admin", "secret").can_access("bob"));
+    }
+
+    #[test]
+    fn login_and_logout() {
+        let mut auth = Auth::new(vec![user("alice", "secret")]);
+        assert!(auth.is_enabled());
+        assert!(!Auth::default().is_enabled());
+
+        assert_eq!(auth.login("alice", "wrong"), None);
+        assert_eq!(auth.login("bob", "secret"), None);
+
+        let token = auth.login("alice", "secret").unwrap();
+        assert_eq!(auth.user(&token).map(|u| u.name.as_str()), Some("alice"));
+        assert!(auth.user("unknown").is_none());
+
+        auth.logout(&token);
+        assert!(auth.user(&token).is_none());
+    }
+
+    #[test]
+    fn expired_sessions() {
+        let mut auth = Auth::new(vec![user("alice", "secret")]);
+        let stale = auth.login("alice", "secret").unwrap();
+        let forgotten = auth.login("alice", "secret").unwrap();
+
+        for token in [&stale, &forgotten] {
+            auth.sessions.get_mut(token).unwrap().expires_at = Utc::now() - Duration::seconds(1);
+        }
+
+        // presenting an expired token removes it
+        assert!(auth.user(&stale).is_none());
+        assert!(!auth.sessions.contains_key(&stale));
+
+        // the other one is swept on the next login
+        let token = auth.login("alice", "secret").unwrap();
+        assert!(!auth.sessions.contains_key(&forgotten));
+        assert_eq!(auth.sessions.len(), 1);
+        assert!(auth.user(&token).is_some());
+    }
+
+    #[test]
+    fn replaced_users() {
+        let alice = user("alice", "secret");
+        let mut auth = Auth::new(vec![alice.clone(), user("bob", "secret")]);
+        let alice_token = auth.login("alice", "secret").unwrap();
+        let bob_token = auth.login("bob", "secret").unwrap();
+
+        // unchanged hash keeps the session, a new password drops it
+        auth.set_users(vec![alice, user("bob", "changed")]);
+        assert!(auth.user(&alice_token).is_some());
+        assert!(auth.user(&bob_token).is_none());
+
+        auth.set_users(vec![user("bob", "changed")]);
+        assert!(auth.user(&alice_token).is_none());
+    }
+
+    #[test]
+    fn unknown_users() {
+        let hash = hash_password("secret").unwrap();
+        assert!(verify_password_hash(Some(&hash), "secret"));
+
+        // the throwaway hash never lets anyone in, not even an empty password
+        assert!(!verify_password_hash(None, "secret"));
+        assert!(!verify_password_hash(None, ""));
+    }
+
+    #[test]
+    fn sessions_after_verification() {
+        let alice = user("alice", "secret");
+        let hash = alice.password_hash().to_owned();
+        let mut auth = Auth::new(vec![alice]);
+
+        let token = auth.open_session("alice", &hash).unwrap();
+        assert!(auth.user(&token).is_some());
+
+        // the password changed or the user left while hashing
+        assert_eq!(auth.open_session("alice", "$argon2id$stale"), None);
+        auth.set_users(vec![user("alice", "changed")]);
+        assert_eq!(auth.open_session("alice", &hash), None);
+        auth.set_users(Vec::new());
+        assert_eq!(auth.open_session("alice", &hash), None);
+    }
+}
@@ -0,0 +1,778 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{Result, bail};
+use chrono::Local;
+use clap::CommandFactory;
+use clap_complete::Shell;
+
+use crate::{
+    analytics::{
+        Period,
+        check::check_report,
+        diff::diff,
+        rates::watch_rates,
+        top::{top_channels, top_videos},
+        years::year_summaries,
+    },
+    auth::hash_password,
+    config::{Config, ExportFormat, OutputFormat, QueryArgs, TopKind},
+    demo::{DemoOptions, generate, to_v1_json},
+    journal::{compact, read_journal, rewrite_journal},
+    pdf::year_report,
+    profile::DEFAULT_PROFILE,
+    schema::{load_metadata_from_file, load_metadata_from_paths},
+    utils::{DateTimeUtility, to_csv_line, to_markdown_table, to_watches_csv},
+};
+
+/// Run command, treating closed stdout (e.g. piped into `head`) as success
+pub fn run_command<F>(f: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()>,
+{
+    let mut out = io::stdout().lock();
+
+    match f(&mut out).and_then(|_| Ok(out.flush()?)) {
+        Err(e)
+            if e.downcast_ref::<io::Error>()
+                .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe) =>
+        {
+            Ok(())
+        }
+        res => res,
+    }
+}
+
+/// `ytm diff` command
+pub fn run_diff(out: &mut dyn Write, old: &Path, new: &Path, format: OutputFormat) -> Result<()> {
+    let old_table = load_metadata_from_file(old)?;
+    let new_table = load_metadata_from_file(new)?;
+    let d = diff(&old_table, &new_table);
+
+    match format {
+        OutputFormat::Json => {
+            writeln!(out, "{}", serde_json::to_string_pretty(&d)?)?;
+        }
+        OutputFormat::Csv => {
+            writeln!(out, "change,watched_at,id,title,channel_name")?;
+
+            for (label, entries) in [("added", &d.added), ("removed", &d.removed)] {
+                for e in entries {
+                    let time = e.watched_at.to_rfc3339();
+                    let line = to_csv_line([label, &time, &e.id, &e.title, &e.channel_name]);
+
+                    writeln!(out, "{}", line)?;
+                }
+            }
+        }
+        OutputFormat::Plain => {
+            writeln!(
+                out,
+                "Old      : {} ({} entries, {})",
+                old.display(),
+                d.old_coverage.count,
+                d.old_coverage.to_range_string()
+            )?;
+            writeln!(
+                out,
+                "New      : {} ({} entries, {})",
+                new.display(),
+                d.new_coverage.count,
+                d.new_coverage.to_range_string()
+            )?;
+            writeln!(
+                out,
+                "Added    : {} entries ({} new videos)",
+                d.added.len(),
+                d.added_videos
+            )?;
+            writeln!(
+                out,
+                "Removed  : {} entries ({} videos gone)",
+                d.removed.len(),
+                d.removed_videos
+            )?;
+
+            for (label, entries) in [("Added", &d.added), ("Removed", &d.removed)] {
+                if entries.is_empty() {
+                    continue;
+                }
+
+                writeln!(out)?;
+                writeln!(out, "{} entries:", label)?;
+
+                for e in entries {
+                    writeln!(
+                        out,
+                        "  {}  {}  {} [{}]",
+                        e.to_datetime_local(),
+                        e.id,
+                        e.title,
+                        e.channel_name
+                    )?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `ytm demo --output` command
+pub fn run_demo_output(path: &Path, options: &DemoOptions) -> Result<()> {
+    let entries = generate(options);
+    let writer = BufWriter::new(File::create(path)?);
+
+    serde_json::to_writer_pretty(writer, &to_v1_json(&entries))?;
+
+    log::info!("Wrote {} entries to {}", entries.len(), path.display());
+
+    Ok(())
+}
+
+/// `ytm top` command
+pub fn run_top(
+    out: &mut dyn Write,
+    kind: TopKind,
+    file: &Path,
+    n: usize,
+    year: Option<i32>,
+    format: OutputFormat,
+) -> Result<()> {
+    let period = match year {
+        Some(y) => match Period::year(y) {
+            Some(v) => v,
+            None => bail!("year {} is out of range", y),
+        },
+        None => Period::default(),
+    };
+    let metadata_table = load_metadata_from_file(file)?;
+
+    match kind {
+        TopKind::Channels => {
+            let list = top_channels(&metadata_table, &period, n);
+
+            match format {
+                OutputFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(&list)?)?,
+                OutputFormat::Csv => {
+                    writeln!(out, "rank,watch_count,video_count,id,name")?;
+
+                    for v in list {
+                        let line = to_csv_line([
+                            v.rank.to_string().as_str(),
+                            &v.watch_count.to_string(),
+                            &v.video_count.to_string(),
+                            &v.id,
+                            &v.name,
+                        ]);
+
+                        writeln!(out, "{}", line)?;
+                    }
+                }
+                OutputFormat::Plain => {
+                    writeln!(
+                        out,
+                        "{:>4}  {:>7}  {:>6}  Channel",
+                        "#", "Watched", "Videos"
+                    )?;
+
+                    for v in list {
+                        writeln!(
+                            out,
+                            "{:>4}  {:>7}  {:>6}  {}",
+                            v.rank, v.watch_count, v.video_count, v.name
+                        )?;
+                    }
+                }
+            }
+        }
+        TopKind::Videos => {
+            let list = top_videos(&metadata_table, &period, n);
+
+            match format {
+                OutputFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(&list)?)?,
+                OutputFormat::Csv => {
+                    writeln!(out, "rank,watch_count,id,title,channel_name")?;
+
+                    for v in list {
+                        let line = to_csv_line([
+                            v.rank.to_string().as_str(),
+                            &v.watch_count.to_string(),
+                            &v.id,
+                            &v.title,
+                            &v.channel_name,
+                        ]);
+
+                        writeln!(out, "{}", line)?;
+                    }
+                }
+                OutputFormat::Plain => {
+                    writeln!(
+                        out,
+                        "{:>4}  {:>7}  {:<11}  Title [Channel]",
+                        "#", "Watched", "ID"
+                    )?;
+
+                    for v in list {
+                        writeln!(
+                            out,
+                            "{:>4}  {:>7}  {:<11}  {} [{}]",
+                            v.rank, v.watch_count, v.id, v.title, v.channel_name
+                        )?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `ytm stats` command
+pub fn run_stats(out: &mut dyn Write, file: &Path, format: OutputFormat) -> Result<()> {
+    let metadata_table = load_metadata_from_file(file)?;
+    let list = watch_rates(&metadata_table);
+
+    match format {
+        OutputFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(&list)?)?,
+        OutputFormat::Csv => {
+            writeln!(
+                out,
+                "period,days,day_average,day_median,week_average,week_median,month_average,month_median"
+            )?;
+
+            for v in list {
+                let line = to_csv_line([
+                    v.label().as_str(),
+                    &v.days.to_string(),
+                    &v.per_day.average.to_string(),
+                    &v.per_day.median.to_string(),
+                    &v.per_week.average.to_string(),
+                    &v.per_week.median.to_string(),
+                    &v.per_month.average.to_string(),
+                    &v.per_month.median.to_string(),
+                ]);
+
+                writeln!(out, "{}", line)?;
+            }
+        }
+        OutputFormat::Plain => {
+            writeln!(
+                out,
+                "{:<8}  {:>5}  {:>15}  {:>15}  {:>15}",
+                "Period", "Days", "Day (avg/med)", "Week (avg/med)", "Month (avg/med)"
+            )?;
+
+            for v in list {
+                writeln!(
+                    out,
+                    "{:<8}  {:>5}  {:>7.1} /{:>6.1}  {:>7.1} /{:>6.1}  {:>7.1} /{:>6.1}",
+                    v.label(),
+                    v.days,
+                    v.per_day.average,
+                    v.per_day.median,
+                    v.per_week.average,
+                    v.per_week.median,
+                    v.per_month.average,
+                    v.per_month.median
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `ytm report` command
+pub fn run_report(out: &mut dyn Write, file: &Path, format: OutputFormat) -> Result<()> {
+    let metadata_table = load_metadata_from_file(file)?;
+    let list = year_summaries(&metadata_table);
+
+    match format {
+        OutputFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(&list)?)?,
+        OutputFormat::Csv => {
+            writeln!(
+                out,
+                "year,watch_count,change,video_count,channel_count,top_channel,busiest_month"
+            )?;
+
+            for v in list {
+                let line = to_csv_line([
+                    v.year.to_string().as_str(),
+                    &v.watch_count.to_string(),
+                    &v.change.map(|c| format!("{:.1}", c)).unwrap_or_default(),
+                    &v.video_count.to_string(),
+                    &v.channel_count.to_string(),
+                    v.top_channel.as_ref().map_or("", |c| &c.channel.name),
+                    v.busiest_month.as_ref().map_or("", |m| m.name()),
+                ]);
+
+                writeln!(out, "{}", line)?;
+            }
+        }
+        OutputFormat::Plain => {
+            writeln!(
+                out,
+                "{:<4}  {:>7}  {:>7}  {:>6}  {:>8}  {:<9}  Top Channel",
+                "Year", "Watched", "Change", "Videos", "Channels", "Month"
+            )?;
+
+            for v in list {
+                writeln!(
+                    out,
+                    "{:<4}  {:>7}  {:>7}  {:>6}  {:>8}  {:<9}  {}",
+                    v.year,
+                    v.watch_count,
+                    v.change.map_or("-".to_string(), |c| format!("{:+.1}%", c)),
+                    v.video_count,
+                    v.channel_count,
+                    v.busiest_month.as_ref().map_or("-", |m| m.name()),
+                    v.top_channel.as_ref().map_or("-", |c| &c.channel.name)
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `ytm report --pdf` command
+pub fn run_report_pdf(file: &Path, path: &Path) -> Result<()> {
+    let metadata_table = load_metadata_from_file(file)?;
+    let list = year_summaries(&metadata_table);
+
+    std::fs::write(path, year_report(&list, Local::now()))?;
+
+    log::info!("Wrote report of {} years to {}", list.len(), path.display());
+
+    Ok(())
+}
+
+/// `ytm query` command
+pub fn run_query(out: &mut dyn Write, args: &QueryArgs, format: OutputFormat) -> Result<()> {
+    let mut metadata_table = load_metadata_from_file(&args.file)?;
+    metadata_table.set_collation(args.collation.unwrap_or_default());
+    metadata_table.set_half_life(args.half_life);
+
+    let (_, data) = metadata_table.get_collection(&args.to_filter()?);
+
+    match format {
+        OutputFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(&data)?)?,
+        OutputFormat::Csv => {
+            writeln!(
+                out,
+                "watched_at,watch_count,id,title,channel_id,channel_name,last_watched_at"
+            )?;
+
+            for v in data {
+                let line = to_csv_line([
+                    v.watched_at.to_rfc3339().as_str(),
+                    &v.watch_count.to_string(),
+                    &v.id,
+                    &v.title,
+                    &v.channel.id,
+                    &v.channel.name,
+                    &v.last_watched_at.to_rfc3339(),
+                ]);
+
+                writeln!(out, "{}", line)?;
+            }
+        }
+        OutputFormat::Plain => {
+            for v in data {
+                writeln!(
+                    out,
+                    "{}  {:>4}x  {}  {} [{}]",
+                    v.watched_at.to_datetime_string(),
+                    v.watch_count,
+                    v.id,
+                    v.title,
+                    v.channel.name
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `ytm export` command
+pub fn run_export(out: &mut dyn Write, args: &QueryArgs, format: ExportFormat) -> Result<()> {
+    let mut metadata_table = load_metadata_from_file(&args.file)?;
+    metadata_table.set_collation(args.collation.unwrap_or_default());
+    metadata_table.set_half_life(args.half_life);
+
+    let (_, data) = metadata_table.get_collection(&args.to_filter()?);
+
+    match format {
+        ExportFormat::Md => out.write_all(to_markdown_table(&data).as_bytes())?,
+        ExportFormat::Csv => out.write_all(to_watches_csv(&data).as_bytes())?,
+        #[cfg(feature = "parquet")]
+        ExportFormat::Parquet => {
+            let mut buf = Vec::new();
+
+            crate::export::write_parquet(&mut buf, &data)?;
+            out.write_all(&buf)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// `ytm export --output` command
+pub fn run_export_file(path: &Path, args: &QueryArgs, format: ExportFormat) -> Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+
+    run_export(&mut out, args, format)?;
+    out.flush()?;
+
+    log::info!("Wrote export to {}", path.display());
+
+    Ok(())
+}
+
+/// `ytm compact` command
+pub fn run_compact(out: &mut dyn Write, config: &Config) -> Result<()> {
+    let Some(journal) = &config.journal else {
+        bail!("No journal to compact. Pass `--journal` or set it in the config file");
+    };
+
+    let mut histories = Vec::new();
+
+    if !config.file.is_empty() {
+        histories.push((
+            DEFAULT_PROFILE.to_owned(),
+            load_metadata_from_paths(&config.file, config.lenient)?,
+        ));
+    }
+
+    for p in &config.profiles {
+        histories.push((p.name.clone(), load_metadata_from_file(&p.path)?));
+    }
+
+    let tables = histories
+        .iter()
+        .map(|(name, table)| (name.as_str(), table))
+        .collect::<Vec<_>>();
+    let entries = read_journal(journal)?;
+    let (kept, stats) = compact(entries, &tables, config.default_profile());
+
+    rewrite_journal(journal, &kept)?;
+
+    writeln!(
+        out,
+        "Kept {} entries, dropped {} covered by exports and {} repeats",
+        stats.kept, stats.exported, stats.duplicates
+    )?;
+
+    Ok(())
+}
+
+/// `ytm completions` command
+pub fn run_completions(out: &mut dyn Write, shell: Shell) -> Result<()> {
+    let mut cmd = Config::command();
+    let name = cmd.get_name().to_owned();
+
+    // generator panics on write errors, render into buffer first
+    let mut buf = Vec::new();
+    clap_complete::generate(shell, &mut cmd, name, &mut buf);
+    out.write_all(&buf)?;
+
+    Ok(())
+}
+
+/// `ytm man` command
+pub fn run_man(out: &mut dyn Write) -> Result<()> {
+    clap_mangen::Man::new(Config::command()).render(out)?;
+
+    Ok(())
+}
+
+/// `ytm hash-password` command
+pub fn run_hash_password(out: &mut dyn Write) -> Result<()> {
+    let mut password = String::new();
+    io::stdin().read_line(&mut password)?;
+
+    let password = password.trim_end_matches(['\r', '\n']);
+
+    if password.is_empty() {
+        bail!("No password given on stdin");
+    }
+
+    writeln!(out, "{}", hash_password(password)?)?;
+
+    Ok(())
+}
+
+/// `ytm check` command
+pub fn run_check(
+    out: &mut dyn Write,
+    file: &Path,
+    strict: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    let metadata_table = load_metadata_from_file(file)?;
+    let schema_version = metadata_table.source().and_then(|s| s.schema_version);
+    let r = check_report(&metadata_table, schema_version);
+
+    match format {
+        OutputFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(&r)?)?,
+        OutputFormat::Csv => {
+            writeln!(out, "key,value")?;
+            writeln!(
+                out,
+                "schema_version,{}",
+                r.schema_version.unwrap_or_default()
+            )?;
+            writeln!(out, "total_count_raw,{}", r.total_count_raw)?;
+            writeln!(out, "total_count,{}", r.total_count)?;
+            writeln!(out, "rewatched_videos,{}", r.rewatched_videos)?;
+            writeln!(out, "duplicate_entries,{}", r.duplicate_entries)?;
+            writeln!(out, "missing_id_entries,{}", r.missing_id_entries)?;
+            writeln!(out, "missing_channel_entries,{}", r.missing_channel_entries)?;
+            writeln!(out, "future_entries,{}", r.future_entries)?;
+            writeln!(out, "gap_count,{}", r.gap_count)?;
+        }
+        OutputFormat::Plain => {
+            let version = r
+                .schema_version
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| String::from("-"));
+
+            writeln!(out, "File         : {}", file.display())?;
+            writeln!(out, "Schema       : v{}", version)?;
+            writeln!(out, "Entries      : {}", r.total_count_raw)?;
+            writeln!(out, "Videos       : {}", r.total_count)?;
+            writeln!(out, "Rewatched    : {}", r.rewatched_videos)?;
+            writeln!(out, "Duplicates   : {}", r.duplicate_entries)?;
+            writeln!(out, "Coverage     : {}", r.coverage.to_range_string())?;
+
+            for w in &r.warnings {
+                writeln!(out, "warning: {}", w)?;
+            }
+        }
+    }
+
+    if strict && !r.warnings.is_empty() {
+        bail!("{} warnings found", r.warnings.len());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use clap::Parser;
+
+    use super::*;
+    use crate::{config::Command, schema::testing::watch_on};
+
+    /// History file only used by the test `name`
+    fn history(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("ytm-cli-{}-{}.json", name, std::process::id()));
+        let entries = [
+            watch_on("aaaaaaaaaaa", "X", "2023-01-01T12:00:00Z"),
+            watch_on("aaaaaaaaaaa", "X", "2023-02-01T12:00:00Z"),
+            watch_on("aaaaaaaaaaa", "X", "2023-03-01T12:00:00Z"),
+            watch_on("bbbbbbbbbbb", "X", "2023-04-01T12:00:00Z"),
+            watch_on("ccccccccccc", "Y", "2022-05-01T12:00:00Z"),
+            watch_on("ccccccccccc", "Y", "2022-06-01T12:00:00Z"),
+            watch_on("ddddddddddd", "Z", "2022-07-01T12:00:00Z"),
+        ];
+        std::fs::write(&path, to_v1_json(&entries).to_string()).unwrap();
+
+        path
+    }
+
+    fn output(f: impl FnOnce(&mut dyn Write) -> Result<()>) -> String {
+        let mut out = Vec::new();
+        f(&mut out).unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn top_channels_output() {
+        let file = history("top-channels");
+
+        let csv = output(|out| run_top(out, TopKind::Channels, &file, 2, None, OutputFormat::Csv));
+        assert_eq!(
+            csv,
+            "rank,watch_count,video_count,id,name\n1,4,2,UCX,X\n2,2,1,UCY,Y\n"
+        );
+
+        let plain = output(|out| {
+            run_top(
+                out,
+                TopKind::Channels,
+                &file,
+                10,
+                Some(2022),
+                OutputFormat::Plain,
+            )
+        });
+        let lines = plain.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].ends_with("Y"), "{plain}");
+        assert!(lines[2].ends_with("Z"), "{plain}");
+
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn top_videos_output() {
+        let file = history("top-videos");
+
+        let json = output(|out| {
+            run_top(
+                out,
+                TopKind::Videos,
+                &file,
+                1,
+                Some(2023),
+                OutputFormat::Json,
+            )
+        });
+        let list: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(list.as_array().unwrap().len(), 1);
+        assert_eq!(list[0]["id"], "aaaaaaaaaaa");
+        assert_eq!(list[0]["watch_count"], 3);
+
+        let csv = output(|out| {
+            run_top(
+                out,
+                TopKind::Videos,
+                &file,
+                10,
+                Some(2020),
+                OutputFormat::Csv,
+            )
+        });
+        assert_eq!(csv, "rank,watch_count,id,title,channel_name\n");
+
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn stats_output() {
+        let file = history("stats");
+
+        let csv = output(|out| run_stats(out, &file, OutputFormat::Csv));
+        let lines = csv.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("period,days,day_average"));
+        assert!(lines[1].starts_with("Overall,336,"), "{csv}");
+        assert!(lines[2].starts_with("2022,245,"), "{csv}");
+        assert!(lines[3].starts_with("2023,91,"), "{csv}");
+
+        let json = output(|out| run_stats(out, &file, OutputFormat::Json));
+        let list: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(list[0]["year"], serde_json::Value::Null);
+        assert_eq!(list[2]["year"], 2023);
+        assert_eq!(list[2]["per_month"]["average"], 1.0);
+        assert_eq!(list[2]["per_month"]["median"], 1.0);
+
+        let plain = output(|out| run_stats(out, &file, OutputFormat::Plain));
+        assert_eq!(plain.lines().count(), 4);
+        assert!(plain.lines().nth(1).unwrap().starts_with("Overall"));
+
+        std::fs::remove_file(file).unwrap();
+    }
+
+    /// Arguments of `ytm query` followed by `args`
+    fn query_args(file: &Path, args: &[&str]) -> QueryArgs {
+        let file = file.to_str().unwrap();
+        let argv = ["ytm", "query", "--file", file]
+            .into_iter()
+            .chain(args.iter().copied());
+
+        match Config::try_parse_from(argv).unwrap().command {
+            Some(Command::Query { args, .. }) => args,
+            v => panic!("unexpected command {v:?}"),
+        }
+    }
+
+    fn query_ids(file: &Path, args: &[&str]) -> Vec<String> {
+        let args = query_args(file, args);
+        let csv = output(|out| run_query(out, &args, OutputFormat::Csv));
+
+        csv.lines()
+            .skip(1)
+            .map(|v| v.split(',').nth(2).unwrap()[..1].to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn query_filters() {
+        let file = history("query");
+
+        assert_eq!(query_ids(&file, &[]), ["b", "a", "d", "c"]);
+        assert_eq!(query_ids(&file, &["--channel", "X"]), ["b", "a"]);
+        assert_eq!(query_ids(&file, &["--title", "Video c"]), ["c"]);
+        assert_eq!(
+            query_ids(&file, &["--from", "2022-06-15", "--to", "2023-01-31"]),
+            ["a", "d"]
+        );
+        assert_eq!(
+            query_ids(&file, &["--order", "most_watched", "--limit", "2"]),
+            ["a", "c"]
+        );
+        assert_eq!(
+            query_ids(&file, &["--order", "oldest"]),
+            ["c", "d", "a", "b"]
+        );
+
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn query_formats() {
+        let file = history("query-formats");
+        let args = query_args(&file, &["--channel", "Y"]);
+
+        let json = output(|out| run_query(out, &args, OutputFormat::Json));
+        let list: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(list[0]["id"], "ccccccccccc");
+        assert_eq!(list[0]["watch_count"], 2);
+
+        let plain = output(|out| run_query(out, &args, OutputFormat::Plain));
+        assert_eq!(plain.lines().count(), 1);
+        assert!(
+            plain.contains("   2x  ccccccccccc  Video ccccccccccc [Y]"),
+            "{plain}"
+        );
+
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn query_rejected_flags() {
+        let file = history("query-rejected");
+        let file = file.to_str().unwrap();
+
+        for args in [
+            &["--order", "loudest"][..],
+            &["--from", "yesterday"],
+            &["--limit", "-1"],
+        ] {
+            let argv = ["ytm", "query", "--file", file]
+                .into_iter()
+                .chain(args.iter().copied());
+            assert!(Config::try_parse_from(argv).is_err(), "{args:?}");
+        }
+
+        let args = query_args(
+            Path::new(file),
+            &["--from", "2023-01-01", "--to", "2022-01-01"],
+        );
+        assert!(args.to_filter().is_err());
+
+        std::fs::remove_file(file).unwrap();
+    }
+}
@@ -1,17 +1,1198 @@
-use std::path::PathBuf;
+use std::{
+    collections::BTreeMap,
+    fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-use clap::Parser;
+use anyhow::{Context, Error, Result, bail};
+use chrono::{DateTime, Utc};
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use log::LevelFilter;
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    auth::{User, hash_password, is_password_hash},
+    demo::{DemoOptions, MAX_DEMO_DAYS},
+    frontend::ExternalFrontend,
+    i18n::Lang,
+    profile::DEFAULT_PROFILE,
+    schema::{
+        Boundaries, Collation, DEFAULT_HALF_LIFE_DAYS, Expr, IgnoreList, InvalidFilter,
+        MetadataFilter, Order, PeriodShortcut, TitleRule, ValidatedFilter, View, WatchMatch,
+        parse_filter_time,
+    },
+    service::UiOptions,
+    snapshot::SnapshotOptions,
+};
 
 #[derive(Clone, Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Config {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// TOML config file, command line flags and `YTM_*` variables take precedence
+    #[arg(short, long, env = "YTM_CONFIG", value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// JSON History File, Takeout archive or directory of them, served as the
+    /// default profile. Repeat it to merge several exports
+    #[arg(short, long, env = "YTM_FILE")]
+    pub file: Vec<PathBuf>,
+
+    /// Serve a generated demo history as the default profile
+    #[arg(long, conflicts_with = "file")]
+    pub demo: bool,
+
+    /// Named profile in `name=path` form, can be repeated
+    #[arg(long = "profile", value_name = "NAME=PATH")]
+    pub profiles: Vec<ProfileArg>,
+
+    /// User account in `name:password[:profile,...]` form, can be repeated.
+    /// The password is best given as the hash printed by `ytm hash-password`.
+    /// Login is required once at least one user is defined
+    #[arg(long = "user", value_name = "NAME:PASSWORD[:PROFILES]")]
+    pub users: Vec<UserArg>,
+
+    /// Older JSON History File to compare the default profile against on `/diff`
+    #[arg(long, env = "YTM_DIFF_BASE", value_name = "PATH")]
+    pub diff_base: Option<PathBuf>,
+
+    /// Collapse repeats of the same video logged within this many seconds,
+    /// e.g. `300`
+    #[arg(long, env = "YTM_DEDUPE_WINDOW", value_name = "SECONDS")]
+    pub dedupe_window: Option<u64>,
+
+    /// Serve `/duplicates`, videos under different ids with nearly the same
+    /// title
+    #[arg(long, env = "YTM_DUPLICATES")]
+    pub duplicates: bool,
+
+    /// Fold possible duplicates into their most watched video on load
+    #[arg(long, env = "YTM_MERGE_DUPLICATES")]
+    pub merge_duplicates: bool,
+
+    /// Skip history entries that can't be read instead of failing the load,
+    /// they are reported on the about page
+    #[arg(long, env = "YTM_LENIENT")]
+    pub lenient: bool,
+
+    /// Locale of the alphabetical orders, e.g. `sv`. `å`, `ä` and `ö` sort
+    /// after `z` in Swedish, next to `a` and `o` in German
+    #[arg(long, env = "YTM_COLLATION", value_name = "LOCALE")]
+    pub collation: Option<Collation>,
+
+    /// Days after which a watch counts half toward the `relevance` order,
+    /// 30 when omitted
+    #[arg(long, env = "YTM_HALF_LIFE", value_name = "DAYS")]
+    pub half_life: Option<u32>,
+
+    /// Milliseconds a filter may run before the request is refused as too
+    /// expensive, 5000 when omitted and 0 for no limit
+    #[arg(long, env = "YTM_QUERY_TIMEOUT", value_name = "MS")]
+    pub query_timeout: Option<u64>,
+
+    /// Bytes a request body may have before it is refused with `413`,
+    /// 65536 when omitted
+    #[arg(long, env = "YTM_MAX_BODY", value_name = "BYTES")]
+    pub max_body: Option<usize>,
+
+    /// Language of the web UI, `en` or `de`. Negotiated from the browser's
+    /// `Accept-Language` when omitted
+    #[arg(long, env = "YTM_LANG", value_name = "LANG")]
+    pub lang: Option<Lang>,
+
+    /// Site videos and channels are opened on, YouTube or the base URL of
+    /// an Invidious or Piped instance, e.g. `https://yewtu.be`
+    #[arg(long, env = "YTM_EXTERNAL_FRONTEND", value_name = "URL")]
+    pub external_frontend: Option<ExternalFrontend>,
+
+    /// JSON file keeping short links across restarts, created on the first
+    /// link
+    #[arg(long, env = "YTM_LINKS_FILE", value_name = "PATH")]
+    pub links_file: Option<PathBuf>,
+
+    /// Bearer token enabling `POST /api/history` to report watches live
+    #[arg(
+        long,
+        env = "YTM_INGEST_TOKEN",
+        value_name = "TOKEN",
+        hide_env_values = true
+    )]
+    pub ingest_token: Option<String>,
+
+    /// Append-only file keeping reported watches, created when missing
+    #[arg(long, env = "YTM_JOURNAL", value_name = "PATH")]
+    pub journal: Option<PathBuf>,
+
+    /// Secret signing the cookie which remembers index page settings, a
+    /// random one is used when omitted so they are forgotten on restart
+    #[arg(
+        long,
+        env = "YTM_PREFS_KEY",
+        value_name = "KEY",
+        hide_env_values = true
+    )]
+    pub prefs_key: Option<String>,
+
+    /// Which address is server bound to [default: 127.0.0.1]
+    #[arg(long, env = "YTM_ADDR")]
+    pub addr: Option<IpAddr>,
+
+    /// Which port is server running on [default: 8000]
+    #[arg(short, long, env = "YTM_PORT")]
+    pub port: Option<u16>,
+
+    /// Listen on this named pipe instead of TCP, e.g. `ytm` for
+    /// `\\.\pipe\ytm`
+    #[cfg(windows)]
+    #[arg(long, env = "YTM_PIPE", value_name = "NAME")]
+    pub pipe: Option<String>,
+
+    /// Don't serve the YouTube player, only the tables and reports. Nothing
+    /// is fetched from YouTube on startup
+    #[arg(long, env = "YTM_NO_PLAYER")]
+    pub no_player: bool,
+
+    /// Name advertised over mDNS as `<name>.local` when listening on a LAN
+    /// address
+    #[cfg(feature = "mdns")]
+    #[arg(
+        long,
+        env = "YTM_MDNS_NAME",
+        value_name = "NAME",
+        default_value = "ytm"
+    )]
+    pub mdns_name: String,
+
+    /// Don't advertise the server over mDNS
+    #[cfg(feature = "mdns")]
+    #[arg(long, env = "YTM_NO_MDNS")]
+    pub no_mdns: bool,
+
+    /// Export tracing spans to this OTLP/HTTP endpoint, e.g.
+    /// `http://localhost:4318/v1/traces`
+    #[cfg(feature = "otlp")]
+    #[arg(long, env = "YTM_OTLP_ENDPOINT", value_name = "URL")]
+    pub otlp_endpoint: Option<String>,
+
+    /// More logs, `-v` for debug and `-vv` for trace
+    #[arg(short, long, action = ArgAction::Count, global = true, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Less logs, `-q` for warnings, `-qq` for errors and `-qqq` for none
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub quiet: u8,
+
+    /// UI defaults, only settable through the config file
+    #[arg(skip)]
+    pub ui: UiOptions,
+
+    /// Title rewrite rules, only read from the config file
+    #[arg(skip)]
+    pub title_rules: Vec<TitleRule>,
+
+    /// Content dropped from the dataset, only read from the config file
+    #[arg(skip)]
+    pub ignore: IgnoreList,
+
+    /// Periodic snapshots, only read from the config file
+    #[arg(skip)]
+    pub snapshot: Option<SnapshotOptions>,
+
+    /// Log level, only settable through the config file. `-v`, `-q` and
+    /// `YTM_LOG_LEVEL` take precedence
+    #[arg(skip)]
+    pub log_level: Option<LevelFilter>,
+}
+
+/// Command
+#[derive(Clone, Subcommand, Debug)]
+pub enum Command {
+    /// Compare two history exports without starting the server
+    Diff {
+        /// Older JSON History File
+        old: PathBuf,
+
+        /// Newer JSON History File
+        new: PathBuf,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Plain)]
+        format: OutputFormat,
+    },
+
+    /// Generate a fake history and serve it, or write it as JSON History File
+    Demo(DemoArgs),
+
+    /// Print most watched channels or videos
+    Top {
+        /// What to rank
+        #[arg(value_enum)]
+        kind: TopKind,
+
+        /// JSON History File
+        #[arg(short, long)]
+        file: PathBuf,
+
+        /// Number of rows
+        #[arg(short, default_value_t = 10)]
+        n: usize,
+
+        /// Only count watches in this year
+        #[arg(long)]
+        year: Option<i32>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Plain)]
+        format: OutputFormat,
+    },
+
+    /// Print average and median watches per day, week and month
+    Stats {
+        /// JSON History File
+        #[arg(short, long)]
+        file: PathBuf,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Plain)]
+        format: OutputFormat,
+    },
+
+    /// Print a summary of every year, or write it as PDF
+    Report {
+        /// JSON History File
+        #[arg(short, long)]
+        file: PathBuf,
+
+        /// Write a PDF with a chart and a table of the years to this path
+        #[arg(long, value_name = "PATH")]
+        pdf: Option<PathBuf>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Plain)]
+        format: OutputFormat,
+    },
+
+    /// Print entries matching the same filters as the web UI
+    Query {
+        #[command(flatten)]
+        args: QueryArgs,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Plain)]
+        format: OutputFormat,
+    },
+
+    /// Write entries matching the same filters as the web UI in a format
+    /// meant for other tools
+    Export {
+        #[command(flatten)]
+        args: QueryArgs,
+
+        /// Export format
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Drop journal entries covered by the exports of their profile, and
+    /// repeats, from `--journal`. Stop the server first, it keeps appending
+    /// to the old file otherwise
+    Compact,
+
+    /// Parse a JSON History File and report what would be served
+    Check {
+        /// JSON History File
+        #[arg(short, long)]
+        file: PathBuf,
+
+        /// Exit with an error when there are warnings
+        #[arg(long)]
+        strict: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Plain)]
+        format: OutputFormat,
+    },
+
+    /// Install, remove or run as a Windows service
+    #[cfg(windows)]
+    Service {
+        #[command(subcommand)]
+        action: ServiceAction,
+    },
+
+    /// Print shell completion script
+    Completions {
+        /// Target shell
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+
+    /// Print man page in roff format
+    Man,
+
+    /// Read a password from stdin and print its hash, to be used as the
+    /// password of `--user` or `users` in the config file
+    HashPassword,
+}
+
+/// ServiceAction
+#[cfg(windows)]
+#[derive(Clone, Subcommand, Debug)]
+pub enum ServiceAction {
+    /// Register a service starting with Windows, serving with the settings
+    /// of `--config`. Needs an administrator prompt
+    Install {
+        /// Service name
+        #[arg(long, default_value = crate::winservice::DEFAULT_SERVICE_NAME)]
+        name: String,
+    },
+
+    /// Stop and delete the service. Needs an administrator prompt
+    Uninstall {
+        /// Service name
+        #[arg(long, default_value = crate::winservice::DEFAULT_SERVICE_NAME)]
+        name: String,
+    },
+
+    /// Serve as the service, only started by the service control manager
+    Run {
+        /// Service name
+        #[arg(long, default_value = crate::winservice::DEFAULT_SERVICE_NAME)]
+        name: String,
+    },
+}
+
+/// TopKind
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum TopKind {
+    Channels,
+    Videos,
+}
+
+/// DemoArgs
+#[derive(Clone, Args, Debug)]
+pub struct DemoArgs {
+    /// Total watch entries to generate
+    #[arg(long, default_value_t = 10_000)]
+    pub entries: usize,
+
+    /// Number of distinct channels
+    #[arg(long, default_value_t = 50)]
+    pub channels: usize,
+
+    /// Days of history, ending today
+    #[arg(
+        long,
+        default_value_t = 5 * 365,
+        value_parser = clap::value_parser!(i64).range(1..=MAX_DEMO_DAYS)
+    )]
+    pub days: i64,
+
+    /// Random seed, same seed produce the same history
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Write the history to this file instead of serving it
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+impl DemoArgs {
+    pub fn to_options(&self) -> DemoOptions {
+        DemoOptions {
+            entries: self.entries,
+            channels: self.channels,
+            days: self.days,
+            seed: self.seed,
+        }
+    }
+}
+
+/// QueryArgs
+#[derive(Clone, Args, Debug)]
+pub struct QueryArgs {
     /// JSON History File
     #[arg(short, long)]
     pub file: PathBuf,
 
-    /// Which port is server running on
-    #[arg(short, long, default_value_t = 8000)]
-    pub port: u16,
+    /// Exact video ID
+    #[arg(long)]
+    pub id: Option<String>,
+
+    /// Title or channel name contains, case insensitive, title matches
+    /// first
+    #[arg(long)]
+    pub search: Option<String>,
+
+    /// Title contains, case insensitive
+    #[arg(long)]
+    pub title: Option<String>,
+
+    /// Channel name contains, case insensitive
+    #[arg(long)]
+    pub channel: Option<String>,
+
+    /// Exact channel ID
+    #[arg(long)]
+    pub channel_id: Option<String>,
+
+    /// Watched on this product, e.g. `YouTube Music`
+    #[arg(long)]
+    pub product: Option<String>,
+
+    /// Also list videos only ever shown as ads
+    #[arg(long)]
+    pub ads: bool,
+
+    /// First watched at or after, see `--match`. RFC 3339 timestamp or
+    /// YYYY-MM-DD
+    #[arg(long, value_parser = parse_time)]
+    pub from: Option<DateTime<Utc>>,
+
+    /// First watched at or before, see `--match`. RFC 3339 timestamp or
+    /// YYYY-MM-DD
+    #[arg(long, value_parser = parse_time_end)]
+    pub to: Option<DateTime<Utc>>,
+
+    /// First watched inside a named period, `last_<n>d`, `this_month`,
+    /// `this_year` or a year such as `2019`
+    #[arg(long)]
+    pub period: Option<PeriodShortcut>,
+
+    /// Last watched at or after, RFC 3339 timestamp or YYYY-MM-DD
+    #[arg(long, value_parser = parse_time)]
+    pub last_from: Option<DateTime<Utc>>,
+
+    /// Last watched at or before, RFC 3339 timestamp or YYYY-MM-DD
+    #[arg(long, value_parser = parse_time_end)]
+    pub last_to: Option<DateTime<Utc>>,
+
+    /// Whether watches right at the time bounds match, inclusive, exclusive
+    /// or half_open (`from` only)
+    #[arg(long, default_value_t = Boundaries::Inclusive)]
+    pub boundaries: Boundaries,
+
+    /// Watch `--from`, `--to` and `--period` look at, first_watch, last_watch
+    /// or any_watch
+    #[arg(long = "match", default_value_t = WatchMatch::FirstWatch)]
+    pub watch_match: WatchMatch,
+
+    /// Filter expression, e.g. `watch_count > 3 && watched_at.year() == 2021`
+    #[arg(long)]
+    pub expr: Option<Expr>,
+
+    /// Tolerate typos in `--search`, `--title` and `--channel`, closest
+    /// matches first
+    #[arg(long)]
+    pub fuzzy: bool,
+
+    /// Match letter case exactly in `--search`, `--title` and `--channel`
+    #[arg(long)]
+    pub case_sensitive: bool,
+
+    /// Only match whole words in `--search`, `--title` and `--channel`
+    #[arg(long)]
+    pub whole_word: bool,
+
+    /// latest, oldest, most_watched, least_watched, recently_watched,
+    /// least_recently_watched, relevance, alphabetical or
+    /// reverse_alphabetical
+    #[arg(long, default_value_t = Order::Latest)]
+    pub order: Order,
+
+    /// Locale of the alphabetical orders, e.g. `sv`
+    #[arg(long, value_name = "LOCALE")]
+    pub collation: Option<Collation>,
+
+    /// Days after which a watch counts half toward the `relevance` order
+    #[arg(long, value_name = "DAYS", default_value_t = DEFAULT_HALF_LIFE_DAYS)]
+    pub half_life: u32,
+
+    /// Maximum entries to print, all when omitted
+    #[arg(long)]
+    pub limit: Option<usize>,
 }
 
-impl Config {}
+impl QueryArgs {
+    /// Every match up to `--limit`, which isn't capped like pages are
+    pub fn to_filter(&self) -> Result<ValidatedFilter, InvalidFilter> {
+        ValidatedFilter::unbounded(MetadataFilter {
+            id: self.id.clone(),
+            search: self.search.clone(),
+            title: self.title.clone(),
+            channel_name: self.channel.clone(),
+            channel_id: self.channel_id.clone(),
+            product: self.product.clone(),
+            ads: self.ads,
+            from: self.from,
+            to: self.to,
+            period: self.period,
+            last_from: self.last_from,
+            last_to: self.last_to,
+            boundaries: self.boundaries,
+            watch_match: self.watch_match,
+            // reports of the command line are per video
+            view: View::Videos,
+            expr: self.expr.clone(),
+            fuzzy: self.fuzzy,
+            case_sensitive: self.case_sensitive,
+            whole_word: self.whole_word,
+            order: self.order.clone(),
+            page: 1,
+            limit: self.limit.unwrap_or(usize::MAX),
+        })
+    }
+}
+
+/// OutputFormat
+///
+/// Output format of the command line reports
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum OutputFormat {
+    Plain,
+    Json,
+    Csv,
+}
+
+/// ExportFormat
+///
+/// Output format of `ytm export`
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ExportFormat {
+    /// Markdown table
+    Md,
+    /// CSV with one row per watch, oldest first
+    Csv,
+    /// Parquet file with one row per watch
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl Config {
+    /// Log level requested with `-v` or `-q`
+    pub fn verbosity(&self) -> Option<LevelFilter> {
+        match (self.verbose, self.quiet) {
+            (0, 0) => None,
+            (1, 0) => Some(LevelFilter::Debug),
+            (_, 0) => Some(LevelFilter::Trace),
+            (_, 1) => Some(LevelFilter::Warn),
+            (_, 2) => Some(LevelFilter::Error),
+            (_, _) => Some(LevelFilter::Off),
+        }
+    }
+
+    /// Profile served on the root path, the one of journal entries naming none
+    pub fn default_profile(&self) -> Option<&str> {
+        if !self.file.is_empty() || self.demo {
+            return Some(DEFAULT_PROFILE);
+        }
+
+        self.profiles.first().map(|p| p.name.as_str())
+    }
+
+    /// Merge the config file underneath the parsed command line. Called
+    /// again on the original arguments whenever the config file is reloaded
+    pub fn resolve(&self) -> Result<Self> {
+        let mut config = self.clone();
+
+        if let Some(path) = config.config.clone() {
+            config.merge(ConfigFile::from_path(&path)?)?;
+        }
+
+        if config.lang.is_some() {
+            config.ui.lang = config.lang;
+        }
+
+        if config.duplicates {
+            config.ui.duplicates = true;
+        }
+
+        if let Some(v) = &config.external_frontend {
+            config.ui.external_frontend = v.clone();
+        }
+
+        if let Some(v) = config.query_timeout {
+            config.ui.query_timeout = v;
+        }
+
+        if let Some(v) = config.max_body {
+            config.ui.max_body = v;
+        }
+
+        let needs_history = match &config.command {
+            None => true,
+            Some(Command::Demo(args)) => args.output.is_none(),
+            #[cfg(windows)]
+            Some(Command::Service {
+                action: ServiceAction::Run { .. },
+            }) => true,
+            Some(_) => false,
+        };
+
+        if needs_history && config.file.is_empty() && config.profiles.is_empty() && !config.demo {
+            bail!(
+                "No history to serve. Pass `--file`, `--profile` or `--demo`, or set them in the config file"
+            );
+        }
+
+        Ok(config)
+    }
+
+    /// Fill settings not given on the command line from the config file
+    fn merge(&mut self, file: ConfigFile) -> Result<()> {
+        if !self.demo && self.file.is_empty() {
+            self.file = file.file;
+        }
+
+        self.diff_base = self.diff_base.take().or(file.diff_base);
+        self.dedupe_window = self.dedupe_window.or(file.dedupe_window);
+        self.merge_duplicates |= file.merge_duplicates == Some(true);
+        self.lenient |= file.lenient == Some(true);
+        self.collation = self.collation.or(file.collation);
+        self.half_life = self.half_life.or(file.half_life);
+        self.links_file = self.links_file.take().or(file.links_file);
+        self.ingest_token = self.ingest_token.take().or(file.ingest_token);
+        self.journal = self.journal.take().or(file.journal);
+        self.prefs_key = self.prefs_key.take().or(file.prefs_key);
+        self.addr = self.addr.or(file.addr);
+        self.port = self.port.or(file.port);
+        self.no_player |= file.player == Some(false);
+
+        #[cfg(feature = "mdns")]
+        {
+            self.no_mdns |= file.mdns == Some(false);
+        }
+
+        #[cfg(windows)]
+        {
+            self.pipe = self.pipe.take().or(file.pipe);
+        }
+        file.ui.merge_into(&mut self.ui);
+        self.log_level = file.log_level;
+        self.title_rules = file.title_rules;
+        self.ignore = file.ignore;
+        self.snapshot = file.snapshot;
+
+        for (name, path) in file.profiles {
+            validate_profile_name(&name)?;
+
+            if !self.profiles.iter().any(|p| p.name == name) {
+                self.profiles.push(ProfileArg { name, path });
+            }
+        }
+
+        for user in file.users {
+            if user.name.is_empty() || user.password.is_empty() {
+                bail!("invalid user in config file, name and password cannot be empty");
+            }
+
+            user.check_password_hash()?;
+
+            if !self.users.iter().any(|u| u.name == user.name) {
+                self.users.push(user);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// ConfigFile
+///
+/// Settings read from the TOML config file. `log_level`, `users` and `ui`
+/// are applied on reload, the rest only at startup
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    #[serde(deserialize_with = "one_or_many")]
+    pub file: Vec<PathBuf>,
+    pub diff_base: Option<PathBuf>,
+    pub dedupe_window: Option<u64>,
+    pub merge_duplicates: Option<bool>,
+    pub lenient: Option<bool>,
+    pub collation: Option<Collation>,
+    pub half_life: Option<u32>,
+    pub links_file: Option<PathBuf>,
+    pub ingest_token: Option<String>,
+    pub journal: Option<PathBuf>,
+    pub prefs_key: Option<String>,
+    pub addr: Option<IpAddr>,
+    pub port: Option<u16>,
+    pub player: Option<bool>,
+    /// Accepted without the `mdns` feature so configs stay portable
+    pub mdns: Option<bool>,
+    /// Only used on Windows, accepted elsewhere for the same reason
+    pub pipe: Option<String>,
+    pub profiles: BTreeMap<String, PathBuf>,
+    pub log_level: Option<LevelFilter>,
+    pub users: Vec<UserArg>,
+    pub ui: UiFile,
+    pub title_rules: Vec<TitleRule>,
+    pub ignore: IgnoreList,
+    pub snapshot: Option<SnapshotOptions>,
+}
+
+impl ConfigFile {
+    /// Read config file, relative paths are resolved against its directory
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file `{}`", path.display()))?;
+        let mut file: Self = toml::from_str(&content)
+            .with_context(|| format!("invalid config file `{}`", path.display()))?;
+
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let resolve = |p: PathBuf| if p.is_relative() { dir.join(p) } else { p };
+
+        file.file = file.file.into_iter().map(resolve).collect();
+        file.diff_base = file.diff_base.map(resolve);
+        file.links_file = file.links_file.map(resolve);
+        file.journal = file.journal.map(resolve);
+
+        if let Some(snapshot) = &mut file.snapshot {
+            snapshot.dir = resolve(std::mem::take(&mut snapshot.dir));
+        }
+        file.profiles = file
+            .profiles
+            .into_iter()
+            .map(|(k, v)| (k, resolve(v)))
+            .collect();
+
+        Ok(file)
+    }
+}
+
+/// UiFile
+///
+/// `[ui]` of the config file, keys left out keep the value they already have
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiFile {
+    pub limit: Option<usize>,
+    pub order: Option<Order>,
+    pub page_limits: Option<Vec<usize>>,
+    pub lang: Option<Lang>,
+    pub external_frontend: Option<ExternalFrontend>,
+    pub duplicates: Option<bool>,
+    pub query_timeout: Option<u64>,
+    pub max_body: Option<usize>,
+}
+
+impl UiFile {
+    pub fn merge_into(self, ui: &mut UiOptions) {
+        if let Some(v) = self.limit {
+            ui.limit = v;
+        }
+
+        if let Some(v) = self.order {
+            ui.order = v;
+        }
+
+        if let Some(v) = self.page_limits {
+            ui.page_limits = v;
+        }
+
+        if let Some(v) = self.lang {
+            ui.lang = Some(v);
+        }
+
+        if let Some(v) = self.external_frontend {
+            ui.external_frontend = v;
+        }
+
+        if let Some(v) = self.duplicates {
+            ui.duplicates = v;
+        }
+
+        if let Some(v) = self.query_timeout {
+            ui.query_timeout = v;
+        }
+
+        if let Some(v) = self.max_body {
+            ui.max_body = v;
+        }
+    }
+}
+
+/// `file` of the config file, a single path or a list of them
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<PathBuf>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(PathBuf),
+        Many(Vec<PathBuf>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(v) => vec![v],
+        OneOrMany::Many(v) => v,
+    })
+}
+
+/// Lower time bound of `ytm query`, see [`parse_filter_time`]
+fn parse_time(s: &str) -> Result<DateTime<Utc>> {
+    parse_filter_time(s, false)
+        .with_context(|| format!("invalid time `{}`, expected RFC 3339 or YYYY-MM-DD", s))
+}
+
+/// Upper time bound of `ytm query`, a date takes in the whole day
+fn parse_time_end(s: &str) -> Result<DateTime<Utc>> {
+    parse_filter_time(s, true)
+        .with_context(|| format!("invalid time `{}`, expected RFC 3339 or YYYY-MM-DD", s))
+}
+
+/// Profile names end up in URLs, keep them simple
+fn validate_profile_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!(
+            "invalid profile name `{}`, only alphanumeric, `-` and `_` are allowed",
+            name
+        );
+    }
+
+    Ok(())
+}
+
+/// ProfileArg
+///
+/// Profile definition passed through the command line
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProfileArg {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl FromStr for ProfileArg {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((name, path)) = s.split_once('=') else {
+            bail!("invalid profile `{}`, expected `name=path`", s);
+        };
+
+        let name = name.trim();
+
+        validate_profile_name(name)?;
+
+        Ok(Self {
+            name: name.to_owned(),
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+/// UserArg
+///
+/// User account passed through the command line or the config file
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserArg {
+    pub name: String,
+    /// Clear text or an argon2 PHC string, see [`hash_password`]
+    pub password: String,
+    pub profiles: Option<Vec<String>>,
+}
+
+impl UserArg {
+    /// Create the user, a clear text password is hashed unless it matches
+    /// the hash of `current`, so reloads keep the sessions of the user
+    pub fn to_user(&self, current: Option<&User>) -> Result<User> {
+        let password_hash = match current {
+            _ if is_password_hash(&self.password) => self.password.clone(),
+            Some(u) if u.verify_password(&self.password) => u.password_hash().to_owned(),
+            _ => {
+                log::warn!(
+                    "Password of user `{}` is given in clear text, use the hash printed by `ytm hash-password` instead",
+                    self.name
+                );
+
+                hash_password(&self.password)?
+            }
+        };
+
+        User::new(self.name.clone(), password_hash, self.profiles.clone())
+    }
+
+    /// Fail early on a malformed hash rather than when serving
+    fn check_password_hash(&self) -> Result<()> {
+        if is_password_hash(&self.password) {
+            User::new(self.name.clone(), self.password.clone(), None)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for UserArg {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, ':');
+
+        let (Some(name), Some(password)) = (parts.next(), parts.next()) else {
+            bail!("invalid user `{}`, expected `name:password[:profiles]`", s);
+        };
+
+        if name.is_empty() || password.is_empty() {
+            bail!("invalid user `{}`, name and password cannot be empty", s);
+        }
+
+        let profiles = parts.next().map(|v| {
+            v.split(',')
+                .map(|p| p.trim().to_owned())
+                .filter(|p| !p.is_empty())
+                .collect()
+        });
+
+        let user = Self {
+            name: name.to_owned(),
+            password: password.to_owned(),
+            profiles,
+        };
+        user.check_password_hash()?;
+
+        Ok(user)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demo_days_range() {
+        assert!(Config::try_parse_from(["ytm", "demo", "--days", "9223372036854775807"]).is_err());
+        assert!(Config::try_parse_from(["ytm", "demo", "--days", "0"]).is_err());
+        assert!(Config::try_parse_from(["ytm", "demo", "--days", "365"]).is_ok());
+    }
+
+    #[test]
+    fn user_password_hash() {
+        let hash = hash_password("secret").unwrap();
+        let arg = format!("alice:{hash}:a,b").parse::<UserArg>().unwrap();
+        assert_eq!(arg.password, hash);
+
+        let user = arg.to_user(None).unwrap();
+        assert_eq!(user.password_hash(), hash);
+        assert!(user.verify_password("secret"));
+        assert!(user.can_access("b"));
+
+        assert!("alice:$argon2id$broken".parse::<UserArg>().is_err());
+    }
+
+    #[test]
+    fn user_clear_password() {
+        let arg = "alice:secret".parse::<UserArg>().unwrap();
+        let user = arg.to_user(None).unwrap();
+        assert_ne!(user.password_hash(), "secret");
+        assert!(user.verify_password("secret"));
+
+        // reloads reuse the hash so sessions survive
+        let reloaded = arg.to_user(Some(&user)).unwrap();
+        assert_eq!(reloaded.password_hash(), user.password_hash());
+
+        let changed = "alice:other".parse::<UserArg>().unwrap();
+        let changed = changed.to_user(Some(&user)).unwrap();
+        assert_ne!(changed.password_hash(), user.password_hash());
+    }
+
+    #[test]
+    fn profile_arg() {
+        assert_eq!(
+            " alice =exports/alice.json".parse::<ProfileArg>().unwrap(),
+            ProfileArg {
+                name: "alice".to_owned(),
+                path: PathBuf::from("exports/alice.json"),
+            }
+        );
+
+        for s in [
+            "alice",
+            "=alice.json",
+            "al ice=a.json",
+            "a/b=a.json",
+            "ä=a.json",
+        ] {
+            assert!(s.parse::<ProfileArg>().is_err(), "{s}");
+        }
+    }
+
+    /// Config file only used by the test `name`, in a directory of its own
+    fn config_file(name: &str, content: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ytm-config-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("ytm.toml");
+        fs::write(&path, content).unwrap();
+
+        path
+    }
+
+    fn resolve(args: &[&str]) -> Result<Config> {
+        Config::try_parse_from(["ytm"].iter().chain(args))?.resolve()
+    }
+
+    #[test]
+    fn config_file_merge() {
+        let path = config_file(
+            "merge",
+            r#"
+                file = "history.json"
+                diff_base = "/exports/old.json"
+                addr = "0.0.0.0"
+                port = 8001
+                dedupe_window = 300
+
+                [profiles]
+                alice = "alice.json"
+                bob = "bob.json"
+
+                [[users]]
+                name = "alice"
+                password = "secret"
+            "#,
+        );
+        let dir = path.parent().unwrap();
+        let config = path.to_str().unwrap();
+
+        let merged = resolve(&[
+            "--config",
+            config,
+            "--port",
+            "9000",
+            "--profile",
+            "alice=mine.json",
+        ])
+        .unwrap();
+        assert_eq!(merged.file, [dir.join("history.json")]);
+        assert_eq!(merged.diff_base, Some(PathBuf::from("/exports/old.json")));
+        assert_eq!(merged.addr, Some(IpAddr::from([0, 0, 0, 0])));
+        assert_eq!(merged.port, Some(9000));
+        assert_eq!(merged.dedupe_window, Some(300));
+        assert_eq!(
+            merged.profiles,
+            [
+                ProfileArg {
+                    name: "alice".to_owned(),
+                    path: PathBuf::from("mine.json"),
+                },
+                ProfileArg {
+                    name: "bob".to_owned(),
+                    path: dir.join("bob.json"),
+                },
+            ]
+        );
+        assert_eq!(merged.users.len(), 1);
+
+        // files on the command line replace the ones of the config file
+        let merged = resolve(&["--config", config, "--file", "other.json"]).unwrap();
+        assert_eq!(merged.file, [PathBuf::from("other.json")]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn config_file_rejected() {
+        for (name, content) in [
+            ("unknown-key", "prot = 8000"),
+            ("unknown-ui-key", "[ui]\nlimt = 5"),
+            ("bad-profile", "[profiles]\n\"a b\" = \"a.json\""),
+            (
+                "empty-password",
+                "[[users]]\nname = \"alice\"\npassword = \"\"",
+            ),
+        ] {
+            let path = config_file(name, content);
+
+            assert!(
+                resolve(&["--demo", "--config", path.to_str().unwrap()]).is_err(),
+                "{name}"
+            );
+            fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        }
+
+        assert!(resolve(&["--demo", "--config", "/nonexistent/ytm.toml"]).is_err());
+        assert!(resolve(&[]).is_err());
+    }
+
+    #[test]
+    fn ui_merged_by_key() {
+        let path = config_file(
+            "ui",
+            r#"
+                [ui]
+                limit = 50
+                query_timeout = 1000
+            "#,
+        );
+        let config = path.to_str().unwrap();
+
+        let mut args = Config::try_parse_from(["ytm", "--demo", "--config", config]).unwrap();
+        args.ui.page_limits = vec![50, 500];
+
+        let merged = args.resolve().unwrap();
+        assert_eq!(merged.ui.limit, 50);
+        assert_eq!(merged.ui.page_limits, [50, 500]);
+        assert_eq!(merged.ui.order, Order::Latest);
+        assert_eq!(merged.ui.query_timeout, 1000);
+
+        // flags take precedence over `[ui]`
+        let merged = resolve(&[
+            "--demo",
+            "--config",
+            config,
+            "--query-timeout",
+            "0",
+            "--lang",
+            "de",
+        ])
+        .unwrap();
+        assert_eq!(merged.ui.query_timeout, 0);
+        assert_eq!(merged.ui.lang, Some(Lang::De));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn env_overrides() {
+        let path = config_file("env", "port = 8001\nhalf_life = 7");
+        let config = path.to_str().unwrap();
+
+        // SAFETY: no other test reads these variables
+        unsafe {
+            std::env::set_var("YTM_PORT", "9001");
+            std::env::set_var("YTM_HALF_LIFE", "14");
+        }
+
+        let merged = resolve(&["--demo", "--config", config]).unwrap();
+        assert_eq!(merged.port, Some(9001));
+        assert_eq!(merged.half_life, Some(14));
+
+        let merged = resolve(&["--demo", "--config", config, "--port", "9002"]).unwrap();
+        assert_eq!(merged.port, Some(9002));
+
+        unsafe {
+            std::env::remove_var("YTM_PORT");
+            std::env::remove_var("YTM_HALF_LIFE");
+        }
+
+        let merged = resolve(&["--demo", "--config", config]).unwrap();
+        assert_eq!(merged.port, Some(8001));
+        assert_eq!(merged.half_life, Some(7));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
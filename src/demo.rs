@@ -0,0 +1,225 @@
+use chrono::{DateTime, Duration, SubsecRound, Timelike, Utc};
+use once_cell::sync::Lazy;
+use rand::{
+    Rng, SeedableRng,
+    distr::{Distribution, weighted::WeightedIndex},
+    rngs::StdRng,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::schema::{AD_DETAIL, Channel, WatchEntry};
+
+static SEED_JSON: &[u8] = include_bytes!("../data/seed.json");
+static SEED: Lazy<Vec<SeedEntry>> = Lazy::new(|| serde_json::from_slice(SEED_JSON).unwrap());
+
+const ID_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Relative chance of a session starting at each hour of the day
+const HOUR_WEIGHTS: [u32; 24] = [
+    4, 3, 2, 1, 1, 1, 2, 3, 4, 4, 5, 6, 7, 6, 6, 7, 8, 10, 12, 14, 15, 14, 10, 6,
+];
+
+/// Seed entry of real videos used as a base for the demo dataset
+#[derive(Debug, Deserialize)]
+struct SeedEntry {
+    id: String,
+    title: String,
+    channel_id: String,
+    channel_name: String,
+}
+
+/// Longest history generated, about a century
+pub const MAX_DEMO_DAYS: i64 = 36500;
+
+/// DemoOptions
+#[derive(Clone, Debug)]
+pub struct DemoOptions {
+    /// Total watch entries to generate
+    pub entries: usize,
+    /// Number of distinct channels
+    pub channels: usize,
+    /// Days of history, ending now, clamped to `1..=MAX_DEMO_DAYS`
+    pub days: i64,
+    /// Random seed, same seed produce the same dataset
+    pub seed: Option<u64>,
+}
+
+impl Default for DemoOptions {
+    fn default() -> Self {
+        Self {
+            entries: 10_000,
+            channels: 50,
+            days: 5 * 365,
+            seed: None,
+        }
+    }
+}
+
+/// Demo channel with its video pool
+struct DemoChannel {
+    channel: Channel,
+    videos: Vec<(String, String)>,
+    /// Mostly listened to on YouTube Music
+    music: bool,
+}
+
+fn random_id(rng: &mut StdRng, len: usize) -> String {
+    (0..len)
+        .map(|_| ID_ALPHABET[rng.random_range(0..ID_ALPHABET.len())] as char)
+        .collect()
+}
+
+/// Build channel pool, starting from the seed channels and padding it with
+/// made up channels when more are requested
+fn build_channels(rng: &mut StdRng, count: usize) -> Vec<DemoChannel> {
+    let mut channels: Vec<DemoChannel> = Vec::new();
+
+    for s in SEED.iter() {
+        let video = (s.id.clone(), s.title.clone());
+
+        match channels.iter_mut().find(|c| *c.channel.id == *s.channel_id) {
+            Some(c) => c.videos.push(video),
+            None => channels.push(DemoChannel {
+                channel: Channel {
+                    id: s.channel_id.as_str().into(),
+                    name: s.channel_name.as_str().into(),
+                },
+                videos: vec![video],
+                music: channels.len() % 4 == 1,
+            }),
+        }
+    }
+
+    channels.truncate(count.max(1));
+
+    for n in channels.len()..count {
+        let name = format!("Demo Channel {}", n + 1);
+        let videos = (0..rng.random_range(5..40))
+            .map(|k| (random_id(rng, 11), format!("{} - Episode {}", name, k + 1)))
+            .collect();
+
+        channels.push(DemoChannel {
+            channel: Channel {
+                id: format!("UC{}", random_id(rng, 22)).into(),
+                name: name.into(),
+            },
+            videos,
+            music: n % 4 == 1,
+        });
+    }
+
+    // seed channels only have a handful of videos, give them some more
+    for c in channels.iter_mut() {
+        while c.videos.len() < 5 {
+            let title = format!("{} - Episode {}", c.channel.name, c.videos.len() + 1);
+            c.videos.push((random_id(rng, 11), title));
+        }
+    }
+
+    channels
+}
+
+/// Zipf-like weights so a few items dominate, like real watching habits
+fn zipf_weights(len: usize, exponent: f64) -> Vec<f64> {
+    (1..=len).map(|r| 1.0 / (r as f64).powf(exponent)).collect()
+}
+
+/// Generate a realistic fake watch history
+///
+/// Entries are grouped into viewing sessions biased toward evening hours,
+/// channel and video popularity follow a long tail distribution. Returned
+/// entries are ordered by latest first, like Takeout exports
+pub fn generate(options: &DemoOptions) -> Vec<WatchEntry> {
+    let mut rng = match options.seed {
+        Some(s) => StdRng::seed_from_u64(s),
+        None => StdRng::from_os_rng(),
+    };
+
+    let channels = build_channels(&mut rng, options.channels);
+    let channel_dist = WeightedIndex::new(zipf_weights(channels.len(), 1.1)).unwrap();
+    let video_dists = channels
+        .iter()
+        .map(|c| WeightedIndex::new(zipf_weights(c.videos.len(), 1.0)).unwrap())
+        .collect::<Vec<_>>();
+    let hour_dist = WeightedIndex::new(HOUR_WEIGHTS).unwrap();
+
+    let end = Utc::now().trunc_subsecs(3);
+    let start = end - Duration::days(options.days.clamp(1, MAX_DEMO_DAYS));
+    let span = (end - start).num_days().max(1);
+
+    let mut entries = Vec::with_capacity(options.entries);
+
+    while entries.len() < options.entries {
+        let day = start + Duration::days(rng.random_range(0..span));
+        let mut time: DateTime<Utc> = day
+            .with_hour(hour_dist.sample(&mut rng) as u32)
+            .and_then(|t| t.with_minute(rng.random_range(0..60)))
+            .and_then(|t| t.with_second(rng.random_range(0..60)))
+            .unwrap_or(day);
+
+        // sessions usually stick to a channel for a while
+        let mut c = channel_dist.sample(&mut rng);
+        let session_len = rng.random_range(1..=8).min(options.entries - entries.len());
+
+        for _ in 0..session_len {
+            if time > end {
+                break;
+            }
+
+            if rng.random_bool(0.3) {
+                c = channel_dist.sample(&mut rng);
+            }
+
+            let channel = &channels[c];
+            let (id, title) = &channel.videos[video_dists[c].sample(&mut rng)];
+            let product = if channel.music && rng.random_bool(0.8) {
+                "YouTube Music"
+            } else {
+                "YouTube"
+            };
+
+            entries.push(WatchEntry {
+                id: id.clone(),
+                title: title.clone(),
+                channel: channel.channel.clone(),
+                time,
+                products: vec![product.to_owned()],
+                ad: false,
+            });
+
+            time += Duration::seconds(rng.random_range(90..1200));
+        }
+    }
+
+    entries.sort_by_key(|v| std::cmp::Reverse(v.time));
+
+    entries
+}
+
+/// Serialize entries into version 1 JSON structure
+pub fn to_v1_json(entries: &[WatchEntry]) -> serde_json::Value {
+    entries
+        .iter()
+        .map(|e| {
+            let mut v = json!({
+                "header": "YouTube",
+                "title": format!("Watched {}", e.title),
+                "titleUrl": format!("https://www.youtube.com/watch?v={}", e.id),
+                "subtitles": [{
+                    "name": e.channel.name,
+                    "url": format!("https://www.youtube.com/channel/{}", e.channel.id),
+                }],
+                "time": e.time,
+                "products": e.products,
+                "activityControls": ["YouTube watch history"],
+            });
+
+            if e.ad {
+                v["details"] = json!([{ "name": AD_DETAIL }]);
+            }
+
+            v
+        })
+        .collect()
+}
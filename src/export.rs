@@ -0,0 +1,88 @@
+//! Columnar export of watches
+//!
+//! One row per watch rather than per video, the shape data tools expect
+
+use std::{io::Write, sync::Arc};
+
+use anyhow::Result;
+use parquet::{
+    basic::Compression,
+    data_type::{ByteArray, ByteArrayType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+
+use crate::schema::Metadata;
+
+const WATCH_SCHEMA: &str = "
+    message watch {
+        REQUIRED INT64 watched_at (TIMESTAMP(MICROS, true));
+        REQUIRED BYTE_ARRAY video_id (STRING);
+        REQUIRED BYTE_ARRAY title (STRING);
+        REQUIRED BYTE_ARRAY channel_id (STRING);
+        REQUIRED BYTE_ARRAY channel_name (STRING);
+    }
+";
+
+/// Watches per row group, bounds memory on large histories
+const ROW_GROUP_SIZE: usize = 64 * 1024;
+
+/// Write every watch of `data` as Snappy compressed Parquet, oldest first
+pub fn write_parquet<W: Write + Send>(out: W, data: &[Metadata]) -> Result<()> {
+    let schema = Arc::new(parse_message_type(WATCH_SCHEMA)?);
+    let props = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+
+    let mut watches = data
+        .iter()
+        .flat_map(|m| m.watch_timeline.iter().map(move |t| (t, m)))
+        .collect::<Vec<_>>();
+
+    watches.sort_by_key(|(t, _)| **t);
+
+    let mut writer = SerializedFileWriter::new(out, schema, Arc::new(props))?;
+
+    for chunk in watches.chunks(ROW_GROUP_SIZE) {
+        let times = chunk
+            .iter()
+            .map(|(t, _)| t.timestamp_micros())
+            .collect::<Vec<i64>>();
+        let strings: [Vec<ByteArray>; 4] = [
+            chunk.iter().map(|(_, m)| m.id.as_str().into()).collect(),
+            chunk.iter().map(|(_, m)| m.title.as_ref().into()).collect(),
+            chunk
+                .iter()
+                .map(|(_, m)| m.channel.id.as_ref().into())
+                .collect(),
+            chunk
+                .iter()
+                .map(|(_, m)| m.channel.name.as_ref().into())
+                .collect(),
+        ];
+
+        let mut row_group = writer.next_row_group()?;
+
+        if let Some(mut column) = row_group.next_column()? {
+            column
+                .typed::<Int64Type>()
+                .write_batch(&times, None, None)?;
+            column.close()?;
+        }
+
+        for values in &strings {
+            if let Some(mut column) = row_group.next_column()? {
+                column
+                    .typed::<ByteArrayType>()
+                    .write_batch(values, None, None)?;
+                column.close()?;
+            }
+        }
+
+        row_group.close()?;
+    }
+
+    writer.close()?;
+
+    Ok(())
+}
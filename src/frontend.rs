@@ -0,0 +1,94 @@
+//! Links out of the UI
+//!
+//! Invidious and Piped mirror the `/watch?v=` and `/channel/` paths of
+//! YouTube, so a base URL is enough to point links at any of them
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Base URL of YouTube itself
+pub const YOUTUBE: &str = "https://www.youtube.com";
+
+/// ExternalFrontend
+///
+/// Site videos and channels are opened on, e.g. `https://yewtu.be`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternalFrontend {
+    base: String,
+}
+
+impl ExternalFrontend {
+    /// Base URL, without trailing slash
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    /// Host of the base URL, used as link label
+    pub fn host(&self) -> &str {
+        let host = self.base.split_once("://").map_or(&*self.base, |v| v.1);
+
+        host.split('/').next().unwrap_or(host)
+    }
+
+    pub fn watch_url(&self, id: &str) -> String {
+        format!("{}/watch?v={}", self.base, id)
+    }
+
+    pub fn channel_url(&self, id: &str) -> String {
+        format!("{}/channel/{}", self.base, id)
+    }
+}
+
+impl Default for ExternalFrontend {
+    fn default() -> Self {
+        Self {
+            base: YOUTUBE.to_owned(),
+        }
+    }
+}
+
+impl FromStr for ExternalFrontend {
+    type Err = anyhow::Error;
+
+    /// Accepts `http` and `https` URLs without query string or fragment
+    fn from_str(s: &str) -> Result<Self> {
+        let base = s.trim().trim_end_matches('/');
+        let Some((scheme, rest)) = base.split_once("://") else {
+            bail!("invalid external frontend `{}`, expected an http(s) URL", s);
+        };
+
+        if !matches!(scheme, "http" | "https")
+            || rest.is_empty()
+            || rest.starts_with('/')
+            || rest.contains(['?', '#', ' '])
+        {
+            bail!("invalid external frontend `{}`, expected an http(s) URL", s);
+        }
+
+        Ok(Self {
+            base: base.to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for ExternalFrontend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.base)
+    }
+}
+
+impl Serialize for ExternalFrontend {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ExternalFrontend {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
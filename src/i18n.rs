@@ -0,0 +1,158 @@
+//! UI translations
+//!
+//! Catalogs live in `locales/<lang>.ftl` and use the plain message subset
+//! of Fluent: one `key = value` per line, `#` comments and `{ $n }` as the
+//! only placeholder. Missing messages fall back to English
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use anyhow::{Result, bail};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use strum::IntoEnumIterator;
+
+use crate::schema::Order;
+
+static EN: Lazy<Catalog> =
+    Lazy::new(|| Catalog::parse(Lang::En, include_str!("../locales/en.ftl")));
+static DE: Lazy<Catalog> =
+    Lazy::new(|| Catalog::parse(Lang::De, include_str!("../locales/de.ftl")));
+
+/// Lang
+///
+/// Language the UI is available in
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Lang {
+    #[default]
+    En,
+    De,
+}
+
+impl Lang {
+    /// Best supported language of an `Accept-Language` header, `None` when
+    /// none of them is
+    pub fn negotiate(accept_language: &str) -> Option<Self> {
+        let mut ranges = accept_language
+            .split(',')
+            .filter_map(|v| {
+                let mut parts = v.split(';');
+                let tag = parts.next()?.trim();
+                let q = parts
+                    .find_map(|p| p.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.parse::<f32>().ok())?;
+
+                Some((tag, q))
+            })
+            .filter(|(_, q)| *q > 0.0)
+            .collect::<Vec<(&str, f32)>>();
+
+        // stable, equal weights keep the header order
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranges.into_iter().find_map(|(tag, _)| tag.parse().ok())
+    }
+
+    /// Language tag, e.g. for `Content-Language`
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::De => "de",
+        }
+    }
+
+    pub fn catalog(&self) -> &'static Catalog {
+        match self {
+            Self::En => &EN,
+            Self::De => &DE,
+        }
+    }
+}
+
+impl FromStr for Lang {
+    type Err = anyhow::Error;
+
+    /// Accepts tags such as `de` or `de-AT`, only the language is looked at
+    fn from_str(s: &str) -> Result<Self> {
+        let lang = s.split(['-', '_']).next().unwrap_or_default();
+
+        match lang.to_ascii_lowercase().as_str() {
+            "en" => Ok(Self::En),
+            "de" => Ok(Self::De),
+            _ => bail!("unsupported language `{}`, expected en or de", s),
+        }
+    }
+}
+
+impl fmt::Display for Lang {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.tag())
+    }
+}
+
+impl Serialize for Lang {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Lang {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Catalog
+///
+/// Messages of one language
+#[derive(Debug)]
+pub struct Catalog {
+    pub lang: Lang,
+    messages: HashMap<&'static str, &'static str>,
+}
+
+impl Catalog {
+    fn parse(lang: Lang, source: &'static str) -> Self {
+        let messages = source
+            .lines()
+            .map(str::trim)
+            .filter(|v| !v.is_empty() && !v.starts_with('#'))
+            .filter_map(|v| v.split_once('='))
+            .map(|(k, v)| (k.trim(), v.trim()))
+            .collect();
+
+        Self { lang, messages }
+    }
+
+    /// Message of `key`, the English one when missing, the key itself when
+    /// missing in English too
+    pub fn get<'a>(&self, key: &'a str) -> &'a str {
+        self.messages
+            .get(key)
+            .or_else(|| EN.messages.get(key))
+            .copied()
+            .unwrap_or(key)
+    }
+
+    /// Message of `key` with `{ $n }` replaced
+    pub fn get_n(&self, key: &str, n: usize) -> String {
+        self.get(key).replace("{ $n }", &n.to_string())
+    }
+
+    /// Label of an order, see [`Order::to_string_label`]
+    pub fn order_label(&self, order: &Order) -> String {
+        let key = format!("order-{}", order);
+
+        match self.get(&key) {
+            v if v == key => order.to_string_label(),
+            v => v.to_owned(),
+        }
+    }
+
+    /// Orders paired with their translated labels
+    pub fn collect_order_key_label_pair(&self) -> Vec<(String, String)> {
+        Order::iter()
+            .map(|v| (v.to_string(), self.order_label(&v)))
+            .collect()
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::schema::{Channel, MetadataTable, WatchEntry};
+
+/// JournalEntry
+///
+/// Watch reported live, one JSON line of the journal
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JournalEntry {
+    /// Profile the watch belongs to, the default one when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub channel_id: String,
+    pub channel_name: String,
+    pub time: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product: Option<String>,
+}
+
+impl JournalEntry {
+    /// Profile of the entry, `default` standing in when it names none
+    pub fn profile_or<'a>(&'a self, default: Option<&'a str>) -> Option<&'a str> {
+        self.profile.as_deref().or(default)
+    }
+
+    pub fn to_watch_entry(&self) -> WatchEntry {
+        WatchEntry {
+            id: self.id.clone(),
+            title: self.title.clone(),
+            channel: Channel {
+                id: self.channel_id.as_str().into(),
+                name: self.channel_name.as_str().into(),
+            },
+            time: self.time,
+            products: self.product.iter().cloned().collect(),
+            ad: false,
+        }
+    }
+}
+
+/// Journal
+///
+/// Append-only JSONL file of live watches, kept apart from the exported
+/// history so that file is never written to
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+}
+
+impl Journal {
+    /// Open `path` for appending, created when missing. A torn last line is
+    /// cut so the next entry starts on its own line
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open journal `{}`", path.display()))?;
+
+        let text = fs::read(path)?;
+
+        if !text.is_empty() && !text.ends_with(b"\n") {
+            let len = text.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+
+            log::warn!("Cut torn last line of journal `{}`", path.display());
+            file.set_len(len as u64)?;
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one entry, synced to disk before returning
+    pub fn append(&mut self, entry: &JournalEntry) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        self.file
+            .write_all(&line)
+            .and_then(|_| self.file.sync_data())
+            .with_context(|| format!("failed to write journal `{}`", self.path.display()))
+    }
+}
+
+/// Read every entry of the journal at `path`, none when it doesn't exist
+/// yet. A torn last line, left by a crash in the middle of an append, is
+/// skipped
+pub fn read_journal(path: &Path) -> Result<Vec<JournalEntry>> {
+    let text = match fs::read_to_string(path) {
+        Ok(v) => v,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e).with_context(|| format!("failed to read journal `{}`", path.display()));
+        }
+    };
+
+    let lines = text.lines().collect::<Vec<&str>>();
+    let last = lines.iter().rposition(|v| !v.trim().is_empty());
+    let mut entries = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str::<JournalEntry>(line) {
+            Ok(v) => entries.push(v),
+            Err(e) if Some(i) == last && !text.ends_with('\n') => {
+                log::warn!(
+                    "Skipped torn last line of journal `{}`. {}",
+                    path.display(),
+                    e
+                );
+            }
+            Err(e) => bail!(
+                "invalid journal `{}` at line {}. {}",
+                path.display(),
+                i + 1,
+                e
+            ),
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Add the entries of `profile` to a table freshly loaded from an export.
+/// Entries at or before the newest watch of the export are covered by it and
+/// skipped, returns how many were added
+pub fn replay(
+    metadata_table: &mut MetadataTable,
+    entries: &[JournalEntry],
+    profile: &str,
+    default: Option<&str>,
+) -> usize {
+    let newest = metadata_table.watch_timeline_ref().last().copied();
+
+    entries
+        .iter()
+        .filter(|e| e.profile_or(default) == Some(profile))
+        .filter(|e| newest.is_none_or(|t| e.time > t))
+        .filter(|e| metadata_table.insert_entry(e.to_watch_entry()))
+        .count()
+}
+
+/// CompactStats
+///
+/// Outcome of [`compact`]
+#[derive(Debug, Default, Serialize)]
+pub struct CompactStats {
+    pub kept: usize,
+    /// Entries covered by the export of their profile
+    pub exported: usize,
+    /// Repeats of an earlier entry of the journal
+    pub duplicates: usize,
+}
+
+/// Drop entries replay would skip, those covered by the export of their
+/// profile and repeats. Entries of profiles missing from `tables` are kept
+pub fn compact(
+    entries: Vec<JournalEntry>,
+    tables: &[(&str, &MetadataTable)],
+    default: Option<&str>,
+) -> (Vec<JournalEntry>, CompactStats) {
+    let mut stats = CompactStats::default();
+    let mut seen = HashSet::new();
+    let mut kept = Vec::new();
+
+    for e in entries {
+        let profile = e.profile_or(default).map(|v| v.to_owned());
+        let newest = tables
+            .iter()
+            .find(|(name, _)| Some(*name) == profile.as_deref())
+            .and_then(|(_, table)| table.watch_timeline_ref().last().copied());
+
+        if newest.is_some_and(|t| e.time <= t) {
+            stats.exported += 1;
+        } else if !seen.insert((profile, e.id.clone(), e.time)) {
+            stats.duplicates += 1;
+        } else {
+            kept.push(e);
+        }
+    }
+
+    stats.kept = kept.len();
+
+    (kept, stats)
+}
+
+/// Replace the journal at `path` with `entries`, through a temporary file so
+/// a crash never leaves half a journal
+pub fn rewrite_journal(path: &Path, entries: &[JournalEntry]) -> Result<()> {
+    let mut buf = Vec::new();
+
+    for e in entries {
+        serde_json::to_writer(&mut buf, e)?;
+        buf.push(b'\n');
+    }
+
+    let tmp = path.with_extension("jsonl.tmp");
+
+    fs::write(&tmp, buf).with_context(|| format!("failed to write `{}`", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("failed to write `{}`", path.display()))?;
+
+    Ok(())
+}
+
+/// Ingest
+///
+/// Settings of `POST /api/history`
+#[derive(Debug)]
+pub struct Ingest {
+    /// Bearer token expected from clients
+    pub token: String,
+    /// Watches are only kept in memory without a journal
+    pub journal: Option<Journal>,
+}
@@ -1,10 +1,84 @@
+//! YouTube Memories Viewer
+//!
+//! Besides the `ytm` binary, the crate can be embedded in other projects:
+//! loaders in [`schema`], reports in [`analytics`], and the web server through
+//! `Server::builder`. Other history formats can be plugged in by implementing
+//! [`schema::SchemaLoader`] and passing it to [`schema::register_loader`]
+//!
+//! The parsing core (`schema`, `analytics`, `utils`) builds without the
+//! default `server` feature, which makes it usable from `wasm32` targets. The
+//! `wasm` feature adds a `wasm-bindgen` wrapper on top of it, and the `python`
+//! feature a pyo3 module
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! let table = ytm::load_metadata_from_file("watch-history.json".as_ref())?;
+//!
+//! ytm::Server::builder().metadata(table).port(8000).serve().await
+//! # }
+//! ```
+
+pub mod analytics;
+#[cfg(feature = "server")]
+pub mod auth;
+#[cfg(feature = "server")]
+pub mod cli;
+#[cfg(feature = "server")]
 pub mod config;
+#[cfg(feature = "server")]
+pub mod demo;
+#[cfg(feature = "parquet")]
+pub mod export;
+#[cfg(feature = "server")]
+pub mod frontend;
+#[cfg(feature = "server")]
+pub mod i18n;
+#[cfg(feature = "server")]
+pub mod journal;
+#[cfg(feature = "server")]
+pub mod links;
+#[cfg(feature = "server")]
+pub mod logs;
+#[cfg(feature = "mdns")]
+pub mod mdns;
+#[cfg(feature = "server")]
+pub mod pdf;
+#[cfg(feature = "server")]
+pub mod playback;
+pub mod profile;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "server")]
+pub mod reload;
 pub mod schema;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "server")]
 pub mod service;
+#[cfg(feature = "server")]
 pub mod shutdown;
+#[cfg(feature = "server")]
+pub mod snapshot;
+#[cfg(feature = "otlp")]
+pub mod telemetry;
+#[cfg(feature = "server")]
+pub mod transport;
 pub mod utils;
+#[cfg(feature = "server")]
 pub mod vault;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(all(windows, feature = "server"))]
+pub mod winservice;
+#[cfg(feature = "server")]
 pub mod youtube;
 
+pub use schema::{
+    Metadata, MetadataFilter, MetadataTable, WatchEntry, load_metadata_from_file,
+    load_metadata_from_paths,
+};
+#[cfg(feature = "server")]
+pub use server::{Server, ServerBuilder};
+
 pub const IFRAME_API_URL: &str = "https://www.youtube.com/iframe_api";
 pub const LOCAL_WIDGET_API_PATH: &str = "/www-widgetapi.js";
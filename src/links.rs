@@ -0,0 +1,101 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use rand::Rng;
+
+use crate::utils::to_hex_string;
+
+/// Prefix of short link paths, followed by the token
+pub const SHORT_LINK_PREFIX: &str = "/s/";
+
+/// ShortLinks
+///
+/// Tokens pointing at filtered index pages. Kept in memory, and written to
+/// a JSON file after every new link when one is configured
+#[derive(Debug, Default)]
+pub struct ShortLinks {
+    path: Option<PathBuf>,
+    links: BTreeMap<String, String>,
+}
+
+impl ShortLinks {
+    /// Links only living as long as the server
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Links persisted to `path`, read back when the file exists
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let links = match fs::read_to_string(path) {
+            Ok(v) => serde_json::from_str(&v)
+                .with_context(|| format!("invalid short links file `{}`", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("failed to read short links file `{}`", path.display())
+                });
+            }
+        };
+
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            links,
+        })
+    }
+
+    /// Token for `target`, the existing one when it was already shortened
+    pub fn insert(&mut self, target: String) -> Result<String> {
+        if let Some((token, _)) = self.links.iter().find(|(_, v)| **v == target) {
+            return Ok(token.clone());
+        }
+
+        let token = loop {
+            let v = to_hex_string(&rand::rng().random::<[u8; 4]>());
+
+            if !self.links.contains_key(&v) {
+                break v;
+            }
+        };
+
+        self.links.insert(token.clone(), target);
+
+        if let Err(e) = self.save() {
+            self.links.remove(&token);
+
+            return Err(e);
+        }
+
+        Ok(token)
+    }
+
+    pub fn get(&self, token: &str) -> Option<&str> {
+        self.links.get(token).map(|v| v.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.links.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+
+    /// Write through a temporary file so a crash never leaves half a file
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let tmp = path.with_extension("json.tmp");
+
+        fs::write(&tmp, serde_json::to_vec_pretty(&self.links)?)
+            .with_context(|| format!("failed to write `{}`", tmp.display()))?;
+        fs::rename(&tmp, path).with_context(|| format!("failed to write `{}`", path.display()))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+//! In-memory log ring buffer
+//!
+//! Every record passed to `env_logger` is also kept in a bounded buffer so a
+//! headless instance can be inspected on `/logs`
+
+use std::{collections::VecDeque, sync::Mutex};
+
+use chrono::{DateTime, Utc};
+use log::{Level, Log, Metadata, Record};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+/// Maximum records kept in memory
+pub const LOG_CAPACITY: usize = 1000;
+
+static BUFFER: Lazy<Mutex<VecDeque<LogRecord>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(LOG_CAPACITY)));
+
+/// LogRecord
+#[derive(Clone, Debug, Serialize)]
+pub struct LogRecord {
+    pub time: DateTime<Utc>,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+/// RingLogger
+///
+/// `env_logger` wrapper copying records into the ring buffer
+struct RingLogger {
+    inner: env_logger::Logger,
+}
+
+impl Log for RingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.inner.matches(record) {
+            return;
+        }
+
+        let entry = LogRecord {
+            time: Utc::now(),
+            level: record.level(),
+            target: record.target().to_owned(),
+            message: record.args().to_string(),
+        };
+
+        if let Ok(mut buffer) = BUFFER.lock() {
+            if buffer.len() == LOG_CAPACITY {
+                buffer.pop_front();
+            }
+
+            buffer.push_back(entry);
+        }
+
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Install the logger built by `builder`, keeping records in the ring buffer
+pub fn init(mut builder: env_logger::Builder) {
+    let inner = builder.build();
+    let max_level = inner.filter();
+
+    if log::set_boxed_logger(Box::new(RingLogger { inner })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// Most recent records at or above `level`, latest first
+pub fn recent(level: Level, limit: usize) -> Vec<LogRecord> {
+    let Ok(buffer) = BUFFER.lock() else {
+        return Vec::new();
+    };
+
+    buffer
+        .iter()
+        .rev()
+        .filter(|r| r.level <= level)
+        .take(limit)
+        .cloned()
+        .collect()
+}
@@ -1,140 +1,331 @@
-use std::net::SocketAddr;
-use std::sync::Arc;
+use std::{
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    slice,
+};
 
 use anyhow::Result;
+use chrono::Duration;
 use clap::Parser;
 use env_logger::Env;
-use hyper::server::conn::http1;
-use hyper_util::rt::TokioIo;
-use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{Semaphore, broadcast, mpsc};
-use tokio::time::{Duration, sleep};
-use ytm::config::Config;
-use ytm::schema::load_metadata_from_file;
-use ytm::service::ServiceHandler;
-use ytm::shutdown::Shutdown;
-use ytm::vault::Vault;
-use ytm::youtube::load_youtube_components;
-
-const MAX_CONNECTIONS: usize = 250;
-
-/// Listener
-struct Listener {
-    listener: TcpListener,
-    vault: Vault,
-    limit_connection: Arc<Semaphore>,
-    notify_shutdown: broadcast::Sender<()>,
-    shutdown_complete_tx: mpsc::Sender<()>,
+use log::LevelFilter;
+use ytm::cli::{
+    run_check, run_command, run_compact, run_completions, run_demo_output, run_diff, run_export,
+    run_export_file, run_hash_password, run_man, run_query, run_report, run_report_pdf, run_stats,
+    run_top,
+};
+#[cfg(windows)]
+use ytm::config::ServiceAction;
+use ytm::config::{Command, Config};
+use ytm::demo::{DemoOptions, generate};
+use ytm::journal::{JournalEntry, read_journal, replay};
+use ytm::profile::DEFAULT_PROFILE;
+use ytm::schema::{
+    DEFAULT_HALF_LIFE_DAYS, IgnoreList, MetadataTable, TitleRule, load_metadata_from_paths,
+};
+use ytm::server::{DEFAULT_PORT, Server};
+use ytm::snapshot::run_snapshots;
+#[cfg(windows)]
+use ytm::winservice;
+use ytm::{logs, reload};
+
+/// Drop ignored channels and titles when an ignore list is configured
+fn exclude(metadata_table: &mut MetadataTable, list: &IgnoreList) {
+    if list.is_empty() {
+        return;
+    }
+
+    let removed = metadata_table.exclude(list);
+
+    log::info!("Excluded {} ignored watches", removed);
 }
 
-impl Listener {
-    async fn run(&mut self) -> Result<()> {
-        log::debug!("Accepting inbound connections");
-
-        let mut http = http1::Builder::new();
-        http.keep_alive(true);
-
-        loop {
-            let permit = self.limit_connection.clone().acquire_owned().await.unwrap();
-            let mut shutdown = Shutdown::new(self.notify_shutdown.subscribe());
-
-            let socket = self.accept().await?;
-            let io = TokioIo::new(socket);
-
-            let service = ServiceHandler {
-                vault: self.vault.clone(),
-            };
-            let connection = http.serve_connection(io, service);
-
-            tokio::spawn(async move {
-                tokio::select! {
-                    res = connection => {
-                        if let Err(e) = res {
-                            log::error!("failed to serve connection: {:?}", e);
-                        }
-                    }
-                    _ = shutdown.recv() => {}
-                }
+/// Clean up titles when rules are configured
+fn rewrite_titles(metadata_table: &mut MetadataTable, rules: &[TitleRule]) {
+    if rules.is_empty() {
+        return;
+    }
 
-                drop(permit);
-            });
-        }
+    let changed = metadata_table.rewrite_titles(rules);
+
+    log::info!("Rewrote {} titles with {} rules", changed, rules.len());
+}
+
+/// Add the watches of the journal made after the export of `profile`
+fn replay_journal(
+    metadata_table: &mut MetadataTable,
+    journal: &[JournalEntry],
+    profile: &str,
+    default: Option<&str>,
+) {
+    if journal.is_empty() {
+        return;
     }
 
-    async fn accept(&mut self) -> Result<TcpStream> {
-        let mut backoff = 1;
+    let added = replay(metadata_table, journal, profile, default);
 
-        loop {
-            match self.listener.accept().await {
-                Ok((socket, _)) => return Ok(socket),
-                Err(err) => {
-                    if backoff > 64 {
-                        log::error!("failed reconnecting too many times. {:?}", err);
-                        return Err(err.into());
-                    }
-                }
-            };
+    log::info!("Replayed {} journal entries into `{}`", added, profile);
+}
 
-            log::info!("reconnecting in {}s", backoff);
-            sleep(Duration::from_secs(backoff)).await;
+/// Collapse near-duplicate watches when a window is configured
+fn dedupe(metadata_table: &mut MetadataTable, window: Option<u64>) {
+    let Some(window) = window.filter(|v| *v > 0) else {
+        return;
+    };
 
-            backoff *= 2;
-        }
+    let before = metadata_table.total_count_raw();
+    // windows past what a duration holds collapse every repeat anyway
+    let seconds = i64::try_from(window).unwrap_or(i64::MAX);
+    let removed =
+        metadata_table.collapse_repeats(Duration::try_seconds(seconds).unwrap_or(Duration::MAX));
+
+    log::info!(
+        "Collapsed {} repeats within {}s, {} entries before, {} after",
+        removed,
+        window,
+        before,
+        metadata_table.total_count_raw()
+    );
+}
+
+/// Fold possible duplicates when asked to
+fn merge_duplicates(metadata_table: &mut MetadataTable, enabled: bool) {
+    if !enabled {
+        return;
     }
+
+    let merged = metadata_table.merge_duplicates();
+
+    log::info!("Merged {} possible duplicates", merged);
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args = Config::parse();
+
+    // level is capped by `log::set_max_level` instead, so it can be changed
+    // by `-v`/`-q` and on config reload
     let env = Env::default()
-        .filter_or("YTM_LOG_LEVEL", "info")
+        .filter_or("YTM_LOG_LEVEL", "trace")
         .write_style_or("YTM_LOG_STYLE", "always");
+    let mut logger = env_logger::Builder::from_env(env);
 
-    env_logger::init_from_env(env);
+    if args.verbosity().is_some() {
+        logger.filter_level(LevelFilter::Trace);
+    }
 
-    let config = Config::parse();
+    logs::init(logger);
 
-    let (notify_shutdown, _) = broadcast::channel(1);
-    let (shutdown_complete_tx, mut shutdown_complete_rx) = mpsc::channel(1);
+    let config = args.resolve()?;
 
-    log::info!("Preparing files and components...");
+    reload::apply_log_level(&config);
 
-    let metadata_table = load_metadata_from_file(&config.file)?;
-    let youtube = load_youtube_components().await?;
+    let mut demo = config.demo.then(DemoOptions::default);
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], config.port));
-    let listener = TcpListener::bind(addr).await?;
+    if let Some(command) = &config.command {
+        match command {
+            Command::Diff { old, new, format } => {
+                return run_command(|out| run_diff(out, old, new, *format));
+            }
+            Command::Top {
+                kind,
+                file,
+                n,
+                year,
+                format,
+            } => {
+                return run_command(|out| run_top(out, *kind, file, *n, *year, *format));
+            }
+            Command::Stats { file, format } => {
+                return run_command(|out| run_stats(out, file, *format));
+            }
+            Command::Report { file, pdf, format } => match pdf {
+                Some(path) => return run_report_pdf(file, path),
+                None => return run_command(|out| run_report(out, file, *format)),
+            },
+            Command::Query { args, format } => {
+                return run_command(|out| run_query(out, args, *format));
+            }
+            Command::Export {
+                args,
+                format,
+                output,
+            } => match output {
+                Some(path) => return run_export_file(path, args, *format),
+                None => return run_command(|out| run_export(out, args, *format)),
+            },
+            Command::Compact => {
+                return run_command(|out| run_compact(out, &config));
+            }
+            Command::Check {
+                file,
+                strict,
+                format,
+            } => {
+                return run_command(|out| run_check(out, file, *strict, *format));
+            }
+            Command::Completions { shell } => {
+                return run_command(|out| run_completions(out, *shell));
+            }
+            Command::Man => return run_command(run_man),
+            Command::HashPassword => return run_command(run_hash_password),
+            #[cfg(windows)]
+            Command::Service { action } => match action {
+                ServiceAction::Install { name } => {
+                    let Some(path) = &config.config else {
+                        anyhow::bail!("Pass `--config` with the settings the service serves");
+                    };
 
-    log::info!("Listening on http://{}", addr);
+                    return winservice::install(name, path);
+                }
+                ServiceAction::Uninstall { name } => return winservice::uninstall(name),
+                ServiceAction::Run { name } => {
+                    let name = name.clone();
+                    let handle = tokio::runtime::Handle::current();
+
+                    // the dispatcher blocks until the service stops
+                    return tokio::task::block_in_place(|| {
+                        winservice::run(&name, move |stop| {
+                            handle.block_on(serve(args, config, None, stop))
+                        })
+                    });
+                }
+            },
+            Command::Demo(args) => match &args.output {
+                Some(path) => return run_demo_output(path, &args.to_options()),
+                None => demo = Some(args.to_options()),
+            },
+        }
+    }
+
+    serve(args, config, demo, ytm::shutdown::signal()).await
+}
+
+/// Load the histories and serve them until `signal` completes
+async fn serve<F>(args: Config, config: Config, demo: Option<DemoOptions>, signal: F) -> Result<()>
+where
+    F: Future<Output = ()>,
+{
+    log::info!("Preparing files and components...");
 
-    let mut server = Listener {
-        listener,
-        vault: Vault::new(metadata_table, youtube),
-        limit_connection: Arc::new(Semaphore::new(MAX_CONNECTIONS)),
-        notify_shutdown,
-        shutdown_complete_tx,
+    #[cfg(feature = "otlp")]
+    let tracer_provider = match &config.otlp_endpoint {
+        Some(endpoint) => Some(ytm::telemetry::init(endpoint)?),
+        None => None,
     };
 
-    tokio::select! {
-        res = server.run() => {
-            if let Err(e) = res {
-                log::error!("{:?}", e);
-            }
-        }
-        _ = tokio::signal::ctrl_c() => {
-            log::info!("Shutting down, please wait...");
+    let addr = SocketAddr::new(
+        config.addr.unwrap_or(IpAddr::from([127, 0, 0, 1])),
+        config.port.unwrap_or(DEFAULT_PORT),
+    );
+    let journal = match &config.journal {
+        Some(path) => read_journal(path)?,
+        None => Vec::new(),
+    };
+    let default_profile = config.default_profile();
+    let load = |paths: &[PathBuf], profile: Option<&str>| -> Result<MetadataTable> {
+        let mut metadata_table = load_metadata_from_paths(paths, config.lenient)?;
+
+        if let Some(profile) = profile {
+            replay_journal(&mut metadata_table, &journal, profile, default_profile);
         }
+
+        metadata_table.set_collation(config.collation.unwrap_or_default());
+        metadata_table.set_half_life(config.half_life.unwrap_or(DEFAULT_HALF_LIFE_DAYS));
+        exclude(&mut metadata_table, &config.ignore);
+        rewrite_titles(&mut metadata_table, &config.title_rules);
+        dedupe(&mut metadata_table, config.dedupe_window);
+        merge_duplicates(&mut metadata_table, config.merge_duplicates);
+
+        Ok(metadata_table)
+    };
+
+    let mut builder = Server::builder()
+        .addr(addr)
+        .player(!config.no_player)
+        .ui(config.ui.clone());
+
+    if !config.file.is_empty() {
+        builder = builder.metadata(load(&config.file, Some(DEFAULT_PROFILE))?);
     }
 
-    let Listener {
-        notify_shutdown,
-        shutdown_complete_tx,
-        ..
-    } = server;
+    if let Some(options) = &demo {
+        log::info!("Generating demo history with {} entries", options.entries);
 
-    drop(notify_shutdown);
-    drop(shutdown_complete_tx);
+        let mut metadata_table = MetadataTable::from_entries(generate(options));
+        replay_journal(
+            &mut metadata_table,
+            &journal,
+            DEFAULT_PROFILE,
+            default_profile,
+        );
+        metadata_table.set_collation(config.collation.unwrap_or_default());
+        metadata_table.set_half_life(config.half_life.unwrap_or(DEFAULT_HALF_LIFE_DAYS));
+        exclude(&mut metadata_table, &config.ignore);
+        rewrite_titles(&mut metadata_table, &config.title_rules);
+        dedupe(&mut metadata_table, config.dedupe_window);
+        merge_duplicates(&mut metadata_table, config.merge_duplicates);
 
-    let _ = shutdown_complete_rx.recv().await;
+        builder = builder.metadata(metadata_table);
+    }
+
+    for p in &config.profiles {
+        log::debug!("Loading profile `{}`", p.name);
+
+        builder = builder.profile(&p.name, load(slice::from_ref(&p.path), Some(&p.name))?);
+    }
+
+    if let Some(path) = &config.diff_base {
+        builder = builder.diff_base(load(slice::from_ref(path), None)?);
+    }
+
+    for u in &config.users {
+        builder = builder.user(u.to_user(None)?);
+    }
+
+    if let Some(path) = &config.links_file {
+        builder = builder.links_file(path);
+    }
+
+    if let Some(token) = &config.ingest_token {
+        builder = builder.ingest_token(token);
+    }
+
+    if let Some(path) = &config.journal {
+        builder = builder.journal(path);
+    }
+
+    if let Some(key) = &config.prefs_key {
+        builder = builder.prefs_key(key);
+    }
+
+    #[cfg(windows)]
+    if let Some(name) = &config.pipe {
+        builder = builder.pipe(name);
+    }
+
+    #[cfg(feature = "mdns")]
+    if !config.no_mdns {
+        builder = builder.mdns_name(&config.mdns_name);
+    }
+
+    let server = builder.build().await?;
+
+    if let Some(options) = &config.snapshot {
+        tokio::spawn(run_snapshots(options.clone(), server.vault().clone()));
+    }
+
+    if config.config.is_some() {
+        tokio::spawn(reload::watch_config(args, server.vault().clone()));
+    }
+
+    let res = server.serve_with_shutdown(signal).await;
+
+    #[cfg(feature = "otlp")]
+    if let Some(provider) = tracer_provider {
+        let _ = provider.shutdown();
+    }
 
-    Ok(())
+    res
 }
@@ -0,0 +1,70 @@
+//! Zeroconf advertisement
+//!
+//! Announces the server on the local network as an `_http._tcp` service, so
+//! other devices find it as `<name>.local` without knowing its address
+
+use std::net::SocketAddr;
+
+use anyhow::Result;
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+
+const SERVICE_TYPE: &str = "_http._tcp.local.";
+
+/// Advertisement
+///
+/// Registered service, withdrawn when dropped
+pub struct Advertisement {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+impl Advertisement {
+    /// Advertise `addr` as `name`, `None` on loopback addresses which other
+    /// devices can't reach anyway. Unspecified addresses advertise every
+    /// interface
+    pub fn start(name: &str, addr: SocketAddr) -> Result<Option<Self>> {
+        if addr.ip().is_loopback() {
+            return Ok(None);
+        }
+
+        let host = format!("{}.local.", name);
+        let properties = [("path", "/"), ("version", env!("CARGO_PKG_VERSION"))];
+        let info = if addr.ip().is_unspecified() {
+            ServiceInfo::new(SERVICE_TYPE, name, &host, (), addr.port(), &properties[..])?
+                .enable_addr_auto()
+        } else {
+            ServiceInfo::new(
+                SERVICE_TYPE,
+                name,
+                &host,
+                addr.ip(),
+                addr.port(),
+                &properties[..],
+            )?
+        };
+
+        let fullname = info.get_fullname().to_owned();
+        let daemon = ServiceDaemon::new()?;
+
+        daemon.register(info)?;
+
+        log::info!(
+            "Advertising as http://{}:{}",
+            host.trim_end_matches('.'),
+            addr.port()
+        );
+
+        Ok(Some(Self { daemon, fullname }))
+    }
+}
+
+impl Drop for Advertisement {
+    fn drop(&mut self) {
+        // goodbye packets are sent before the daemon stops
+        if let Ok(rx) = self.daemon.unregister(&self.fullname) {
+            let _ = rx.recv_timeout(std::time::Duration::from_secs(1));
+        }
+
+        let _ = self.daemon.shutdown();
+    }
+}
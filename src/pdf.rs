@@ -0,0 +1,273 @@
+//! PDF rendering of the yearly summary
+//!
+//! Text is set in the standard Helvetica font, which every reader ships, so
+//! nothing is embedded. That font only covers Windows-1252, other characters
+//! such as CJK titles print as `?`
+
+use chrono::{DateTime, Local};
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+
+use crate::analytics::years::YearSummary;
+
+/// A4 portrait, in points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 48.0;
+
+const CHART_HEIGHT: f32 = 180.0;
+const ROW_HEIGHT: f32 = 18.0;
+
+const FONT: Name = Name(b"F1");
+const FONT_BOLD: Name = Name(b"F2");
+
+/// Table columns, title, x offset from the margin and maximum characters
+const COLUMNS: [(&str, f32, usize); 7] = [
+    ("Year", 0.0, 4),
+    ("Watches", 40.0, 8),
+    ("Change", 95.0, 8),
+    ("Videos", 150.0, 8),
+    ("Channels", 200.0, 8),
+    ("Busiest Month", 255.0, 18),
+    ("Top Channel", 360.0, 28),
+];
+
+/// Render the summaries, oldest first as returned by
+/// [`year_summaries`](crate::analytics::years::year_summaries)
+pub fn year_report(years: &[YearSummary], generated_at: DateTime<Local>) -> Vec<u8> {
+    let mut pages = vec![Content::new()];
+    let mut y = PAGE_HEIGHT - MARGIN;
+
+    let content = &mut pages[0];
+
+    text(
+        content,
+        FONT_BOLD,
+        18.0,
+        MARGIN,
+        y - 18.0,
+        "YouTube Memories, Years",
+    );
+    y -= 36.0;
+
+    let watch_count = years.iter().map(|v| v.watch_count).sum::<usize>();
+    let subtitle = format!(
+        "{} watches over {} years, generated {}",
+        watch_count,
+        years.len(),
+        generated_at.format("%Y-%m-%d %H:%M")
+    );
+
+    text(content, FONT, 10.0, MARGIN, y, &subtitle);
+    y -= 24.0;
+
+    if years.is_empty() {
+        text(content, FONT, 10.0, MARGIN, y - 10.0, "No watch recorded.");
+    } else {
+        bar_chart(content, years, y);
+        y -= CHART_HEIGHT + 40.0;
+
+        header_row(content, y);
+        y -= ROW_HEIGHT;
+
+        for v in years {
+            if y < MARGIN {
+                pages.push(Content::new());
+                y = PAGE_HEIGHT - MARGIN - ROW_HEIGHT;
+
+                header_row(pages.last_mut().unwrap(), y);
+                y -= ROW_HEIGHT;
+            }
+
+            year_row(pages.last_mut().unwrap(), v, y);
+            y -= ROW_HEIGHT;
+        }
+    }
+
+    write(pages)
+}
+
+/// Watches per year, one bar each
+fn bar_chart(content: &mut Content, years: &[YearSummary], top: f32) {
+    let width = PAGE_WIDTH - 2.0 * MARGIN;
+    let bottom = top - CHART_HEIGHT;
+    let max = years
+        .iter()
+        .map(|v| v.watch_count)
+        .max()
+        .unwrap_or(0)
+        .max(1) as f32;
+    let slot = width / years.len() as f32;
+    let bar = (slot * 0.7).min(48.0);
+
+    content.set_fill_rgb(0.9, 0.18, 0.15);
+
+    for (i, v) in years.iter().enumerate() {
+        let height = (CHART_HEIGHT - 14.0) * v.watch_count as f32 / max;
+        let x = MARGIN + slot * i as f32 + (slot - bar) / 2.0;
+
+        content.rect(x, bottom, bar, height);
+    }
+
+    content.fill_nonzero();
+
+    content
+        .set_stroke_rgb(0.6, 0.6, 0.6)
+        .set_line_width(0.5)
+        .move_to(MARGIN, bottom)
+        .line_to(MARGIN + width, bottom)
+        .stroke();
+
+    content.set_fill_rgb(0.0, 0.0, 0.0);
+
+    for (i, v) in years.iter().enumerate() {
+        let height = (CHART_HEIGHT - 14.0) * v.watch_count as f32 / max;
+        let center = MARGIN + slot * i as f32 + slot / 2.0;
+        let count = v.watch_count.to_string();
+        let year = v.year.to_string();
+
+        text(
+            content,
+            FONT,
+            7.0,
+            center - width_of(&count, 7.0) / 2.0,
+            bottom + height + 3.0,
+            &count,
+        );
+        text(
+            content,
+            FONT,
+            8.0,
+            center - width_of(&year, 8.0) / 2.0,
+            bottom - 12.0,
+            &year,
+        );
+    }
+}
+
+fn header_row(content: &mut Content, y: f32) {
+    for (title, x, _) in COLUMNS {
+        text(content, FONT_BOLD, 9.0, MARGIN + x, y, title);
+    }
+
+    content
+        .set_stroke_rgb(0.6, 0.6, 0.6)
+        .set_line_width(0.5)
+        .move_to(MARGIN, y - 5.0)
+        .line_to(PAGE_WIDTH - MARGIN, y - 5.0)
+        .stroke();
+}
+
+fn year_row(content: &mut Content, v: &YearSummary, y: f32) {
+    let cells = [
+        v.year.to_string(),
+        v.watch_count.to_string(),
+        v.change.map_or("-".to_string(), |c| format!("{:+.1}%", c)),
+        v.video_count.to_string(),
+        v.channel_count.to_string(),
+        v.busiest_month.as_ref().map_or("-".to_string(), |m| {
+            format!("{} ({})", m.name(), m.watch_count)
+        }),
+        v.top_channel.as_ref().map_or("-".to_string(), |c| {
+            format!("{} ({})", c.channel.name, c.watch_count)
+        }),
+    ];
+
+    for ((_, x, max), cell) in COLUMNS.iter().zip(cells) {
+        text(content, FONT, 9.0, MARGIN + x, y, &truncate(&cell, *max));
+    }
+}
+
+fn text(content: &mut Content, font: Name, size: f32, x: f32, y: f32, s: &str) {
+    content
+        .begin_text()
+        .set_font(font, size)
+        .next_line(x, y)
+        .show(Str(&to_win_ansi(s)))
+        .end_text();
+}
+
+/// Rough Helvetica width, good enough to center short numbers
+fn width_of(s: &str, size: f32) -> f32 {
+    s.chars().count() as f32 * size * 0.556
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        return s.to_string();
+    }
+
+    let mut v = s.chars().take(max - 3).collect::<String>();
+    v.push_str("...");
+
+    v
+}
+
+/// Encode to Windows-1252, what `WinAnsiEncoding` expects
+fn to_win_ansi(s: &str) -> Vec<u8> {
+    s.chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{A0}'..='\u{FF}' => c as u8,
+            '€' => 0x80,
+            '‚' => 0x82,
+            '„' => 0x84,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '™' => 0x99,
+            _ => b'?',
+        })
+        .collect()
+}
+
+fn write(pages: Vec<Content>) -> Vec<u8> {
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let font_id = Ref::new(3);
+    let font_bold_id = Ref::new(4);
+    let info_id = Ref::new(5);
+    let first_id = 6;
+
+    let page_ids = (0..pages.len())
+        .map(|i| Ref::new(first_id + 2 * i as i32))
+        .collect::<Vec<Ref>>();
+
+    let mut pdf = Pdf::new();
+
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id)
+        .kids(page_ids.iter().copied())
+        .count(pages.len() as i32);
+    pdf.document_info(info_id)
+        .title(TextStr("YouTube Memories, Years"))
+        .producer(TextStr(concat!("ytm ", env!("CARGO_PKG_VERSION"))));
+
+    pdf.type1_font(font_id)
+        .base_font(Name(b"Helvetica"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.type1_font(font_bold_id)
+        .base_font(Name(b"Helvetica-Bold"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+
+    for (content, page_id) in pages.into_iter().zip(page_ids) {
+        let content_id = Ref::new(page_id.get() + 1);
+        let mut page = pdf.page(page_id);
+
+        page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+            .parent(page_tree_id)
+            .contents(content_id);
+        page.resources()
+            .fonts()
+            .pair(FONT, font_id)
+            .pair(FONT_BOLD, font_bold_id);
+        page.finish();
+
+        pdf.stream(content_id, &content.finish());
+    }
+
+    pdf.finish()
+}
@@ -0,0 +1,76 @@
+//! Player state across page loads
+//!
+//! The index page reports what its embedded player is doing, keyed by a
+//! browser cookie, and picks it up again after a reload or on another page
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Cookie identifying the browser a [`PlayerState`] belongs to
+pub const PLAYER_COOKIE: &str = "ytm_player";
+
+/// Browsers remembered at most, the least recently updated is forgotten first
+const MAX_STATES: usize = 1024;
+
+/// PlayerState
+///
+/// What the embedded player was doing when last reported
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PlayerState {
+    pub video_id: String,
+    /// Seconds into the video
+    pub time: f64,
+    /// Position of the video in the results of `page`, starting at 1
+    #[serde(default)]
+    pub index: Option<usize>,
+    /// Path and query of the page the video was opened from
+    #[serde(default)]
+    pub page: Option<String>,
+    #[serde(default)]
+    pub playing: bool,
+    /// Set by the server
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// PlayerStates
+///
+/// Last [`PlayerState`] of each browser, kept in memory
+#[derive(Debug, Default)]
+pub struct PlayerStates {
+    states: HashMap<String, PlayerState>,
+}
+
+impl PlayerStates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&PlayerState> {
+        self.states.get(id)
+    }
+
+    pub fn insert(&mut self, id: &str, mut state: PlayerState) {
+        state.updated_at = Utc::now();
+
+        if self.states.len() >= MAX_STATES && !self.states.contains_key(id) {
+            let oldest = self
+                .states
+                .iter()
+                .min_by_key(|(_, v)| v.updated_at)
+                .map(|(k, _)| k.clone());
+
+            if let Some(k) = oldest {
+                self.states.remove(&k);
+            }
+        }
+
+        self.states.insert(id.to_owned(), state);
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.states.remove(id);
+    }
+}
@@ -0,0 +1,168 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::schema::{MetadataTable, WatchEntry};
+
+/// Name used for the profile loaded through `--file`
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Profiles
+///
+/// Named collection of metadata tables, the first inserted profile act as the
+/// default one which is served on the root path. Tables are shared so
+/// requests can read a snapshot without holding the lock
+#[derive(Debug, Default)]
+pub struct Profiles {
+    default: Option<String>,
+    tables: BTreeMap<String, Arc<MetadataTable>>,
+    /// Bumped every time a profile's table is replaced, starts at 1
+    versions: BTreeMap<String, u64>,
+}
+
+impl Profiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert profile, returning `false` if the name is already taken
+    pub fn insert(&mut self, name: &str, metadata_table: MetadataTable) -> bool {
+        if self.tables.contains_key(name) {
+            return false;
+        }
+
+        if self.default.is_none() {
+            self.default = Some(name.to_owned());
+        }
+
+        self.tables
+            .insert(name.to_owned(), Arc::new(metadata_table));
+        self.versions.insert(name.to_owned(), 1);
+
+        true
+    }
+
+    /// Swap the table of an existing profile and bump its version. The
+    /// previous table is handed back so it can outlive the swap, `None` and
+    /// nothing replaced when the profile doesn't exist
+    pub fn replace(
+        &mut self,
+        name: &str,
+        metadata_table: MetadataTable,
+    ) -> Option<Arc<MetadataTable>> {
+        let table = self.tables.get_mut(name)?;
+        let previous = std::mem::replace(table, Arc::new(metadata_table));
+
+        *self.versions.entry(name.to_owned()).or_default() += 1;
+
+        Some(previous)
+    }
+
+    /// Add a watch to a profile's table and bump its version when it is new,
+    /// `None` when the profile doesn't exist. The table is copied first when
+    /// a request still reads a snapshot of it
+    pub fn insert_entry(&mut self, name: &str, entry: WatchEntry) -> Option<bool> {
+        let table = self.tables.get_mut(name)?;
+
+        if table.contains_watch(&entry.id, entry.time) {
+            return Some(false);
+        }
+
+        let inserted = Arc::make_mut(table).insert_entry(entry);
+
+        if inserted {
+            *self.versions.entry(name.to_owned()).or_default() += 1;
+        }
+
+        Some(inserted)
+    }
+
+    /// Dataset version of a profile, 0 when it doesn't exist
+    pub fn version(&self, name: &str) -> u64 {
+        self.versions.get(name).copied().unwrap_or_default()
+    }
+
+    pub fn default_name(&self) -> Option<&str> {
+        self.default.as_deref()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.tables.keys().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MetadataTable> {
+        self.tables.get(name).map(|v| &**v)
+    }
+
+    /// Shared handle on a profile's table, unaffected by later changes
+    pub fn snapshot(&self, name: &str) -> Option<Arc<MetadataTable>> {
+        self.tables.get(name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::watch;
+
+    fn table(id: &str) -> MetadataTable {
+        MetadataTable::from_entries([watch(id, "2024-01-01T12:00:00Z")])
+    }
+
+    #[test]
+    fn first_inserted_is_default() {
+        let mut profiles = Profiles::new();
+        assert!(profiles.is_empty());
+        assert_eq!(profiles.default_name(), None);
+
+        assert!(profiles.insert("bob", table("b")));
+        assert!(profiles.insert("alice", table("a")));
+        assert!(!profiles.insert("bob", table("c")));
+
+        assert_eq!(profiles.default_name(), Some("bob"));
+        assert_eq!(profiles.names(), ["alice", "bob"]);
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles.get("bob").unwrap().data()[0].id, "b");
+        assert!(profiles.get("carol").is_none());
+    }
+
+    #[test]
+    fn versions() {
+        let mut profiles = Profiles::new();
+        profiles.insert("alice", table("a"));
+        profiles.insert("bob", table("b"));
+        assert_eq!(profiles.version("alice"), 1);
+        assert_eq!(profiles.version("carol"), 0);
+
+        let snapshot = profiles.snapshot("alice").unwrap();
+        let previous = profiles.replace("alice", table("c")).unwrap();
+        assert!(Arc::ptr_eq(&snapshot, &previous));
+        assert_eq!(profiles.version("alice"), 2);
+        assert_eq!(profiles.version("bob"), 1);
+        assert!(profiles.replace("carol", table("c")).is_none());
+
+        // snapshots are left untouched by new entries
+        let snapshot = profiles.snapshot("bob").unwrap();
+        assert_eq!(
+            profiles.insert_entry("bob", watch("d", "2024-01-02T12:00:00Z")),
+            Some(true)
+        );
+        assert_eq!(
+            profiles.insert_entry("bob", watch("d", "2024-01-02T12:00:00Z")),
+            Some(false)
+        );
+        assert_eq!(
+            profiles.insert_entry("carol", watch("d", "2024-01-02T12:00:00Z")),
+            None
+        );
+        assert_eq!(profiles.version("bob"), 2);
+        assert_eq!(snapshot.data().len(), 1);
+        assert_eq!(profiles.get("bob").unwrap().data().len(), 2);
+    }
+}
@@ -0,0 +1,156 @@
+//! pyo3 module exposing the parser and analytics to Python, values are handed
+//! over as plain `dict`/`list` so they can be fed straight into pandas
+
+use std::path::PathBuf;
+
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyDict, PyString},
+};
+use serde::Serialize;
+
+use crate::{
+    analytics::{
+        diff::diff as diff_tables,
+        quality::{DEFAULT_GAP_DAYS, quality_report},
+    },
+    schema::{MetadataTable, ValidatedFilter, load_metadata_from_file},
+};
+
+fn to_py_err(e: impl std::fmt::Display) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+/// Convert serializable value into Python object through `json.loads`
+fn to_py<'py, T: Serialize>(py: Python<'py>, value: &T) -> PyResult<Bound<'py, PyAny>> {
+    let json = serde_json::to_string(value).map_err(to_py_err)?;
+
+    py.import("json")?.call_method1("loads", (json,))
+}
+
+/// Watch event row
+#[derive(Serialize)]
+struct EventRow<'a> {
+    id: &'a str,
+    title: &'a str,
+    channel_id: &'a str,
+    channel_name: &'a str,
+    time: chrono::DateTime<chrono::Utc>,
+}
+
+/// History
+#[pyclass(module = "ytm")]
+pub struct History {
+    metadata_table: MetadataTable,
+}
+
+#[pymethods]
+impl History {
+    #[getter]
+    fn total_count_raw(&self) -> usize {
+        self.metadata_table.total_count_raw()
+    }
+
+    #[getter]
+    fn total_count(&self) -> usize {
+        self.metadata_table.total_count()
+    }
+
+    /// Filter with the same parameters as the web UI, e.g.
+    /// `query(title="music", order="most_watched", limit=50)`
+    #[pyo3(signature = (**kwargs))]
+    fn query<'py>(
+        &mut self,
+        py: Python<'py>,
+        kwargs: Option<&Bound<'py, PyDict>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let mut pairs: Vec<(String, String)> = Vec::new();
+
+        if let Some(kwargs) = kwargs {
+            for (k, v) in kwargs.iter() {
+                if v.is_none() {
+                    continue;
+                }
+
+                pairs.push((k.extract()?, v.str()?.to_string()));
+            }
+        }
+
+        let query = serde_urlencoded::to_string(&pairs).map_err(to_py_err)?;
+        let filter = ValidatedFilter::parse(&query).map_err(to_py_err)?;
+        let (pagination, data) = self.metadata_table.get_collection(&filter);
+
+        let result = PyDict::new(py);
+        result.set_item("pagination", to_py(py, &pagination)?)?;
+        result.set_item("data", to_py(py, &data)?)?;
+
+        Ok(result.into_any())
+    }
+
+    /// Every aggregated video as a list of dicts
+    fn records<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_py(py, &self.metadata_table.data())
+    }
+
+    /// Every single watch event as a list of dicts
+    fn events<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let rows = self
+            .metadata_table
+            .data()
+            .iter()
+            .flat_map(|m| {
+                m.watch_timeline.iter().map(|t| EventRow {
+                    id: &m.id,
+                    title: &m.title,
+                    channel_id: &m.channel.id,
+                    channel_name: &m.channel.name,
+                    time: *t,
+                })
+            })
+            .collect::<Vec<EventRow>>();
+
+        to_py(py, &rows)
+    }
+
+    /// Coverage and gap report
+    #[pyo3(signature = (gap_days = DEFAULT_GAP_DAYS))]
+    fn quality<'py>(&self, py: Python<'py>, gap_days: i64) -> PyResult<Bound<'py, PyAny>> {
+        to_py(py, &quality_report(&self.metadata_table, gap_days))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "History(total_count_raw={}, total_count={})",
+            self.metadata_table.total_count_raw(),
+            self.metadata_table.total_count()
+        )
+    }
+}
+
+/// Load JSON History File
+#[pyfunction]
+fn load_metadata(path: PathBuf) -> PyResult<History> {
+    let metadata_table = load_metadata_from_file(&path).map_err(to_py_err)?;
+
+    Ok(History { metadata_table })
+}
+
+/// Compare two histories on watch event level
+#[pyfunction]
+fn diff<'py>(py: Python<'py>, old: &History, new: &History) -> PyResult<Bound<'py, PyAny>> {
+    to_py(py, &diff_tables(&old.metadata_table, &new.metadata_table))
+}
+
+#[pymodule]
+fn ytm(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add(
+        "__version__",
+        PyString::new(m.py(), env!("CARGO_PKG_VERSION")),
+    )?;
+    m.add_class::<History>()?;
+    m.add_function(wrap_pyfunction!(load_metadata, m)?)?;
+    m.add_function(wrap_pyfunction!(diff, m)?)?;
+
+    Ok(())
+}
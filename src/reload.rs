@@ -0,0 +1,251 @@
+//! Config file hot-reload
+//!
+//! The config file is checked for changes every few seconds, or right away on
+//! `SIGHUP`. Only runtime settings are applied, changes to startup settings
+//! like history paths or the listening address need a restart
+
+use std::{env, fs, path::Path, time::SystemTime};
+
+use anyhow::Result;
+use log::LevelFilter;
+use tokio::time::{Duration, sleep};
+
+use crate::{auth::User, config::Config, vault::Vault};
+
+/// How often the config file modification time is checked
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[cfg(unix)]
+type Hangup = tokio::signal::unix::Signal;
+
+#[cfg(not(unix))]
+type Hangup = ();
+
+/// Apply log level from `-v`/`-q`, or from the config unless `YTM_LOG_LEVEL`
+/// is set
+pub fn apply_log_level(config: &Config) {
+    if let Some(level) = config.verbosity() {
+        log::set_max_level(level);
+    } else if env::var_os("YTM_LOG_LEVEL").is_none() {
+        log::set_max_level(config.log_level.unwrap_or(LevelFilter::Info));
+    }
+}
+
+/// Apply runtime settings to the running server
+pub fn apply(vault: &Vault, config: &Config) {
+    apply_log_level(config);
+
+    let mut state = vault.state.lock().unwrap();
+
+    state.ui = config.ui.clone();
+
+    let users = config
+        .users
+        .iter()
+        .map(|u| u.to_user(state.auth.get(&u.name)))
+        .collect::<Result<Vec<User>>>();
+
+    match users {
+        Ok(v) => state.auth.set_users(v),
+        Err(e) => log::error!("failed to apply users, keeping the current ones. {:?}", e),
+    }
+}
+
+/// Watch the config file of `args` and apply its runtime settings on change
+pub async fn watch_config(args: Config, vault: Vault) {
+    let Some(path) = args.config.clone() else {
+        return;
+    };
+
+    let mut current = match args.resolve() {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+    let mut modified = modified_at(&path);
+    let mut hangup = hangup_signal();
+
+    loop {
+        let forced = tokio::select! {
+            _ = sleep(POLL_INTERVAL) => false,
+            _ = recv_hangup(&mut hangup) => true,
+        };
+
+        let m = modified_at(&path);
+
+        if !forced && m == modified {
+            continue;
+        }
+
+        modified = m;
+
+        let next = match args.resolve() {
+            Ok(v) => v,
+            Err(e) => {
+                log::error!("failed to reload config, keeping the current one. {:?}", e);
+                continue;
+            }
+        };
+
+        if needs_restart(&next, &current) {
+            log::warn!(
+                "history paths, load settings, address or player changed, restart to apply them"
+            );
+        }
+
+        apply(&vault, &next);
+        current = next;
+
+        log::info!("Config reloaded from {}", path.display());
+    }
+}
+
+/// Whether startup settings differ, they are only applied on restart
+fn needs_restart(next: &Config, current: &Config) -> bool {
+    next.file != current.file
+        || next.diff_base != current.diff_base
+        || next.dedupe_window != current.dedupe_window
+        || next.merge_duplicates != current.merge_duplicates
+        || next.lenient != current.lenient
+        || next.collation != current.collation
+        || next.half_life != current.half_life
+        || next.links_file != current.links_file
+        || next.ingest_token != current.ingest_token
+        || next.journal != current.journal
+        || next.prefs_key != current.prefs_key
+        || next.title_rules != current.title_rules
+        || next.ignore != current.ignore
+        || next.snapshot != current.snapshot
+        || next.profiles != current.profiles
+        || next.addr != current.addr
+        || next.port != current.port
+        || next.no_player != current.no_player
+        || mdns_changed(next, current)
+        || pipe_changed(next, current)
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(unix)]
+fn hangup_signal() -> Option<Hangup> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    signal(SignalKind::hangup()).ok()
+}
+
+#[cfg(not(unix))]
+fn hangup_signal() -> Option<Hangup> {
+    None
+}
+
+#[cfg(unix)]
+async fn recv_hangup(hangup: &mut Option<Hangup>) {
+    match hangup {
+        Some(s) => {
+            s.recv().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(not(unix))]
+async fn recv_hangup(_: &mut Option<Hangup>) {
+    std::future::pending().await
+}
+
+#[cfg(feature = "mdns")]
+fn mdns_changed(next: &Config, current: &Config) -> bool {
+    next.no_mdns != current.no_mdns || next.mdns_name != current.mdns_name
+}
+
+#[cfg(not(feature = "mdns"))]
+fn mdns_changed(_: &Config, _: &Config) -> bool {
+    false
+}
+
+#[cfg(windows)]
+fn pipe_changed(next: &Config, current: &Config) -> bool {
+    next.pipe != current.pipe
+}
+
+#[cfg(not(windows))]
+fn pipe_changed(_: &Config, _: &Config) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::{
+        auth::{Auth, hash_password},
+        links::ShortLinks,
+        profile::Profiles,
+        service::UiOptions,
+    };
+
+    fn config(args: &[&str]) -> Config {
+        let args = ["ytm", "--demo"].iter().chain(args);
+
+        Config::try_parse_from(args).unwrap().resolve().unwrap()
+    }
+
+    #[test]
+    fn startup_settings() {
+        let current = config(&["--port", "8000"]);
+
+        assert!(!needs_restart(&config(&["--port", "8000"]), &current));
+        assert!(needs_restart(&config(&["--port", "8001"]), &current));
+        assert!(needs_restart(
+            &config(&["--port", "8000", "--dedupe-window", "60"]),
+            &current
+        ));
+        assert!(needs_restart(
+            &config(&["--port", "8000", "--ingest-token", "t"]),
+            &current
+        ));
+
+        // runtime settings are applied in place
+        let mut next = config(&["--port", "8000", "--user", "alice:secret", "--lang", "de"]);
+        next.log_level = Some(LevelFilter::Debug);
+        next.ui.limit = 50;
+        assert!(!needs_restart(&next, &current));
+    }
+
+    #[test]
+    fn apply_runtime_settings() {
+        let alice = User::new("alice".to_owned(), hash_password("secret").unwrap(), None).unwrap();
+        let bob = User::new("bob".to_owned(), hash_password("secret").unwrap(), None).unwrap();
+        let vault = Vault::new(
+            Profiles::new(),
+            Auth::new(vec![alice, bob]),
+            None,
+            None,
+            UiOptions::default(),
+            ShortLinks::new(),
+            None,
+        );
+        let (alice_token, bob_token) = {
+            let auth = &mut vault.state.lock().unwrap().auth;
+
+            (
+                auth.login("alice", "secret").unwrap(),
+                auth.login("bob", "secret").unwrap(),
+            )
+        };
+
+        let mut next = config(&["--user", "alice:secret", "--user", "carol:secret"]);
+        next.ui.limit = 50;
+        apply(&vault, &next);
+
+        let mut state = vault.state.lock().unwrap();
+        assert_eq!(state.ui.limit, 50);
+
+        // unchanged users keep their sessions, removed ones are logged out
+        assert!(state.auth.user(&alice_token).is_some());
+        assert!(state.auth.user(&bob_token).is_none());
+        assert!(state.auth.login("carol", "secret").is_some());
+    }
+}
@@ -0,0 +1,51 @@
+use std::io::{Cursor, Read, Seek};
+
+use anyhow::{Result, bail};
+use zip::ZipArchive;
+
+use super::{Decoded, detect_loader};
+
+/// Names of the watch history in English Takeout archives
+const HISTORY_NAMES: [&str; 2] = ["watch-history.json", "watch-history.html"];
+
+/// Read the watch history out of a Takeout zip archive, along with its path
+/// in the archive. Files named like the English export are taken first,
+/// other JSON and HTML files are probed with the registered loaders so
+/// localized exports are found too
+pub fn read_history_from_zip<R: Read + Seek>(rdr: R) -> Result<(String, Vec<u8>)> {
+    match find_history_in_zip(rdr)? {
+        Some(v) => Ok(v),
+        None => bail!("No watch history found in the archive"),
+    }
+}
+
+/// Like [`read_history_from_zip`], `None` when no history is found
+pub(crate) fn find_history_in_zip<R: Read + Seek>(rdr: R) -> Result<Option<(String, Vec<u8>)>> {
+    let mut archive = ZipArchive::new(rdr)?;
+    let names = archive
+        .file_names()
+        .map(|v| v.map(|v| v.into_owned()))
+        .collect::<Result<Vec<String>, _>>()?;
+    let file_name = |name: &str| name.rsplit('/').next().unwrap_or(name).to_lowercase();
+
+    let named = names
+        .iter()
+        .filter(|v| HISTORY_NAMES.contains(&file_name(v).as_str()));
+    let others = names.iter().filter(|v| {
+        let name = file_name(v);
+
+        !HISTORY_NAMES.contains(&name.as_str())
+            && (name.ends_with(".json") || name.ends_with(".html"))
+    });
+
+    for name in named.chain(others) {
+        let mut bytes = Vec::new();
+        archive.by_name(name)?.read_to_end(&mut bytes)?;
+
+        if detect_loader(&mut Decoded::new(Cursor::new(&bytes))?)?.is_some() {
+            return Ok(Some((name.clone(), bytes)));
+        }
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,161 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{Result, bail};
+use icu_normalizer::{
+    DecomposingNormalizerBorrowed, properties::CanonicalCombiningClassMapBorrowed,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Collation
+///
+/// Language specific ordering of strings used by the alphabetical orders.
+/// Letters compare by their base form first, then accents, then case, so
+/// `Ärger` sorts next to `arg` rather than after `z`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Collation {
+    /// Language neutral order, also used for languages without tailoring
+    #[default]
+    Root,
+    /// `sv` and `fi`, `å`, `ä` and `ö` are letters of their own after `z`
+    Swedish,
+    /// `da`, `nb`, `nn` and `no`, `æ`, `ø` and `å` are letters of their own
+    /// after `z`
+    Danish,
+}
+
+/// CollationKey
+///
+/// Sort key of a string, compared level by level
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CollationKey {
+    /// Base letters, case and accents removed
+    primary: Vec<u32>,
+    /// Accents, each base letter is followed by its marks
+    secondary: Vec<char>,
+    /// Lower case before upper case, hiragana before katakana
+    tertiary: Vec<u8>,
+}
+
+impl Collation {
+    /// Sort key of `s`, full-width forms and ligatures are folded to their
+    /// plain counterparts and katakana to hiragana
+    pub fn key(&self, s: &str) -> CollationKey {
+        let nfkd = DecomposingNormalizerBorrowed::new_nfkd();
+        let ccc = CanonicalCombiningClassMapBorrowed::new();
+
+        let mut key = CollationKey {
+            primary: Vec::with_capacity(s.len()),
+            secondary: Vec::with_capacity(s.len()),
+            tertiary: Vec::with_capacity(s.len()),
+        };
+        let mut buf = [0; 4];
+
+        for c in s.chars() {
+            let upper = c.is_uppercase() as u8;
+
+            if let Some(tailored) = self.tailored(c) {
+                key.push(tailored, upper);
+                continue;
+            }
+
+            if c == 'ß' {
+                key.push(weight('s'), 0);
+                key.push(weight('s'), 0);
+                continue;
+            }
+
+            for d in nfkd.normalize(c.encode_utf8(&mut buf)).chars() {
+                if ccc.get_u8(d) != 0 {
+                    key.secondary.push(d);
+                } else if ('\u{30A1}'..='\u{30F6}').contains(&d) {
+                    key.push(weight(d) - (0x60 << 2), 1);
+                } else {
+                    let lower = d.to_lowercase().next().unwrap_or(d);
+
+                    key.push(weight(lower), upper.max(d.is_uppercase() as u8));
+                }
+            }
+        }
+
+        key
+    }
+
+    /// Letters sorted after `z`, in order
+    fn tailored(&self, c: char) -> Option<u32> {
+        let letters: &[&[char]] = match self {
+            Self::Root => return None,
+            Self::Swedish => &[&['å', 'Å'], &['ä', 'Ä', 'æ', 'Æ'], &['ö', 'Ö', 'ø', 'Ø']],
+            Self::Danish => &[&['æ', 'Æ', 'ä', 'Ä'], &['ø', 'Ø', 'ö', 'Ö'], &['å', 'Å']],
+        };
+
+        letters
+            .iter()
+            .position(|v| v.contains(&c))
+            .map(|i| weight('z') + 1 + i as u32)
+    }
+}
+
+impl CollationKey {
+    fn push(&mut self, primary: u32, tertiary: u8) {
+        self.primary.push(primary);
+        self.secondary.push('\0');
+        self.tertiary.push(tertiary);
+    }
+}
+
+/// Primary weight of a plain character, leaves room for tailored letters
+/// right after it
+fn weight(c: char) -> u32 {
+    (c as u32) << 2
+}
+
+impl FromStr for Collation {
+    type Err = anyhow::Error;
+
+    /// Accepts locale tags such as `sv` or `de-DE`, only the language is
+    /// looked at
+    fn from_str(s: &str) -> Result<Self> {
+        let lang = s
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match lang.as_str() {
+            "sv" | "fi" => Ok(Self::Swedish),
+            "da" | "nb" | "nn" | "no" => Ok(Self::Danish),
+            "root" | "und" => Ok(Self::Root),
+            v if (2..=3).contains(&v.len()) && v.bytes().all(|b| b.is_ascii_lowercase()) => {
+                Ok(Self::Root)
+            }
+            _ => bail!(
+                "invalid locale `{}`, expected a tag such as `de` or `sv-SE`",
+                s
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Collation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Root => f.write_str("root"),
+            Self::Swedish => f.write_str("sv"),
+            Self::Danish => f.write_str("da"),
+        }
+    }
+}
+
+impl Serialize for Collation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Collation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,232 @@
+use std::{
+    fmt,
+    io::{self, BufRead, Cursor, Read, Seek, SeekFrom},
+};
+
+use anyhow::{Result, bail};
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+/// Encoding
+///
+/// Text encoding of an export, sniffed from its first bytes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    /// Sniff from the BOM, or from the zero bytes around the leading ASCII
+    /// character every JSON document starts with
+    pub fn sniff(prefix: &[u8]) -> Self {
+        match prefix {
+            [0xEF, 0xBB, 0xBF, ..] => Self::Utf8Bom,
+            [0xFF, 0xFE, ..] => Self::Utf16Le,
+            [0xFE, 0xFF, ..] => Self::Utf16Be,
+            [a, 0, ..] if a.is_ascii() && *a != 0 => Self::Utf16Le,
+            [0, b, ..] if b.is_ascii() && *b != 0 => Self::Utf16Be,
+            _ => Self::Utf8,
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf8Bom => "UTF-8 with BOM",
+            Self::Utf16Le => "UTF-16 LE",
+            Self::Utf16Be => "UTF-16 BE",
+        };
+
+        f.write_str(s)
+    }
+}
+
+/// Decoded
+///
+/// Reader yielding UTF-8 without BOM. UTF-8 is passed through, skipping the
+/// BOM, UTF-16 is transcoded in memory
+pub enum Decoded<R> {
+    Utf8(R),
+    Utf8Bom(R),
+    Transcoded(Cursor<Vec<u8>>),
+}
+
+impl<R: BufRead + Seek> Decoded<R> {
+    pub fn new(mut rdr: R) -> Result<Self> {
+        let encoding = Encoding::sniff(rdr.fill_buf()?);
+
+        match encoding {
+            Encoding::Utf8 => Ok(Self::Utf8(rdr)),
+            Encoding::Utf8Bom => {
+                rdr.seek(SeekFrom::Start(UTF8_BOM.len() as u64))?;
+
+                Ok(Self::Utf8Bom(rdr))
+            }
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                log::info!("Transcoding {} file to UTF-8", encoding);
+
+                let mut bytes = Vec::new();
+                rdr.read_to_end(&mut bytes)?;
+
+                Ok(Self::Transcoded(Cursor::new(
+                    transcode_utf16(&bytes, encoding)?.into_bytes(),
+                )))
+            }
+        }
+    }
+}
+
+fn transcode_utf16(bytes: &[u8], encoding: Encoding) -> Result<String> {
+    if !bytes.len().is_multiple_of(2) {
+        bail!("Invalid UTF-16 file, odd number of bytes");
+    }
+
+    let units = bytes.chunks_exact(2).map(|v| match encoding {
+        Encoding::Utf16Be => u16::from_be_bytes([v[0], v[1]]),
+        _ => u16::from_le_bytes([v[0], v[1]]),
+    });
+
+    let mut s = String::with_capacity(bytes.len() / 2);
+
+    for c in char::decode_utf16(units) {
+        match c {
+            Ok(c) => s.push(c),
+            Err(e) => bail!("Invalid UTF-16 file. {}", e),
+        }
+    }
+
+    Ok(s.strip_prefix('\u{FEFF}').map(str::to_owned).unwrap_or(s))
+}
+
+impl<R: Read> Read for Decoded<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Utf8(r) | Self::Utf8Bom(r) => r.read(buf),
+            Self::Transcoded(r) => r.read(buf),
+        }
+    }
+}
+
+impl<R: BufRead> BufRead for Decoded<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self {
+            Self::Utf8(r) | Self::Utf8Bom(r) => r.fill_buf(),
+            Self::Transcoded(r) => r.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self {
+            Self::Utf8(r) | Self::Utf8Bom(r) => r.consume(amt),
+            Self::Transcoded(r) => r.consume(amt),
+        }
+    }
+}
+
+impl<R: Seek> Seek for Decoded<R> {
+    /// Positions of a BOM prefixed file are relative to the end of the BOM
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let bom = UTF8_BOM.len() as u64;
+
+        match self {
+            Self::Utf8(r) => r.seek(pos),
+            Self::Utf8Bom(r) => {
+                let pos = match pos {
+                    SeekFrom::Start(n) => SeekFrom::Start(n + bom),
+                    v => v,
+                };
+
+                Ok(r.seek(pos)?.saturating_sub(bom))
+            }
+            Self::Transcoded(r) => r.seek(pos),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON: &str = r#"[{"title": "Watched Käse 🧀"}]"#;
+
+    fn utf16(s: &str, encoding: Encoding, bom: bool) -> Vec<u8> {
+        let text = bom.then_some('\u{FEFF}').into_iter().chain(s.chars());
+
+        text.collect::<String>()
+            .encode_utf16()
+            .flat_map(|v| match encoding {
+                Encoding::Utf16Be => v.to_be_bytes(),
+                _ => v.to_le_bytes(),
+            })
+            .collect()
+    }
+
+    fn decode(bytes: Vec<u8>) -> Result<String> {
+        let mut s = String::new();
+        Decoded::new(Cursor::new(bytes))?.read_to_string(&mut s)?;
+
+        Ok(s)
+    }
+
+    #[test]
+    fn sniff() {
+        assert_eq!(Encoding::sniff(JSON.as_bytes()), Encoding::Utf8);
+        assert_eq!(Encoding::sniff(b""), Encoding::Utf8);
+        assert_eq!(Encoding::sniff(b"\xEF\xBB\xBF["), Encoding::Utf8Bom);
+        assert_eq!(Encoding::sniff(b"\xFF\xFE[\0"), Encoding::Utf16Le);
+        assert_eq!(Encoding::sniff(b"\xFE\xFF\0["), Encoding::Utf16Be);
+        assert_eq!(Encoding::sniff(b"[\0{\0"), Encoding::Utf16Le);
+        assert_eq!(Encoding::sniff(b"\0[\0{"), Encoding::Utf16Be);
+        assert_eq!(Encoding::sniff(b"\0\0"), Encoding::Utf8);
+    }
+
+    #[test]
+    fn utf8() {
+        assert_eq!(decode(JSON.as_bytes().to_vec()).unwrap(), JSON);
+
+        let with_bom = [UTF8_BOM, JSON.as_bytes()].concat();
+        assert_eq!(decode(with_bom).unwrap(), JSON);
+    }
+
+    #[test]
+    fn utf16_with_and_without_bom() {
+        for encoding in [Encoding::Utf16Le, Encoding::Utf16Be] {
+            for bom in [true, false] {
+                let bytes = utf16(JSON, encoding, bom);
+
+                assert_eq!(Encoding::sniff(&bytes), encoding, "{encoding} {bom}");
+                assert_eq!(decode(bytes).unwrap(), JSON, "{encoding} {bom}");
+            }
+        }
+    }
+
+    #[test]
+    fn bom_relative_seek() {
+        let with_bom = [UTF8_BOM, JSON.as_bytes()].concat();
+        let mut decoded = Decoded::new(Cursor::new(with_bom)).unwrap();
+
+        assert_eq!(decoded.stream_position().unwrap(), 0);
+        assert_eq!(decoded.seek(SeekFrom::Start(2)).unwrap(), 2);
+
+        let mut s = String::new();
+        decoded.read_to_string(&mut s).unwrap();
+        assert_eq!(s, JSON[2..]);
+    }
+
+    #[test]
+    fn invalid_utf16() {
+        let mut odd = utf16(JSON, Encoding::Utf16Le, true);
+        odd.push(b'\n');
+        assert!(decode(odd).is_err());
+
+        // lone high surrogate
+        let mut unpaired = utf16("[", Encoding::Utf16Le, false);
+        unpaired.extend(0xD800u16.to_le_bytes());
+        assert!(decode(unpaired).is_err());
+    }
+}
@@ -0,0 +1,762 @@
+use std::{borrow::Cow, cmp::Ordering, fmt, str::FromStr};
+
+use anyhow::{Result, bail};
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::Metadata;
+
+/// Expr
+///
+/// Filter expression evaluated against each [`Metadata`], e.g.
+/// `watch_count > 3 && channel.name.contains("music") && watched_at.year() == 2021`
+///
+/// Fields are `id`, `title`, `channel.id`, `channel.name`, `watched_at` (first
+/// watch), `last_watched_at` and `watch_count`. Strings have `contains`, `starts_with`, `ends_with`,
+/// `to_lowercase`, `to_uppercase` and `len`, times have `year`, `month`,
+/// `day`, `hour` and `weekday` (Monday is 1) in local time. Times compare
+/// against RFC 3339 or `YYYY-MM-DD` strings. Expressions are type checked
+/// when parsed
+#[derive(Clone, Debug)]
+pub struct Expr {
+    source: String,
+    node: Node,
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            depth: 0,
+        };
+        let (node, ty) = parser.or()?;
+
+        if let Some(t) = parser.tokens.get(parser.pos) {
+            bail!("unexpected `{}`", t);
+        }
+
+        if ty != Type::Bool {
+            bail!("expression must be a boolean, found {}", ty);
+        }
+
+        Ok(Self {
+            source: source.to_owned(),
+            node,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn matches(&self, m: &Metadata) -> bool {
+        is_true(&self.node, m)
+    }
+}
+
+impl FromStr for Expr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl Serialize for Expr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for Expr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+
+        Self::parse(&s).map_err(|e| serde::de::Error::custom(format!("invalid expr: {}", e)))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Type {
+    Str,
+    Int,
+    Bool,
+    Time,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Str => "string",
+            Self::Int => "number",
+            Self::Bool => "boolean",
+            Self::Time => "time",
+        };
+
+        f.write_str(s)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Field {
+    Id,
+    Title,
+    ChannelId,
+    ChannelName,
+    WatchedAt,
+    LastWatchedAt,
+    WatchCount,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Method {
+    Contains,
+    StartsWith,
+    EndsWith,
+    ToLowercase,
+    ToUppercase,
+    Len,
+    Year,
+    Month,
+    Day,
+    Hour,
+    Weekday,
+}
+
+impl Method {
+    /// Receiver type, argument type and return type
+    fn signature(&self) -> (Type, Option<Type>, Type) {
+        match self {
+            Self::Contains | Self::StartsWith | Self::EndsWith => {
+                (Type::Str, Some(Type::Str), Type::Bool)
+            }
+            Self::ToLowercase | Self::ToUppercase => (Type::Str, None, Type::Str),
+            Self::Len => (Type::Str, None, Type::Int),
+            Self::Year | Self::Month | Self::Day | Self::Hour | Self::Weekday => {
+                (Type::Time, None, Type::Int)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Clone, Debug)]
+enum Literal {
+    Str(String),
+    Int(i64),
+    Bool(bool),
+    Time(DateTime<Utc>),
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Literal(Literal),
+    Field(Field),
+    Method(Method, Box<Node>, Option<Box<Node>>),
+    Cmp(CmpOp, Box<Node>, Box<Node>),
+    Not(Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Int(i64),
+    Op(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ident(v) => f.write_str(v),
+            Self::Str(v) => write!(f, "{:?}", v),
+            Self::Int(v) => write!(f, "{}", v),
+            Self::Op(v) => f.write_str(v),
+        }
+    }
+}
+
+/// Longest expression accepted, in tokens
+const MAX_TOKENS: usize = 1000;
+
+/// Deepest nesting of `!`, parentheses and method arguments, the parser and
+/// the evaluator recurse once per level
+const MAX_DEPTH: usize = 64;
+
+const OPS: [&str; 13] = [
+    "&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "(", ")", ".", "-",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(i, c)) = chars.peek() {
+        if tokens.len() > MAX_TOKENS {
+            bail!("expression longer than {} tokens", MAX_TOKENS);
+        }
+
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut ident = String::new();
+
+            while let Some(&(_, c)) = chars
+                .peek()
+                .filter(|(_, c)| c.is_ascii_alphanumeric() || *c == '_')
+            {
+                ident.push(c);
+                chars.next();
+            }
+
+            tokens.push(Token::Ident(ident));
+        } else if c.is_ascii_digit() {
+            let mut digits = String::new();
+
+            while let Some(&(_, c)) = chars.peek().filter(|(_, c)| c.is_ascii_digit()) {
+                digits.push(c);
+                chars.next();
+            }
+
+            tokens.push(Token::Int(digits.parse()?));
+        } else if c == '"' || c == '\'' {
+            chars.next();
+            let mut s = String::new();
+
+            loop {
+                match chars.next() {
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, v)) => s.push(v),
+                        None => bail!("unterminated string"),
+                    },
+                    Some((_, v)) if v == c => break,
+                    Some((_, v)) => s.push(v),
+                    None => bail!("unterminated string"),
+                }
+            }
+
+            tokens.push(Token::Str(s));
+        } else {
+            match OPS.iter().find(|op| source[i..].starts_with(*op)) {
+                Some(op) => {
+                    for _ in 0..op.len() {
+                        chars.next();
+                    }
+
+                    tokens.push(Token::Op(op));
+                }
+                None => bail!("unexpected `{}`", c),
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parse a time literal, RFC 3339 or a local date
+fn parse_time(s: &str) -> Option<DateTime<Utc>> {
+    if let Ok(v) = DateTime::parse_from_rfc3339(s) {
+        return Some(v.with_timezone(&Utc));
+    }
+
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?;
+
+    Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()
+        .map(|v| v.with_timezone(&Utc))
+}
+
+/// Recursive descent parser, type checking while building the tree
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Current nesting, see [`MAX_DEPTH`]
+    depth: usize,
+}
+
+impl Parser {
+    /// Run `f` one nesting level deeper, refused past [`MAX_DEPTH`]
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= MAX_DEPTH {
+            bail!("expression nested deeper than {} levels", MAX_DEPTH);
+        }
+
+        self.depth += 1;
+        let value = f(self);
+        self.depth -= 1;
+
+        value
+    }
+
+    fn peek_op(&self, op: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Op(v)) if *v == op)
+    }
+
+    fn eat_op(&mut self, op: &str) -> bool {
+        let found = self.peek_op(op);
+
+        if found {
+            self.pos += 1;
+        }
+
+        found
+    }
+
+    fn expect_op(&mut self, op: &str) -> Result<()> {
+        if !self.eat_op(op) {
+            match self.tokens.get(self.pos) {
+                Some(t) => bail!("expected `{}`, found `{}`", op, t),
+                None => bail!("expected `{}`, found end of expression", op),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let Some(t) = self.tokens.get(self.pos).cloned() else {
+            bail!("unexpected end of expression");
+        };
+
+        self.pos += 1;
+
+        Ok(t)
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Ident(v) => Ok(v),
+            t => bail!("expected a name, found `{}`", t),
+        }
+    }
+
+    fn or(&mut self) -> Result<(Node, Type)> {
+        let mut left = self.and()?;
+
+        while self.eat_op("||") {
+            let right = self.and()?;
+            expect_bool(&left.1, "||")?;
+            expect_bool(&right.1, "||")?;
+
+            left = (Node::Or(Box::new(left.0), Box::new(right.0)), Type::Bool);
+        }
+
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<(Node, Type)> {
+        let mut left = self.not()?;
+
+        while self.eat_op("&&") {
+            let right = self.not()?;
+            expect_bool(&left.1, "&&")?;
+            expect_bool(&right.1, "&&")?;
+
+            left = (Node::And(Box::new(left.0), Box::new(right.0)), Type::Bool);
+        }
+
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<(Node, Type)> {
+        if self.eat_op("!") {
+            let (node, ty) = self.nested(Self::not)?;
+            expect_bool(&ty, "!")?;
+
+            return Ok((Node::Not(Box::new(node)), Type::Bool));
+        }
+
+        self.cmp()
+    }
+
+    fn cmp(&mut self) -> Result<(Node, Type)> {
+        let left = self.postfix()?;
+
+        let op = match self.tokens.get(self.pos) {
+            Some(Token::Op("==")) => CmpOp::Eq,
+            Some(Token::Op("!=")) => CmpOp::Ne,
+            Some(Token::Op("<")) => CmpOp::Lt,
+            Some(Token::Op("<=")) => CmpOp::Le,
+            Some(Token::Op(">")) => CmpOp::Gt,
+            Some(Token::Op(">=")) => CmpOp::Ge,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+
+        let right = self.postfix()?;
+        let (left_ty, right_ty) = (left.1, right.1);
+        let left = coerce_time(left, right_ty)?;
+        let right = coerce_time(right, left_ty)?;
+
+        if left.1 != right.1 {
+            bail!("cannot compare {} with {}", left.1, right.1);
+        }
+
+        if left.1 == Type::Bool && !matches!(op, CmpOp::Eq | CmpOp::Ne) {
+            bail!("booleans can only be compared with `==` and `!=`");
+        }
+
+        Ok((
+            Node::Cmp(op, Box::new(left.0), Box::new(right.0)),
+            Type::Bool,
+        ))
+    }
+
+    fn postfix(&mut self) -> Result<(Node, Type)> {
+        let (mut node, mut ty) = self.primary()?;
+
+        while self.eat_op(".") {
+            let name = self.ident()?;
+            let method = match name.as_str() {
+                "contains" => Method::Contains,
+                "starts_with" => Method::StartsWith,
+                "ends_with" => Method::EndsWith,
+                "to_lowercase" => Method::ToLowercase,
+                "to_uppercase" => Method::ToUppercase,
+                "len" => Method::Len,
+                "year" => Method::Year,
+                "month" => Method::Month,
+                "day" => Method::Day,
+                "hour" => Method::Hour,
+                "weekday" => Method::Weekday,
+                _ => bail!("unknown method `{}`", name),
+            };
+            let (receiver, param, ret) = method.signature();
+
+            if ty != receiver {
+                bail!("`{}` is not a method of {}", name, ty);
+            }
+
+            self.expect_op("(")?;
+
+            let arg = match param {
+                Some(param) => {
+                    let (arg, arg_ty) = self.nested(Self::or)?;
+
+                    if arg_ty != param {
+                        bail!("`{}` expects a {}, found {}", name, param, arg_ty);
+                    }
+
+                    Some(Box::new(arg))
+                }
+                None => None,
+            };
+
+            self.expect_op(")")?;
+
+            node = Node::Method(method, Box::new(node), arg);
+            ty = ret;
+        }
+
+        Ok((node, ty))
+    }
+
+    fn primary(&mut self) -> Result<(Node, Type)> {
+        let node = match self.next()? {
+            Token::Str(v) => (Node::Literal(Literal::Str(v)), Type::Str),
+            Token::Int(v) => (Node::Literal(Literal::Int(v)), Type::Int),
+            Token::Op("-") => match self.next()? {
+                Token::Int(v) => (Node::Literal(Literal::Int(-v)), Type::Int),
+                t => bail!("expected a number, found `{}`", t),
+            },
+            Token::Op("(") => {
+                let inner = self.nested(Self::or)?;
+                self.expect_op(")")?;
+
+                inner
+            }
+            Token::Ident(v) => match v.as_str() {
+                "true" => (Node::Literal(Literal::Bool(true)), Type::Bool),
+                "false" => (Node::Literal(Literal::Bool(false)), Type::Bool),
+                "id" => (Node::Field(Field::Id), Type::Str),
+                "title" => (Node::Field(Field::Title), Type::Str),
+                "watched_at" => (Node::Field(Field::WatchedAt), Type::Time),
+                "last_watched_at" => (Node::Field(Field::LastWatchedAt), Type::Time),
+                "watch_count" => (Node::Field(Field::WatchCount), Type::Int),
+                "channel" => {
+                    self.expect_op(".")?;
+
+                    match self.ident()?.as_str() {
+                        "id" => (Node::Field(Field::ChannelId), Type::Str),
+                        "name" => (Node::Field(Field::ChannelName), Type::Str),
+                        v => bail!("unknown field `channel.{}`", v),
+                    }
+                }
+                _ => bail!("unknown field `{}`", v),
+            },
+            t => bail!("unexpected `{}`", t),
+        };
+
+        Ok(node)
+    }
+}
+
+fn expect_bool(ty: &Type, op: &str) -> Result<()> {
+    if *ty != Type::Bool {
+        bail!("`{}` expects booleans, found {}", op, ty);
+    }
+
+    Ok(())
+}
+
+/// Turn a string literal compared against a time into a time literal
+fn coerce_time((node, ty): (Node, Type), other: Type) -> Result<(Node, Type)> {
+    match (node, ty, other) {
+        (Node::Literal(Literal::Str(s)), Type::Str, Type::Time) => match parse_time(&s) {
+            Some(v) => Ok((Node::Literal(Literal::Time(v)), Type::Time)),
+            None => bail!("invalid time `{}`, use RFC 3339 or YYYY-MM-DD", s),
+        },
+        (node, ty, _) => Ok((node, ty)),
+    }
+}
+
+enum Value<'a> {
+    Str(Cow<'a, str>),
+    Int(i64),
+    Bool(bool),
+    Time(DateTime<Utc>),
+}
+
+impl Value<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Str(a), Self::Str(b)) => a.cmp(b),
+            (Self::Int(a), Self::Int(b)) => a.cmp(b),
+            (Self::Bool(a), Self::Bool(b)) => a.cmp(b),
+            (Self::Time(a), Self::Time(b)) => a.cmp(b),
+            // ruled out by type checking
+            _ => Ordering::Less,
+        }
+    }
+}
+
+fn eval<'a>(node: &'a Node, m: &'a Metadata) -> Value<'a> {
+    match node {
+        Node::Literal(v) => match v {
+            Literal::Str(v) => Value::Str(Cow::Borrowed(v)),
+            Literal::Int(v) => Value::Int(*v),
+            Literal::Bool(v) => Value::Bool(*v),
+            Literal::Time(v) => Value::Time(*v),
+        },
+        Node::Field(f) => match f {
+            Field::Id => Value::Str(Cow::Borrowed(&m.id)),
+            Field::Title => Value::Str(Cow::Borrowed(&m.title)),
+            Field::ChannelId => Value::Str(Cow::Borrowed(&m.channel.id)),
+            Field::ChannelName => Value::Str(Cow::Borrowed(&m.channel.name)),
+            Field::WatchedAt => Value::Time(m.watched_at),
+            Field::LastWatchedAt => Value::Time(m.last_watched_at),
+            Field::WatchCount => Value::Int(m.watch_count as i64),
+        },
+        Node::Method(method, receiver, arg) => {
+            let receiver = eval(receiver, m);
+            let arg = arg.as_ref().map(|v| eval(v, m));
+
+            match (method, receiver, arg) {
+                (Method::Contains, Value::Str(s), Some(Value::Str(a))) => {
+                    Value::Bool(s.contains(a.as_ref()))
+                }
+                (Method::StartsWith, Value::Str(s), Some(Value::Str(a))) => {
+                    Value::Bool(s.starts_with(a.as_ref()))
+                }
+                (Method::EndsWith, Value::Str(s), Some(Value::Str(a))) => {
+                    Value::Bool(s.ends_with(a.as_ref()))
+                }
+                (Method::ToLowercase, Value::Str(s), _) => Value::Str(Cow::Owned(s.to_lowercase())),
+                (Method::ToUppercase, Value::Str(s), _) => Value::Str(Cow::Owned(s.to_uppercase())),
+                (Method::Len, Value::Str(s), _) => Value::Int(s.chars().count() as i64),
+                (method, Value::Time(t), _) => {
+                    let t = t.with_timezone(&Local);
+
+                    Value::Int(match method {
+                        Method::Year => t.year() as i64,
+                        Method::Month => t.month() as i64,
+                        Method::Day => t.day() as i64,
+                        Method::Hour => t.hour() as i64,
+                        _ => t.weekday().number_from_monday() as i64,
+                    })
+                }
+                // ruled out by type checking
+                _ => Value::Bool(false),
+            }
+        }
+        Node::Cmp(op, left, right) => {
+            let ord = eval(left, m).cmp(&eval(right, m));
+
+            Value::Bool(match op {
+                CmpOp::Eq => ord == Ordering::Equal,
+                CmpOp::Ne => ord != Ordering::Equal,
+                CmpOp::Lt => ord == Ordering::Less,
+                CmpOp::Le => ord != Ordering::Greater,
+                CmpOp::Gt => ord == Ordering::Greater,
+                CmpOp::Ge => ord != Ordering::Less,
+            })
+        }
+        Node::Not(v) => Value::Bool(!is_true(v, m)),
+        Node::And(a, b) => Value::Bool(is_true(a, m) && is_true(b, m)),
+        Node::Or(a, b) => Value::Bool(is_true(a, m) || is_true(b, m)),
+    }
+}
+
+fn is_true(node: &Node, m: &Metadata) -> bool {
+    matches!(eval(node, m), Value::Bool(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::schema::Channel;
+
+    fn metadata() -> Metadata {
+        let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+
+        Metadata {
+            id: "dQw4w9WgXcQ".to_owned(),
+            title: Arc::from("Never Gonna Give You Up"),
+            channel: Channel {
+                id: Arc::from("UCuAXFkgsw1L7xaCfnd5JJOw"),
+                name: Arc::from("Rick Astley"),
+            },
+            watched_at: at("2021-06-15T12:00:00Z"),
+            last_watched_at: at("2023-06-15T12:00:00Z"),
+            watch_count: 4,
+            watch_timeline: Vec::new(),
+            products: Vec::new(),
+            ad: false,
+        }
+    }
+
+    fn eval(source: &str) -> bool {
+        Expr::parse(source).unwrap().matches(&metadata())
+    }
+
+    fn error(source: &str) -> String {
+        Expr::parse(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn tokens() {
+        let tokens = tokenize("a.b(\"x\\\"y\") >= -12 && !c || d != 'z'").unwrap();
+
+        assert_eq!(
+            tokens,
+            [
+                Token::Ident("a".into()),
+                Token::Op("."),
+                Token::Ident("b".into()),
+                Token::Op("("),
+                Token::Str("x\"y".into()),
+                Token::Op(")"),
+                Token::Op(">="),
+                Token::Op("-"),
+                Token::Int(12),
+                Token::Op("&&"),
+                Token::Op("!"),
+                Token::Ident("c".into()),
+                Token::Op("||"),
+                Token::Ident("d".into()),
+                Token::Op("!="),
+                Token::Str("z".into()),
+            ]
+        );
+        assert!(tokenize("title == \"open").is_err());
+        assert!(tokenize("a # b").is_err());
+        assert!(tokenize("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn type_checking() {
+        assert_eq!(
+            error("watch_count"),
+            "expression must be a boolean, found number"
+        );
+        assert_eq!(error("title == 3"), "cannot compare string with number");
+        assert_eq!(
+            error("true < false"),
+            "booleans can only be compared with `==` and `!=`"
+        );
+        assert_eq!(
+            error("watch_count.len()"),
+            "`len` is not a method of number"
+        );
+        assert_eq!(
+            error("title.contains(3)"),
+            "`contains` expects a string, found number"
+        );
+        assert_eq!(
+            error("watch_count && true"),
+            "`&&` expects booleans, found number"
+        );
+        assert_eq!(error("!title"), "`!` expects booleans, found string");
+        assert_eq!(error("views > 3"), "unknown field `views`");
+        assert_eq!(error("channel.url == \"\""), "unknown field `channel.url`");
+        assert_eq!(error("title.trim()"), "unknown method `trim`");
+        assert_eq!(
+            error("watched_at > \"yesterday\""),
+            "invalid time `yesterday`, use RFC 3339 or YYYY-MM-DD"
+        );
+        assert_eq!(error("(true"), "expected `)`, found end of expression");
+        assert_eq!(error("true true"), "unexpected `true`");
+    }
+
+    #[test]
+    fn evaluation() {
+        assert!(eval("watch_count > 3 && watch_count <= 4"));
+        assert!(eval("watch_count != -1"));
+        assert!(eval("channel.name.to_lowercase().contains('rick')"));
+        assert!(eval(
+            "title.starts_with(\"Never\") && title.ends_with(\"Up\")"
+        ));
+        assert!(eval("id.len() == 11 && channel.id.starts_with(\"UC\")"));
+        assert!(eval("watched_at.year() == 2021 && watched_at.month() == 6"));
+        assert!(eval(
+            "last_watched_at > \"2023-01-01\" && watched_at < \"2021-07-01T00:00:00Z\""
+        ));
+        assert!(eval("!(watch_count == 1) == true"));
+        assert!(eval("false || !false && true"));
+        assert!(!eval("title.contains(\"never\")"));
+        assert!(!eval("true && (false || watch_count < 2)"));
+    }
+
+    #[test]
+    fn limits() {
+        let nested = |n| format!("{}true{}", "(".repeat(n), ")".repeat(n));
+        let negated = |n| format!("{}true", "!".repeat(n));
+
+        assert!(eval(&nested(MAX_DEPTH)));
+        assert!(eval(&negated(MAX_DEPTH)));
+        assert_eq!(
+            error(&nested(MAX_DEPTH + 1)),
+            format!("expression nested deeper than {MAX_DEPTH} levels")
+        );
+        assert!(Expr::parse(&negated(20_000)).is_err());
+        assert!(Expr::parse(&format!("{}true", "title.contains(".repeat(100))).is_err());
+
+        let long = vec!["true"; MAX_TOKENS].join(" && ");
+        assert_eq!(
+            error(&long),
+            format!("expression longer than {MAX_TOKENS} tokens")
+        );
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Utc};
+
+use crate::utils::{
+    extract_youtube_channel_id, extract_youtube_video_id, is_buffer_contains_keywords,
+};
+
+use super::{Channel, Entries, SchemaLoader, Source, WatchEntry, v1::AD_DETAIL};
+
+/// Offsets of the zone abbreviations Takeout writes after times, in minutes
+const ZONE_OFFSETS: [(&str, i32); 36] = [
+    ("UTC", 0),
+    ("GMT", 0),
+    ("WET", 0),
+    ("WEST", 60),
+    ("BST", 60),
+    ("CET", 60),
+    ("CEST", 120),
+    ("EET", 120),
+    ("EEST", 180),
+    ("MSK", 180),
+    ("GST", 240),
+    ("PKT", 300),
+    ("IST", 330),
+    ("ICT", 420),
+    ("WIB", 420),
+    ("WITA", 480),
+    ("WIT", 540),
+    ("SGT", 480),
+    ("HKT", 480),
+    ("PHT", 480),
+    ("AWST", 480),
+    ("JST", 540),
+    ("KST", 540),
+    ("ACST", 570),
+    ("AEST", 600),
+    ("AEDT", 660),
+    ("NZST", 720),
+    ("NZDT", 780),
+    ("HST", -600),
+    ("AKST", -540),
+    ("PST", -480),
+    ("PDT", -420),
+    ("MST", -420),
+    ("CST", -360),
+    ("EST", -300),
+    ("EDT", -240),
+];
+
+/// Layouts of the English Takeout times, once the zone is split off
+const TIME_FORMATS: [&str; 4] = [
+    "%b %d, %Y, %I:%M:%S %p",
+    "%d %b %Y, %H:%M:%S",
+    "%b %d, %Y, %H:%M:%S",
+    "%Y-%m-%d, %H:%M:%S",
+];
+
+/// Offset of a zone like `CET`, `GMT+05:30` or `UTC-8`
+fn zone_offset(zone: &str) -> Option<FixedOffset> {
+    if let Some((_, minutes)) = ZONE_OFFSETS.iter().find(|(name, _)| *name == zone) {
+        return FixedOffset::east_opt(minutes * 60);
+    }
+
+    let rest = zone
+        .strip_prefix("GMT")
+        .or_else(|| zone.strip_prefix("UTC"))?;
+    let (sign, rest) = match rest.split_at_checked(1)? {
+        ("+", v) => (1, v),
+        ("-", v) => (-1, v),
+        _ => return None,
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    let seconds = hours.parse::<i32>().ok()? * 3600 + minutes.parse::<i32>().ok()? * 60;
+
+    FixedOffset::east_opt(sign * seconds)
+}
+
+/// Parse a time like `Jan 1, 2024, 12:00:00 PM CET`. `None` for other
+/// languages, unknown zones are read as UTC and flagged
+fn parse_time(s: &str) -> Option<(DateTime<Utc>, bool)> {
+    let (time, zone) = s.trim().rsplit_once(' ')?;
+    let offset = zone_offset(zone);
+    let time = TIME_FORMATS
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(time, fmt).ok())?;
+
+    match offset {
+        Some(v) => Some((time.and_local_timezone(v).single()?.to_utc(), true)),
+        None => Some((time.and_utc(), false)),
+    }
+}
+
+/// Decode the entities Takeout escapes text with, spaces of any width
+/// become plain ones
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(idx) = rest.find('&') {
+        out.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        let Some(end) = rest.bytes().take(10).position(|b| b == b';') else {
+            out.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let decoded = match &rest[1..end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" | "ensp" | "emsp" | "thinsp" => Some(' '),
+            v => match v.strip_prefix("#x").or_else(|| v.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                None => v
+                    .strip_prefix('#')
+                    .and_then(|v| v.parse().ok())
+                    .and_then(char::from_u32),
+            },
+        };
+
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+
+    out.chars()
+        .map(|c| if c.is_whitespace() { ' ' } else { c })
+        .collect::<String>()
+        .trim()
+        .to_owned()
+}
+
+/// Text of a fragment with its tags left out
+fn text(fragment: &str) -> String {
+    let mut out = String::new();
+    let mut rest = fragment;
+
+    while let Some(idx) = rest.find('<') {
+        out.push_str(&rest[..idx]);
+        rest = rest[idx..]
+            .find('>')
+            .map_or("", |end| &rest[idx + end + 1..]);
+    }
+
+    out.push_str(rest);
+
+    unescape(&out)
+}
+
+/// `href` and text of the first link of a fragment
+fn link(fragment: &str) -> Option<(String, String)> {
+    let start = fragment.find("<a ")?;
+    let fragment = &fragment[start..];
+    let href = fragment.split_once("href=\"")?.1.split_once('"')?.0;
+    let body = fragment.split_once('>')?.1;
+    let body = body.split_once("</a>").map_or(body, |(v, _)| v);
+
+    Some((unescape(href), text(body)))
+}
+
+/// Inner HTML of the first `div` whose class contains `class`
+fn div<'a>(cell: &'a str, class: &str) -> Option<&'a str> {
+    let start = cell.find(class)?;
+    let body = cell[start..].split_once('>')?.1;
+
+    Some(body.split_once("</div>").map_or(body, |(v, _)| v))
+}
+
+/// Parsed outer cell, `entry` is `None` when its time can't be read
+struct Cell {
+    entry: Option<WatchEntry>,
+    known_zone: bool,
+}
+
+/// Read one `outer-cell` of the activity page
+fn parse_cell(cell: &str) -> Option<Cell> {
+    let content = div(cell, "content-cell")?;
+    let lines = content
+        .split("<br>")
+        .filter(|v| !text(v).is_empty())
+        .collect::<Vec<&str>>();
+    let [watched, middle @ .., time] = lines.as_slice() else {
+        return None;
+    };
+    // removed videos aren't linked, like `titleUrl` missing in JSON
+    let (url, title) = link(watched).unwrap_or_else(|| {
+        let title = text(watched);
+
+        (
+            String::new(),
+            title.strip_prefix("Watched ").unwrap_or(&title).to_owned(),
+        )
+    });
+    let channel = middle
+        .iter()
+        .find_map(|v| link(v))
+        .map(|(url, name)| Channel {
+            id: extract_youtube_channel_id(url).into(),
+            name: name.into(),
+        })
+        .unwrap_or_else(|| Channel {
+            id: "-".into(),
+            name: "-".into(),
+        });
+    let products = div(cell, "mdl-typography--caption")
+        .and_then(|v| v.split_once("Products:"))
+        .map(|(_, v)| v.split("<b>").next().unwrap_or(""))
+        .map(|v| {
+            v.split("<br>")
+                .map(text)
+                .filter(|v| !v.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    let ad = div(cell, "mdl-typography--caption")
+        .and_then(|v| v.split_once("Details:"))
+        .is_some_and(|(_, v)| {
+            v.split("<b>")
+                .next()
+                .unwrap_or("")
+                .split("<br>")
+                .any(|v| text(v) == AD_DETAIL)
+        });
+
+    let Some((time, known_zone)) = parse_time(&text(time)) else {
+        return Some(Cell {
+            entry: None,
+            known_zone: true,
+        });
+    };
+
+    Some(Cell {
+        entry: Some(WatchEntry {
+            id: extract_youtube_video_id(url),
+            title,
+            channel,
+            time,
+            products,
+            ad,
+        }),
+        known_zone,
+    })
+}
+
+/// HtmlLoader
+///
+/// Loader of `watch-history.html`, the HTML variant of the Takeout export.
+/// Times are only read in the English layout
+pub struct HtmlLoader;
+
+impl SchemaLoader for HtmlLoader {
+    fn name(&self) -> &str {
+        "html"
+    }
+
+    fn detect(&self, rdr: &mut dyn Source) -> bool {
+        let keys: HashSet<&str> = ["<html", "outer-cell", "content-cell"].into();
+
+        is_buffer_contains_keywords(rdr, &keys)
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn load<'a>(&self, rdr: &'a mut dyn Source) -> Result<Entries<'a>> {
+        let mut html = String::new();
+        rdr.read_to_string(&mut html)?;
+
+        let cells = html
+            .split("outer-cell")
+            .skip(1)
+            .filter_map(parse_cell)
+            .collect::<Vec<Cell>>();
+
+        let invalid = cells.iter().filter(|v| v.entry.is_none()).count();
+        if invalid > 0 {
+            log::warn!("Skipped {} entries without a readable time", invalid);
+        }
+
+        let unknown = cells.iter().filter(|v| !v.known_zone).count();
+        if unknown > 0 {
+            log::warn!("Read {} times of an unknown time zone as UTC", unknown);
+        }
+
+        Ok(Box::new(cells.into_iter().filter_map(|v| v.entry).map(Ok)))
+    }
+}
@@ -0,0 +1,51 @@
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+use super::{Metadata, title::deserialize_regex};
+
+/// IgnoreList
+///
+/// Channels and title patterns excluded from the dataset at load time
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IgnoreList {
+    /// Channel ids or exact channel names
+    pub channels: Vec<String>,
+    #[serde(deserialize_with = "deserialize_regex_list")]
+    pub titles: Vec<Regex>,
+}
+
+impl IgnoreList {
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty() && self.titles.is_empty()
+    }
+
+    pub fn matches(&self, m: &Metadata) -> bool {
+        self.channels
+            .iter()
+            .any(|v| **v == *m.channel.id || **v == *m.channel.name)
+            || self.titles.iter().any(|re| re.is_match(&m.title))
+    }
+}
+
+impl PartialEq for IgnoreList {
+    fn eq(&self, other: &Self) -> bool {
+        self.channels == other.channels
+            && self
+                .titles
+                .iter()
+                .map(Regex::as_str)
+                .eq(other.titles.iter().map(Regex::as_str))
+    }
+}
+
+fn deserialize_regex_list<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Regex>, D::Error> {
+    #[derive(Deserialize)]
+    struct Pattern(#[serde(deserialize_with = "deserialize_regex")] Regex);
+
+    let list = Vec::<Pattern>::deserialize(deserializer)?;
+
+    Ok(list.into_iter().map(|v| v.0).collect())
+}
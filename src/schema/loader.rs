@@ -0,0 +1,195 @@
+use std::{
+    fmt,
+    io::{BufRead, Seek, SeekFrom},
+    sync::{Arc, RwLock},
+};
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+use super::{WatchEntry, html::HtmlLoader, v1::V1Loader};
+
+/// Boxed iterator of loaded watch entries, which may parse them while they
+/// are pulled from the reader
+pub type Entries<'a> = Box<dyn Iterator<Item = Result<WatchEntry>> + 'a>;
+
+/// MalformedEntry
+///
+/// Entry a loader couldn't read while the ones after it still can be. Yielded
+/// as the error of [`Entries`], lenient loads skip it and go on
+#[derive(Clone, Debug, Serialize)]
+pub struct MalformedEntry {
+    /// Position of the entry in the file, from 1
+    pub index: usize,
+    /// Line of the file the entry starts on
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for MalformedEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid entry {} at line {}: {}",
+            self.index, self.line, self.message
+        )
+    }
+}
+
+impl std::error::Error for MalformedEntry {}
+
+/// Seekable buffered reader handed to loaders
+pub trait Source: BufRead + Seek {}
+
+impl<T: BufRead + Seek> Source for T {}
+
+/// SchemaLoader
+///
+/// Detect and load a single history format. Implement it to support formats
+/// other than the built-in ones and add it with [`register_loader`]
+pub trait SchemaLoader: Send + Sync {
+    /// Short format name shown in logs, e.g. `v1`
+    fn name(&self) -> &str;
+
+    /// Schema version reported in `SourceInfo`, `None` for formats without one
+    fn schema_version(&self) -> Option<u32> {
+        None
+    }
+
+    /// Check whether the content matches this format. The reader is rewound
+    /// by the caller afterward
+    fn detect(&self, rdr: &mut dyn Source) -> bool;
+
+    /// Parse every watch entry, the reader starts at the beginning
+    fn load<'a>(&self, rdr: &'a mut dyn Source) -> Result<Entries<'a>>;
+}
+
+/// Registered loaders, probed in order
+static LOADERS: Lazy<RwLock<Vec<Arc<dyn SchemaLoader>>>> =
+    Lazy::new(|| RwLock::new(vec![Arc::new(V1Loader), Arc::new(HtmlLoader)]));
+
+/// Register a loader, probed before the ones registered earlier and the
+/// built-in ones
+pub fn register_loader<L: SchemaLoader + 'static>(loader: L) {
+    LOADERS.write().unwrap().insert(0, Arc::new(loader));
+}
+
+/// Names of the registered loaders in probing order
+pub fn loader_names() -> Vec<String> {
+    LOADERS
+        .read()
+        .unwrap()
+        .iter()
+        .map(|l| l.name().to_owned())
+        .collect()
+}
+
+/// Find the first loader detecting the content, the reader is rewound
+/// afterward
+pub fn detect_loader(rdr: &mut dyn Source) -> Result<Option<Arc<dyn SchemaLoader>>> {
+    let loaders = LOADERS.read().unwrap().clone();
+
+    for loader in loaders {
+        let found = loader.detect(rdr);
+        rdr.seek(SeekFrom::Start(0))?;
+
+        if found {
+            log::debug!("Match schema: {}", loader.name());
+
+            return Ok(Some(loader));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::schema::{
+        load_metadata_from_reader,
+        testing::{at, watch},
+    };
+
+    /// `id,time` lines after a `# test-csv` header
+    struct CsvLoader;
+
+    impl SchemaLoader for CsvLoader {
+        fn name(&self) -> &str {
+            "test-csv"
+        }
+
+        fn detect(&self, rdr: &mut dyn Source) -> bool {
+            let mut header = [0; 10];
+
+            rdr.read_exact(&mut header).is_ok() && &header == b"# test-csv"
+        }
+
+        fn load<'a>(&self, rdr: &'a mut dyn Source) -> Result<Entries<'a>> {
+            let entries = rdr.lines().skip(1).map(|line| {
+                let line = line?;
+                let (id, time) = line.split_once(',').unwrap_or((&line, ""));
+
+                Ok(watch(id, time.trim()))
+            });
+
+            Ok(Box::new(entries))
+        }
+    }
+
+    #[test]
+    fn built_in_loaders() {
+        let v1 = r#"[{
+            "header": "YouTube",
+            "title": "Watched Video",
+            "titleUrl": "https://www.youtube.com/watch?v=aaaaaaaaaaa",
+            "subtitles": [{ "name": "Channel", "url": "https://www.youtube.com/channel/UC" }],
+            "time": "2024-01-01T12:00:00Z",
+            "products": ["YouTube"]
+        }]"#;
+        let loader = detect_loader(&mut Cursor::new(v1)).unwrap().unwrap();
+        assert_eq!(loader.name(), "v1");
+        assert_eq!(loader.schema_version(), Some(1));
+
+        let html = r#"<html><div class="outer-cell"><div class="content-cell"></div></div></html>"#;
+        let loader = detect_loader(&mut Cursor::new(html)).unwrap().unwrap();
+        assert_eq!(loader.name(), "html");
+        assert_eq!(loader.schema_version(), None);
+
+        assert!(
+            detect_loader(&mut Cursor::new("plain text"))
+                .unwrap()
+                .is_none()
+        );
+        assert!(load_metadata_from_reader(Cursor::new("plain text")).is_err());
+
+        // built-in loaders are probed after the registered ones
+        assert!(loader_names().ends_with(&["v1".to_owned(), "html".to_owned()]));
+    }
+
+    #[test]
+    fn registered_loader() {
+        register_loader(CsvLoader);
+        assert_eq!(loader_names()[0], "test-csv");
+
+        let csv =
+            "# test-csv\nb,2024-01-02T12:00:00Z\na,2024-01-01T12:00:00Z\nb,2024-01-03T12:00:00Z\n";
+        let mut rdr = Cursor::new(csv);
+        let loader = detect_loader(&mut rdr).unwrap().unwrap();
+        assert_eq!(loader.name(), "test-csv");
+        assert_eq!(rdr.position(), 0);
+
+        let table = load_metadata_from_reader(Cursor::new(csv)).unwrap();
+        assert_eq!(table.total_count_raw(), 3);
+        assert_eq!(table.total_count(), 2);
+
+        let b = table.data().iter().find(|m| m.id == "b").unwrap();
+        assert_eq!(
+            b.watch_timeline,
+            [at("2024-01-02T12:00:00Z"), at("2024-01-03T12:00:00Z")]
+        );
+    }
+}
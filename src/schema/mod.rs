@@ -1,18 +1,67 @@
 use std::{
-    collections::{HashMap, HashSet},
-    fs::File,
-    io::{BufRead, BufReader, Seek, SeekFrom},
-    path::PathBuf,
+    cmp::Ordering,
+    collections::HashSet,
+    convert::Infallible,
+    fmt,
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    io::{BufRead, BufReader, Cursor, Seek},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::Instant,
 };
 
 use anyhow::{Result, bail};
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use rustc_hash::{FxHashMap, FxHashSet};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
 use strum::IntoEnumIterator;
 
-use crate::utils::{DateTimeUtility, is_buffer_contains_keywords, is_json_file};
+use crate::utils::{DateTimeUtility, is_zip_file};
 
+mod archive;
+mod collation;
+mod encoding;
+mod expr;
+mod html;
+mod ignore;
+mod loader;
+mod search;
+mod shortcut;
+#[cfg(test)]
+pub(crate) mod testing;
+mod title;
 mod v1;
+mod validated;
+
+pub use archive::read_history_from_zip;
+pub use collation::{Collation, CollationKey};
+pub use encoding::{Decoded, Encoding};
+pub use expr::Expr;
+pub use ignore::IgnoreList;
+pub use loader::{
+    Entries, MalformedEntry, SchemaLoader, Source, detect_loader, loader_names, register_loader,
+};
+pub use search::{contains_word, fuzzy_distance};
+pub use shortcut::PeriodShortcut;
+pub use title::{MIN_TITLE_KEY_LEN, TitleRule, title_key};
+pub use v1::AD_DETAIL;
+pub use validated::{InvalidFilter, MAX_LIMIT, ValidatedFilter};
+
+/// Watch times shown per video before the full timeline has to be requested
+pub const RECENT_WATCHES_LEN: usize = 5;
+
+/// Weight of a `search` match in the title
+pub const SEARCH_TITLE_BOOST: u32 = 2;
+
+/// Weight of a `search` match in the channel name, a title match says more
+/// about the video
+pub const SEARCH_CHANNEL_BOOST: u32 = 1;
+
+/// Days after which a watch counts half toward the `relevance` order
+pub const DEFAULT_HALF_LIFE_DAYS: u32 = 30;
 
 /// default_page
 pub fn default_page() -> usize {
@@ -61,7 +110,7 @@ impl Pagination {
             (min, max)
         } else {
             let mut s = current_page.saturating_sub(2).max(min);
-            let mut e = (current_page + 2).max(5);
+            let mut e = current_page.saturating_add(2).max(5);
 
             if e > max {
                 let diff = e - max;
@@ -86,7 +135,18 @@ impl Pagination {
 }
 
 /// Order
-#[derive(Debug, Serialize, Deserialize, Clone, strum::Display, strum::EnumIter, Eq, PartialEq)]
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    strum::Display,
+    strum::EnumIter,
+    strum::EnumString,
+    Eq,
+    PartialEq,
+    JsonSchema,
+)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum Order {
@@ -94,6 +154,12 @@ pub enum Order {
     Oldest,
     MostWatched,
     LeastWatched,
+    RecentlyWatched,
+    LeastRecentlyWatched,
+    /// Watch count with each watch decaying by half every half-life
+    Relevance,
+    Alphabetical,
+    ReverseAlphabetical,
 }
 
 impl Order {
@@ -103,6 +169,11 @@ impl Order {
             Self::Oldest => String::from("Oldest"),
             Self::MostWatched => String::from("Most Watched"),
             Self::LeastWatched => String::from("Least Watched"),
+            Self::RecentlyWatched => String::from("Recently Watched"),
+            Self::LeastRecentlyWatched => String::from("Least Recently Watched"),
+            Self::Relevance => String::from("Relevance"),
+            Self::Alphabetical => String::from("Title A-Z"),
+            Self::ReverseAlphabetical => String::from("Title Z-A"),
         }
     }
 
@@ -113,14 +184,193 @@ impl Order {
     }
 }
 
+/// Boundaries
+///
+/// Whether videos watched right at `from` and `to` are taken in, the same
+/// goes for `last_from` and `last_to`
+#[derive(
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    strum::Display,
+    strum::EnumString,
+    Eq,
+    PartialEq,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Boundaries {
+    /// `from <= t <= to`
+    #[default]
+    Inclusive,
+    /// `from < t < to`
+    Exclusive,
+    /// `from <= t < to`, ranges sharing a bound don't overlap
+    HalfOpen,
+}
+
+impl Boundaries {
+    /// Check if `t` is past the lower bound `from`
+    pub fn after(self, t: DateTime<Utc>, from: DateTime<Utc>) -> bool {
+        match self {
+            Self::Exclusive => t > from,
+            Self::Inclusive | Self::HalfOpen => t >= from,
+        }
+    }
+
+    /// Check if `t` is short of the upper bound `to`
+    pub fn before(self, t: DateTime<Utc>, to: DateTime<Utc>) -> bool {
+        match self {
+            Self::Inclusive => t <= to,
+            Self::Exclusive | Self::HalfOpen => t < to,
+        }
+    }
+
+    /// Check if `t` is within the bounds, unbounded on `None` sides
+    pub fn contains(
+        self,
+        t: DateTime<Utc>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> bool {
+        from.is_none_or(|v| self.after(t, v)) && to.is_none_or(|v| self.before(t, v))
+    }
+}
+
+/// WatchMatch
+///
+/// Watch of each video `from`, `to` and `period` are checked against
+#[derive(
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    strum::Display,
+    strum::EnumString,
+    Eq,
+    PartialEq,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum WatchMatch {
+    /// Earliest watch, `watched_at`
+    #[default]
+    FirstWatch,
+    /// Latest watch, `last_watched_at`
+    LastWatch,
+    /// Any watch of the timeline, so rewatches of older videos count too
+    AnyWatch,
+}
+
+/// View
+///
+/// Rows a filter lists, aggregated videos or their single watches
+#[derive(
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    strum::Display,
+    strum::EnumString,
+    Eq,
+    PartialEq,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum View {
+    /// One row per video
+    #[default]
+    Videos,
+    /// One row per watch, `from`, `to` and `period` bound each watch and
+    /// `match` is ignored
+    Events,
+}
+
 /// MetadataFilter
-#[derive(Debug, Serialize, Deserialize)]
+///
+/// Empty fields are unset, as submitted by plain HTML forms. Times also take
+/// the `YYYY-MM-DDTHH:MM` form of `datetime-local` inputs or a `YYYY-MM-DD`
+/// date, in local time, see [`parse_filter_time`]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct MetadataFilter {
+    /// Exact video ID
+    #[serde(default, deserialize_with = "deserialize_non_empty")]
     pub id: Option<String>,
+    /// Title or channel name contains, case insensitive. Title matches come
+    /// first
+    #[serde(default, deserialize_with = "deserialize_non_empty")]
+    pub search: Option<String>,
+    /// Title contains, case insensitive
+    #[serde(default, deserialize_with = "deserialize_non_empty")]
     pub title: Option<String>,
+    /// Channel name contains, case insensitive
+    #[serde(default, deserialize_with = "deserialize_non_empty")]
     pub channel_name: Option<String>,
+    /// Exact channel ID
+    #[serde(default, deserialize_with = "deserialize_non_empty")]
+    pub channel_id: Option<String>,
+    /// Watched on this product, e.g. `YouTube Music`
+    #[serde(default, deserialize_with = "deserialize_non_empty")]
+    pub product: Option<String>,
+    /// Also list videos only ever shown as ads, left out by default
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub ads: bool,
+    /// First watched at or after, see `boundaries` and `match`
+    #[serde(default, deserialize_with = "deserialize_datetime")]
+    #[schemars(with = "Option<String>")]
     pub from: Option<DateTime<Utc>>,
+    /// First watched at or before, see `boundaries` and `match`
+    #[serde(default, deserialize_with = "deserialize_datetime_end")]
+    #[schemars(with = "Option<String>")]
     pub to: Option<DateTime<Utc>>,
+    /// Narrows `from` and `to`, resolved when filtering. `last_<n>d`,
+    /// `this_month`, `this_year` or a year
+    #[serde(default, deserialize_with = "deserialize_non_empty")]
+    #[schemars(with = "Option<String>")]
+    pub period: Option<PeriodShortcut>,
+    /// Last watched at or after, see `boundaries`
+    #[serde(default, deserialize_with = "deserialize_datetime")]
+    #[schemars(with = "Option<String>")]
+    pub last_from: Option<DateTime<Utc>>,
+    /// Last watched at or before, see `boundaries`
+    #[serde(default, deserialize_with = "deserialize_datetime_end")]
+    #[schemars(with = "Option<String>")]
+    pub last_to: Option<DateTime<Utc>>,
+    /// Whether the time bounds themselves match, `inclusive` by default
+    #[serde(default)]
+    pub boundaries: Boundaries,
+    /// Watch `from`, `to` and `period` look at, the first one by default
+    #[serde(default, rename = "match")]
+    pub watch_match: WatchMatch,
+    /// Videos or every watch event as its own row, videos by default
+    #[serde(default)]
+    pub view: View,
+    /// Filter expression, e.g. `watch_count > 3`
+    #[serde(default, deserialize_with = "deserialize_non_empty")]
+    #[schemars(with = "Option<String>")]
+    pub expr: Option<Expr>,
+    /// Tolerate typos in `search`, `title` and `channel_name`, closest
+    /// matches first
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub fuzzy: bool,
+    /// Match letter case exactly in `search`, `title` and `channel_name`,
+    /// ignored by `fuzzy`
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub case_sensitive: bool,
+    /// Only match whole words in `search`, `title` and `channel_name`, so
+    /// `go` leaves out `google`
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub whole_word: bool,
 
     #[serde(default = "default_order")]
     pub order: Order,
@@ -132,27 +382,799 @@ pub struct MetadataFilter {
     pub limit: usize,
 }
 
+impl Default for MetadataFilter {
+    /// Every video, first page of the default order
+    fn default() -> Self {
+        Self {
+            id: None,
+            search: None,
+            title: None,
+            channel_name: None,
+            channel_id: None,
+            product: None,
+            ads: false,
+            from: None,
+            to: None,
+            period: None,
+            last_from: None,
+            last_to: None,
+            boundaries: Boundaries::default(),
+            watch_match: WatchMatch::default(),
+            view: View::default(),
+            expr: None,
+            fuzzy: false,
+            case_sensitive: false,
+            whole_word: false,
+            order: default_order(),
+            page: default_page(),
+            limit: default_limit(),
+        }
+    }
+}
+
 impl MetadataFilter {
-    /// Check if all fields are `None` to pass filtering
+    /// Check if all fields are `None` to pass filtering, ads aside
     pub fn skip(&self) -> bool {
         self.id.is_none()
+            && self.search.is_none()
             && self.title.is_none()
             && self.channel_name.is_none()
+            && self.channel_id.is_none()
+            && self.product.is_none()
             && self.from.is_none()
             && self.to.is_none()
+            && self.period.is_none()
+            && self.last_from.is_none()
+            && self.last_to.is_none()
+            && self.expr.is_none()
+    }
+
+    /// `from` and `to` narrowed by `period` at the current local time
+    pub fn bounds(&self) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+        let Some(period) = &self.period else {
+            return (self.from, self.to);
+        };
+        let (from, to) = period.bounds(Local::now());
+
+        (self.from.max(from), min_bound(self.to, to))
+    }
+
+    /// Check if the metadata passes every set field
+    pub fn matches(&self, m: &Metadata) -> bool {
+        let (from, to) = self.bounds();
+
+        self.matches_within(m, from, to)
+    }
+
+    /// Same as `matches` with bounds resolved once by the caller
+    fn matches_within(
+        &self,
+        m: &Metadata,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> bool {
+        if m.ad && !self.ads {
+            return false;
+        }
+
+        if self.skip() {
+            return true;
+        }
+
+        let id = if let Some(v) = &self.id {
+            m.id == *v
+        } else {
+            true
+        };
+
+        let search = if let Some(v) = &self.search {
+            self.text_typos(v, &m.title).is_some() || self.text_typos(v, &m.channel.name).is_some()
+        } else {
+            true
+        };
+
+        let title = if let Some(v) = &self.title {
+            self.text_typos(v, &m.title).is_some()
+        } else {
+            true
+        };
+
+        let channel_name = if let Some(v) = &self.channel_name {
+            self.text_typos(v, &m.channel.name).is_some()
+        } else {
+            true
+        };
+
+        let channel_id = if let Some(v) = &self.channel_id {
+            *m.channel.id == **v
+        } else {
+            true
+        };
+
+        let product = if let Some(v) = &self.product {
+            m.products.iter().any(|p| p.eq_ignore_ascii_case(v))
+        } else {
+            true
+        };
+
+        let period = match self.watch_match {
+            WatchMatch::FirstWatch => self.boundaries.contains(m.watched_at, from, to),
+            WatchMatch::LastWatch => self.boundaries.contains(m.last_watched_at, from, to),
+            WatchMatch::AnyWatch => {
+                // the timeline is sorted, only its first watch past `from`
+                // can be short of `to`
+                let start = from.map_or(0, |v| {
+                    m.watch_timeline
+                        .partition_point(|t| !self.boundaries.after(*t, v))
+                });
+
+                m.watch_timeline
+                    .get(start)
+                    .is_some_and(|t| to.is_none_or(|v| self.boundaries.before(*t, v)))
+            }
+        };
+
+        let last = self
+            .boundaries
+            .contains(m.last_watched_at, self.last_from, self.last_to);
+
+        let expr = if let Some(v) = &self.expr {
+            v.matches(m)
+        } else {
+            true
+        };
+
+        id && search && title && channel_name && channel_id && product && period && last && expr
+    }
+
+    /// Typos `text` needs to match `query`, always 0 unless `fuzzy`
+    fn text_typos(&self, query: &str, text: &str) -> Option<usize> {
+        if self.fuzzy {
+            return fuzzy_distance(query, text, self.whole_word);
+        }
+
+        let (query, text) = if self.case_sensitive {
+            (query.to_owned(), text.to_owned())
+        } else {
+            (query.to_lowercase(), text.to_lowercase())
+        };
+        let found = if self.whole_word {
+            contains_word(&text, &query)
+        } else {
+            text.contains(&query)
+        };
+
+        found.then_some(0)
+    }
+
+    /// Typos in the text fields of a fuzzy match, 0 when the filter is not
+    /// fuzzy
+    pub fn typos(&self, m: &Metadata) -> usize {
+        if !self.fuzzy {
+            return 0;
+        }
+
+        let fields = [
+            (&self.search, &m.title),
+            (&self.search, &m.channel.name),
+            (&self.title, &m.title),
+            (&self.channel_name, &m.channel.name),
+        ];
+
+        fields
+            .into_iter()
+            .filter_map(|(query, text)| self.text_typos(query.as_deref()?, text))
+            .sum()
+    }
+
+    /// Weight of the fields `search` matches in, 0 without `search`
+    pub fn search_score(&self, m: &Metadata) -> u32 {
+        let Some(v) = &self.search else {
+            return 0;
+        };
+
+        let title = self
+            .text_typos(v, &m.title)
+            .map_or(0, |_| SEARCH_TITLE_BOOST);
+        let channel_name = self
+            .text_typos(v, &m.channel.name)
+            .map_or(0, |_| SEARCH_CHANNEL_BOOST);
+
+        title + channel_name
+    }
+}
+
+fn deserialize_non_empty<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(s) if !s.is_empty() => s.parse().map(Some).map_err(serde::de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
+/// `1`, `true` or `on` as sent by checkboxes, anything else is unset
+fn deserialize_flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    let v = Option::<String>::deserialize(deserializer)?;
+
+    Ok(matches!(v.as_deref(), Some("1" | "true" | "on")))
+}
+
+/// Parse a filter time, RFC 3339 or in local time the `YYYY-MM-DDTHH:MM`
+/// of `datetime-local` inputs or the `YYYY-MM-DD` of `date` inputs. A date
+/// is its start of day, or with `end_of_day` its last instant so upper
+/// bounds take in the whole day
+pub fn parse_filter_time(s: &str, end_of_day: bool) -> Option<DateTime<Utc>> {
+    if let Ok(v) = s.parse::<DateTime<Utc>>() {
+        return Some(v);
+    }
+
+    let time = ["%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(s, f).ok())
+        .or_else(|| {
+            let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?;
+
+            if end_of_day {
+                date.and_hms_nano_opt(23, 59, 59, 999_999_999)
+            } else {
+                Some(date.and_time(NaiveTime::MIN))
+            }
+        })?;
+
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map(|v| v.to_utc())
+}
+
+fn deserialize_time_bound<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    end_of_day: bool,
+) -> Result<Option<DateTime<Utc>>, D::Error> {
+    let Some(s) = Option::<String>::deserialize(deserializer)?.filter(|v| !v.is_empty()) else {
+        return Ok(None);
+    };
+
+    parse_filter_time(&s, end_of_day).map(Some).ok_or_else(|| {
+        serde::de::Error::custom(format!(
+            "invalid time `{}`, expected RFC 3339, YYYY-MM-DDTHH:MM or YYYY-MM-DD",
+            s
+        ))
+    })
+}
+
+/// Lower bound, a date starts at its midnight
+pub(crate) fn deserialize_datetime<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime<Utc>>, D::Error> {
+    deserialize_time_bound(deserializer, false)
+}
+
+/// Upper bound, a date ends at its last instant, right before the next
+/// midnight
+pub(crate) fn deserialize_datetime_end<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime<Utc>>, D::Error> {
+    deserialize_time_bound(deserializer, true)
+}
+
+/// Earliest of two upper bounds, `None` being unbounded
+fn min_bound(a: Option<DateTime<Utc>>, b: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// SourceInfo
+///
+/// File a `MetadataTable` was loaded from. For merged histories, the first
+/// path given along with the total size and newest modification of the files
+#[derive(Clone, Debug, Serialize)]
+pub struct SourceInfo {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified_at: Option<DateTime<Utc>>,
+    pub schema_version: Option<u32>,
+    pub parse_ms: u128,
+    /// Set by lenient loads
+    pub parse_report: Option<ParseReport>,
+}
+
+impl SourceInfo {
+    pub fn to_size_string(&self) -> String {
+        format!("{:.1} MiB", self.size as f64 / (1024.0 * 1024.0))
+    }
+}
+
+/// Skipped entries kept as examples in a [`ParseReport`]
+const MAX_REPORT_EXAMPLES: usize = 10;
+
+/// ParseReport
+///
+/// Malformed entries a lenient load skipped, the first few kept as examples
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ParseReport {
+    pub skipped: usize,
+    pub examples: Vec<SkippedEntry>,
+}
+
+/// SkippedEntry
+///
+/// Malformed entry along with the file it was read from
+#[derive(Clone, Debug, Serialize)]
+pub struct SkippedEntry {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub entry: MalformedEntry,
+}
+
+impl ParseReport {
+    /// Count a skipped entry of `path`, examples are logged as they are found
+    fn add(&mut self, path: &Path, entry: &MalformedEntry) {
+        self.skipped += 1;
+
+        if self.examples.len() < MAX_REPORT_EXAMPLES {
+            log::warn!("Skipped `{}`, {}", path.display(), entry);
+
+            self.examples.push(SkippedEntry {
+                path: path.to_path_buf(),
+                entry: entry.clone(),
+            });
+        }
+    }
+}
+
+/// MemoryUsage
+///
+/// Approximate heap usage of a `MetadataTable`, in bytes
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct MemoryUsage {
+    pub entries: usize,
+    pub strings: usize,
+    pub timelines: usize,
+    pub timeline_points: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.entries + self.strings + self.timelines
+    }
+
+    pub fn to_size_string(&self) -> String {
+        format!("{:.1} MiB", self.total() as f64 / (1024.0 * 1024.0))
     }
 }
 
 /// MetadataTable
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct MetadataTable {
     total_count_raw: usize,
     total_count: usize,
     watch_timeline: Vec<DateTime<Utc>>,
     data: Vec<Metadata>,
+    source: Option<SourceInfo>,
+    collapsed_count: usize,
+    collation: Collation,
+    half_life_days: u32,
 }
 
 impl MetadataTable {
+    /// Aggregate raw watch entries into metadata table
+    pub fn from_entries<I: IntoIterator<Item = WatchEntry>>(entries: I) -> Self {
+        match Self::try_from_entries(entries.into_iter().map(Ok::<_, Infallible>)) {
+            Ok(v) => v,
+            Err(e) => match e {},
+        }
+    }
+
+    /// Same as `from_entries` for entries parsed while they are aggregated,
+    /// gives up on the first error
+    #[tracing::instrument(skip_all)]
+    pub fn try_from_entries<I, E>(entries: I) -> Result<Self, E>
+    where
+        I: IntoIterator<Item = Result<WatchEntry, E>>,
+    {
+        let entries = entries.into_iter();
+
+        // videos are usually rewatched, half the entries is a fair guess
+        let (estimate, _) = entries.size_hint();
+        let mut total_count_raw: usize = 0;
+        let mut watch_timeline: Vec<DateTime<Utc>> = Vec::with_capacity(estimate);
+        let mut map: FxHashMap<String, Metadata> =
+            FxHashMap::with_capacity_and_hasher(estimate / 2, Default::default());
+        let mut interner = Interner::default();
+
+        for r in entries {
+            let r = r?;
+            total_count_raw += 1;
+            watch_timeline.push(r.time);
+
+            if map.contains_key(&r.id) {
+                if let Some(m) = map.get_mut(&r.id) {
+                    // watched_at always the earliest
+                    if r.time < m.watched_at {
+                        m.watched_at = r.time;
+                    }
+
+                    if r.time > m.last_watched_at {
+                        m.last_watched_at = r.time;
+                    }
+
+                    m.watch_count += 1;
+                    m.watch_timeline.push(r.time);
+
+                    for p in &r.products {
+                        if !m.products.iter().any(|v| **v == **p) {
+                            m.products.push(interner.intern(p));
+                        }
+                    }
+
+                    // a single organic watch makes it a regular video
+                    m.ad &= r.ad;
+                }
+            } else {
+                let m = Metadata {
+                    id: r.id.clone(),
+                    title: Arc::from(r.title),
+                    channel: Channel {
+                        id: interner.intern(&r.channel.id),
+                        name: interner.intern(&r.channel.name),
+                    },
+                    watched_at: r.time,
+                    last_watched_at: r.time,
+                    watch_count: 1,
+                    watch_timeline: vec![r.time],
+                    products: r.products.iter().map(|p| interner.intern(p)).collect(),
+                    ad: r.ad,
+                };
+
+                map.insert(r.id.clone(), m);
+            }
+        }
+
+        // sorted once here, sorting on every push is quadratic for heavily
+        // rewatched videos
+        let mut data = map
+            .into_values()
+            .map(|mut m| {
+                m.watch_timeline.sort_unstable();
+                m
+            })
+            .collect::<Vec<Metadata>>();
+        data.sort_by_key(|v| std::cmp::Reverse(v.watched_at));
+        watch_timeline.sort_unstable();
+
+        Ok(Self {
+            total_count_raw,
+            total_count: data.len(),
+            watch_timeline,
+            data,
+            source: None,
+            collapsed_count: 0,
+            collation: Collation::default(),
+            half_life_days: DEFAULT_HALF_LIFE_DAYS,
+        })
+    }
+
+    /// Approximate memory held by the table
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let point = std::mem::size_of::<DateTime<Utc>>();
+        let mut usage = MemoryUsage {
+            entries: self.data.capacity() * std::mem::size_of::<Metadata>(),
+            strings: 0,
+            timelines: self.watch_timeline.capacity() * point,
+            timeline_points: self.watch_timeline.len(),
+        };
+
+        // interned channel strings are shared, count each once
+        let mut shared: HashSet<*const u8> = HashSet::new();
+
+        for m in &self.data {
+            usage.strings += m.id.capacity() + m.title.len();
+
+            for v in [&m.channel.id, &m.channel.name] {
+                if shared.insert(v.as_ptr()) {
+                    usage.strings += v.len();
+                }
+            }
+
+            usage.timelines += m.watch_timeline.capacity() * point;
+            usage.timeline_points += m.watch_timeline.len();
+        }
+
+        usage
+    }
+
+    /// Videos only ever shown as ads
+    pub fn ads_count(&self) -> usize {
+        self.data.iter().filter(|m| m.ad).count()
+    }
+
+    /// Whether any video was only ever shown as an ad
+    pub fn has_ads(&self) -> bool {
+        self.data.iter().any(|m| m.ad)
+    }
+
+    /// Source file, `None` when built from entries directly
+    pub fn source(&self) -> Option<&SourceInfo> {
+        self.source.as_ref()
+    }
+
+    /// Whether the video has a watch at exactly `time`
+    pub fn contains_watch(&self, id: &str, time: DateTime<Utc>) -> bool {
+        self.data
+            .iter()
+            .find(|m| m.id == id)
+            .is_some_and(|m| m.watch_timeline.binary_search(&time).is_ok())
+    }
+
+    /// Add a single watch, e.g. one reported live between exports. Returns
+    /// `false` and leaves the table untouched when the video already has a
+    /// watch at that exact time
+    pub fn insert_entry(&mut self, entry: WatchEntry) -> bool {
+        match self.data.iter().position(|m| m.id == entry.id) {
+            Some(i) => {
+                let m = &mut self.data[i];
+
+                let Err(at) = m.watch_timeline.binary_search(&entry.time) else {
+                    return false;
+                };
+
+                m.watch_timeline.insert(at, entry.time);
+                m.watch_count += 1;
+                m.last_watched_at = m.last_watched_at.max(entry.time);
+
+                for p in entry.products {
+                    if !m.products.iter().any(|v| **v == *p) {
+                        m.products.push(Arc::from(p));
+                    }
+                }
+
+                m.ad &= entry.ad;
+
+                // the first watch moved, keep `data` ordered by latest
+                if entry.time < m.watched_at {
+                    m.watched_at = entry.time;
+
+                    let m = self.data.remove(i);
+                    let at = self.data.partition_point(|v| v.watched_at > m.watched_at);
+                    self.data.insert(at, m);
+                }
+            }
+            None => {
+                let m = Metadata {
+                    id: entry.id,
+                    title: Arc::from(entry.title),
+                    channel: entry.channel,
+                    watched_at: entry.time,
+                    last_watched_at: entry.time,
+                    watch_count: 1,
+                    watch_timeline: vec![entry.time],
+                    products: entry.products.into_iter().map(Arc::from).collect(),
+                    ad: entry.ad,
+                };
+                let at = self.data.partition_point(|v| v.watched_at > m.watched_at);
+
+                self.data.insert(at, m);
+                self.total_count = self.data.len();
+            }
+        }
+
+        let at = self.watch_timeline.partition_point(|t| *t <= entry.time);
+        self.watch_timeline.insert(at, entry.time);
+        self.total_count_raw += 1;
+
+        true
+    }
+
+    /// Collapse repeats of the same video logged within `window` of the
+    /// previous kept watch, Takeout sometimes records a single watch twice.
+    /// Returns how many entries were removed
+    pub fn collapse_repeats(&mut self, window: Duration) -> usize {
+        let mut removed = 0;
+
+        for m in self.data.iter_mut() {
+            let before = m.watch_timeline.len();
+            let mut last: Option<DateTime<Utc>> = None;
+
+            m.watch_timeline.retain(|t| {
+                let keep = last.is_none_or(|l| *t - l > window);
+
+                if keep {
+                    last = Some(*t);
+                }
+
+                keep
+            });
+
+            m.watch_count = m.watch_timeline.len();
+            m.last_watched_at = m.watch_timeline.last().copied().unwrap_or(m.watched_at);
+            removed += before - m.watch_count;
+        }
+
+        if removed > 0 {
+            self.watch_timeline = self
+                .data
+                .iter()
+                .flat_map(|m| m.watch_timeline.iter().copied())
+                .collect();
+            self.watch_timeline.sort_unstable();
+            self.total_count_raw -= removed;
+            self.collapsed_count += removed;
+        }
+
+        removed
+    }
+
+    /// Drop every video matching `list` along with its watches, totals
+    /// end up as if they were never in the source. Returns how many watches
+    /// were removed
+    pub fn exclude(&mut self, list: &IgnoreList) -> usize {
+        if list.is_empty() {
+            return 0;
+        }
+
+        let mut removed = 0;
+
+        self.data.retain(|m| {
+            let keep = !list.matches(m);
+
+            if !keep {
+                removed += m.watch_count;
+            }
+
+            keep
+        });
+
+        if removed > 0 {
+            self.watch_timeline = self
+                .data
+                .iter()
+                .flat_map(|m| m.watch_timeline.iter().copied())
+                .collect();
+            self.watch_timeline.sort_unstable();
+            self.total_count_raw -= removed;
+            self.total_count = self.data.len();
+        }
+
+        removed
+    }
+
+    /// Rewrite titles through `rules`, applied in order with whitespace
+    /// collapsed afterwards. Returns how many titles changed
+    pub fn rewrite_titles(&mut self, rules: &[TitleRule]) -> usize {
+        if rules.is_empty() {
+            return 0;
+        }
+
+        let mut changed = 0;
+
+        for m in self.data.iter_mut() {
+            if let Some(title) = TitleRule::apply_all(rules, &m.title) {
+                m.title = title;
+                changed += 1;
+            }
+        }
+
+        changed
+    }
+
+    /// Videos sharing a [`title_key`] under different ids, ads grouped apart
+    /// from actual watches. Most watched first within each group and groups
+    /// by their total watches
+    pub fn title_groups(&self) -> Vec<Vec<&Metadata>> {
+        let mut map: FxHashMap<(String, bool), Vec<&Metadata>> = FxHashMap::default();
+
+        for m in self.data.iter() {
+            let key = title_key(&m.title);
+
+            if !key.is_empty() {
+                map.entry((key, m.ad)).or_default().push(m);
+            }
+        }
+
+        let mut groups = map
+            .into_values()
+            .filter(|v| v.len() > 1)
+            .map(|mut v| {
+                v.sort_by(|a, b| {
+                    b.watch_count
+                        .cmp(&a.watch_count)
+                        .then_with(|| a.watched_at.cmp(&b.watched_at))
+                });
+                v
+            })
+            .collect::<Vec<Vec<&Metadata>>>();
+
+        groups.sort_by_cached_key(|v| {
+            (
+                std::cmp::Reverse(v.iter().map(|m| m.watch_count).sum::<usize>()),
+                v[0].id.clone(),
+            )
+        });
+
+        groups
+    }
+
+    /// Fold every group of [`MetadataTable::title_groups`] into its most
+    /// watched video, which keeps its id, title and channel. Returns how many
+    /// videos were folded away
+    pub fn merge_duplicates(&mut self) -> usize {
+        let targets = self
+            .title_groups()
+            .into_iter()
+            .flat_map(|v| {
+                let keeper = v[0].id.clone();
+
+                v.into_iter()
+                    .skip(1)
+                    .map(move |m| (m.id.clone(), keeper.clone()))
+            })
+            .collect::<FxHashMap<String, String>>();
+
+        if targets.is_empty() {
+            return 0;
+        }
+
+        let mut folded: FxHashMap<String, Vec<Metadata>> = FxHashMap::default();
+        let (kept, removed): (Vec<Metadata>, Vec<Metadata>) = std::mem::take(&mut self.data)
+            .into_iter()
+            .partition(|m| !targets.contains_key(&m.id));
+
+        for m in removed {
+            folded.entry(targets[&m.id].clone()).or_default().push(m);
+        }
+
+        self.data = kept;
+
+        for m in self.data.iter_mut() {
+            let Some(others) = folded.remove(&m.id) else {
+                continue;
+            };
+
+            for other in others {
+                m.watched_at = m.watched_at.min(other.watched_at);
+                m.last_watched_at = m.last_watched_at.max(other.last_watched_at);
+                m.watch_count += other.watch_count;
+                m.watch_timeline.extend(other.watch_timeline);
+
+                for p in other.products {
+                    if !m.products.contains(&p) {
+                        m.products.push(p);
+                    }
+                }
+            }
+
+            m.watch_timeline.sort_unstable();
+        }
+
+        self.data.sort_by_key(|v| std::cmp::Reverse(v.watched_at));
+        self.total_count = self.data.len();
+
+        targets.len()
+    }
+
+    /// Collation used by the alphabetical orders
+    pub fn set_collation(&mut self, collation: Collation) {
+        self.collation = collation;
+    }
+
+    /// Half-life of the `relevance` order, at least a day
+    pub fn set_half_life(&mut self, days: u32) {
+        self.half_life_days = days.max(1);
+    }
+
+    /// Entries removed by [`MetadataTable::collapse_repeats`], the raw
+    /// total before collapsing is `total_count_raw + collapsed_count`
+    pub fn collapsed_count(&self) -> usize {
+        self.collapsed_count
+    }
+
     pub fn total_count_raw(&self) -> usize {
         self.total_count_raw
     }
@@ -165,49 +1187,185 @@ impl MetadataTable {
         self.watch_timeline.clone()
     }
 
-    pub fn get_collection(&mut self, filter: &MetadataFilter) -> (Pagination, Vec<Metadata>) {
-        let mut filtered = self
-            .data
-            .iter()
-            .filter(|x| {
-                if filter.skip() {
-                    return true;
-                }
+    /// Sorted watch timeline of every entry, without cloning
+    pub fn watch_timeline_ref(&self) -> &[DateTime<Utc>] {
+        &self.watch_timeline
+    }
 
-                let id = if let Some(v) = &filter.id {
-                    x.id == *v
-                } else {
-                    true
-                };
+    /// Aggregated metadata, ordered by latest watched first
+    pub fn data(&self) -> &[Metadata] {
+        &self.data
+    }
 
-                let title = if let Some(v) = &filter.title {
-                    x.title.to_lowercase().contains(&v.to_lowercase())
-                } else {
-                    true
-                };
+    /// Every watch paired with its metadata, oldest first, so the n-th watch
+    /// is a plain index
+    pub fn watch_events(&self) -> Vec<(DateTime<Utc>, &Metadata)> {
+        let mut events = Vec::with_capacity(self.watch_timeline.len());
 
-                let channel_name = if let Some(v) = &filter.channel_name {
-                    x.channel.name.to_lowercase().contains(&v.to_lowercase())
-                } else {
-                    true
-                };
+        for m in self.data.iter() {
+            events.extend(m.watch_timeline.iter().map(|t| (*t, m)));
+        }
 
-                let from = if let Some(v) = &filter.from {
-                    x.watched_at > *v
-                } else {
-                    true
-                };
+        events.sort_by_key(|(t, _)| *t);
 
-                let to = if let Some(v) = &filter.to {
-                    x.watched_at < *v
-                } else {
-                    true
-                };
+        events
+    }
 
-                id && title && channel_name && from && to
+    /// One raw entry per watch, newest first like Takeout. Products are only
+    /// known per video and repeated on each of its watches
+    pub fn to_watch_entries(&self) -> Vec<WatchEntry> {
+        let mut entries = self
+            .watch_events()
+            .into_iter()
+            .map(|(time, m)| WatchEntry {
+                id: m.id.clone(),
+                title: m.title.to_string(),
+                channel: m.channel.clone(),
+                time,
+                products: m.products.iter().map(|v| v.to_string()).collect(),
+                ad: m.ad,
             })
-            .cloned()
-            .collect::<Vec<Metadata>>();
+            .collect::<Vec<WatchEntry>>();
+
+        entries.reverse();
+
+        entries
+    }
+
+    /// Find aggregated metadata by video ID
+    pub fn get(&self, id: &str) -> Option<&Metadata> {
+        self.data.iter().find(|v| v.id == id)
+    }
+
+    /// Number of metadata passing the filter, without collecting them
+    pub fn count(&self, filter: &ValidatedFilter) -> usize {
+        self.count_until(filter, None).expect("no deadline to miss")
+    }
+
+    /// Same as `count`, given up once `deadline` has passed
+    pub fn count_until(
+        &self,
+        filter: &ValidatedFilter,
+        deadline: Option<Instant>,
+    ) -> Result<usize, QueryTooExpensive> {
+        if filter.view == View::Events {
+            return self.events_until(filter, deadline).map(|v| v.len());
+        }
+
+        if filter.skip() && (filter.ads || !self.has_ads()) {
+            return Ok(self.data.len());
+        }
+
+        let (from, to) = filter.bounds();
+        let mut count = 0;
+
+        for (i, x) in self.data.iter().enumerate() {
+            if i % DEADLINE_CHECK_INTERVAL == 0 {
+                check_deadline(deadline)?;
+            }
+
+            count += usize::from(filter.matches_within(x, from, to));
+        }
+
+        Ok(count)
+    }
+
+    /// Watch events of the matching videos within the filter bounds, latest
+    /// first
+    fn events_until(
+        &self,
+        filter: &ValidatedFilter,
+        deadline: Option<Instant>,
+    ) -> Result<Vec<(DateTime<Utc>, &Metadata)>, QueryTooExpensive> {
+        let (from, to) = filter.bounds();
+        let mut events = Vec::new();
+
+        for (i, x) in self.data.iter().enumerate() {
+            if i % DEADLINE_CHECK_INTERVAL == 0 {
+                check_deadline(deadline)?;
+            }
+
+            // the bounds apply to each watch below, not to the video
+            if filter.matches_within(x, None, None) {
+                events.extend(
+                    x.watch_timeline
+                        .iter()
+                        .filter(|t| filter.boundaries.contains(**t, from, to))
+                        .map(|t| (*t, x)),
+                );
+            }
+        }
+
+        check_deadline(deadline)?;
+        events.sort_by_key(|(t, _)| std::cmp::Reverse(*t));
+
+        Ok(events)
+    }
+
+    /// Page of single watches for `view=events`, the `oldest` order lists
+    /// them oldest first and any other order latest first
+    pub fn get_events_until(
+        &self,
+        filter: &ValidatedFilter,
+        deadline: Option<Instant>,
+    ) -> Result<(Pagination, Vec<WatchEvent>), QueryTooExpensive> {
+        let mut events = self.events_until(filter, deadline)?;
+
+        if filter.order == Order::Oldest {
+            events.reverse();
+        }
+
+        let (left, right, total_page) = page_bounds(events.len(), filter.page, filter.limit);
+        let data = events[left..right]
+            .iter()
+            .map(|(time, m)| WatchEvent {
+                id: m.id.clone(),
+                title: m.title.clone(),
+                channel: m.channel.clone(),
+                time: *time,
+            })
+            .collect();
+
+        Ok((Pagination::new(filter.page, total_page, filter.limit), data))
+    }
+
+    pub fn get_collection(&self, filter: &ValidatedFilter) -> (Pagination, Vec<Metadata>) {
+        self.get_collection_until(filter, None)
+            .expect("no deadline to miss")
+    }
+
+    /// Same as `get_collection`, given up once `deadline` has passed. Checked
+    /// while filtering and around sorting, which can't be interrupted
+    #[tracing::instrument(skip_all)]
+    pub fn get_collection_until(
+        &self,
+        filter: &ValidatedFilter,
+        deadline: Option<Instant>,
+    ) -> Result<(Pagination, Vec<Metadata>), QueryTooExpensive> {
+        // `data` is already ordered by latest, only the page needs cloning
+        if filter.skip() && (filter.ads || !self.has_ads()) && filter.order == Order::Latest {
+            let (left, right, total_page) = page_bounds(self.data.len(), filter.page, filter.limit);
+
+            return Ok((
+                Pagination::new(filter.page, total_page, filter.limit),
+                self.data[left..right].to_vec(),
+            ));
+        }
+
+        let (from, to) = filter.bounds();
+        let mut filtered = Vec::new();
+
+        for (i, x) in self.data.iter().enumerate() {
+            if i % DEADLINE_CHECK_INTERVAL == 0 {
+                check_deadline(deadline)?;
+            }
+
+            if filter.matches_within(x, from, to) {
+                filtered.push(x.clone());
+            }
+        }
+
+        check_deadline(deadline)?;
 
         match filter.order {
             Order::Oldest => {
@@ -220,55 +1378,248 @@ impl MetadataTable {
             Order::LeastWatched => {
                 filtered.sort_by_key(|v| v.watch_count);
             }
+            Order::RecentlyWatched => {
+                filtered.sort_by_key(|v| std::cmp::Reverse(v.last_watched_at));
+            }
+            Order::LeastRecentlyWatched => {
+                filtered.sort_by_key(|v| v.last_watched_at);
+            }
+            Order::Relevance => {
+                let now = Utc::now();
+                let half_life = Duration::days(self.half_life_days.into());
+                let mut scored = filtered
+                    .into_iter()
+                    .map(|v| (v.relevance(now, half_life), v))
+                    .collect::<Vec<(f64, Metadata)>>();
+
+                scored.sort_by(|a, b| {
+                    b.0.total_cmp(&a.0)
+                        .then_with(|| b.1.last_watched_at.cmp(&a.1.last_watched_at))
+                });
+                filtered = scored.into_iter().map(|(_, v)| v).collect();
+            }
+            Order::Alphabetical => {
+                filtered.sort_by_cached_key(|v| (self.collation.key(&v.title), v.title.clone()));
+            }
+            Order::ReverseAlphabetical => {
+                filtered.sort_by_cached_key(|v| (self.collation.key(&v.title), v.title.clone()));
+                filtered.reverse();
+            }
             _ => {}
         }
 
-        let total_item = filtered.len();
-        let total_page = (total_item as f64 / filter.limit as f64).ceil() as usize;
-
-        let page_offset = filter.page * filter.limit;
-        let limit_offset = filter.limit;
+        // Stable, so the order only breaks ties between equally good matches
+        if filter.fuzzy || filter.search.is_some() {
+            check_deadline(deadline)?;
+            filtered.sort_by_cached_key(|v| {
+                (std::cmp::Reverse(filter.search_score(v)), filter.typos(v))
+            });
+        }
 
-        let right = page_offset.min(total_item);
+        check_deadline(deadline)?;
 
-        let left = if page_offset < total_item {
-            page_offset.saturating_sub(limit_offset)
-        } else {
-            (total_item - (total_item % limit_offset)).max(0)
-        };
+        let (left, right, total_page) = page_bounds(filtered.len(), filter.page, filter.limit);
 
         filtered.drain(right..);
         filtered.drain(..left);
 
-        (
+        Ok((
             Pagination::new(filter.page, total_page, filter.limit),
             filtered,
-        )
+        ))
+    }
+}
+
+/// Entries filtered between two looks at the clock
+const DEADLINE_CHECK_INTERVAL: usize = 4096;
+
+/// QueryTooExpensive
+///
+/// A filter still running when its deadline passed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QueryTooExpensive;
+
+impl fmt::Display for QueryTooExpensive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("query too expensive, narrow it down")
+    }
+}
+
+impl std::error::Error for QueryTooExpensive {}
+
+fn check_deadline(deadline: Option<Instant>) -> Result<(), QueryTooExpensive> {
+    match deadline {
+        Some(v) if Instant::now() > v => Err(QueryTooExpensive),
+        _ => Ok(()),
+    }
+}
+
+/// Item range and total pages of `page` over `total_item` items
+pub(crate) fn page_bounds(total_item: usize, page: usize, limit: usize) -> (usize, usize, usize) {
+    let total_page = (total_item as f64 / limit as f64).ceil() as usize;
+
+    let page_offset = page.saturating_mul(limit);
+
+    let right = page_offset.min(total_item);
+
+    // past the end shows the last page
+    let left = if page_offset <= total_item {
+        page_offset.saturating_sub(limit)
+    } else {
+        total_item.saturating_sub(1) / limit * limit
+    };
+
+    (left, right, total_page)
+}
+
+/// Interner
+///
+/// Pool of shared strings, repeated values like channel names are stored once
+#[derive(Debug, Default)]
+pub struct Interner {
+    pool: FxHashSet<Arc<str>>,
+}
+
+impl Interner {
+    pub fn intern(&mut self, s: &str) -> Arc<str> {
+        if let Some(v) = self.pool.get(s) {
+            return v.clone();
+        }
+
+        let v: Arc<str> = Arc::from(s);
+        self.pool.insert(v.clone());
+
+        v
     }
 }
 
 /// Channel
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, JsonSchema)]
 pub struct Channel {
+    pub id: Arc<str>,
+    pub name: Arc<str>,
+}
+
+impl Channel {
+    /// Grouping key, channel id is missing on some entries so it falls back
+    /// to the name
+    pub fn key(&self) -> &str {
+        if &*self.id == "-" {
+            &self.name
+        } else {
+            &self.id
+        }
+    }
+}
+
+impl PartialEq for Channel {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Channel {}
+
+impl Hash for Channel {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl PartialOrd for Channel {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Channel {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(other.key())
+    }
+}
+
+/// WatchEntry
+///
+/// Single raw watch record, independent from the source schema
+#[derive(Clone, Debug)]
+pub struct WatchEntry {
+    pub id: String,
+    pub title: String,
+    pub channel: Channel,
+    pub time: DateTime<Utc>,
+    /// Products the entry was logged from, e.g. `YouTube` or `YouTube Music`
+    pub products: Vec<String>,
+    /// Ad impression rather than a watch, Takeout marks these with
+    /// `From Google Ads` details
+    pub ad: bool,
+}
+
+/// WatchEvent
+///
+/// Single watch of a video, a row of `view=events`
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct WatchEvent {
     pub id: String,
-    pub name: String,
+    pub title: Arc<str>,
+    pub channel: Channel,
+    pub time: DateTime<Utc>,
+}
+
+impl WatchEvent {
+    pub fn to_datetime_local(&self) -> String {
+        self.time.to_datetime_string()
+    }
 }
 
 /// Metadata
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, JsonSchema)]
 pub struct Metadata {
     pub id: String,
-    pub title: String,
+    pub title: Arc<str>,
     pub channel: Channel,
+    /// Earliest watch
     pub watched_at: DateTime<Utc>,
+    /// Latest watch
+    pub last_watched_at: DateTime<Utc>,
     pub watch_count: usize,
     pub watch_timeline: Vec<DateTime<Utc>>,
+    /// Every product the video was watched on
+    pub products: Vec<Arc<str>>,
+    /// Only ever shown as an ad, left out of results unless the filter asks
+    /// for `ads`
+    pub ad: bool,
 }
 
 impl Metadata {
     pub fn to_datetime_local(&self) -> String {
         self.watched_at.to_datetime_string()
     }
+
+    pub fn to_last_datetime_local(&self) -> String {
+        self.last_watched_at.to_datetime_string()
+    }
+
+    /// Last few watch times, latest first, formatted in local time
+    pub fn recent_watches(&self) -> Vec<String> {
+        self.watch_timeline
+            .iter()
+            .rev()
+            .take(RECENT_WATCHES_LEN)
+            .map(|v| v.to_datetime_string())
+            .collect()
+    }
+
+    /// Watches weighted by age, a watch at `now` counts 1 and half as much
+    /// every `half_life` before
+    pub fn relevance(&self, now: DateTime<Utc>, half_life: Duration) -> f64 {
+        let half_life = half_life.as_seconds_f64();
+
+        self.watch_timeline
+            .iter()
+            .map(|t| (now - *t).as_seconds_f64().max(0.0) / half_life)
+            .map(|v| (-v).exp2())
+            .sum()
+    }
 }
 
 impl PartialOrd for Metadata {
@@ -291,93 +1642,421 @@ impl PartialEq for Metadata {
 
 impl Eq for Metadata {}
 
-/// Is version 1 json structure
-fn is_v1<R: BufRead>(reader: R) -> bool {
-    let keys: HashSet<&str> = [
-        "\"header\"",
-        "\"title\"",
-        "\"titleUrl\"",
-        "\"subtitles\"",
-        "\"name\"",
-        "\"url\"",
-        "\"time\"",
-        "\"products\"",
-    ]
-    .into();
-
-    is_buffer_contains_keywords(reader, &keys)
-}
-
-/// Load version 1 schema
-fn load_v1<R: BufRead>(reader: R) -> Result<MetadataTable> {
-    log::debug!("Match schema version: 1");
-
-    let raw: Vec<v1::Schema> = serde_json::from_reader(reader)?;
-    let mut total_count_raw: usize = 0;
-    let mut watch_timeline: Vec<DateTime<Utc>> = Vec::new();
-    let mut map: HashMap<String, Metadata> = HashMap::new();
-
-    for r in raw {
-        total_count_raw += 1;
-        watch_timeline.push(r.time);
-
-        if map.contains_key(&r.id) {
-            if let Some(m) = map.get_mut(&r.id) {
-                // watched_at always the earliest
-                if r.time < m.watched_at {
-                    m.watched_at = r.time;
-                }
+/// Load metadata from a history file
+#[tracing::instrument(skip_all, fields(path = %path.display()))]
+pub fn load_metadata_from_file(path: &Path) -> Result<MetadataTable> {
+    log::debug!("Loading metadata from file...");
 
-                m.watch_count += 1;
-                m.watch_timeline.push(r.time);
-                m.watch_timeline.sort();
-            }
-        } else {
-            let m = Metadata {
-                id: r.id.clone(),
-                title: r.title,
-                channel: Channel {
-                    id: r.channel.id,
-                    name: r.channel.name,
-                },
-                watched_at: r.time,
-                watch_count: 1,
-                watch_timeline: vec![r.time],
-            };
+    if !path.is_file() {
+        bail!("`{}` is not a file", path.display());
+    }
+
+    let started = Instant::now();
+    let meta = path.metadata()?;
+    let Some((loader, mut rdr)) = open_history(path)? else {
+        bail!("Unrecognized file structure. The content does not match any registered schema")
+    };
+    let mut metadata_table = MetadataTable::try_from_entries(loader.load(&mut rdr)?)?;
+
+    metadata_table.source = Some(SourceInfo {
+        path: path.to_path_buf(),
+        size: meta.len(),
+        modified_at: meta.modified().ok().map(DateTime::<Utc>::from),
+        schema_version: loader.schema_version(),
+        parse_ms: started.elapsed().as_millis(),
+        parse_report: None,
+    });
+
+    Ok(metadata_table)
+}
+
+/// Load the history files and directories of `paths` into one table.
+/// Directories are read for their JSON, HTML and zip files, skipping the ones
+/// holding no history. Watches found in several exports are counted once.
+/// `lenient` skips malformed entries instead of failing, they are listed in
+/// the [`ParseReport`] of the source
+#[tracing::instrument(skip_all)]
+pub fn load_metadata_from_paths(paths: &[PathBuf], lenient: bool) -> Result<MetadataTable> {
+    let started = Instant::now();
+    let mut files = Vec::new();
 
-            map.insert(r.id.clone(), m);
+    for path in paths {
+        if path.is_dir() {
+            files.extend(history_files_in(path)?.into_iter().map(|v| (v, true)));
+        } else if path.is_file() {
+            files.push((path.clone(), false));
+        } else {
+            bail!("`{}` is not a file", path.display());
         }
     }
 
-    let mut data = map.into_values().collect::<Vec<Metadata>>();
-    data.sort_by(|a, b| b.watched_at.cmp(&a.watched_at));
-    watch_timeline.sort();
+    let mut loaded = Vec::new();
+    let mut size = 0;
+    let mut modified_at = None;
+    let mut schema_versions = FxHashSet::default();
 
-    Ok(MetadataTable {
-        total_count_raw,
-        total_count: data.len(),
-        watch_timeline,
-        data,
-    })
+    for (path, optional) in files {
+        log::debug!("Loading metadata from `{}`...", path.display());
+
+        let meta = path.metadata()?;
+        let Some((loader, rdr)) = open_history(&path)? else {
+            if optional {
+                log::warn!("Skipped `{}`, no history recognized", path.display());
+                continue;
+            }
+
+            bail!(
+                "Unrecognized file structure of `{}`. The content does not match any registered schema",
+                path.display()
+            );
+        };
+
+        size += meta.len();
+        modified_at = modified_at.max(meta.modified().ok().map(DateTime::<Utc>::from));
+        schema_versions.insert(loader.schema_version());
+        loaded.push((path, loader, rdr));
+    }
+
+    if loaded.is_empty() {
+        bail!("No history found in the given paths");
+    }
+
+    let merging = loaded.len() > 1;
+    if merging {
+        log::info!("Merging {} history files", loaded.len());
+    }
+
+    let mut streams = Vec::new();
+    for (path, loader, rdr) in &mut loaded {
+        let path: &Path = path;
+        streams.push(loader.load(rdr)?.map(move |v| (path, v)));
+    }
+
+    // overlapping exports repeat the same watches, the time tells them apart
+    // from rewatches
+    let mut seen = FxHashSet::default();
+    let mut report = ParseReport::default();
+    let entries = streams
+        .into_iter()
+        .flatten()
+        .filter_map(|(path, v)| match v {
+            Ok(v) => (!merging || seen.insert((v.id.clone(), v.time))).then_some(Ok(v)),
+            Err(e) => match e.downcast_ref::<MalformedEntry>() {
+                Some(entry) if lenient => {
+                    report.add(path, entry);
+                    None
+                }
+                _ => Some(Err(
+                    e.context(format!("failed to load `{}`", path.display()))
+                )),
+            },
+        });
+    let mut metadata_table = MetadataTable::try_from_entries(entries)?;
+
+    if report.skipped > 0 {
+        log::warn!("Skipped {} malformed entries", report.skipped);
+    }
+
+    metadata_table.source = Some(SourceInfo {
+        path: paths[0].clone(),
+        size,
+        modified_at,
+        schema_version: match schema_versions.len() {
+            1 => schema_versions.into_iter().next().flatten(),
+            _ => None,
+        },
+        parse_ms: started.elapsed().as_millis(),
+        parse_report: lenient.then_some(report),
+    });
+
+    Ok(metadata_table)
 }
 
-/// Load metadata from a json file
-pub fn load_metadata_from_file(path: &PathBuf) -> Result<MetadataTable> {
-    log::debug!("Loading metadata from file...");
+/// JSON, HTML and zip files of a directory, sorted by name
+fn history_files_in(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let extension = path.extension().map(|v| v.to_string_lossy().to_lowercase());
+
+        if path.is_file() && matches!(extension.as_deref(), Some("json" | "html" | "zip")) {
+            files.push(path);
+        }
+    }
 
-    if !is_json_file(path) {
-        bail!("Unsupported file format. Please use valid JSON file");
+    if files.is_empty() {
+        bail!("No history files in `{}`", dir.display());
     }
 
+    files.sort();
+
+    Ok(files)
+}
+
+/// Loader of a history file along with the reader it loads from
+type OpenHistory = (Arc<dyn SchemaLoader>, Box<dyn Source>);
+
+/// Open a history file and detect its format, a zip archive is searched for
+/// the history first. `None` when nothing is recognized
+fn open_history(path: &Path) -> Result<Option<OpenHistory>> {
     let file = File::open(path)?;
-    let mut rdr = BufReader::new(file);
 
-    if is_v1(&mut rdr) {
-        let _ = rdr.seek(SeekFrom::Start(0))?;
-        let metadata_table = load_v1(rdr)?;
+    // parsed in memory, nothing of the archive is extracted to disk
+    let mut rdr: Box<dyn Source> = if is_zip_file(path) {
+        let Some((name, bytes)) = archive::find_history_in_zip(BufReader::new(file))? else {
+            return Ok(None);
+        };
+        log::info!("Loading `{}` from the archive", name);
 
-        Ok(metadata_table)
+        Box::new(Decoded::new(Cursor::new(bytes))?)
     } else {
-        bail!("Unrecognized JSON structure. The JSON structure does not match any defined schema");
+        Box::new(Decoded::new(BufReader::new(file))?)
+    };
+
+    Ok(detect_loader(&mut rdr)?.map(|loader| (loader, rdr)))
+}
+
+/// Detect schema version, the reader is rewound afterward. Expects UTF-8,
+/// wrap other encodings in [`Decoded`] first
+pub fn detect_schema_version<R: BufRead + Seek>(rdr: &mut R) -> Result<Option<u32>> {
+    Ok(detect_loader(rdr)?.and_then(|l| l.schema_version()))
+}
+
+/// Load metadata from any seekable reader, e.g. in-memory bytes through
+/// `std::io::Cursor`. UTF-16 and BOM prefixed content is transcoded first
+pub fn load_metadata_from_reader<R: BufRead + Seek>(rdr: R) -> Result<MetadataTable> {
+    let mut rdr = Decoded::new(rdr)?;
+    let loader = find_loader(&mut rdr)?;
+
+    MetadataTable::try_from_entries(loader.load(&mut rdr)?)
+}
+
+fn find_loader(rdr: &mut dyn Source) -> Result<Arc<dyn SchemaLoader>> {
+    match detect_loader(rdr)? {
+        Some(v) => Ok(v),
+        None => {
+            bail!("Unrecognized file structure. The content does not match any registered schema")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::{at, watch};
+
+    #[test]
+    fn collapse_repeats() {
+        let mut table = MetadataTable::from_entries([
+            watch("a", "2024-01-01T12:00:00Z"),
+            watch("a", "2024-01-01T12:00:50Z"),
+            // measured from the last kept watch, not the previous repeat
+            watch("a", "2024-01-01T12:01:40Z"),
+            watch("a", "2024-01-01T12:05:00Z"),
+            // exactly one window later still counts as a repeat
+            watch("b", "2024-01-01T12:00:10Z"),
+            watch("b", "2024-01-01T12:01:10Z"),
+        ]);
+
+        assert_eq!(table.collapse_repeats(Duration::seconds(60)), 2);
+        assert_eq!(table.collapsed_count(), 2);
+        assert_eq!(table.total_count_raw(), 4);
+        assert_eq!(table.total_count(), 2);
+        assert_eq!(
+            table.watch_timeline(),
+            [
+                at("2024-01-01T12:00:00Z"),
+                at("2024-01-01T12:00:10Z"),
+                at("2024-01-01T12:01:40Z"),
+                at("2024-01-01T12:05:00Z"),
+            ]
+        );
+
+        let a = table.data().iter().find(|m| m.id == "a").unwrap();
+        assert_eq!(a.watch_count, 3);
+        assert_eq!(a.watched_at, at("2024-01-01T12:00:00Z"));
+        assert_eq!(a.last_watched_at, at("2024-01-01T12:05:00Z"));
+
+        let b = table.data().iter().find(|m| m.id == "b").unwrap();
+        assert_eq!(b.watch_count, 1);
+        assert_eq!(b.last_watched_at, at("2024-01-01T12:00:10Z"));
+
+        // collapsing again adds up
+        assert_eq!(table.collapse_repeats(Duration::seconds(300)), 2);
+        assert_eq!(table.collapsed_count(), 4);
+        assert_eq!(table.total_count_raw(), 2);
+    }
+
+    #[test]
+    fn collapse_nothing() {
+        let mut table = MetadataTable::from_entries([
+            watch("a", "2024-01-01T12:00:00Z"),
+            watch("a", "2024-01-02T12:00:00Z"),
+        ]);
+
+        assert_eq!(table.collapse_repeats(Duration::zero()), 0);
+        assert_eq!(table.collapse_repeats(Duration::seconds(60)), 0);
+        assert_eq!(table.collapsed_count(), 0);
+        assert_eq!(table.total_count_raw(), 2);
+
+        assert_eq!(table.collapse_repeats(Duration::MAX), 1);
+        assert_eq!(table.data()[0].watch_timeline, [at("2024-01-01T12:00:00Z")]);
+    }
+
+    fn ids(table: &MetadataTable, query: &str) -> Vec<String> {
+        let filter = ValidatedFilter::parse(query).unwrap();
+
+        table
+            .get_collection(&filter)
+            .1
+            .into_iter()
+            .map(|v| v.id)
+            .collect()
+    }
+
+    #[test]
+    fn first_and_last_watch() {
+        let table = MetadataTable::from_entries([
+            watch("a", "2024-03-01T12:00:00Z"),
+            watch("a", "2024-01-01T12:00:00Z"),
+            watch("b", "2024-02-01T12:00:00Z"),
+            watch("c", "2023-12-01T12:00:00Z"),
+            watch("c", "2024-04-01T12:00:00Z"),
+        ]);
+
+        let a = table.data().iter().find(|m| m.id == "a").unwrap();
+        assert_eq!(a.watched_at, at("2024-01-01T12:00:00Z"));
+        assert_eq!(a.last_watched_at, at("2024-03-01T12:00:00Z"));
+
+        let b = table.data().iter().find(|m| m.id == "b").unwrap();
+        assert_eq!(b.watched_at, b.last_watched_at);
+
+        assert_eq!(ids(&table, "order=recently_watched"), ["c", "a", "b"]);
+        assert_eq!(ids(&table, "order=least_recently_watched"), ["b", "a", "c"]);
+
+        assert_eq!(ids(&table, "last_from=2024-02-15T00:00:00Z"), ["a", "c"]);
+        assert_eq!(ids(&table, "last_to=2024-02-15T00:00:00Z"), ["b"]);
+
+        let march = "from=2024-03-01T00:00:00Z&to=2024-03-31T00:00:00Z";
+        assert!(ids(&table, march).is_empty());
+        assert_eq!(ids(&table, &format!("{march}&match=last_watch")), ["a"]);
+    }
+
+    #[test]
+    fn pages() {
+        assert_eq!(page_bounds(25, 1, 10), (0, 10, 3));
+        assert_eq!(page_bounds(25, 3, 10), (20, 25, 3));
+        assert_eq!(page_bounds(20, 2, 10), (10, 20, 2));
+
+        // past the end shows the last page
+        assert_eq!(page_bounds(25, 4, 10), (20, 25, 3));
+        assert_eq!(page_bounds(20, 5, 10), (10, 20, 2));
+        assert_eq!(page_bounds(25, usize::MAX, 10), (20, 25, 3));
+
+        assert_eq!(page_bounds(0, 1, 10), (0, 0, 0));
+        assert_eq!(page_bounds(0, 3, 10), (0, 0, 0));
+    }
+
+    #[test]
+    fn boundaries() {
+        let (from, to) = (at("2024-01-01T00:00:00Z"), at("2024-01-02T00:00:00Z"));
+        let inside = at("2024-01-01T12:00:00Z");
+        let cases = [
+            (Boundaries::Inclusive, [true, true, true]),
+            (Boundaries::Exclusive, [false, true, false]),
+            (Boundaries::HalfOpen, [true, true, false]),
+        ];
+
+        for (boundaries, expected) in cases {
+            let found = [from, inside, to].map(|t| boundaries.contains(t, Some(from), Some(to)));
+            assert_eq!(found, expected, "{boundaries}");
+
+            // `None` sides are unbounded
+            assert!(boundaries.contains(to, Some(from), None));
+            assert!(boundaries.contains(from, None, Some(to)));
+            assert!(boundaries.contains(at("1970-01-01T00:00:00Z"), None, None));
+            assert!(!boundaries.contains(at("2023-12-31T00:00:00Z"), Some(from), None));
+        }
+    }
+
+    #[test]
+    fn filter_times() {
+        let local = |s: &str| {
+            let time = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f").unwrap();
+
+            Local
+                .from_local_datetime(&time)
+                .earliest()
+                .unwrap()
+                .to_utc()
+        };
+
+        assert_eq!(
+            parse_filter_time("2024-01-01T12:00:00+02:00", false),
+            Some(at("2024-01-01T10:00:00Z"))
+        );
+        assert_eq!(
+            parse_filter_time("2024-01-01T12:00:00Z", true),
+            Some(at("2024-01-01T12:00:00Z"))
+        );
+
+        // without an offset the time is local
+        assert_eq!(
+            parse_filter_time("2024-01-01T08:30", false),
+            Some(local("2024-01-01 08:30:00.0"))
+        );
+        assert_eq!(
+            parse_filter_time("2024-01-01T08:30:15", true),
+            Some(local("2024-01-01 08:30:15.0"))
+        );
+
+        // a date alone spans the whole local day
+        assert_eq!(
+            parse_filter_time("2024-01-01", false),
+            Some(local("2024-01-01 00:00:00.0"))
+        );
+        assert_eq!(
+            parse_filter_time("2024-01-01", true),
+            Some(local("2024-01-01 23:59:59.999999999"))
+        );
+
+        for s in [
+            "",
+            "yesterday",
+            "2024-13-01",
+            "2024-01-01T25:00",
+            "01/02/2024",
+        ] {
+            assert_eq!(parse_filter_time(s, false), None, "{s}");
+        }
+    }
+
+    #[test]
+    fn merged_duplicates() {
+        let titled = |id: &str, title: &str, time: &str, ad: bool| WatchEntry {
+            title: title.to_owned(),
+            ad,
+            ..watch(id, time)
+        };
+        let mut table = MetadataTable::from_entries([
+            titled("a", "Song Name", "2024-01-01T12:00:00Z", false),
+            titled("a", "Song Name", "2024-01-02T12:00:00Z", false),
+            titled("b", "Song Name [HD]", "2024-01-03T12:00:00Z", false),
+            titled("c", "Song Name (Ad)", "2024-01-04T12:00:00Z", true),
+            titled("d", "Song Name (Ad)", "2024-01-05T12:00:00Z", true),
+        ]);
+
+        // ads only fold into ads
+        assert_eq!(table.merge_duplicates(), 2);
+        assert_eq!(table.total_count(), 2);
+
+        let a = table.data().iter().find(|m| m.id == "a").unwrap();
+        assert!(!a.ad);
+        assert_eq!(a.watch_count, 3);
+        assert_eq!(a.last_watched_at, at("2024-01-03T12:00:00Z"));
+
+        let ad = table.data().iter().find(|m| m.ad).unwrap();
+        assert_eq!(ad.watch_count, 2);
     }
 }
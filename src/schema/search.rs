@@ -0,0 +1,197 @@
+use icu_normalizer::{
+    DecomposingNormalizerBorrowed, properties::CanonicalCombiningClassMapBorrowed,
+};
+
+/// Typos tolerated in a query word, none up to 4 characters, one up to 8
+/// and two past that
+fn max_typos(len: usize) -> usize {
+    match len {
+        0..=4 => 0,
+        5..=8 => 1,
+        _ => 2,
+    }
+}
+
+/// Lowercased words with accents, punctuation and full-width forms folded
+/// away
+fn words(s: &str) -> Vec<Vec<char>> {
+    let nfkd = DecomposingNormalizerBorrowed::new_nfkd();
+    let ccc = CanonicalCombiningClassMapBorrowed::new();
+
+    let mut words = Vec::new();
+    let mut word = Vec::new();
+
+    for c in nfkd.normalize(s).chars() {
+        if ccc.get_u8(c) != 0 {
+            continue;
+        }
+
+        if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+        } else if !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+/// Fewest edits turning `query` into `word`, or into a prefix of it with
+/// `prefix` so an unfinished word at the end of a search still matches.
+/// Swapping two adjacent letters counts as one edit
+fn distance(query: &[char], word: &[char], prefix: bool) -> usize {
+    let mut before = vec![0; word.len() + 1];
+    let mut prev = (0..=word.len()).collect::<Vec<usize>>();
+    let mut curr = vec![0; word.len() + 1];
+
+    for (i, q) in query.iter().enumerate() {
+        curr[0] = i + 1;
+
+        for (j, w) in word.iter().enumerate() {
+            let cost = usize::from(q != w);
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+
+            if i > 0 && j > 0 && *q == word[j - 1] && query[i - 1] == *w {
+                curr[j + 1] = curr[j + 1].min(before[j - 1] + 1);
+            }
+        }
+
+        std::mem::swap(&mut before, &mut prev);
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    if !prefix {
+        return prev[word.len()];
+    }
+
+    prev.into_iter().min().unwrap_or(0)
+}
+
+/// Whether `query` occurs in `text` without a letter or digit right before
+/// or after it, `go` is found in `let's go!` but not in `google`
+pub fn contains_word(text: &str, query: &str) -> bool {
+    let starts_word = query.starts_with(char::is_alphanumeric);
+    let ends_word = query.ends_with(char::is_alphanumeric);
+    let mut start = 0;
+
+    while let Some(i) = text[start..].find(query).map(|i| start + i) {
+        let before = text[..i].chars().next_back();
+        let after = text[i + query.len()..].chars().next();
+
+        let joined = starts_word && before.is_some_and(char::is_alphanumeric)
+            || ends_word && after.is_some_and(char::is_alphanumeric);
+
+        if !joined {
+            return true;
+        }
+
+        start = i + text[i..].chars().next().map_or(1, char::len_utf8);
+    }
+
+    false
+}
+
+/// Typos needed for `query` to match `text`, lower is a better match
+///
+/// Plain case insensitive containment costs nothing, otherwise every word
+/// of the query has to be within its tolerated typos of some word of the
+/// text, `None` when one is not. `whole_word` compares whole words only
+pub fn fuzzy_distance(query: &str, text: &str, whole_word: bool) -> Option<usize> {
+    let (query_lower, text_lower) = (query.to_lowercase(), text.to_lowercase());
+    let found = if whole_word {
+        contains_word(&text_lower, &query_lower)
+    } else {
+        text_lower.contains(&query_lower)
+    };
+
+    if found {
+        return Some(0);
+    }
+
+    let text = words(text);
+    let mut total = 0;
+
+    for q in words(query) {
+        let typos = text
+            .iter()
+            .map(|w| distance(&q, w, !whole_word))
+            .min()
+            .filter(|v| *v <= max_typos(q.len()))?;
+
+        total += typos;
+    }
+
+    Some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typos() {
+        assert_eq!(max_typos(4), 0);
+        assert_eq!(max_typos(8), 1);
+        assert_eq!(max_typos(9), 2);
+
+        assert_eq!(fuzzy_distance("RUST", "Learning rust fast", false), Some(0));
+        assert_eq!(
+            fuzzy_distance("progrmming", "Programming in Rust", false),
+            Some(1)
+        );
+
+        // a swap of two letters is one edit
+        assert_eq!(
+            fuzzy_distance("pyhton tutorial", "Python Tutorial", false),
+            Some(1)
+        );
+        assert_eq!(fuzzy_distance("rsut", "Learning rust", false), None);
+
+        assert_eq!(fuzzy_distance("xyzzy", "Learning rust", false), None);
+        assert_eq!(
+            fuzzy_distance("python rsut", "Python Tutorial", false),
+            None
+        );
+    }
+
+    #[test]
+    fn folded_and_prefix_words() {
+        assert_eq!(fuzzy_distance("beyonce", "Beyoncé - Halo", false), Some(0));
+        assert_eq!(fuzzy_distance("ｈａｌｏ", "Beyoncé - Halo", false), Some(0));
+
+        // an unfinished last word matches a prefix, unless whole words only
+        assert_eq!(
+            fuzzy_distance("tutorisl", "Tutorials for beginners", false),
+            Some(1)
+        );
+        assert_eq!(
+            fuzzy_distance("tutorisl", "Tutorials for beginners", true),
+            None
+        );
+
+        assert_eq!(fuzzy_distance("go", "google", false), Some(0));
+        assert_eq!(fuzzy_distance("go", "google", true), None);
+    }
+
+    #[test]
+    fn whole_words() {
+        assert!(contains_word("let's go!", "go"));
+        assert!(contains_word("go", "go"));
+        assert!(!contains_word("google", "go"));
+        assert!(!contains_word("ergo", "go"));
+
+        // a joined match doesn't hide a later free one
+        assert!(contains_word("ago, go", "go"));
+        assert!(contains_word("café go", "go"));
+        assert!(!contains_word("naïvego", "go"));
+
+        // punctuation at the edges of the query needs no word boundary
+        assert!(contains_word("c++ rocks", "c++"));
+        assert!(contains_word("learn c++17", "c++"));
+        assert!(!contains_word("abc++", "c++"));
+    }
+}
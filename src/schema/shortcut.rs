@@ -0,0 +1,223 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{Result, bail};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Longest `last_<n>d` accepted, about a century
+pub const MAX_LAST_DAYS: u32 = 36500;
+
+/// PeriodShortcut
+///
+/// Named date range resolved against the current local time, one of
+/// `last_<n>d`, `this_month`, `this_year` or a year such as `2019`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PeriodShortcut {
+    LastDays(u32),
+    ThisMonth,
+    ThisYear,
+    Year(i32),
+}
+
+impl PeriodShortcut {
+    /// Shortcuts offered by the web UI, years of the watch timeline newest
+    /// first after the relative ones, paired with their labels
+    pub fn collect_key_label_pair(timeline: &[DateTime<Utc>]) -> Vec<(String, String)> {
+        let mut list = vec![
+            Self::LastDays(7),
+            Self::LastDays(30),
+            Self::ThisMonth,
+            Self::ThisYear,
+        ];
+
+        if let (Some(first), Some(last)) = (timeline.first(), timeline.last()) {
+            let first = first.with_timezone(&Local).year();
+            let last = last.with_timezone(&Local).year();
+
+            list.extend((first..=last).rev().map(Self::Year));
+        }
+
+        list.into_iter()
+            .map(|v| (v.to_string(), v.to_string_label()))
+            .collect()
+    }
+
+    pub fn to_string_label(&self) -> String {
+        match self {
+            Self::LastDays(n) => format!("Last {} days", n),
+            Self::ThisMonth => String::from("This month"),
+            Self::ThisYear => String::from("This year"),
+            Self::Year(y) => y.to_string(),
+        }
+    }
+
+    /// `from` and `to` bounds at `now`, unbounded on `None` sides. A year
+    /// ends at its last instant so inclusive bounds leave out the next one
+    pub fn bounds(&self, now: DateTime<Local>) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+        match self {
+            Self::LastDays(n) => (
+                now.checked_sub_signed(Duration::days(*n as i64))
+                    .map(|v| v.to_utc()),
+                None,
+            ),
+            Self::ThisMonth => (local_midnight(now.year(), now.month()), None),
+            Self::ThisYear => (local_midnight(now.year(), 1), None),
+            Self::Year(y) => (
+                local_midnight(*y, 1),
+                local_midnight(*y + 1, 1).map(|v| v - Duration::nanoseconds(1)),
+            ),
+        }
+    }
+}
+
+fn local_midnight(year: i32, month: u32) -> Option<DateTime<Utc>> {
+    let date = NaiveDate::from_ymd_opt(year, month, 1)?;
+
+    Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()
+        .map(|v| v.to_utc())
+}
+
+impl FromStr for PeriodShortcut {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "this_month" => return Ok(Self::ThisMonth),
+            "this_year" => return Ok(Self::ThisYear),
+            _ => {}
+        }
+
+        if let Some(n) = s.strip_prefix("last_").and_then(|v| v.strip_suffix('d')) {
+            match n.parse::<u32>() {
+                Ok(n) if (1..=MAX_LAST_DAYS).contains(&n) => return Ok(Self::LastDays(n)),
+                _ => bail!(
+                    "invalid day count in `{}`, expected 1 to {}",
+                    s,
+                    MAX_LAST_DAYS
+                ),
+            }
+        }
+
+        match s.parse::<i32>() {
+            Ok(y) if (1..=9999).contains(&y) => Ok(Self::Year(y)),
+            _ => bail!(
+                "unknown period `{}`, expected last_<n>d, this_month, this_year or a year",
+                s
+            ),
+        }
+    }
+}
+
+impl fmt::Display for PeriodShortcut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LastDays(n) => write!(f, "last_{}d", n),
+            Self::ThisMonth => f.write_str("this_month"),
+            Self::ThisYear => f.write_str("this_year"),
+            Self::Year(y) => write!(f, "{}", y),
+        }
+    }
+}
+
+impl Serialize for PeriodShortcut {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PeriodShortcut {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(s: &str) -> DateTime<Local> {
+        let naive = s.parse::<chrono::NaiveDateTime>().unwrap();
+
+        Local.from_local_datetime(&naive).earliest().unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            "last_7d".parse::<PeriodShortcut>().unwrap(),
+            PeriodShortcut::LastDays(7)
+        );
+        assert_eq!(
+            "this_month".parse::<PeriodShortcut>().unwrap(),
+            PeriodShortcut::ThisMonth
+        );
+        assert_eq!(
+            "this_year".parse::<PeriodShortcut>().unwrap(),
+            PeriodShortcut::ThisYear
+        );
+        assert_eq!(
+            "2019".parse::<PeriodShortcut>().unwrap(),
+            PeriodShortcut::Year(2019)
+        );
+        assert_eq!(
+            format!("last_{MAX_LAST_DAYS}d")
+                .parse::<PeriodShortcut>()
+                .unwrap(),
+            PeriodShortcut::LastDays(MAX_LAST_DAYS)
+        );
+
+        for s in [
+            "last_0d",
+            "last_d",
+            "last_-1d",
+            "last_36501d",
+            "last_4000000000d",
+            "last_7",
+            "0",
+            "10000",
+            "yesterday",
+            "",
+        ] {
+            assert!(s.parse::<PeriodShortcut>().is_err(), "{s}");
+        }
+
+        for s in ["last_30d", "this_month", "this_year", "1999"] {
+            assert_eq!(s.parse::<PeriodShortcut>().unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn bounds() {
+        let now = local("2024-02-15T10:30:00");
+        let midnight = |s: &str| Some(local(&format!("{s}T00:00:00")).to_utc());
+
+        assert_eq!(
+            PeriodShortcut::LastDays(10).bounds(now),
+            (Some(local("2024-02-05T10:30:00").to_utc()), None)
+        );
+        assert_eq!(
+            PeriodShortcut::ThisMonth.bounds(now),
+            (midnight("2024-02-01"), None)
+        );
+        assert_eq!(
+            PeriodShortcut::ThisYear.bounds(now),
+            (midnight("2024-01-01"), None)
+        );
+
+        // a year ends right before the next one starts
+        let (from, to) = PeriodShortcut::Year(2019).bounds(now);
+        assert_eq!(from, midnight("2019-01-01"));
+        assert_eq!(
+            to.unwrap() + Duration::nanoseconds(1),
+            midnight("2020-01-01").unwrap()
+        );
+
+        // the longest period stays within the range of times
+        let (from, _) = PeriodShortcut::LastDays(MAX_LAST_DAYS).bounds(now);
+        assert_eq!(from.unwrap().year(), 1924);
+    }
+}
@@ -0,0 +1,36 @@
+//! Builders shared by the unit tests
+
+use chrono::{DateTime, Utc};
+
+use super::{Channel, WatchEntry};
+
+/// UTC time from an RFC 3339 string
+pub fn at(s: &str) -> DateTime<Utc> {
+    s.parse().unwrap()
+}
+
+/// Watch of `id` on a channel of the same name
+pub fn watch(id: &str, time: &str) -> WatchEntry {
+    WatchEntry {
+        id: id.to_owned(),
+        title: format!("Video {id}"),
+        channel: Channel {
+            id: format!("UC{id}").into(),
+            name: id.into(),
+        },
+        time: at(time),
+        products: Vec::new(),
+        ad: false,
+    }
+}
+
+/// Watch of `id` on the channel `channel`
+pub fn watch_on(id: &str, channel: &str, time: &str) -> WatchEntry {
+    WatchEntry {
+        channel: Channel {
+            id: format!("UC{channel}").into(),
+            name: channel.into(),
+        },
+        ..watch(id, time)
+    }
+}
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use icu_normalizer::{
+    DecomposingNormalizerBorrowed, properties::CanonicalCombiningClassMapBorrowed,
+};
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+/// TitleRule
+///
+/// Regex replacement applied to video titles at load time, `replace` may
+/// refer to capture groups such as `$1`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TitleRule {
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub pattern: Regex,
+    #[serde(default)]
+    pub replace: String,
+}
+
+impl TitleRule {
+    /// Apply every rule in order, `None` when the title is left unchanged
+    /// or the rules would empty it
+    pub fn apply_all(rules: &[Self], title: &str) -> Option<Arc<str>> {
+        let mut value = title.to_string();
+
+        for rule in rules {
+            value = rule.pattern.replace_all(&value, &rule.replace).into_owned();
+        }
+
+        let value = value.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+        (!value.is_empty() && value != title).then(|| Arc::from(value))
+    }
+}
+
+impl PartialEq for TitleRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern.as_str() == other.pattern.as_str() && self.replace == other.replace
+    }
+}
+
+/// Shortest title key compared for duplicates, shorter ones such as
+/// `live` are too common to mean the same video
+pub const MIN_TITLE_KEY_LEN: usize = 5;
+
+/// Key under which re-uploads of a video are expected to meet. Bracketed
+/// parts such as `(Official Video)` or `[HD]` are dropped, then accents,
+/// case, punctuation and full-width forms are folded away. Empty when too
+/// little is left to compare
+pub fn title_key(title: &str) -> String {
+    let nfkd = DecomposingNormalizerBorrowed::new_nfkd();
+    let ccc = CanonicalCombiningClassMapBorrowed::new();
+
+    let mut depth = 0usize;
+    let mut key = String::with_capacity(title.len());
+
+    for c in nfkd.normalize(title).chars() {
+        match c {
+            '(' | '[' | '{' | '【' | '「' => depth += 1,
+            ')' | ']' | '}' | '】' | '」' => depth = depth.saturating_sub(1),
+            _ if depth > 0 || ccc.get_u8(c) != 0 => {}
+            _ if c.is_alphanumeric() => key.extend(c.to_lowercase()),
+            _ if !key.is_empty() && !key.ends_with(' ') => key.push(' '),
+            _ => {}
+        }
+    }
+
+    let key = key.trim_end();
+
+    if key.chars().count() < MIN_TITLE_KEY_LEN {
+        return String::new();
+    }
+
+    key.to_owned()
+}
+
+pub(super) fn deserialize_regex<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Regex, D::Error> {
+    let s = String::deserialize(deserializer)?;
+
+    Regex::new(&s).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn title_keys() {
+        assert_eq!(title_key("Song Name (Official Video) [HD]"), "song name");
+        assert_eq!(title_key("【MV】 Song Name 「Live」"), "song name");
+        assert_eq!(title_key("Song   Name!!"), "song name");
+        assert_eq!(title_key("Beyoncé - Halo"), "beyonce halo");
+        assert_eq!(title_key("Ｓｏｎｇ　Ｎａｍｅ"), "song name");
+
+        // a stray closing bracket drops nothing
+        assert_eq!(title_key("a) Song Name"), "a song name");
+    }
+
+    #[test]
+    fn short_title_keys() {
+        assert_eq!(title_key("Live (2019)"), "");
+        assert_eq!(title_key("(Official Video)"), "");
+        assert_eq!(title_key("Outro"), "outro");
+    }
+}
@@ -1,7 +1,20 @@
-use chrono::{DateTime, Utc};
+use std::{
+    collections::HashSet,
+    io::{self, Read},
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Deserializer, Serialize, de::Visitor};
 
-use crate::utils::{extract_youtube_channel_id, extract_youtube_video_id};
+use crate::utils::{
+    extract_youtube_channel_id, extract_youtube_video_id, is_buffer_contains_keywords,
+};
+
+use super::{Entries, MalformedEntry, SchemaLoader, Source, WatchEntry};
+
+/// Detail name Takeout gives ad impressions
+pub const AD_DETAIL: &str = "From Google Ads";
 
 /// Video ID deserializer
 ///
@@ -30,6 +43,48 @@ where
     }
 }
 
+/// Parse timestamps of varying precision and offsets, a missing offset is
+/// taken as UTC
+pub fn parse_timestamp(s: &str) -> Option<DateTime<Utc>> {
+    let s = s.trim();
+
+    if let Ok(v) = DateTime::parse_from_rfc3339(s) {
+        return Some(v.with_timezone(&Utc));
+    }
+
+    for fmt in ["%Y-%m-%dT%H:%M:%S%.f%z", "%Y-%m-%d %H:%M:%S%.f%z"] {
+        if let Ok(v) = DateTime::parse_from_str(s, fmt) {
+            return Some(v.with_timezone(&Utc));
+        }
+    }
+
+    for fmt in [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(v) = NaiveDateTime::parse_from_str(s, fmt) {
+            return Some(v.and_utc());
+        }
+    }
+
+    None
+}
+
+/// Time deserializer
+///
+/// Relaxed timestamp parsing, `None` when the value cannot be read so a
+/// single bad entry doesn't fail the whole file
+fn time_de<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = Option::<String>::deserialize(deserializer)?;
+
+    Ok(s.as_deref().and_then(parse_timestamp))
+}
+
 /// Channel deserializer
 ///
 /// Extract channel object from array sequences
@@ -101,6 +156,15 @@ impl Default for Channel {
     }
 }
 
+/// Detail
+///
+/// Note attached to an entry, e.g. `From Google Ads` on ad impressions
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Detail {
+    #[serde(default)]
+    pub name: String,
+}
+
 /// Schema version 1 based on the JSON structures
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Schema {
@@ -114,7 +178,8 @@ pub struct Schema {
     #[serde(default, deserialize_with = "video_title_de")]
     pub title: String,
 
-    pub time: DateTime<Utc>,
+    #[serde(default, deserialize_with = "time_de")]
+    pub time: Option<DateTime<Utc>>,
 
     #[serde(
         rename(deserialize = "subtitles"),
@@ -122,4 +187,305 @@ pub struct Schema {
         deserialize_with = "channel_de"
     )]
     pub channel: Channel,
+
+    #[serde(default)]
+    pub products: Vec<String>,
+
+    #[serde(default)]
+    pub details: Vec<Detail>,
+}
+
+impl Schema {
+    /// Check if the entry is an ad impression rather than a watch
+    fn is_ad(&self) -> bool {
+        self.details.iter().any(|v| v.name == AD_DETAIL)
+    }
+
+    /// Convert into a watch entry, `None` without a valid time
+    fn into_entry(self) -> Option<WatchEntry> {
+        let ad = self.is_ad();
+
+        Some(WatchEntry {
+            id: self.id,
+            title: self.title,
+            channel: super::Channel {
+                id: self.channel.id.into(),
+                name: self.channel.name.into(),
+            },
+            time: self.time?,
+            products: self.products,
+            ad,
+        })
+    }
+}
+
+/// Reader counting the line breaks passing through it
+struct CountLines<'a> {
+    rdr: &'a mut dyn Source,
+    lines: &'a mut usize,
+}
+
+impl Read for CountLines<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.rdr.read(buf)?;
+        *self.lines += buf[..len].iter().filter(|b| **b == b'\n').count();
+
+        Ok(len)
+    }
+}
+
+/// Entries of the top-level array, each one parsed when it is pulled so the
+/// raw array is never held in memory
+struct EntryStream<'a> {
+    rdr: &'a mut dyn Source,
+    read: usize,
+    line: usize,
+    invalid: usize,
+    done: bool,
+}
+
+impl EntryStream<'_> {
+    /// Next byte other than whitespace, left in the reader
+    fn peek(&mut self) -> Result<Option<u8>> {
+        loop {
+            let buf = self.rdr.fill_buf()?;
+
+            if buf.is_empty() {
+                return Ok(None);
+            }
+
+            let skipped = buf.iter().position(|b| !b.is_ascii_whitespace());
+            let len = skipped.unwrap_or(buf.len());
+            let next = skipped.map(|i| buf[i]);
+
+            self.line += buf[..len].iter().filter(|b| **b == b'\n').count();
+            self.rdr.consume(len);
+
+            if next.is_some() {
+                return Ok(next);
+            }
+        }
+    }
+
+    /// Next element of the array, `None` past its end. The inner error is an
+    /// entry of the wrong shape, the ones after it can still be read
+    fn next_schema(&mut self) -> Result<Option<Result<Schema, MalformedEntry>>> {
+        let expected = if self.read == 0 { b'[' } else { b',' };
+
+        match self.peek()? {
+            Some(b) if b == expected => self.rdr.consume(1),
+            Some(b']') if self.read > 0 => return self.end(),
+            _ => bail!(
+                "expected `{}` before entry {} at line {}",
+                expected as char,
+                self.read + 1,
+                self.line
+            ),
+        }
+
+        // skipped first so the entry starts on the line counted
+        if self.peek()? == Some(b']') && self.read == 0 {
+            return self.end();
+        }
+
+        self.read += 1;
+
+        // read as plain JSON first, so the reader is past the entry even
+        // when its fields don't fit. Objects end on `}`, the deserializer
+        // doesn't read past them
+        let start = self.line;
+        let mut de = serde_json::Deserializer::from_reader(CountLines {
+            rdr: &mut *self.rdr,
+            lines: &mut self.line,
+        });
+        let value = serde_json::Value::deserialize(&mut de)
+            .with_context(|| format!("invalid JSON in entry {} at line {}", self.read, start))?;
+
+        Ok(Some(Schema::deserialize(value).map_err(|e| {
+            MalformedEntry {
+                index: self.read,
+                line: start,
+                message: e.to_string(),
+            }
+        })))
+    }
+
+    /// Consume the closing `]`, only whitespace may follow it
+    fn end(&mut self) -> Result<Option<Result<Schema, MalformedEntry>>> {
+        self.rdr.consume(1);
+
+        if self.peek()?.is_some() {
+            bail!("trailing characters after the entries");
+        }
+
+        Ok(None)
+    }
+}
+
+impl Iterator for EntryStream<'_> {
+    type Item = Result<WatchEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            match self.next_schema() {
+                Ok(Some(Ok(v))) => match v.into_entry() {
+                    Some(v) => return Some(Ok(v)),
+                    None => self.invalid += 1,
+                },
+                Ok(Some(Err(e))) => return Some(Err(e.into())),
+                Ok(None) => {
+                    self.done = true;
+
+                    if self.invalid > 0 {
+                        log::warn!("Skipped {} entries without a readable `time`", self.invalid);
+                    }
+                }
+                Err(e) => {
+                    self.done = true;
+
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// V1Loader
+///
+/// Loader of the version 1 Takeout JSON structure
+pub struct V1Loader;
+
+impl SchemaLoader for V1Loader {
+    fn name(&self) -> &str {
+        "v1"
+    }
+
+    fn schema_version(&self) -> Option<u32> {
+        Some(1)
+    }
+
+    fn detect(&self, rdr: &mut dyn Source) -> bool {
+        let keys: HashSet<&str> = [
+            "\"header\"",
+            "\"title\"",
+            "\"titleUrl\"",
+            "\"subtitles\"",
+            "\"name\"",
+            "\"url\"",
+            "\"time\"",
+            "\"products\"",
+        ]
+        .into();
+
+        is_buffer_contains_keywords(rdr, &keys)
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn load<'a>(&self, rdr: &'a mut dyn Source) -> Result<Entries<'a>> {
+        Ok(Box::new(EntryStream {
+            rdr,
+            read: 0,
+            line: 1,
+            invalid: 0,
+            done: false,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::testing::at;
+
+    #[test]
+    fn timestamp_formats() {
+        let expected = at("2024-03-05T06:07:08Z");
+
+        for s in [
+            "2024-03-05T06:07:08Z",
+            "2024-03-05T06:07:08.000Z",
+            "2024-03-05T06:07:08+00:00",
+            "2024-03-05T06:07:08+0000",
+            "2024-03-05 06:07:08+0000",
+            "2024-03-05T06:07:08",
+            "2024-03-05 06:07:08",
+            "  2024-03-05T06:07:08Z\n",
+        ] {
+            assert_eq!(parse_timestamp(s), Some(expected), "{s}");
+        }
+
+        assert_eq!(
+            parse_timestamp("2024-03-05T06:07:08.123456Z"),
+            Some(at("2024-03-05T06:07:08.123456Z"))
+        );
+        assert_eq!(
+            parse_timestamp("2024-03-05T06:07:08.5"),
+            Some(at("2024-03-05T06:07:08.5Z"))
+        );
+        assert_eq!(
+            parse_timestamp("2024-03-05T06:07"),
+            Some(at("2024-03-05T06:07:00Z"))
+        );
+        assert_eq!(
+            parse_timestamp("2024-03-05 06:07"),
+            Some(at("2024-03-05T06:07:00Z"))
+        );
+    }
+
+    #[test]
+    fn timestamp_offsets() {
+        let expected = at("2024-03-05T06:07:08Z");
+
+        for s in [
+            "2024-03-05T08:07:08+02:00",
+            "2024-03-05T08:07:08+0200",
+            "2024-03-05 08:07:08+0200",
+            "2024-03-05T01:07:08-05:00",
+            "2024-03-05T01:07:08.000-0500",
+        ] {
+            assert_eq!(parse_timestamp(s), Some(expected), "{s}");
+        }
+
+        // across the date line
+        assert_eq!(
+            parse_timestamp("2024-03-05T23:30:00-01:00"),
+            Some(at("2024-03-06T00:30:00Z"))
+        );
+    }
+
+    #[test]
+    fn timestamp_rejected() {
+        for s in [
+            "",
+            "   ",
+            "yesterday",
+            "2024-03-05",
+            "2024-13-05T06:07:08Z",
+            "2024-02-30T06:07:08Z",
+            "2024-03-05T25:07:08Z",
+            "2024-03-05T06:07:08+25:00",
+            "1709618828",
+            "05/03/2024 06:07",
+        ] {
+            assert_eq!(parse_timestamp(s), None, "{s:?}");
+        }
+    }
+
+    #[test]
+    fn unreadable_time() {
+        let entry = |time: &str| {
+            let json = format!(r#"{{"title": "Watched a", "time": {time}}}"#);
+
+            serde_json::from_str::<Schema>(&json).unwrap().time
+        };
+
+        assert_eq!(
+            entry(r#""2024-03-05T06:07:08Z""#),
+            Some(at("2024-03-05T06:07:08Z"))
+        );
+        assert_eq!(entry(r#""not a time""#), None);
+        assert_eq!(entry("null"), None);
+    }
 }
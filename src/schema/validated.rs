@@ -0,0 +1,81 @@
+use std::{fmt, ops::Deref};
+
+use super::MetadataFilter;
+
+/// Largest page a request gets, bigger `limit`s are lowered to it
+pub const MAX_LIMIT: usize = 1000;
+
+/// InvalidFilter
+///
+/// Filter value rejected by [`ValidatedFilter`], the message names the field
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidFilter(pub String);
+
+impl fmt::Display for InvalidFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InvalidFilter {}
+
+/// ValidatedFilter
+///
+/// Filter whose values were checked, the only kind tables are queried with
+#[derive(Debug)]
+pub struct ValidatedFilter(MetadataFilter);
+
+impl ValidatedFilter {
+    /// Check a requested page, `limit` is capped at [`MAX_LIMIT`]
+    pub fn new(filter: MetadataFilter) -> Result<Self, InvalidFilter> {
+        let mut filter = Self::unbounded(filter)?.0;
+        filter.limit = filter.limit.min(MAX_LIMIT);
+
+        Ok(Self(filter))
+    }
+
+    /// Check a filter whose `limit` is left as is, for listings which aren't
+    /// rendered page by page like exports
+    pub fn unbounded(filter: MetadataFilter) -> Result<Self, InvalidFilter> {
+        if filter.limit == 0 {
+            return Err(InvalidFilter("`limit` must be at least 1".to_owned()));
+        }
+
+        if filter.page == 0 {
+            return Err(InvalidFilter("`page` starts at 1".to_owned()));
+        }
+
+        if let (Some(from), Some(to)) = (filter.from, filter.to)
+            && from > to
+        {
+            return Err(InvalidFilter("`from` is after `to`".to_owned()));
+        }
+
+        if let (Some(from), Some(to)) = (filter.last_from, filter.last_to)
+            && from > to
+        {
+            return Err(InvalidFilter("`last_from` is after `last_to`".to_owned()));
+        }
+
+        Ok(Self(filter))
+    }
+
+    /// Parse and check the query string of the index page and the API
+    pub fn parse(query: &str) -> Result<Self, InvalidFilter> {
+        serde_urlencoded::from_str::<MetadataFilter>(query)
+            .map_err(|e| InvalidFilter(e.to_string()))
+            .and_then(Self::new)
+    }
+
+    pub fn into_inner(self) -> MetadataFilter {
+        self.0
+    }
+}
+
+impl Deref for ValidatedFilter {
+    type Target = MetadataFilter;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
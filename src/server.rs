@@ -0,0 +1,461 @@
+use std::{
+    future::Future,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Result, bail};
+use hyper::server::conn::http1;
+use hyper_util::rt::TokioIo;
+use tokio::{
+    net::TcpListener,
+    sync::{Semaphore, broadcast, mpsc},
+    time::{Duration, sleep},
+};
+
+use crate::{
+    auth::{Auth, User},
+    journal::{Ingest, Journal},
+    links::ShortLinks,
+    profile::{DEFAULT_PROFILE, Profiles},
+    schema::MetadataTable,
+    service::{ServiceHandler, UiOptions},
+    shutdown::Shutdown,
+    transport::Transport,
+    vault::Vault,
+    youtube::YouTube,
+};
+
+/// Default maximum concurrent connections
+pub const MAX_CONNECTIONS: usize = 250;
+
+/// Default port
+pub const DEFAULT_PORT: u16 = 8000;
+
+/// Listener
+struct Listener<T> {
+    listener: T,
+    vault: Vault,
+    limit_connection: Arc<Semaphore>,
+    notify_shutdown: broadcast::Sender<()>,
+    shutdown_complete_tx: mpsc::Sender<()>,
+}
+
+impl<T: Transport> Listener<T> {
+    async fn run(&mut self) -> Result<()> {
+        log::debug!("Accepting inbound connections");
+
+        let mut http = http1::Builder::new();
+        http.keep_alive(true);
+
+        loop {
+            let permit = self.limit_connection.clone().acquire_owned().await.unwrap();
+            let mut shutdown = Shutdown::new(self.notify_shutdown.subscribe());
+
+            let socket = self.accept().await?;
+            let io = TokioIo::new(socket);
+
+            let service = ServiceHandler::new(self.vault.clone());
+            let connection = http.serve_connection(io, service);
+
+            tokio::spawn(async move {
+                tokio::select! {
+                    res = connection => {
+                        if let Err(e) = res {
+                            log::error!("failed to serve connection: {:?}", e);
+                        }
+                    }
+                    _ = shutdown.recv() => {}
+                }
+
+                drop(permit);
+            });
+        }
+    }
+
+    async fn accept(&mut self) -> Result<T::Io> {
+        let mut backoff = 1;
+
+        loop {
+            match self.listener.accept().await {
+                Ok(socket) => return Ok(socket),
+                Err(err) => {
+                    if backoff > 64 {
+                        log::error!("failed reconnecting too many times. {:?}", err);
+                        return Err(err.into());
+                    }
+                }
+            };
+
+            log::info!("reconnecting in {}s", backoff);
+            sleep(Duration::from_secs(backoff)).await;
+
+            backoff *= 2;
+        }
+    }
+}
+
+/// ServerBuilder
+///
+/// Builder for [`Server`], obtained from [`Server::builder`]
+pub struct ServerBuilder {
+    metadata: Option<MetadataTable>,
+    profiles: Vec<(String, MetadataTable)>,
+    users: Vec<User>,
+    diff_base: Option<MetadataTable>,
+    youtube: Option<YouTube>,
+    player: bool,
+    ui: UiOptions,
+    addr: SocketAddr,
+    max_connections: usize,
+    links_file: Option<PathBuf>,
+    ingest_token: Option<String>,
+    journal: Option<PathBuf>,
+    prefs_key: Option<String>,
+    #[cfg(feature = "mdns")]
+    mdns_name: Option<String>,
+    #[cfg(windows)]
+    pipe: Option<String>,
+}
+
+impl Default for ServerBuilder {
+    fn default() -> Self {
+        Self {
+            metadata: None,
+            profiles: Vec::new(),
+            users: Vec::new(),
+            diff_base: None,
+            youtube: None,
+            player: true,
+            ui: UiOptions::default(),
+            addr: SocketAddr::from(([127, 0, 0, 1], DEFAULT_PORT)),
+            max_connections: MAX_CONNECTIONS,
+            links_file: None,
+            ingest_token: None,
+            journal: None,
+            prefs_key: None,
+            #[cfg(feature = "mdns")]
+            mdns_name: None,
+            #[cfg(windows)]
+            pipe: None,
+        }
+    }
+}
+
+impl ServerBuilder {
+    /// Metadata served as the default profile
+    pub fn metadata(mut self, metadata_table: MetadataTable) -> Self {
+        self.metadata = Some(metadata_table);
+        self
+    }
+
+    /// Additional named profile served under `/p/:name/`
+    pub fn profile(mut self, name: &str, metadata_table: MetadataTable) -> Self {
+        self.profiles.push((name.to_owned(), metadata_table));
+        self
+    }
+
+    /// User account, login is required once at least one user is added
+    pub fn user(mut self, user: User) -> Self {
+        self.users.push(user);
+        self
+    }
+
+    /// Older dataset the default profile is compared against on `/diff`
+    pub fn diff_base(mut self, metadata_table: MetadataTable) -> Self {
+        self.diff_base = Some(metadata_table);
+        self
+    }
+
+    /// Preloaded YouTube components, fetched on build when not provided
+    pub fn youtube(mut self, youtube: YouTube) -> Self {
+        self.youtube = Some(youtube);
+        self
+    }
+
+    /// Serve the YouTube player, enabled by default. Without the `player`
+    /// feature it is only served when components are provided through
+    /// [`ServerBuilder::youtube`]
+    pub fn player(mut self, player: bool) -> Self {
+        self.player = player;
+        self
+    }
+
+    /// Defaults of the index page
+    pub fn ui(mut self, ui: UiOptions) -> Self {
+        self.ui = ui;
+        self
+    }
+
+    /// Listen on `127.0.0.1` with the given port
+    pub fn port(mut self, port: u16) -> Self {
+        self.addr.set_port(port);
+        self
+    }
+
+    /// Listen on the given address
+    pub fn addr(mut self, addr: SocketAddr) -> Self {
+        self.addr = addr;
+        self
+    }
+
+    pub fn max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+
+    /// Persist short links to this JSON file, they are lost on restart
+    /// otherwise
+    pub fn links_file(mut self, path: &Path) -> Self {
+        self.links_file = Some(path.to_path_buf());
+        self
+    }
+
+    /// Accept watches on `POST /api/history` from clients sending this
+    /// bearer token
+    pub fn ingest_token(mut self, token: &str) -> Self {
+        self.ingest_token = Some(token.to_owned());
+        self
+    }
+
+    /// Append watches reported on `POST /api/history` to this file, they are
+    /// only kept in memory otherwise
+    pub fn journal(mut self, path: &Path) -> Self {
+        self.journal = Some(path.to_path_buf());
+        self
+    }
+
+    /// Key signing the preferences cookie, a random one makes remembered
+    /// settings last until the next restart
+    pub fn prefs_key(mut self, key: &str) -> Self {
+        self.prefs_key = Some(key.to_owned());
+        self
+    }
+
+    /// Advertise the server on the local network as `<name>.local` when it
+    /// listens on a non-loopback address
+    #[cfg(feature = "mdns")]
+    pub fn mdns_name(mut self, name: &str) -> Self {
+        self.mdns_name = Some(name.to_owned());
+        self
+    }
+
+    /// Listen on this named pipe instead of TCP, e.g. `ytm` for
+    /// `\\.\pipe\ytm`
+    #[cfg(windows)]
+    pub fn pipe(mut self, name: &str) -> Self {
+        self.pipe = Some(name.to_owned());
+        self
+    }
+
+    /// Validate the configuration and load missing components
+    pub async fn build(self) -> Result<Server> {
+        let mut profiles = Profiles::new();
+
+        if let Some(metadata_table) = self.metadata {
+            profiles.insert(DEFAULT_PROFILE, metadata_table);
+        }
+
+        for (name, metadata_table) in self.profiles {
+            if !profiles.insert(&name, metadata_table) {
+                bail!("Duplicate profile name `{}`", name);
+            }
+        }
+
+        if profiles.is_empty() {
+            bail!("No metadata to serve. Provide a default metadata or at least one profile");
+        }
+
+        let youtube = match (self.player, self.youtube) {
+            (false, _) => None,
+            (true, Some(v)) => Some(v),
+            #[cfg(feature = "player")]
+            (true, None) => Some(crate::youtube::load_youtube_components().await?),
+            #[cfg(not(feature = "player"))]
+            (true, None) => None,
+        };
+
+        let links = match &self.links_file {
+            Some(path) => {
+                let links = ShortLinks::from_path(path)?;
+                log::debug!("Loaded {} short links", links.len());
+
+                links
+            }
+            None => ShortLinks::new(),
+        };
+
+        let ingest = match self.ingest_token {
+            Some(token) if token.is_empty() => bail!("Ingest token must not be empty"),
+            Some(token) => Some(Ingest {
+                token,
+                journal: self.journal.as_deref().map(Journal::open).transpose()?,
+            }),
+            None => None,
+        };
+
+        if self.prefs_key.as_ref().is_some_and(|v| v.is_empty()) {
+            bail!("Preferences key must not be empty");
+        }
+
+        if ingest.as_ref().is_some_and(|v| v.journal.is_none()) {
+            log::warn!("No journal configured, reported watches are lost on restart");
+        }
+
+        #[cfg(feature = "player")]
+        let missing_widgetapi = youtube
+            .as_ref()
+            .is_some_and(|v| v.widgetapi_script.is_none());
+
+        let vault = Vault::new(
+            profiles,
+            Auth::new(self.users),
+            self.diff_base,
+            youtube,
+            self.ui,
+            links,
+            ingest,
+        );
+
+        if let Some(key) = self.prefs_key {
+            vault.state.lock().unwrap().prefs_key = key.into_bytes().into();
+        }
+
+        #[cfg(feature = "player")]
+        if missing_widgetapi {
+            tokio::spawn(crate::youtube::retry_widgetapi(vault.clone()));
+        }
+
+        Ok(Server {
+            vault,
+            addr: self.addr,
+            max_connections: self.max_connections,
+            #[cfg(feature = "mdns")]
+            mdns_name: self.mdns_name,
+            #[cfg(windows)]
+            pipe: self.pipe,
+        })
+    }
+
+    /// Build and serve until `Ctrl-C`
+    pub async fn serve(self) -> Result<()> {
+        self.build().await?.serve().await
+    }
+}
+
+/// Server
+///
+/// Embeddable ytm web server
+pub struct Server {
+    vault: Vault,
+    addr: SocketAddr,
+    max_connections: usize,
+    #[cfg(feature = "mdns")]
+    mdns_name: Option<String>,
+    #[cfg(windows)]
+    pipe: Option<String>,
+}
+
+impl Server {
+    pub fn builder() -> ServerBuilder {
+        ServerBuilder::default()
+    }
+
+    /// Shared state, can be used to inspect or update data while serving
+    pub fn vault(&self) -> &Vault {
+        &self.vault
+    }
+
+    /// Service handler sharing this server's state, requests can be driven
+    /// through it directly without binding a port
+    pub fn handler(&self) -> ServiceHandler {
+        ServiceHandler::new(self.vault.clone())
+    }
+
+    /// Serve until `Ctrl-C`, see [`shutdown::signal`](crate::shutdown::signal)
+    /// for the other signals on Windows
+    pub async fn serve(self) -> Result<()> {
+        self.serve_with_shutdown(crate::shutdown::signal()).await
+    }
+
+    /// Serve until the `signal` future completes
+    pub async fn serve_with_shutdown<F>(self, signal: F) -> Result<()>
+    where
+        F: Future<Output = ()>,
+    {
+        #[cfg(windows)]
+        if let Some(name) = &self.pipe {
+            let listener = crate::transport::NamedPipeListener::bind(name)?;
+
+            return self.serve_with_transport(listener, signal).await;
+        }
+
+        let listener = TcpListener::bind(self.addr).await?;
+
+        self.serve_with_listener(listener, signal).await
+    }
+
+    /// Serve on an already bound listener until the `signal` future
+    /// completes, e.g. one bound to port `0` to get an ephemeral port
+    pub async fn serve_with_listener<F>(self, listener: TcpListener, signal: F) -> Result<()>
+    where
+        F: Future<Output = ()>,
+    {
+        #[cfg(feature = "mdns")]
+        let _advertisement = match &self.mdns_name {
+            Some(name) => crate::mdns::Advertisement::start(name, listener.local_addr()?)
+                .inspect_err(|e| log::warn!("failed to advertise over mDNS. {:?}", e))
+                .ok()
+                .flatten(),
+            None => None,
+        };
+
+        self.serve_with_transport(listener, signal).await
+    }
+
+    /// Serve connections of any [`Transport`] until the `signal` future
+    /// completes
+    pub async fn serve_with_transport<T, F>(self, listener: T, signal: F) -> Result<()>
+    where
+        T: Transport,
+        F: Future<Output = ()>,
+    {
+        let (notify_shutdown, _) = broadcast::channel(1);
+        let (shutdown_complete_tx, mut shutdown_complete_rx) = mpsc::channel(1);
+
+        log::info!("Listening on {}", listener.location()?);
+
+        let mut server = Listener {
+            listener,
+            vault: self.vault,
+            limit_connection: Arc::new(Semaphore::new(self.max_connections)),
+            notify_shutdown,
+            shutdown_complete_tx,
+        };
+
+        tokio::select! {
+            res = server.run() => {
+                if let Err(e) = res {
+                    log::error!("{:?}", e);
+                }
+            }
+            _ = signal => {
+                log::info!("Shutting down, please wait...");
+            }
+        }
+
+        let Listener {
+            notify_shutdown,
+            shutdown_complete_tx,
+            ..
+        } = server;
+
+        drop(notify_shutdown);
+        drop(shutdown_complete_tx);
+
+        let _ = shutdown_complete_rx.recv().await;
+
+        Ok(())
+    }
+}
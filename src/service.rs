@@ -1,123 +1,2352 @@
-use std::pin::Pin;
+use std::{
+    convert::Infallible,
+    fmt,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
 use askama::Template;
 use chrono::{DateTime, Utc};
-use http_body_util::Full;
+use hmac::{Hmac, KeyInit, Mac};
+use http_body_util::{
+    BodyExt, Full, LengthLimitError, Limited, StreamBody, combinators::UnsyncBoxBody,
+};
 use hyper::{
-    Method, Request, Response, StatusCode,
-    body::{Bytes, Incoming},
+    HeaderMap, Method, Request, Response, StatusCode, Uri,
+    body::{Bytes, Frame, Incoming},
+    header::{self, HeaderValue},
     service::Service,
 };
+use log::Level;
+use rand::Rng;
+use schemars::{JsonSchema, Schema, schema_for};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::mpsc;
+use tracing::Instrument;
 
 use crate::{
     LOCAL_WIDGET_API_PATH,
-    schema::{Metadata, MetadataFilter, Order, Pagination},
-    vault::Vault,
+    analytics::{
+        Coverage, Period,
+        calendar::{MonthCalendar, month_calendar},
+        cowatch::{
+            CoWatchNode, CoWatchPair, CoWatchUnit, DEFAULT_MIN_SHARED, co_watch_network,
+            co_watch_pairs,
+        },
+        diff::{HistoryDiff, diff},
+        duplicates::{DuplicateGroup, duplicate_groups},
+        milestones::{Milestones, milestones},
+        products::{ProductStats, product_stats},
+        quality::{
+            DEFAULT_GAP_DAYS, Gap, MAX_GAP_DAYS, QualityReport, quality_report, suspected_pauses,
+        },
+        rates::{WatchRates, watch_rates},
+        resurface::{
+            DEFAULT_RESURFACE_IDLE_DAYS, DEFAULT_RESURFACE_MIN_DAYS, MAX_RESURFACE_IDLE_DAYS,
+            Resurfaced, resurface,
+        },
+        sessions::{
+            DEFAULT_SESSION_GAP_MINUTES, MAX_SESSION_GAP_MINUTES, Session, SessionReport,
+            watch_sessions,
+        },
+        stopped::{
+            DEFAULT_STOPPED_MIN_WATCHES, DEFAULT_STOPPED_MONTHS, StoppedChannel, stopped_channels,
+        },
+        top::{TopChannel, TopVideo, top_channels, top_videos},
+        years::{YearSummary, year_summaries},
+        zoom::{DEFAULT_ZOOM_WIDTH, ZoomedTimeline, zoom_timeline},
+    },
+    auth::{SESSION_COOKIE, SESSION_TTL_DAYS, User, verify_password_hash},
+    config::TopKind,
+    frontend::ExternalFrontend,
+    i18n::{Catalog, Lang},
+    journal::JournalEntry,
+    links::SHORT_LINK_PREFIX,
+    logs::{LogRecord, recent},
+    playback::{PLAYER_COOKIE, PlayerState},
+    schema::{
+        MemoryUsage, Metadata, MetadataFilter, MetadataTable, Order, Pagination, PeriodShortcut,
+        QueryTooExpensive, SourceInfo, ValidatedFilter, View, WatchEvent, default_limit,
+        default_order, default_page, deserialize_datetime, deserialize_datetime_end, page_bounds,
+    },
+    utils::{
+        DateTimeUtility, constant_time_eq, get_cookie, to_hex_string, to_markdown_table,
+        to_watches_csv,
+    },
+    vault::{State, Vault},
+    youtube::WIDGETAPI_RETRY_AFTER,
 };
 
-type Body = Full<Bytes>;
+type Body = UnsyncBoxBody<Bytes, Infallible>;
+
+/// Rendered HTML is sent out in chunks of this size
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Chunks rendered ahead of the client before rendering waits
+const STREAM_BUFFER: usize = 4;
 
 const PAGE_LIMITS: [usize; 10] = [5, 10, 15, 20, 25, 50, 100, 250, 500, 1000];
 static CSS: &[u8] = include_bytes!("../assets/style.css");
 static ALPINE_JS: &[u8] = include_bytes!("../assets/alpine.js");
 static CHART_JS: &[u8] = include_bytes!("../assets/chart.js");
+static MANIFEST: &[u8] = include_bytes!("../assets/manifest.json");
+static SERVICE_WORKER_JS: &[u8] = include_bytes!("../assets/sw.js");
+static FAVICON: &[u8] = include_bytes!("../assets/icons/favicon.ico");
+static APPLE_TOUCH_ICON: &[u8] = include_bytes!("../assets/icons/apple-touch-icon.png");
+static ICON_192: &[u8] = include_bytes!("../assets/icons/icon-192.png");
+static ICON_512: &[u8] = include_bytes!("../assets/icons/icon-512.png");
+
+/// Icons only change with the binary, a week keeps them out of most reloads
+const ICON_CACHE_CONTROL: &str = "public, max-age=604800";
+
+/// Served while `www-widgetapi.js` is still being retried
+const WIDGETAPI_PLACEHOLDER: &str = "console.warn(\"ytm: the YouTube player script could not be downloaded yet, retrying on the server\");\n";
 
 fn full<T: Into<Bytes>>(chunk: T) -> Body {
-    Full::new(chunk.into())
+    Full::new(chunk.into()).boxed_unsync()
+}
+
+/// ChunkWriter
+///
+/// Template sink forwarding rendered output to the response body in chunks
+struct ChunkWriter {
+    tx: mpsc::Sender<Bytes>,
+    buf: String,
+}
+
+impl ChunkWriter {
+    fn flush(&mut self) -> fmt::Result {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+
+        let chunk = std::mem::replace(&mut self.buf, String::with_capacity(STREAM_CHUNK_SIZE));
+
+        // client is gone, stop rendering
+        self.tx
+            .blocking_send(Bytes::from(chunk))
+            .map_err(|_| fmt::Error)
+    }
+}
+
+impl fmt::Write for ChunkWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.buf.push_str(s);
+
+        if self.buf.len() >= STREAM_CHUNK_SIZE {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Render template on a blocking thread and stream it out while rendering,
+/// used for pages which grow with the dataset
+fn stream_template<T: Template + Send + 'static>(template: T) -> Response<Body> {
+    let (tx, rx) = mpsc::channel::<Bytes>(STREAM_BUFFER);
+
+    tokio::task::spawn_blocking(move || {
+        let mut writer = ChunkWriter {
+            tx,
+            buf: String::with_capacity(STREAM_CHUNK_SIZE),
+        };
+
+        if template.render_into(&mut writer).is_ok() {
+            let _ = writer.flush();
+        }
+    });
+
+    let stream = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (Ok(Frame::data(chunk)), rx))
+    });
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .body(StreamBody::new(stream).boxed_unsync())
+        .unwrap()
 }
 
 #[derive(Template)]
 #[template(path = "index.html")]
-struct IndexTemplate<'a> {
-    pagination: &'a Pagination,
-    page_limits: &'a Vec<usize>,
-    orders: &'a Vec<(String, String)>,
-    filter: &'a MetadataFilter,
+struct IndexTemplate {
+    t: &'static Catalog,
+    base_path: String,
+    user: Option<String>,
+    has_diff: bool,
+    has_logs: bool,
+    has_player: bool,
+    has_duplicates: bool,
+    external: ExternalFrontend,
+    profile: String,
+    profiles: Vec<String>,
+    pagination: Pagination,
+    /// Query string pairs carried over by the pagination form
+    page_query: Vec<(String, String)>,
+    /// `rel="prev"` and `rel="next"` links of the page
+    prev_href: Option<String>,
+    next_href: Option<String>,
+    /// Every page of the applied filter, laid out for printing
+    print_href: String,
+    /// Every page of the applied filter as a Markdown table
+    export_href: String,
+    focus_search: bool,
+    /// Position of the result focused on load, starting at 1
+    focus_result: Option<usize>,
+    /// Video opened in the player on load
+    open_id: Option<String>,
+    page_limits: Vec<usize>,
+    orders: Vec<(String, String)>,
+    period_chips: Vec<(String, String)>,
+    filter: MetadataFilter,
     total_count_raw: usize,
     total_count: usize,
-    watch_timeline: &'a Vec<DateTime<Utc>>,
-    data: &'a Vec<Metadata>,
+    /// Videos only ever shown as ads
+    ads_count: usize,
+    /// Latest watch, the month the calendar opens on
+    latest_watch: Option<DateTime<Utc>>,
+    pauses: Vec<Gap>,
+    products: Vec<ProductStats>,
+    data: Vec<Metadata>,
+    /// Rows of `view=events`, `data` is left empty then
+    events: Vec<WatchEvent>,
 }
 
-#[derive(Debug)]
-pub struct ServiceHandler {
-    pub vault: Vault,
+#[derive(Template)]
+#[template(path = "print.html")]
+struct PrintTemplate {
+    base_path: String,
+    order: String,
+    printed_at: String,
+    data: Vec<Metadata>,
 }
 
-impl ServiceHandler {
-    pub fn run(&self, req: Request<Incoming>) -> Result<Response<Body>> {
-        let payload = (req.method(), req.uri().path());
-        let mut state = self.vault.state.lock().unwrap();
+#[derive(Template)]
+#[template(path = "diff.html")]
+struct DiffTemplate {
+    base_path: String,
+    external: ExternalFrontend,
+    diff: HistoryDiff,
+}
 
-        log::debug!("{} {}", payload.0, payload.1);
-
-        match payload {
-            // index.html
-            (&Method::GET, "/") => {
-                let query = req.uri().query().unwrap_or("");
-
-                let filter = serde_urlencoded::from_str(query).unwrap();
-                let (pagination, data) = state.metadata_table.get_collection(&filter);
-
-                let html = IndexTemplate {
-                    pagination: &pagination,
-                    page_limits: &PAGE_LIMITS.to_vec(),
-                    orders: &Order::collect_key_label_pair(),
-                    filter: &filter,
-                    total_count_raw: state.metadata_table.total_count_raw(),
-                    total_count: state.metadata_table.total_count(),
-                    watch_timeline: &state.metadata_table.watch_timeline(),
-                    data: &data,
-                };
-                let res = Response::new(full(html.render().unwrap()));
+#[derive(Template)]
+#[template(path = "quality.html")]
+struct QualityTemplate<'a> {
+    base_path: &'a str,
+    report: &'a QualityReport,
+}
 
-                Ok(res)
-            }
-            (&Method::GET, "/style.css") => {
-                let res = Response::new(full(Bytes::from_static(CSS)));
+#[derive(Template)]
+#[template(path = "sessions.html")]
+struct SessionsTemplate<'a> {
+    base_path: &'a str,
+    external: &'a ExternalFrontend,
+    report: &'a SessionReport,
+    page: &'a SessionPage<'a>,
+}
 
-                Ok(res)
-            }
-            (&Method::GET, "/alpine.js") => {
-                let res = Response::new(full(Bytes::from_static(ALPINE_JS)));
+#[derive(Template)]
+#[template(path = "years.html")]
+struct YearsTemplate<'a> {
+    base_path: &'a str,
+    years: &'a [YearSummary],
+    rates: &'a [WatchRates],
+}
 
-                Ok(res)
-            }
-            (&Method::GET, "/chart.js") => {
-                let res = Response::new(full(Bytes::from_static(CHART_JS)));
+#[derive(Template)]
+#[template(path = "milestones.html")]
+struct MilestonesTemplate<'a> {
+    base_path: &'a str,
+    external: &'a ExternalFrontend,
+    milestones: &'a Milestones,
+}
 
-                Ok(res)
-            }
-            (&Method::GET, "/iframe_api") => {
-                let res = Response::new(full(state.youtube.iframe_api_script.clone()));
+#[derive(Template)]
+#[template(path = "duplicates.html")]
+struct DuplicatesTemplate<'a> {
+    base_path: &'a str,
+    external: &'a ExternalFrontend,
+    groups: &'a [DuplicateGroup],
+}
 
-                Ok(res)
-            }
-            (&Method::GET, LOCAL_WIDGET_API_PATH) => {
-                let res = Response::new(full(state.youtube.widgetapi_script.clone()));
+#[derive(Template)]
+#[template(path = "resurface.html")]
+struct ResurfaceTemplate<'a> {
+    base_path: &'a str,
+    external: &'a ExternalFrontend,
+    query: &'a ResurfaceQuery,
+    list: &'a [Resurfaced],
+}
 
-                Ok(res)
+#[derive(Template)]
+#[template(path = "about.html")]
+struct AboutTemplate<'a> {
+    base_path: &'a str,
+    info: &'a DatasetInfo<'a>,
+}
+
+#[derive(Template)]
+#[template(path = "logs.html")]
+struct LogsTemplate<'a> {
+    base_path: &'a str,
+    query: &'a LogsQuery,
+    levels: &'a Vec<Level>,
+    records: &'a Vec<LogRecord>,
+}
+
+#[derive(Template)]
+#[template(path = "login.html")]
+struct LoginTemplate {
+    failed: bool,
+}
+
+/// UiOptions
+///
+/// Defaults of the index page, used when the query string omits them
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiOptions {
+    pub limit: usize,
+    pub order: Order,
+    pub page_limits: Vec<usize>,
+    /// Negotiated from `Accept-Language` when unset
+    pub lang: Option<Lang>,
+    /// Where videos and channels are opened
+    pub external_frontend: ExternalFrontend,
+    /// Serve the possible duplicates page
+    pub duplicates: bool,
+    /// Milliseconds a filter may run before the request gives up, 0 for no
+    /// limit
+    pub query_timeout: u64,
+    /// Bytes a request body may have, bodies are buffered before routing
+    /// and only small forms and reported watches are posted
+    pub max_body: usize,
+}
+
+impl UiOptions {
+    /// When a filter started now has to be done by
+    fn query_deadline(&self) -> Option<Instant> {
+        (self.query_timeout > 0).then(|| Instant::now() + Duration::from_millis(self.query_timeout))
+    }
+}
+
+impl Default for UiOptions {
+    fn default() -> Self {
+        Self {
+            limit: default_limit(),
+            order: default_order(),
+            page_limits: PAGE_LIMITS.to_vec(),
+            lang: None,
+            external_frontend: ExternalFrontend::default(),
+            duplicates: false,
+            query_timeout: DEFAULT_QUERY_TIMEOUT_MS,
+            max_body: DEFAULT_MAX_BODY,
+        }
+    }
+}
+
+/// Milliseconds a filter may run by default, a few seconds of a core
+pub const DEFAULT_QUERY_TIMEOUT_MS: u64 = 5000;
+
+/// Bytes a request body may have by default
+pub const DEFAULT_MAX_BODY: usize = 64 * 1024;
+
+/// Preferences cookie name
+pub const PREFS_COOKIE: &str = "ytm_prefs";
+
+/// How long the index page settings are remembered
+const PREFS_TTL_DAYS: i64 = 365;
+
+/// Preferences
+///
+/// Index page settings of the last visit, kept in [`PREFS_COOKIE`] signed
+/// with the server's key so clients can't plant arbitrary values
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+struct Preferences {
+    limit: Option<usize>,
+    order: Option<Order>,
+    view: Option<View>,
+}
+
+impl Preferences {
+    /// Read settings from a query string or a cookie value, anything the
+    /// index page doesn't offer is dropped
+    fn parse(value: &str, ui: &UiOptions) -> Self {
+        let pairs = serde_urlencoded::from_str::<Vec<(String, String)>>(value).unwrap_or_default();
+        let get = |key: &str| pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+
+        Self {
+            limit: get("limit")
+                .and_then(|v| v.parse().ok())
+                .filter(|v| ui.page_limits.contains(v)),
+            order: get("order").and_then(|v| v.parse().ok()),
+            view: get("view").and_then(|v| v.parse().ok()),
+        }
+    }
+
+    /// Read settings from a cookie value, nothing when its signature doesn't
+    /// match
+    fn from_cookie(value: &str, key: &[u8], ui: &UiOptions) -> Self {
+        match value.rsplit_once('.') {
+            Some((payload, signature))
+                if constant_time_eq(
+                    signature.as_bytes(),
+                    prefs_signature(key, payload).as_bytes(),
+                ) =>
+            {
+                Self::parse(payload, ui)
             }
-            // 404
-            _ => Ok(Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .body(full(""))
-                .unwrap()),
+            _ => Self::default(),
+        }
+    }
+
+    /// Settings of the request, falling back to the remembered ones
+    fn or(self, other: &Self) -> Self {
+        Self {
+            limit: self.limit.or(other.limit),
+            order: self.order.or_else(|| other.order.clone()),
+            view: self.view.or(other.view),
         }
     }
+
+    fn to_cookie(&self, key: &[u8]) -> String {
+        let payload = serde_urlencoded::to_string(self).unwrap_or_default();
+
+        format!(
+            "{}={}.{}; Path=/; SameSite=Lax; Max-Age={}",
+            PREFS_COOKIE,
+            payload,
+            prefs_signature(key, &payload),
+            PREFS_TTL_DAYS * 24 * 60 * 60
+        )
+    }
 }
 
-/// hyper service trait implementation
-impl Service<Request<Incoming>> for ServiceHandler {
-    type Response = Response<Body>;
-    type Error = anyhow::Error;
-    type Future =
-        Pin<Box<dyn Future<Output = std::result::Result<Self::Response, Self::Error>> + Send>>;
+/// Hex encoded HMAC-SHA256 of a preferences cookie payload
+fn prefs_signature(key: &[u8], payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(payload.as_bytes());
 
-    fn call(&self, req: Request<Incoming>) -> Self::Future {
-        let res = self.run(req);
+    to_hex_string(&mac.finalize().into_bytes())
+}
+
+/// Fill `limit` and `order` missing from the query string with the
+/// remembered preferences, then UI defaults, and `view` with the remembered
+/// one
+fn with_ui_defaults(query: &str, ui: &UiOptions, prefs: &Preferences) -> String {
+    let mut pairs = serde_urlencoded::from_str::<Vec<(String, String)>>(query).unwrap_or_default();
+
+    if !pairs.iter().any(|(k, _)| k == "limit") {
+        pairs.push((
+            "limit".to_owned(),
+            prefs.limit.unwrap_or(ui.limit).to_string(),
+        ));
+    }
+
+    if !pairs.iter().any(|(k, _)| k == "order") {
+        pairs.push((
+            "order".to_owned(),
+            prefs.order.as_ref().unwrap_or(&ui.order).to_string(),
+        ));
+    }
+
+    if let Some(view) = prefs.view
+        && !pairs.iter().any(|(k, _)| k == "view")
+    {
+        pairs.push(("view".to_owned(), view.to_string()));
+    }
+
+    serde_urlencoded::to_string(pairs).unwrap_or_default()
+}
+
+/// BuildInfo
+#[derive(Debug, Serialize)]
+struct BuildInfo {
+    version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    profile: &'static str,
+    features: Vec<&'static str>,
+}
+
+impl BuildInfo {
+    fn current() -> Self {
+        let features = [
+            ("server", cfg!(feature = "server")),
+            ("wasm", cfg!(feature = "wasm")),
+            ("python", cfg!(feature = "python")),
+        ];
+
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            profile: if cfg!(debug_assertions) {
+                "debug"
+            } else {
+                "release"
+            },
+            features: features
+                .into_iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| name)
+                .collect(),
+        }
+    }
+}
+
+/// DatasetInfo
+///
+/// What the current profile is serving, shown on `/about` and `/api/info`
+#[derive(Debug, Serialize)]
+struct DatasetInfo<'a> {
+    profile: &'a str,
+    /// See [`DATASET_VERSION_HEADER`]
+    version: u64,
+    source: Option<&'a SourceInfo>,
+    total_count_raw: usize,
+    total_count: usize,
+    collapsed_count: usize,
+    coverage: Coverage,
+    memory: MemoryUsage,
+    build: BuildInfo,
+}
+
+/// Print query
+#[derive(Debug, Deserialize)]
+struct PrintQuery {
+    #[serde(default)]
+    print: u8,
+}
+
+/// Focus
+///
+/// Element of the index page focused on load
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Focus {
+    Search,
+    Results,
+}
+
+/// Keyboard navigation query
+///
+/// Deep links of the index page, read next to the filter
+#[derive(Debug, Deserialize)]
+struct NavigationQuery {
+    focus: Option<Focus>,
+    open: Option<usize>,
+}
+
+/// Quality report query
+#[derive(Debug, Deserialize)]
+struct QualityQuery {
+    #[serde(default = "default_gap_days")]
+    gap_days: i64,
+}
+
+fn default_gap_days() -> i64 {
+    DEFAULT_GAP_DAYS
+}
+
+/// Sessions query
+#[derive(Debug, Deserialize)]
+struct SessionsQuery {
+    #[serde(default = "default_session_gap_minutes")]
+    gap_minutes: i64,
+
+    #[serde(default = "default_page")]
+    page: usize,
+
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+fn default_session_gap_minutes() -> i64 {
+    DEFAULT_SESSION_GAP_MINUTES
+}
+
+/// `400` for a session gap out of `1..=MAX_SESSION_GAP_MINUTES`
+fn invalid_gap_minutes(gap_minutes: i64) -> Option<Response<Body>> {
+    (!(1..=MAX_SESSION_GAP_MINUTES).contains(&gap_minutes)).then(|| {
+        bad_request(format!(
+            "gap_minutes must be 1 to {}",
+            MAX_SESSION_GAP_MINUTES
+        ))
+    })
+}
+
+/// Single page of sessions, shown on `/sessions` and `/api/sessions`
+#[derive(Serialize)]
+struct SessionPage<'a> {
+    gap_minutes: i64,
+    total_sessions: usize,
+    /// Sent in the envelope
+    #[serde(skip)]
+    pagination: Pagination,
+    sessions: &'a [Session],
+}
+
+/// Stopped channels query
+#[derive(Debug, Deserialize)]
+struct StoppedQuery {
+    #[serde(default = "default_stopped_months")]
+    months: u32,
+
+    #[serde(default = "default_stopped_min_watches")]
+    min_watches: usize,
+
+    #[serde(default = "default_limit")]
+    n: usize,
+}
+
+/// Resurface query
+#[derive(Debug, Deserialize)]
+struct ResurfaceQuery {
+    #[serde(default = "default_resurface_idle_days")]
+    idle_days: i64,
+
+    #[serde(default = "default_resurface_min_days")]
+    min_days: usize,
+
+    #[serde(default = "default_limit")]
+    n: usize,
+}
+
+fn default_resurface_idle_days() -> i64 {
+    DEFAULT_RESURFACE_IDLE_DAYS
+}
+
+fn default_resurface_min_days() -> usize {
+    DEFAULT_RESURFACE_MIN_DAYS
+}
+
+fn default_stopped_months() -> u32 {
+    DEFAULT_STOPPED_MONTHS
+}
+
+fn default_stopped_min_watches() -> usize {
+    DEFAULT_STOPPED_MIN_WATCHES
+}
+
+/// Co-watch query
+#[derive(Debug, Deserialize)]
+struct CoWatchQuery {
+    #[serde(default)]
+    unit: CoWatchUnit,
+
+    #[serde(default = "default_session_gap_minutes")]
+    gap_minutes: i64,
+
+    #[serde(default = "default_min_shared")]
+    min_shared: usize,
+
+    #[serde(default = "default_limit")]
+    n: usize,
+
+    /// List channels with their partners instead of plain pairs
+    #[serde(default)]
+    network: bool,
+}
+
+fn default_min_shared() -> usize {
+    DEFAULT_MIN_SHARED
+}
+
+/// Top list query, `year` takes precedence over `from` and `to`
+#[derive(Debug, Deserialize)]
+struct TopQuery {
+    kind: TopKind,
+
+    year: Option<i32>,
+
+    #[serde(default, deserialize_with = "deserialize_datetime")]
+    from: Option<DateTime<Utc>>,
+
+    #[serde(default, deserialize_with = "deserialize_datetime_end")]
+    to: Option<DateTime<Utc>>,
+
+    #[serde(default = "default_top_n")]
+    n: usize,
+}
+
+fn default_top_n() -> usize {
+    10
+}
+
+/// Video timeline query
+#[derive(Debug, Deserialize)]
+struct TimelineQuery {
+    id: String,
+}
+
+/// Zoomed timeline query, every watch unless `id` picks a video
+#[derive(Debug, Deserialize)]
+struct ZoomQuery {
+    id: Option<String>,
+
+    #[serde(default, deserialize_with = "deserialize_datetime")]
+    from: Option<DateTime<Utc>>,
+
+    #[serde(default, deserialize_with = "deserialize_datetime_end")]
+    to: Option<DateTime<Utc>>,
+
+    /// Chart width in pixels
+    #[serde(default = "default_zoom_width")]
+    width: usize,
+}
+
+fn default_zoom_width() -> usize {
+    DEFAULT_ZOOM_WIDTH
+}
+
+/// Full watch timeline of a single video
+#[derive(Serialize)]
+struct VideoTimeline<'a> {
+    id: &'a str,
+    title: &'a str,
+    /// Watch page on the external frontend
+    url: String,
+    watch_count: usize,
+    watch_timeline: &'a [DateTime<Utc>],
+}
+
+/// Item of an API response with its page on the external frontend
+#[derive(Serialize, JsonSchema)]
+struct Linked<T> {
+    #[serde(flatten)]
+    item: T,
+    url: String,
+}
+
+/// Names of the schemas served under `/api/schema/`
+const API_SCHEMAS: [&str; 12] = [
+    "filter",
+    "metadata",
+    "videos",
+    "events",
+    "top-channels",
+    "top-videos",
+    "stopped-channels",
+    "co-watch",
+    "co-watch-network",
+    "calendar",
+    "years",
+    "timeline",
+];
+
+/// JSON Schema of a filter or of the `data` of an API response
+fn api_schema(name: &str) -> Option<Schema> {
+    let schema = match name {
+        "filter" => schema_for!(MetadataFilter),
+        "metadata" => schema_for!(Metadata),
+        "videos" => schema_for!(Vec<Linked<Metadata>>),
+        "events" => schema_for!(Vec<Linked<WatchEvent>>),
+        "top-channels" => schema_for!(Vec<TopChannel>),
+        "top-videos" => schema_for!(Vec<Linked<TopVideo>>),
+        "stopped-channels" => schema_for!(Vec<StoppedChannel>),
+        "co-watch" => schema_for!(Vec<CoWatchPair>),
+        "co-watch-network" => schema_for!(Vec<CoWatchNode>),
+        "calendar" => schema_for!(MonthCalendar),
+        "years" => schema_for!(Vec<YearSummary>),
+        "timeline" => schema_for!(ZoomedTimeline),
+        _ => return None,
+    };
+
+    Some(schema)
+}
+
+/// Number of videos, or watch events for `view=events`, matching a filter
+#[derive(Serialize)]
+struct VideoCount {
+    count: usize,
+}
+
+/// Logs query
+#[derive(Debug, Deserialize)]
+struct LogsQuery {
+    #[serde(default = "default_log_level")]
+    level: Level,
+
+    #[serde(default = "default_log_limit")]
+    limit: usize,
+}
+
+fn default_log_level() -> Level {
+    Level::Info
+}
+
+fn default_log_limit() -> usize {
+    200
+}
+
+/// ShortLink
+///
+/// Response of `POST /api/links`
+#[derive(Debug, Serialize)]
+struct ShortLink {
+    token: String,
+    path: String,
+}
+
+/// Login form payload
+#[derive(Debug, Deserialize)]
+struct LoginForm {
+    name: String,
+    password: String,
+}
+
+/// Watch reported on `POST /api/history`
+#[derive(Debug, Deserialize)]
+struct HistoryPayload {
+    /// Profile the watch belongs to, the default one when unset
+    #[serde(default)]
+    profile: Option<String>,
+    id: String,
+    title: String,
+    /// Channel name
+    channel: String,
+    #[serde(default)]
+    channel_id: String,
+    /// Time of the watch, now when unset
+    #[serde(default)]
+    timestamp: Option<DateTime<Utc>>,
+    #[serde(default)]
+    product: Option<String>,
+}
+
+impl HistoryPayload {
+    /// Check the payload and turn it into a journal entry
+    fn validate(self, now: DateTime<Utc>) -> Result<JournalEntry, String> {
+        if !is_video_id(&self.id) {
+            return Err(format!("invalid video id `{}`", self.id));
+        }
+
+        if self.title.trim().is_empty() {
+            return Err("title must not be empty".to_string());
+        }
+
+        let time = self.timestamp.unwrap_or(now);
+
+        if time > now + chrono::Duration::minutes(5) {
+            return Err("timestamp is in the future".to_string());
+        }
+
+        Ok(JournalEntry {
+            profile: self.profile,
+            id: self.id,
+            title: self.title,
+            channel_id: self.channel_id,
+            channel_name: self.channel,
+            time,
+            product: self.product,
+        })
+    }
+}
+
+/// Whether `id` looks like a YouTube video ID
+fn is_video_id(id: &str) -> bool {
+    id.len() == 11
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// Request ID header, accepted from a reverse proxy and echoed back
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Version of the profile a response was built from, changes when its
+/// table is replaced
+pub const DATASET_VERSION_HEADER: &str = "x-dataset-version";
+
+/// RequestId
+///
+/// Request extension holding the ID of the current request
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+/// Reuse request ID from the proxy when it looks sane, generate one otherwise
+fn request_id(headers: &HeaderMap) -> String {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty() && v.len() <= 128 && v.bytes().all(|b| b.is_ascii_graphic()))
+        .map(|v| v.to_owned())
+        .unwrap_or_else(|| to_hex_string(&rand::rng().random::<[u8; 8]>()))
+}
+
+#[derive(Clone, Debug)]
+pub struct ServiceHandler {
+    pub vault: Vault,
+}
+
+/// Split `/p/:name/...` into profile name and the remaining path
+fn split_profile_path(path: &str) -> (Option<&str>, &str) {
+    match path.strip_prefix("/p/") {
+        Some(rest) => match rest.find('/') {
+            Some(idx) => (Some(&rest[..idx]), &rest[idx..]),
+            None => (Some(rest), "/"),
+        },
+        None => (None, path),
+    }
+}
+
+fn not_found() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .body(full(""))
+        .unwrap()
+}
+
+fn bad_request(message: String) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::BAD_REQUEST)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(full(message))
+        .unwrap()
+}
+
+/// Filter given up on, see [`UiOptions::query_timeout`]
+fn too_expensive(e: QueryTooExpensive, timeout: u64) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::UNPROCESSABLE_ENTITY)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(full(format!("{}, it took over {} ms", e, timeout)))
+        .unwrap()
+}
+
+fn no_content() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .body(full(""))
+        .unwrap()
+}
+
+fn payload_too_large() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::PAYLOAD_TOO_LARGE)
+        .body(full(""))
+        .unwrap()
+}
+
+/// Envelope
+///
+/// Shape of every JSON response, `pagination` is null unless the data is a
+/// page
+#[derive(Serialize)]
+struct Envelope<'a, T> {
+    data: &'a T,
+    pagination: Option<&'a Pagination>,
+    meta: EnvelopeMeta,
+}
+
+#[derive(Serialize)]
+struct EnvelopeMeta {
+    /// See [`DATASET_VERSION_HEADER`], null outside of profiles
+    dataset_version: Option<u64>,
+}
+
+fn json<T: Serialize>(dataset_version: Option<u64>, value: &T) -> Result<Response<Body>> {
+    let envelope = Envelope {
+        data: value,
+        pagination: None,
+        meta: EnvelopeMeta { dataset_version },
+    };
+    let res = Response::builder()
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(full(serde_json::to_vec(&envelope)?))?;
+
+    Ok(res)
+}
+
+/// Page of a paginated endpoint, linked to the other pages through an
+/// RFC 8288 `Link` header
+fn json_page<T: Serialize>(
+    dataset_version: Option<u64>,
+    uri: &Uri,
+    pagination: &Pagination,
+    value: &T,
+) -> Result<Response<Body>> {
+    let envelope = Envelope {
+        data: value,
+        pagination: Some(pagination),
+        meta: EnvelopeMeta { dataset_version },
+    };
+    let mut res = Response::builder()
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(full(serde_json::to_vec(&envelope)?))?;
+
+    if let Some(v) = page_links(uri, pagination) {
+        res.headers_mut().insert(header::LINK, v);
+    }
+
+    Ok(res)
+}
+
+/// `Link` header value pointing at the first, previous, next and last pages,
+/// e.g. `</api/videos?limit=20&page=3>; rel="next"`
+fn page_links(uri: &Uri, pagination: &Pagination) -> Option<HeaderValue> {
+    let pairs = serde_urlencoded::from_str::<Vec<(String, String)>>(uri.query().unwrap_or(""))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(k, _)| k != "page")
+        .collect::<Vec<(String, String)>>();
+    let link = |page: usize, rel: &str| {
+        let mut pairs = pairs.clone();
+        pairs.push(("page".to_owned(), page.to_string()));
+
+        format!(
+            "<{}?{}>; rel=\"{}\"",
+            uri.path(),
+            serde_urlencoded::to_string(pairs).unwrap_or_default(),
+            rel
+        )
+    };
+
+    let mut links = Vec::new();
+
+    if pagination.total_page > 0 {
+        links.push(link(1, "first"));
+    }
+
+    if let Some(v) = pagination.prev_page {
+        links.push(link(v, "prev"));
+    }
+
+    if let Some(v) = pagination.next_page {
+        links.push(link(v, "next"));
+    }
+
+    if pagination.total_page > 0 {
+        links.push(link(pagination.total_page, "last"));
+    }
+
+    HeaderValue::from_str(&links.join(", ")).ok()
+}
+
+fn icon(content_type: &'static str, bytes: &'static [u8]) -> Response<Body> {
+    Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CACHE_CONTROL, ICON_CACHE_CONTROL)
+        .body(full(Bytes::from_static(bytes)))
+        .unwrap()
+}
+
+fn redirect(location: &str) -> Result<Response<Body>> {
+    let res = Response::builder()
+        .status(StatusCode::SEE_OTHER)
+        .header(header::LOCATION, location)
+        .body(full(""))?;
+
+    Ok(res)
+}
+
+/// ProfileHandler
+///
+/// Handler of a profile route, run on the blocking pool since filters and
+/// reports can keep a core busy for seconds
+type ProfileHandler = fn(&ProfileRequest) -> Result<Response<Body>>;
+
+/// ProfileRequest
+///
+/// Request resolved to a profile, with a snapshot of everything its handler
+/// reads so the vault lock is only taken again for caches and links
+struct ProfileRequest {
+    req: Request<Bytes>,
+    vault: Vault,
+    user: Option<User>,
+    profile: String,
+    profile_names: Vec<String>,
+    /// Path relative to `/p/:name`
+    path: String,
+    base_path: String,
+    version: u64,
+    metadata_table: Arc<MetadataTable>,
+    diff_base: Option<Arc<MetadataTable>>,
+    ui: UiOptions,
+    prefs_key: Arc<[u8]>,
+    has_player: bool,
+}
+
+impl ProfileRequest {
+    fn query(&self) -> &str {
+        self.req.uri().query().unwrap_or("")
+    }
+
+    fn json<T: Serialize>(&self, value: &T) -> Result<Response<Body>> {
+        json(Some(self.version), value)
+    }
+}
+
+/// Handler of a profile route, `None` when nothing matches
+fn profile_route(method: &Method, path: &str) -> Option<ProfileHandler> {
+    let handler: ProfileHandler = match (method, path) {
+        (&Method::GET, "/") => get_index,
+        (&Method::GET, "/export.md") => get_export_markdown,
+        (&Method::GET, p) if p.starts_with("/channel/") && p.ends_with("/export.csv") => {
+            get_channel_csv
+        }
+        (&Method::GET, "/diff") => get_diff,
+        (&Method::GET, "/quality") => get_quality,
+        (&Method::GET, "/sessions" | "/api/sessions") => get_sessions,
+        (&Method::GET, "/years" | "/api/years") => get_years,
+        (&Method::GET, "/milestones" | "/api/milestones") => get_milestones,
+        (&Method::GET, "/duplicates" | "/api/duplicates") => get_duplicates,
+        (&Method::GET, "/resurface" | "/api/resurface") => get_resurface,
+        (&Method::GET, p) if p.starts_with("/api/calendar/") => get_calendar,
+        (&Method::GET, "/api/stats/top") => get_top,
+        (&Method::GET, "/api/stats/stopped-channels") => get_stopped_channels,
+        (&Method::GET, "/api/stats/co-watch") => get_co_watch,
+        (&Method::POST, "/api/links") => post_links,
+        (&Method::GET, "/about" | "/api/info") => get_info,
+        (&Method::GET, "/api/videos") => get_videos,
+        (&Method::GET, "/api/videos/count") => get_video_count,
+        (&Method::GET, "/api/videos/timeline") => get_video_timeline,
+        (&Method::GET, "/api/timeline") => get_timeline,
+        _ => return None,
+    };
+
+    Some(handler)
+}
+
+/// index.html
+fn get_index(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let metadata_table = &*cx.metadata_table;
+    let ui = &cx.ui;
+    let req = &cx.req;
+    let lang = ui
+        .lang
+        .or_else(|| {
+            req.headers()
+                .get(header::ACCEPT_LANGUAGE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(Lang::negotiate)
+        })
+        .unwrap_or_default();
+    let query = cx.query();
+    let remembered = Preferences::from_cookie(
+        get_cookie(req.headers(), PREFS_COOKIE).unwrap_or(""),
+        &cx.prefs_key,
+        ui,
+    );
+    let prefs = Preferences::parse(query, ui).or(&remembered);
+    let navigation = match serde_urlencoded::from_str::<NavigationQuery>(query) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+    let print = match serde_urlencoded::from_str::<PrintQuery>(query) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+    let query = with_ui_defaults(query, ui, &prefs);
+    let page_query = serde_urlencoded::from_str::<Vec<(String, String)>>(&query)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(k, _)| !["page", "focus", "open", "print"].contains(&k.as_str()))
+        .filter(|(_, v)| !v.is_empty())
+        .collect::<Vec<(String, String)>>();
+
+    let filter: MetadataFilter = match serde_urlencoded::from_str(&query) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+
+    if print.print != 0 {
+        let filter = match ValidatedFilter::unbounded(MetadataFilter {
+            page: 1,
+            limit: usize::MAX,
+            ..filter
+        }) {
+            Ok(v) => v,
+            Err(e) => return Ok(bad_request(e.to_string())),
+        };
+        let (_, data) = match metadata_table.get_collection_until(&filter, ui.query_deadline()) {
+            Ok(v) => v,
+            Err(e) => return Ok(too_expensive(e, ui.query_timeout)),
+        };
+        let html = PrintTemplate {
+            base_path: cx.base_path.clone(),
+            order: filter.order.to_string_label(),
+            printed_at: Utc::now().to_datetime_string(),
+            data,
+        };
+
+        return Ok(stream_template(html));
+    }
+
+    let filter = match ValidatedFilter::new(filter) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+    let collection = match filter.view {
+        View::Videos => metadata_table
+            .get_collection_until(&filter, ui.query_deadline())
+            .map(|(p, data)| (p, data, Vec::new())),
+        View::Events => metadata_table
+            .get_events_until(&filter, ui.query_deadline())
+            .map(|(p, events)| (p, Vec::new(), events)),
+    };
+    let (pagination, data, events) = match collection {
+        Ok(v) => v,
+        Err(e) => return Ok(too_expensive(e, ui.query_timeout)),
+    };
+    // links to this page with one more query pair
+    let href = |key: &str, value: String| {
+        let mut pairs = page_query.clone();
+        pairs.push((key.to_owned(), value));
+
+        format!(
+            "{}/?{}",
+            cx.base_path,
+            serde_urlencoded::to_string(pairs).unwrap_or_default()
+        )
+    };
+    let prev_href = pagination.prev_page.map(|v| href("page", v.to_string()));
+    let next_href = pagination.next_page.map(|v| href("page", v.to_string()));
+    let print_href = href("print", "1".to_owned());
+    let export_href = format!(
+        "{}/export.md?{}",
+        cx.base_path,
+        serde_urlencoded::to_string(
+            page_query
+                .iter()
+                .filter(|(k, _)| k != "limit")
+                .collect::<Vec<_>>()
+        )
+        .unwrap_or_default()
+    );
+    let open = navigation.open.filter(|v| (1..=data.len()).contains(v));
+    let focus_result = open
+        .or_else(|| (navigation.focus == Some(Focus::Results) && !data.is_empty()).then_some(1));
+    let open_id = open
+        .filter(|_| cx.has_player)
+        .map(|v| data[v - 1].id.clone());
+
+    let html = IndexTemplate {
+        t: lang.catalog(),
+        base_path: cx.base_path.clone(),
+        user: cx.user.as_ref().map(|u| u.name.clone()),
+        has_diff: cx.diff_base.is_some(),
+        has_logs: cx.user.as_ref().is_some_and(|u| u.is_unrestricted()),
+        has_player: cx.has_player,
+        has_duplicates: ui.duplicates,
+        external: ui.external_frontend.clone(),
+        profile: cx.profile.clone(),
+        profiles: cx.profile_names.clone(),
+        prev_href,
+        next_href,
+        print_href,
+        export_href,
+        focus_search: navigation.focus == Some(Focus::Search),
+        focus_result,
+        open_id,
+        pagination,
+        page_query,
+        page_limits: ui.page_limits.clone(),
+        orders: lang.catalog().collect_order_key_label_pair(),
+        period_chips: PeriodShortcut::collect_key_label_pair(metadata_table.watch_timeline_ref()),
+        filter: filter.into_inner(),
+        total_count_raw: metadata_table.total_count_raw(),
+        total_count: metadata_table.total_count(),
+        ads_count: metadata_table.ads_count(),
+        latest_watch: metadata_table.watch_timeline_ref().last().copied(),
+        pauses: suspected_pauses(metadata_table).pauses,
+        products: product_stats(metadata_table),
+        data,
+        events,
+    };
+    let mut res = stream_template(html);
+
+    res.headers_mut().insert(
+        header::CONTENT_LANGUAGE,
+        HeaderValue::from_static(lang.tag()),
+    );
+    res.headers_mut()
+        .insert(header::VARY, HeaderValue::from_static("Accept-Language"));
+
+    if prefs != remembered {
+        res.headers_mut().insert(
+            header::SET_COOKIE,
+            prefs.to_cookie(&cx.prefs_key).parse().unwrap(),
+        );
+    }
+
+    Ok(res)
+}
+
+fn get_export_markdown(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let query = cx.query();
+    let filter: MetadataFilter = match serde_urlencoded::from_str(query) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+
+    // every match unless a page is asked for
+    let filter = if serde_urlencoded::from_str::<Vec<(String, String)>>(query)
+        .unwrap_or_default()
+        .iter()
+        .any(|(k, _)| k == "limit")
+    {
+        ValidatedFilter::new(filter)
+    } else {
+        ValidatedFilter::unbounded(MetadataFilter {
+            page: 1,
+            limit: usize::MAX,
+            ..filter
+        })
+    };
+    let filter = match filter {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+
+    let (_, data) = match cx
+        .metadata_table
+        .get_collection_until(&filter, cx.ui.query_deadline())
+    {
+        Ok(v) => v,
+        Err(e) => return Ok(too_expensive(e, cx.ui.query_timeout)),
+    };
+    let res = Response::builder()
+        .header(header::CONTENT_TYPE, "text/markdown; charset=utf-8")
+        .body(full(to_markdown_table(&data)))?;
+
+    Ok(res)
+}
+
+fn get_channel_csv(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let id = cx
+        .path
+        .strip_prefix("/channel/")
+        .and_then(|v| v.strip_suffix("/export.csv"))
+        .unwrap_or("");
+
+    if id.is_empty()
+        || !id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+    {
+        return Ok(not_found());
+    }
+
+    let filter = ValidatedFilter::unbounded(MetadataFilter {
+        channel_id: Some(id.to_owned()),
+        page: 1,
+        limit: usize::MAX,
+        ..Default::default()
+    })?;
+    let (_, data) = cx.metadata_table.get_collection(&filter);
+
+    if data.is_empty() {
+        return Ok(not_found());
+    }
+
+    let res = Response::builder()
+        .header(header::CONTENT_TYPE, "text/csv; charset=utf-8")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}.csv\"", id),
+        )
+        .body(full(to_watches_csv(&data)))?;
+
+    Ok(res)
+}
+
+fn get_diff(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let Some(diff_base) = &cx.diff_base else {
+        return Ok(not_found());
+    };
+
+    let html = DiffTemplate {
+        base_path: cx.base_path.clone(),
+        external: cx.ui.external_frontend.clone(),
+        diff: diff(diff_base, &cx.metadata_table),
+    };
+
+    Ok(stream_template(html))
+}
+
+fn get_quality(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let query = match serde_urlencoded::from_str::<QualityQuery>(cx.query()) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+
+    if !(1..=MAX_GAP_DAYS).contains(&query.gap_days) {
+        return Ok(bad_request(format!(
+            "gap_days must be 1 to {}",
+            MAX_GAP_DAYS
+        )));
+    }
+
+    let report = quality_report(&cx.metadata_table, query.gap_days);
+    let html = QualityTemplate {
+        base_path: &cx.base_path,
+        report: &report,
+    };
+    let res = Response::new(full(html.render().unwrap()));
+
+    Ok(res)
+}
+
+fn get_sessions(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let query = match serde_urlencoded::from_str::<SessionsQuery>(cx.query()) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+
+    if let Some(res) = invalid_gap_minutes(query.gap_minutes) {
+        return Ok(res);
+    }
+
+    // paged like every other listing
+    let paging = match ValidatedFilter::new(MetadataFilter {
+        page: query.page,
+        limit: query.limit,
+        ..Default::default()
+    }) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+
+    let report = watch_sessions(&cx.metadata_table, query.gap_minutes);
+    let (left, right, total_page) = page_bounds(report.sessions.len(), paging.page, paging.limit);
+    let page = SessionPage {
+        gap_minutes: report.gap_minutes,
+        total_sessions: report.sessions.len(),
+        pagination: Pagination::new(paging.page, total_page, paging.limit),
+        sessions: &report.sessions[left..right],
+    };
+
+    if cx.path == "/api/sessions" {
+        return json_page(Some(cx.version), cx.req.uri(), &page.pagination, &page);
+    }
+
+    let html = SessionsTemplate {
+        base_path: &cx.base_path,
+        external: &cx.ui.external_frontend,
+        report: &report,
+        page: &page,
+    };
+    let res = Response::new(full(html.render().unwrap()));
+
+    Ok(res)
+}
+
+fn get_years(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let cached = cx
+        .vault
+        .state
+        .lock()
+        .unwrap()
+        .years
+        .get(&cx.profile)
+        .cloned();
+    let years = match cached {
+        Some(v) => v,
+        None => {
+            let v = year_summaries(&cx.metadata_table);
+            let mut state = cx.vault.state.lock().unwrap();
+
+            // not cached for a table replaced in the meantime
+            if state.profiles.version(&cx.profile) == cx.version {
+                state.years.insert(cx.profile.clone(), v.clone());
+            }
+
+            v
+        }
+    };
+
+    if cx.path == "/api/years" {
+        return cx.json(&years);
+    }
+
+    let html = YearsTemplate {
+        base_path: &cx.base_path,
+        years: &years,
+        rates: &watch_rates(&cx.metadata_table),
+    };
+    let res = Response::new(full(html.render().unwrap()));
+
+    Ok(res)
+}
+
+fn get_milestones(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let milestones = milestones(&cx.metadata_table);
+
+    if cx.path == "/api/milestones" {
+        return cx.json(&milestones);
+    }
+
+    let html = MilestonesTemplate {
+        base_path: &cx.base_path,
+        external: &cx.ui.external_frontend,
+        milestones: &milestones,
+    };
+    let res = Response::new(full(html.render().unwrap()));
+
+    Ok(res)
+}
+
+fn get_duplicates(cx: &ProfileRequest) -> Result<Response<Body>> {
+    if !cx.ui.duplicates {
+        return Ok(not_found());
+    }
+
+    let groups = duplicate_groups(&cx.metadata_table);
+
+    if cx.path == "/api/duplicates" {
+        return cx.json(&groups);
+    }
+
+    let html = DuplicatesTemplate {
+        base_path: &cx.base_path,
+        external: &cx.ui.external_frontend,
+        groups: &groups,
+    };
+    let res = Response::new(full(html.render().unwrap()));
+
+    Ok(res)
+}
+
+fn get_resurface(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let query = match serde_urlencoded::from_str::<ResurfaceQuery>(cx.query()) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+
+    if !(0..=MAX_RESURFACE_IDLE_DAYS).contains(&query.idle_days) {
+        return Ok(bad_request(format!(
+            "idle_days must be 0 to {}",
+            MAX_RESURFACE_IDLE_DAYS
+        )));
+    }
+
+    let list = resurface(&cx.metadata_table, query.idle_days, query.min_days, query.n);
+
+    if cx.path == "/api/resurface" {
+        return cx.json(&list);
+    }
+
+    let html = ResurfaceTemplate {
+        base_path: &cx.base_path,
+        external: &cx.ui.external_frontend,
+        query: &query,
+        list: &list,
+    };
+    let res = Response::new(full(html.render().unwrap()));
+
+    Ok(res)
+}
+
+fn get_calendar(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let Some((year, month)) = cx.path["/api/calendar/".len()..].split_once('/') else {
+        return Ok(not_found());
+    };
+    let (Ok(year), Ok(month)) = (year.parse::<i32>(), month.parse::<u32>()) else {
+        return Ok(not_found());
+    };
+
+    match month_calendar(&cx.metadata_table, year, month) {
+        Some(v) => cx.json(&v),
+        None => Ok(not_found()),
+    }
+}
+
+fn get_top(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let query = match serde_urlencoded::from_str::<TopQuery>(cx.query()) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+    let period = match query.year {
+        Some(y) => match Period::year(y) {
+            Some(v) => v,
+            None => return Ok(bad_request(format!("year {} is out of range", y))),
+        },
+        None => Period {
+            from: query.from,
+            to: query.to,
+        },
+    };
+
+    match query.kind {
+        TopKind::Channels => cx.json(&top_channels(&cx.metadata_table, &period, query.n)),
+        TopKind::Videos => cx.json(
+            &top_videos(&cx.metadata_table, &period, query.n)
+                .into_iter()
+                .map(|v| Linked {
+                    url: cx.ui.external_frontend.watch_url(&v.id),
+                    item: v,
+                })
+                .collect::<Vec<_>>(),
+        ),
+    }
+}
+
+fn get_stopped_channels(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let query = match serde_urlencoded::from_str::<StoppedQuery>(cx.query()) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+
+    cx.json(&stopped_channels(
+        &cx.metadata_table,
+        query.months,
+        query.min_watches,
+        query.n,
+    ))
+}
+
+fn get_co_watch(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let query = match serde_urlencoded::from_str::<CoWatchQuery>(cx.query()) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+
+    if let Some(res) = invalid_gap_minutes(query.gap_minutes) {
+        return Ok(res);
+    }
+
+    let mut pairs = co_watch_pairs(
+        &cx.metadata_table,
+        query.unit,
+        query.gap_minutes,
+        query.min_shared,
+    );
+
+    if query.network {
+        let mut nodes = co_watch_network(&pairs, query.n);
+        nodes.truncate(query.n);
+
+        return cx.json(&nodes);
+    }
+
+    pairs.truncate(query.n);
+
+    cx.json(&pairs)
+}
+
+fn post_links(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let filter = match ValidatedFilter::parse(&String::from_utf8_lossy(cx.req.body())) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+    // re-encoded so the target is always a valid `Location`
+    let query = serde_urlencoded::to_string(&*filter)?;
+
+    let token = cx
+        .vault
+        .state
+        .lock()
+        .unwrap()
+        .links
+        .insert(format!("{}/?{}", cx.base_path, query))?;
+
+    cx.json(&ShortLink {
+        path: format!("{}{}", SHORT_LINK_PREFIX, token),
+        token,
+    })
+}
+
+fn get_info(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let metadata_table = &*cx.metadata_table;
+    let info = DatasetInfo {
+        profile: &cx.profile,
+        version: cx.version,
+        source: metadata_table.source(),
+        total_count_raw: metadata_table.total_count_raw(),
+        total_count: metadata_table.total_count(),
+        collapsed_count: metadata_table.collapsed_count(),
+        coverage: Coverage::of(metadata_table),
+        memory: metadata_table.memory_usage(),
+        build: BuildInfo::current(),
+    };
+
+    if cx.path == "/api/info" {
+        return cx.json(&info);
+    }
+
+    let html = AboutTemplate {
+        base_path: &cx.base_path,
+        info: &info,
+    };
+    let res = Response::new(full(html.render().unwrap()));
+
+    Ok(res)
+}
+
+fn get_videos(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let filter = match ValidatedFilter::parse(cx.query()) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+
+    if filter.view == View::Events {
+        let (pagination, data) = match cx
+            .metadata_table
+            .get_events_until(&filter, cx.ui.query_deadline())
+        {
+            Ok(v) => v,
+            Err(e) => return Ok(too_expensive(e, cx.ui.query_timeout)),
+        };
+        let data = data
+            .into_iter()
+            .map(|v| Linked {
+                url: cx.ui.external_frontend.watch_url(&v.id),
+                item: v,
+            })
+            .collect::<Vec<_>>();
+
+        return json_page(Some(cx.version), cx.req.uri(), &pagination, &data);
+    }
+
+    let (pagination, data) = match cx
+        .metadata_table
+        .get_collection_until(&filter, cx.ui.query_deadline())
+    {
+        Ok(v) => v,
+        Err(e) => return Ok(too_expensive(e, cx.ui.query_timeout)),
+    };
+    let data = data
+        .into_iter()
+        .map(|v| Linked {
+            url: cx.ui.external_frontend.watch_url(&v.id),
+            item: v,
+        })
+        .collect::<Vec<_>>();
+
+    json_page(Some(cx.version), cx.req.uri(), &pagination, &data)
+}
+
+fn get_video_count(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let filter = match ValidatedFilter::parse(cx.query()) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+
+    let count = match cx
+        .metadata_table
+        .count_until(&filter, cx.ui.query_deadline())
+    {
+        Ok(v) => v,
+        Err(e) => return Ok(too_expensive(e, cx.ui.query_timeout)),
+    };
+
+    cx.json(&VideoCount { count })
+}
+
+fn get_video_timeline(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let Ok(query) = serde_urlencoded::from_str::<TimelineQuery>(cx.query()) else {
+        return Ok(not_found());
+    };
+    let Some(m) = cx.metadata_table.get(&query.id) else {
+        return Ok(not_found());
+    };
+
+    cx.json(&VideoTimeline {
+        id: &m.id,
+        title: &m.title,
+        url: cx.ui.external_frontend.watch_url(&m.id),
+        watch_count: m.watch_count,
+        watch_timeline: &m.watch_timeline,
+    })
+}
+
+fn get_timeline(cx: &ProfileRequest) -> Result<Response<Body>> {
+    let query = match serde_urlencoded::from_str::<ZoomQuery>(cx.query()) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+    let timeline = match &query.id {
+        Some(id) => match cx.metadata_table.get(id) {
+            Some(m) => &m.watch_timeline,
+            None => return Ok(not_found()),
+        },
+        None => cx.metadata_table.watch_timeline_ref(),
+    };
+
+    cx.json(&zoom_timeline(timeline, query.from, query.to, query.width))
+}
+
+impl ServiceHandler {
+    pub fn new(vault: Vault) -> Self {
+        Self { vault }
+    }
+
+    /// Handle a buffered request, usable without a listener e.g. in tests
+    pub async fn run(&self, req: Request<Bytes>) -> Result<Response<Body>> {
+        let id = req
+            .extensions()
+            .get::<RequestId>()
+            .map(|v| v.0.as_str())
+            .unwrap_or("-");
+
+        log::debug!("[{}] {} {}", id, req.method(), req.uri().path());
+
+        // Public routes
+        match (req.method(), req.uri().path()) {
+            (&Method::GET, "/style.css") => {
+                return Ok(Response::new(full(Bytes::from_static(CSS))));
+            }
+            (&Method::GET, "/alpine.js") => {
+                return Ok(Response::new(full(Bytes::from_static(ALPINE_JS))));
+            }
+            (&Method::GET, "/chart.js") => {
+                return Ok(Response::new(full(Bytes::from_static(CHART_JS))));
+            }
+            (&Method::GET, "/manifest.json") => {
+                let res = Response::builder()
+                    .header(header::CONTENT_TYPE, "application/manifest+json")
+                    .body(full(Bytes::from_static(MANIFEST)))?;
+
+                return Ok(res);
+            }
+            (&Method::GET, "/favicon.ico") => return Ok(icon("image/x-icon", FAVICON)),
+            (&Method::GET, "/apple-touch-icon.png") => {
+                return Ok(icon("image/png", APPLE_TOUCH_ICON));
+            }
+            (&Method::GET, "/icons/icon-192.png") => return Ok(icon("image/png", ICON_192)),
+            (&Method::GET, "/icons/icon-512.png") => return Ok(icon("image/png", ICON_512)),
+            (&Method::GET, "/sw.js") => {
+                let res = Response::builder()
+                    .header(header::CONTENT_TYPE, "text/javascript")
+                    .header(header::CACHE_CONTROL, "no-cache")
+                    .body(full(Bytes::from_static(SERVICE_WORKER_JS)))?;
+
+                return Ok(res);
+            }
+            (&Method::GET, "/iframe_api") => return self.get_iframe_api(),
+            (&Method::GET, LOCAL_WIDGET_API_PATH) => return self.get_widget_api(),
+            (&Method::GET, "/login") => {
+                let html = LoginTemplate { failed: false };
+
+                return Ok(Response::new(full(html.render().unwrap())));
+            }
+            (&Method::POST, "/login") => return self.post_login(&req).await,
+            (&Method::POST, "/logout") => return self.post_logout(&req),
+            (&Method::POST, "/api/history") => return self.post_history(&req),
+            _ => {}
+        }
+
+        // Authentication
+        let user = {
+            let mut state = self.vault.state.lock().unwrap();
+
+            if state.auth.is_enabled() {
+                let token = get_cookie(req.headers(), SESSION_COOKIE).unwrap_or("");
+
+                match state.auth.user(token) {
+                    Some(u) => Some(u.clone()),
+                    None => return redirect("/login"),
+                }
+            } else {
+                None
+            }
+        };
+
+        // Player state of this browser, see `playback`
+        if req.uri().path() == "/api/player/state" {
+            return self.player_state(&req);
+        }
+
+        // Instance routes, hidden from users restricted to some profiles
+        match (req.method(), req.uri().path()) {
+            (&Method::GET, "/logs" | "/api/logs") => {
+                if !self.can_read_logs(&req, user.as_ref()) {
+                    return Ok(not_found());
+                }
+
+                return get_logs(&req);
+            }
+            (&Method::GET, "/api/schema") => return json(None, &API_SCHEMAS),
+            (&Method::GET, p) if p.starts_with("/api/schema/") => return get_schema(p),
+            (&Method::GET, p) if p.starts_with(SHORT_LINK_PREFIX) => {
+                return self.get_short_link(&p[SHORT_LINK_PREFIX.len()..]);
+            }
+            _ => {}
+        }
+
+        // Profile routes
+        let Some(cx) = self.profile_request(req, user) else {
+            return Ok(not_found());
+        };
+        let version = cx.version;
+        let mut res = match profile_route(cx.req.method(), &cx.path) {
+            Some(handler) => {
+                let span = tracing::Span::current();
+
+                tokio::task::spawn_blocking(move || span.in_scope(|| handler(&cx))).await??
+            }
+            None => not_found(),
+        };
+
+        res.headers_mut()
+            .insert(DATASET_VERSION_HEADER, HeaderValue::from(version));
+
+        Ok(res)
+    }
+
+    /// Resolve the profile of a request and snapshot what its handler reads,
+    /// `None` when it doesn't exist or the user can't access it
+    fn profile_request(&self, req: Request<Bytes>, user: Option<User>) -> Option<ProfileRequest> {
+        let state = self.vault.state.lock().unwrap();
+        let can_access = |name: &str| user.as_ref().is_none_or(|u| u.can_access(name));
+
+        let (profile, path) = split_profile_path(req.uri().path());
+        let base_path = profile.map(|v| format!("/p/{}", v)).unwrap_or_default();
+        let path = path.to_owned();
+        let profile_names = state
+            .profiles
+            .names()
+            .into_iter()
+            .filter(|v| can_access(v))
+            .collect::<Vec<String>>();
+
+        let profile = match profile {
+            Some(v) if can_access(v) => v.to_owned(),
+            Some(_) => return None,
+            None => match state.profiles.default_name().filter(|v| can_access(v)) {
+                Some(v) => v.to_owned(),
+                None => profile_names.first()?.clone(),
+            },
+        };
+        // The diff base is an older export of the default profile only
+        let diff_base = state
+            .diff_base
+            .clone()
+            .filter(|_| state.profiles.default_name() == Some(profile.as_str()));
+
+        Some(ProfileRequest {
+            vault: self.vault.clone(),
+            version: state.profiles.version(&profile),
+            metadata_table: state.profiles.snapshot(&profile)?,
+            diff_base,
+            ui: state.ui.clone(),
+            prefs_key: state.prefs_key.clone(),
+            has_player: state.youtube.is_some(),
+            req,
+            user,
+            profile,
+            profile_names,
+            path,
+            base_path,
+        })
+    }
+
+    fn get_iframe_api(&self) -> Result<Response<Body>> {
+        let state = self.vault.state.lock().unwrap();
+        let Some(youtube) = &state.youtube else {
+            return Ok(not_found());
+        };
+
+        Ok(Response::new(full(youtube.iframe_api_script.clone())))
+    }
+
+    fn get_widget_api(&self) -> Result<Response<Body>> {
+        let state = self.vault.state.lock().unwrap();
+        let Some(youtube) = &state.youtube else {
+            return Ok(not_found());
+        };
+
+        let Some(script) = &youtube.widgetapi_script else {
+            let res = Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .header(header::CONTENT_TYPE, "text/javascript")
+                .header(header::RETRY_AFTER, WIDGETAPI_RETRY_AFTER)
+                .header(header::CACHE_CONTROL, "no-store")
+                .body(full(WIDGETAPI_PLACEHOLDER))?;
+
+            return Ok(res);
+        };
+
+        Ok(Response::new(full(script.clone())))
+    }
+
+    async fn post_login(&self, req: &Request<Bytes>) -> Result<Response<Body>> {
+        let token = match serde_urlencoded::from_bytes::<LoginForm>(req.body()) {
+            Ok(form) => {
+                let password_hash = self
+                    .vault
+                    .state
+                    .lock()
+                    .unwrap()
+                    .auth
+                    .get(&form.name)
+                    .map(|u| u.password_hash().to_owned());
+
+                // argon2 takes a while on purpose, so neither the lock nor
+                // the runtime are held meanwhile
+                let hash = password_hash.clone();
+                let verified = tokio::task::spawn_blocking(move || {
+                    verify_password_hash(hash.as_deref(), &form.password)
+                })
+                .await?;
+
+                match password_hash.filter(|_| verified) {
+                    Some(v) => self
+                        .vault
+                        .state
+                        .lock()
+                        .unwrap()
+                        .auth
+                        .open_session(&form.name, &v),
+                    None => None,
+                }
+            }
+            Err(_) => None,
+        };
+
+        let Some(token) = token else {
+            let html = LoginTemplate { failed: true };
+            let res = Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .body(full(html.render().unwrap()))?;
+
+            return Ok(res);
+        };
+
+        let mut res = redirect("/")?;
+        let cookie = format!(
+            "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}",
+            SESSION_COOKIE,
+            token,
+            SESSION_TTL_DAYS * 24 * 60 * 60
+        );
+        res.headers_mut()
+            .insert(header::SET_COOKIE, cookie.parse().unwrap());
+
+        Ok(res)
+    }
+
+    fn post_logout(&self, req: &Request<Bytes>) -> Result<Response<Body>> {
+        if let Some(token) = get_cookie(req.headers(), SESSION_COOKIE) {
+            self.vault.state.lock().unwrap().auth.logout(token);
+        }
+
+        let mut res = redirect("/login")?;
+        let cookie = format!(
+            "{}=; Path=/; HttpOnly; SameSite=Lax; Max-Age=0",
+            SESSION_COOKIE
+        );
+        res.headers_mut()
+            .insert(header::SET_COOKIE, cookie.parse().unwrap());
+
+        Ok(res)
+    }
+
+    fn post_history(&self, req: &Request<Bytes>) -> Result<Response<Body>> {
+        let mut state = self.vault.state.lock().unwrap();
+        let State {
+            profiles,
+            years,
+            ingest,
+            ..
+        } = &mut *state;
+
+        let Some(ingest) = ingest else {
+            return Ok(not_found());
+        };
+
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .unwrap_or("");
+
+        if !constant_time_eq(token.as_bytes(), ingest.token.as_bytes()) {
+            let res = Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .header(header::WWW_AUTHENTICATE, "Bearer")
+                .body(full(""))?;
+
+            return Ok(res);
+        }
+
+        let mut entry = match serde_json::from_slice::<HistoryPayload>(req.body())
+            .map_err(|e| e.to_string())
+            .and_then(|v| v.validate(Utc::now()))
+        {
+            Ok(v) => v,
+            Err(e) => return Ok(bad_request(e)),
+        };
+
+        let name = match entry.profile.as_deref().or(profiles.default_name()) {
+            Some(v) => v.to_owned(),
+            None => return Ok(not_found()),
+        };
+
+        // replay must not depend on which profile is the default
+        entry.profile = Some(name.clone());
+
+        let Some(table) = profiles.get(&name) else {
+            return Ok(not_found());
+        };
+
+        if table.contains_watch(&entry.id, entry.time) {
+            return json(None, &serde_json::json!({ "inserted": false }));
+        }
+
+        if let Some(journal) = &mut ingest.journal {
+            journal.append(&entry)?;
+        }
+
+        profiles.insert_entry(&name, entry.to_watch_entry());
+        years.remove(&name);
+
+        let id = req
+            .extensions()
+            .get::<RequestId>()
+            .map(|v| v.0.as_str())
+            .unwrap_or("-");
+
+        log::info!("[{}] Added watch of `{}` to `{}`", id, entry.id, name);
+
+        let mut res = json(None, &serde_json::json!({ "inserted": true }))?;
+        *res.status_mut() = StatusCode::CREATED;
+
+        Ok(res)
+    }
+
+    fn player_state(&self, req: &Request<Bytes>) -> Result<Response<Body>> {
+        let cookie = get_cookie(req.headers(), PLAYER_COOKIE)
+            .filter(|v| v.len() == 32 && v.bytes().all(|b| b.is_ascii_hexdigit()));
+        let player_id = cookie
+            .map(|v| v.to_owned())
+            .unwrap_or_else(|| to_hex_string(&rand::rng().random::<[u8; 16]>()));
+        let mut state = self.vault.state.lock().unwrap();
+        let player_states = &mut state.player_states;
+
+        let mut res = match *req.method() {
+            Method::GET => match player_states.get(&player_id) {
+                Some(v) => json(None, v)?,
+                None => no_content(),
+            },
+            Method::PUT => match serde_json::from_slice::<PlayerState>(req.body()) {
+                Ok(v) if is_video_id(&v.video_id) && v.time.is_finite() && v.time >= 0.0 => {
+                    player_states.insert(&player_id, v);
+                    no_content()
+                }
+                Ok(v) => bad_request(format!("invalid player state of `{}`", v.video_id)),
+                Err(e) => bad_request(e.to_string()),
+            },
+            Method::DELETE => {
+                player_states.remove(&player_id);
+                no_content()
+            }
+            _ => not_found(),
+        };
+
+        if cookie.is_none() {
+            let cookie = format!(
+                "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}",
+                PLAYER_COOKIE,
+                player_id,
+                PREFS_TTL_DAYS * 24 * 60 * 60
+            );
+            res.headers_mut()
+                .insert(header::SET_COOKIE, cookie.parse().unwrap());
+        }
+
+        Ok(res)
+    }
+
+    /// Logs may reveal paths and requests of every profile, they are only
+    /// shown to unrestricted users, or on `/api/logs` to holders of the
+    /// ingest token when login is disabled
+    fn can_read_logs(&self, req: &Request<Bytes>, user: Option<&User>) -> bool {
+        if let Some(user) = user {
+            return user.is_unrestricted();
+        }
+
+        let state = self.vault.state.lock().unwrap();
+        let Some(ingest) = &state.ingest else {
+            return false;
+        };
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .unwrap_or("");
+
+        req.uri().path() == "/api/logs"
+            && constant_time_eq(token.as_bytes(), ingest.token.as_bytes())
+    }
+
+    fn get_short_link(&self, token: &str) -> Result<Response<Body>> {
+        let target = self
+            .vault
+            .state
+            .lock()
+            .unwrap()
+            .links
+            .get(token)
+            .map(str::to_owned);
+
+        match target {
+            Some(v) => redirect(&v),
+            None => Ok(not_found()),
+        }
+    }
+}
+
+fn get_logs(req: &Request<Bytes>) -> Result<Response<Body>> {
+    let query = req.uri().query().unwrap_or("");
+    let query = match serde_urlencoded::from_str::<LogsQuery>(query) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+    let records = recent(query.level, query.limit);
+
+    if req.uri().path() == "/api/logs" {
+        return json(None, &records);
+    }
+
+    let html = LogsTemplate {
+        base_path: "",
+        query: &query,
+        levels: &Level::iter().collect(),
+        records: &records,
+    };
+    let res = Response::new(full(html.render().unwrap()));
+
+    Ok(res)
+}
+
+fn get_schema(path: &str) -> Result<Response<Body>> {
+    let Some(schema) = api_schema(&path["/api/schema/".len()..]) else {
+        return Ok(not_found());
+    };
+    let res = Response::builder()
+        .header(header::CONTENT_TYPE, "application/schema+json")
+        .body(full(serde_json::to_vec(&schema)?))?;
+
+    Ok(res)
+}
+
+/// hyper service trait implementation
+impl Service<Request<Incoming>> for ServiceHandler {
+    type Response = Response<Body>;
+    type Error = anyhow::Error;
+    type Future =
+        Pin<Box<dyn Future<Output = std::result::Result<Self::Response, Self::Error>> + Send>>;
+
+    fn call(&self, req: Request<Incoming>) -> Self::Future {
+        let handler = self.clone();
+        let id = request_id(req.headers());
+        let span = tracing::info_span!(
+            "request",
+            request_id = %id,
+            method = %req.method(),
+            path = %req.uri().path()
+        );
+
+        Box::pin(
+            async move {
+                let (mut parts, body) = req.into_parts();
+
+                parts.extensions.insert(RequestId(id.clone()));
+
+                let max_body = handler.vault.state.lock().unwrap().ui.max_body;
+
+                // the client is answered without reading past the limit
+                let mut res = match Limited::new(body, max_body).collect().await {
+                    Ok(body) => {
+                        let req = Request::from_parts(parts, body.to_bytes());
+
+                        handler
+                            .run(req)
+                            .await
+                            .inspect_err(|e| log::error!("[{}] {:?}", id, e))?
+                    }
+                    Err(e) if e.is::<LengthLimitError>() => {
+                        log::warn!("[{}] request body over {} bytes", id, max_body);
+
+                        payload_too_large()
+                    }
+                    Err(e) => return Err(anyhow::anyhow!(e)),
+                };
+
+                res.headers_mut()
+                    .insert(REQUEST_ID_HEADER, HeaderValue::from_str(&id)?);
+
+                Ok(res)
+            }
+            .instrument(span),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cookie_value(prefs: &Preferences, key: &[u8]) -> String {
+        let cookie = prefs.to_cookie(key);
+        let (_, value) = cookie.split(';').next().unwrap().split_once('=').unwrap();
+
+        value.to_owned()
+    }
+
+    #[test]
+    fn signed_preferences() {
+        let ui = UiOptions::default();
+        let prefs = Preferences {
+            limit: Some(50),
+            order: Some(Order::Oldest),
+            view: Some(View::Events),
+        };
+        let value = cookie_value(&prefs, b"key");
+
+        assert_eq!(Preferences::from_cookie(&value, b"key", &ui), prefs);
+        assert_eq!(
+            Preferences::from_cookie(&value, b"other", &ui),
+            Preferences::default()
+        );
+        assert_eq!(
+            Preferences::from_cookie(&value.replace("50", "25"), b"key", &ui),
+            Preferences::default()
+        );
+        assert_eq!(
+            Preferences::from_cookie("limit=50&order=oldest", b"key", &ui),
+            Preferences::default()
+        );
+    }
+
+    #[test]
+    fn remembered_view() {
+        let ui = UiOptions::default();
+        let prefs = Preferences {
+            view: Some(View::Events),
+            ..Default::default()
+        };
+        let query = |query: &str| {
+            serde_urlencoded::from_str::<Vec<(String, String)>>(&with_ui_defaults(
+                query, &ui, &prefs,
+            ))
+            .unwrap()
+            .into_iter()
+            .find(|(k, _)| k == "view")
+            .map(|(_, v)| v)
+        };
+
+        assert_eq!(query("").as_deref(), Some("events"));
+        assert_eq!(query("view=videos").as_deref(), Some("videos"));
+    }
 
-        Box::pin(async { res })
+    #[test]
+    fn profile_path() {
+        assert_eq!(split_profile_path("/"), (None, "/"));
+        assert_eq!(split_profile_path("/api/videos"), (None, "/api/videos"));
+        assert_eq!(split_profile_path("/p/alice"), (Some("alice"), "/"));
+        assert_eq!(split_profile_path("/p/alice/"), (Some("alice"), "/"));
+        assert_eq!(
+            split_profile_path("/p/alice/api/videos"),
+            (Some("alice"), "/api/videos")
+        );
     }
 }
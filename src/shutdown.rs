@@ -29,3 +29,38 @@ impl Shutdown {
         self.is_shutdown = true;
     }
 }
+
+/// Resolve on `Ctrl-C`. On Windows also on `Ctrl-Break`, closing the console
+/// window, logoff and system shutdown, so these stop the server gracefully
+/// instead of killing it
+pub async fn signal() {
+    #[cfg(windows)]
+    {
+        use tokio::signal::windows;
+
+        async fn recv<T, F>(listener: std::io::Result<T>, recv: F)
+        where
+            F: AsyncFnOnce(&mut T) -> Option<()>,
+        {
+            match listener {
+                Ok(mut v) => {
+                    recv(&mut v).await;
+                }
+                Err(_) => std::future::pending().await,
+            }
+        }
+
+        tokio::select! {
+            _ = recv(windows::ctrl_c(), async |v| v.recv().await) => {}
+            _ = recv(windows::ctrl_break(), async |v| v.recv().await) => {}
+            _ = recv(windows::ctrl_close(), async |v| v.recv().await) => {}
+            _ = recv(windows::ctrl_logoff(), async |v| v.recv().await) => {}
+            _ = recv(windows::ctrl_shutdown(), async |v| v.recv().await) => {}
+        }
+    }
+
+    #[cfg(not(windows))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
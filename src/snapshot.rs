@@ -0,0 +1,172 @@
+//! Scheduled snapshots
+//!
+//! Every profile, journal included, is written to a directory at a fixed
+//! interval as a JSON History File, so a home server without cron still gets
+//! regular backups of what it serves
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use serde::Deserialize;
+use tokio::time::{Duration, sleep};
+
+use crate::{
+    analytics::rates::watch_rates,
+    demo::to_v1_json,
+    schema::{MetadataTable, WatchEntry},
+    vault::Vault,
+};
+
+/// SnapshotOptions
+///
+/// `[snapshot]` table of the config file
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SnapshotOptions {
+    /// Created when missing
+    pub dir: PathBuf,
+    /// Seconds between two snapshots
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+    /// Also write `ytm stats` JSON next to each snapshot
+    #[serde(default)]
+    pub stats: bool,
+    /// Snapshots kept per profile, older ones are deleted. All are kept when
+    /// unset
+    #[serde(default)]
+    pub keep: Option<usize>,
+}
+
+fn default_interval() -> u64 {
+    24 * 60 * 60
+}
+
+/// Write snapshots of the served profiles every `options.interval`. The
+/// first one is due an interval after the newest file of the directory, so
+/// restarts don't delay it
+pub async fn run_snapshots(options: SnapshotOptions, vault: Vault) {
+    let interval = Duration::from_secs(options.interval.max(60));
+    let elapsed = newest_snapshot(&options.dir)
+        .and_then(|t| t.elapsed().ok())
+        .unwrap_or(interval);
+
+    sleep(interval.saturating_sub(elapsed)).await;
+
+    loop {
+        let profiles = {
+            let state = vault.state.lock().unwrap();
+
+            state
+                .profiles
+                .names()
+                .into_iter()
+                .filter_map(|name| {
+                    let entries = state.profiles.get(&name)?.to_watch_entries();
+
+                    Some((name, entries))
+                })
+                .collect::<Vec<(String, Vec<WatchEntry>)>>()
+        };
+
+        let opts = options.clone();
+        let res = tokio::task::spawn_blocking(move || write_snapshots(&opts, profiles)).await;
+
+        match res {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => log::error!("failed to write snapshot. {:?}", e),
+            Err(e) => log::error!("snapshot task failed. {:?}", e),
+        }
+
+        sleep(interval).await;
+    }
+}
+
+/// Write one snapshot of each profile, then prune old ones
+pub fn write_snapshots(
+    options: &SnapshotOptions,
+    profiles: Vec<(String, Vec<WatchEntry>)>,
+) -> Result<()> {
+    fs::create_dir_all(&options.dir)
+        .with_context(|| format!("failed to create `{}`", options.dir.display()))?;
+
+    let stamp = Local::now().format("%Y%m%dT%H%M%S");
+
+    for (name, entries) in profiles {
+        let path = options.dir.join(format!("{}-{}.json", name, stamp));
+
+        write_file(&path, &serde_json::to_vec(&to_v1_json(&entries))?)?;
+
+        if options.stats {
+            let stats_path = options.dir.join(format!("{}-{}.stats.json", name, stamp));
+            let rates = watch_rates(&MetadataTable::from_entries(entries));
+
+            write_file(&stats_path, &serde_json::to_vec_pretty(&rates)?)?;
+        }
+
+        log::info!("Wrote snapshot of `{}` to {}", name, path.display());
+
+        if let Some(keep) = options.keep {
+            prune(&options.dir, &name, keep)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Write through a temporary file so a crash never leaves half a snapshot
+fn write_file(path: &Path, data: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+
+    fs::write(&tmp, data).with_context(|| format!("failed to write `{}`", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("failed to write `{}`", path.display()))?;
+
+    Ok(())
+}
+
+/// Delete all but the `keep` newest snapshots of `profile`, with their stats
+fn prune(dir: &Path, profile: &str, keep: usize) -> Result<()> {
+    let prefix = format!("{}-", profile);
+    let mut stamps = fs::read_dir(dir)?
+        .filter_map(|e| e.ok()?.file_name().into_string().ok())
+        .filter_map(|v| {
+            let stamp = v.strip_prefix(&prefix)?.strip_suffix(".json")?;
+
+            // `-` never appears in a stamp, only in longer profile names
+            (!stamp.contains(['.', '-'])).then(|| stamp.to_owned())
+        })
+        .collect::<Vec<String>>();
+
+    stamps.sort_unstable_by(|a, b| b.cmp(a));
+
+    for stamp in stamps.iter().skip(keep) {
+        for suffix in [".json", ".stats.json"] {
+            let path = dir.join(format!("{}{}{}", prefix, stamp, suffix));
+
+            match fs::remove_file(&path) {
+                Ok(_) => log::debug!("Deleted old snapshot {}", path.display()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("failed to delete `{}`", path.display()));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Modification time of the newest snapshot in `dir`
+fn newest_snapshot(dir: &Path) -> Option<SystemTime> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().ends_with(".json"))
+        .filter_map(|e| e.metadata().ok()?.modified().ok())
+        .max()
+}
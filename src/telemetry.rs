@@ -0,0 +1,31 @@
+//! OpenTelemetry export
+//!
+//! Spans from the `tracing` instrumentation are exported through OTLP over
+//! HTTP once `--otlp-endpoint` is given
+
+use anyhow::Result;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{Resource, trace::SdkTracerProvider};
+use tracing_subscriber::layer::SubscriberExt;
+
+/// Install OTLP exporter, the returned provider must be shut down on exit to
+/// flush pending spans
+pub fn init(endpoint: &str) -> Result<SdkTracerProvider> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()?;
+
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name("ytm").build())
+        .build();
+
+    // not `try_init`, it would also try to take over the `log` facade
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("ytm")));
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    Ok(provider)
+}
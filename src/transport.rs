@@ -0,0 +1,96 @@
+//! Connection sources
+//!
+//! The server speaks HTTP over anything implementing [`Transport`], TCP
+//! everywhere and named pipes on Windows, e.g. behind a reverse proxy on the
+//! same host
+
+use std::{future::Future, io};
+
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream},
+};
+
+/// Transport
+///
+/// Accepts inbound connections
+pub trait Transport: Send {
+    type Io: AsyncRead + AsyncWrite + Unpin + Send + 'static;
+
+    /// Wait for the next connection
+    fn accept(&mut self) -> impl Future<Output = io::Result<Self::Io>> + Send;
+
+    /// Where clients connect, for logs
+    fn location(&self) -> io::Result<String>;
+}
+
+impl Transport for TcpListener {
+    type Io = TcpStream;
+
+    async fn accept(&mut self) -> io::Result<TcpStream> {
+        TcpListener::accept(self).await.map(|(socket, _)| socket)
+    }
+
+    fn location(&self) -> io::Result<String> {
+        Ok(format!("http://{}", self.local_addr()?))
+    }
+}
+
+#[cfg(windows)]
+pub use pipe::{NamedPipeListener, pipe_path};
+
+#[cfg(windows)]
+mod pipe {
+    use std::io;
+
+    use tokio::net::windows::named_pipe::{NamedPipeServer, ServerOptions};
+
+    use super::Transport;
+
+    /// Full path of a pipe, `ytm` becomes `\\.\pipe\ytm`
+    pub fn pipe_path(name: &str) -> String {
+        if name.starts_with(r"\\") {
+            name.to_owned()
+        } else {
+            format!(r"\\.\pipe\{}", name)
+        }
+    }
+
+    /// NamedPipeListener
+    ///
+    /// Named pipe server, a new instance of the pipe waits for the next
+    /// client as soon as one connects
+    #[derive(Debug)]
+    pub struct NamedPipeListener {
+        path: String,
+        next: NamedPipeServer,
+    }
+
+    impl NamedPipeListener {
+        /// Create the pipe, failing when another process already owns it
+        pub fn bind(name: &str) -> io::Result<Self> {
+            let path = pipe_path(name);
+            let next = ServerOptions::new()
+                .first_pipe_instance(true)
+                .create(&path)?;
+
+            Ok(Self { path, next })
+        }
+    }
+
+    impl Transport for NamedPipeListener {
+        type Io = NamedPipeServer;
+
+        async fn accept(&mut self) -> io::Result<NamedPipeServer> {
+            self.next.connect().await?;
+
+            let next = ServerOptions::new().create(&self.path)?;
+
+            Ok(std::mem::replace(&mut self.next, next))
+        }
+
+        fn location(&self) -> io::Result<String> {
+            Ok(self.path.clone())
+        }
+    }
+}
@@ -1,38 +1,46 @@
-use std::{collections::HashSet, ffi::OsStr, io::BufRead, path::PathBuf};
+use std::{
+    collections::HashSet,
+    ffi::OsStr,
+    fs::File,
+    io::{BufRead, Read},
+    path::Path,
+};
 
 use chrono::{DateTime, Local, TimeZone};
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use crate::schema::Metadata;
+
 /// Simple checking json file
-pub fn is_json_file(path: &PathBuf) -> bool {
-    let is_json = match path.extension().and_then(OsStr::to_str) {
-        Some("json") => true,
-        _ => false,
-    };
-
-    if path.is_file() && is_json {
-        true
-    } else {
-        false
-    }
+pub fn is_json_file(path: &Path) -> bool {
+    let is_json = matches!(path.extension().and_then(OsStr::to_str), Some("json"));
+
+    path.is_file() && is_json
+}
+
+/// Check zip archive, e.g. a Takeout export, from its local file header
+/// signature
+pub fn is_zip_file(path: &Path) -> bool {
+    let mut magic = [0; 4];
+    let read = File::open(path).and_then(|mut f| f.read_exact(&mut magic));
+
+    read.is_ok() && magic == *b"PK\x03\x04"
 }
 
 /// Check keywords through buffer
 pub fn is_buffer_contains_keywords<R: BufRead>(reader: R, keys: &HashSet<&str>) -> bool {
     let mut found_keys = HashSet::new();
 
-    for line in reader.lines() {
-        if let Ok(line) = line {
-            for key in keys {
-                if !found_keys.contains(key) && line.contains(key) {
-                    found_keys.insert(*key);
-                }
+    for line in reader.lines().map_while(Result::ok) {
+        for key in keys {
+            if !found_keys.contains(key) && line.contains(key) {
+                found_keys.insert(*key);
             }
+        }
 
-            if found_keys.len() == keys.len() {
-                return true;
-            }
+        if found_keys.len() == keys.len() {
+            return true;
         }
     }
 
@@ -71,6 +79,9 @@ pub fn extract_youtube_channel_id(haystack: String) -> String {
 /// DateTime utility from chrono
 pub trait DateTimeUtility {
     fn to_datetime_string(&self) -> String;
+
+    /// Local time in the format of `datetime-local` inputs
+    fn to_datetime_input_string(&self) -> String;
 }
 
 impl<Tz: TimeZone> DateTimeUtility for DateTime<Tz> {
@@ -79,9 +90,17 @@ impl<Tz: TimeZone> DateTimeUtility for DateTime<Tz> {
             .format("%Y-%m-%d %H:%M:%S")
             .to_string()
     }
+
+    fn to_datetime_input_string(&self) -> String {
+        self.with_timezone(&Local)
+            .format("%Y-%m-%dT%H:%M:%S")
+            .to_string()
+    }
 }
 
 /// Fetch some url
+#[cfg(feature = "player")]
+#[tracing::instrument(skip_all, fields(url = url))]
 pub async fn fetch_url(url: &str) -> anyhow::Result<bytes::Bytes> {
     log::debug!("Fetch: {}", url);
 
@@ -95,3 +114,116 @@ pub async fn fetch_url(url: &str) -> anyhow::Result<bytes::Bytes> {
 
     Ok(bytes)
 }
+
+/// Join fields into a single CSV line, quoting them when needed
+pub fn to_csv_line<I, S>(fields: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    fields
+        .into_iter()
+        .map(|f| {
+            let f = f.as_ref();
+
+            if f.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", f.replace('"', "\"\""))
+            } else {
+                f.to_owned()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Join fields into a Markdown table row, escaping pipes and line breaks
+pub fn to_markdown_row<I, S>(fields: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let cells = fields
+        .into_iter()
+        .map(|f| f.as_ref().replace('|', "\\|").replace(['\n', '\r'], " "))
+        .collect::<Vec<String>>();
+
+    format!("| {} |", cells.join(" | "))
+}
+
+/// Markdown table of metadata, titles link to the video
+pub fn to_markdown_table(data: &[Metadata]) -> String {
+    let mut table = String::new();
+
+    table.push_str("| Watched At | Watches | Title | Channel |\n");
+    table.push_str("| --- | ---: | --- | --- |\n");
+
+    for v in data {
+        let title = format!(
+            "[{}](https://www.youtube.com/watch?v={})",
+            v.title.replace('[', "\\[").replace(']', "\\]"),
+            v.id
+        );
+
+        table.push_str(&to_markdown_row([
+            v.watched_at.to_datetime_string().as_str(),
+            &v.watch_count.to_string(),
+            &title,
+            &v.channel.name,
+        ]));
+        table.push('\n');
+    }
+
+    table
+}
+
+/// CSV of every watch of `data`, one row each, oldest first
+pub fn to_watches_csv(data: &[Metadata]) -> String {
+    let mut watches = data
+        .iter()
+        .flat_map(|m| m.watch_timeline.iter().map(move |t| (t, m)))
+        .collect::<Vec<_>>();
+
+    watches.sort_by_key(|(t, _)| **t);
+
+    let mut csv = String::from("watched_at,id,title,channel_id,channel_name\n");
+
+    for (t, m) in watches {
+        csv.push_str(&to_csv_line([
+            t.to_rfc3339().as_str(),
+            &m.id,
+            &m.title,
+            &m.channel.id,
+            &m.channel.name,
+        ]));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// Compare two byte slices in constant time
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Encode bytes into lowercase hex string
+pub fn to_hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Get cookie value by name from `Cookie` header
+#[cfg(feature = "server")]
+pub fn get_cookie<'a>(headers: &'a hyper::HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(hyper::header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|v| v.trim().split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v)
+}
@@ -1,6 +1,15 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
-use crate::{schema::MetadataTable, youtube::YouTube};
+use rand::Rng;
+
+use crate::{
+    analytics::years::YearSummary, auth::Auth, journal::Ingest, links::ShortLinks,
+    playback::PlayerStates, profile::Profiles, schema::MetadataTable, service::UiOptions,
+    youtube::YouTube,
+};
 
 /// Vault
 #[derive(Clone, Debug)]
@@ -11,17 +20,61 @@ pub struct Vault {
 /// State
 #[derive(Debug)]
 pub struct State {
-    pub metadata_table: MetadataTable,
-    pub youtube: YouTube,
+    pub profiles: Profiles,
+    pub auth: Auth,
+    pub diff_base: Option<Arc<MetadataTable>>,
+    /// `None` when the player is disabled
+    pub youtube: Option<YouTube>,
+    pub ui: UiOptions,
+    /// Key signing the preferences cookie, random unless configured
+    pub prefs_key: Arc<[u8]>,
+    /// Year summaries per profile, computed on the first `/years` request
+    pub years: HashMap<String, Vec<YearSummary>>,
+    pub links: ShortLinks,
+    /// `None` when `POST /api/history` is disabled
+    pub ingest: Option<Ingest>,
+    pub player_states: PlayerStates,
 }
 
 impl Vault {
-    pub fn new(metadata_table: MetadataTable, youtube: YouTube) -> Self {
+    pub fn new(
+        profiles: Profiles,
+        auth: Auth,
+        diff_base: Option<MetadataTable>,
+        youtube: Option<YouTube>,
+        ui: UiOptions,
+        links: ShortLinks,
+        ingest: Option<Ingest>,
+    ) -> Self {
         let state = Arc::new(Mutex::new(State {
-            metadata_table,
+            profiles,
+            auth,
+            diff_base: diff_base.map(Arc::new),
             youtube,
+            ui,
+            prefs_key: rand::rng().random::<[u8; 32]>().into(),
+            years: HashMap::new(),
+            links,
+            ingest,
+            player_states: PlayerStates::new(),
         }));
 
         Self { state }
     }
 }
+
+impl State {
+    /// Replace the table of a profile, see [`Profiles::replace`]. Caches
+    /// computed from the old table are dropped
+    pub fn replace_profile(
+        &mut self,
+        name: &str,
+        metadata_table: MetadataTable,
+    ) -> Option<Arc<MetadataTable>> {
+        let previous = self.profiles.replace(name, metadata_table)?;
+
+        self.years.remove(name);
+
+        Some(previous)
+    }
+}
@@ -0,0 +1,60 @@
+//! wasm-bindgen wrapper around the parsing core, so a Takeout export can be
+//! parsed entirely inside the browser
+
+use std::io::Cursor;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    analytics::quality::{DEFAULT_GAP_DAYS, quality_report},
+    schema::{MetadataTable, ValidatedFilter, load_metadata_from_reader},
+};
+
+/// History
+///
+/// Parsed history handle exposed to JavaScript
+#[wasm_bindgen]
+pub struct History {
+    metadata_table: MetadataTable,
+}
+
+#[wasm_bindgen]
+impl History {
+    /// Parse JSON History File content
+    #[wasm_bindgen(constructor)]
+    pub fn new(bytes: &[u8]) -> Result<History, JsError> {
+        let metadata_table = load_metadata_from_reader(Cursor::new(bytes))
+            .map_err(|e| JsError::new(&e.to_string()))?;
+
+        Ok(History { metadata_table })
+    }
+
+    #[wasm_bindgen(js_name = totalCountRaw)]
+    pub fn total_count_raw(&self) -> usize {
+        self.metadata_table.total_count_raw()
+    }
+
+    #[wasm_bindgen(js_name = totalCount)]
+    pub fn total_count(&self) -> usize {
+        self.metadata_table.total_count()
+    }
+
+    /// Filter using the same query string as the web UI, returning
+    /// `{ pagination, data }` as JSON string
+    pub fn query(&mut self, query: &str) -> Result<String, JsError> {
+        let filter = ValidatedFilter::parse(query).map_err(|e| JsError::new(&e.to_string()))?;
+        let (pagination, data) = self.metadata_table.get_collection(&filter);
+
+        let json = serde_json::json!({
+            "pagination": pagination,
+            "data": data,
+        });
+
+        Ok(json.to_string())
+    }
+
+    /// Coverage and gap report as JSON string
+    pub fn quality(&self) -> String {
+        serde_json::to_string(&quality_report(&self.metadata_table, DEFAULT_GAP_DAYS)).unwrap()
+    }
+}
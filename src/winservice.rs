@@ -0,0 +1,166 @@
+//! Windows service
+//!
+//! `ytm service install` registers the binary with the service control
+//! manager, which then starts it with Windows as
+//! `ytm --config <path> service run`
+
+use std::{ffi::OsString, future::Future, path::Path, pin::Pin, sync::Mutex, time::Duration};
+
+use anyhow::{Context, Result};
+use tokio::sync::oneshot;
+use windows_service::{
+    define_windows_service,
+    service::{
+        ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
+        ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+    },
+    service_control_handler::{self, ServiceControlHandlerResult},
+    service_dispatcher,
+    service_manager::{ServiceManager, ServiceManagerAccess},
+};
+
+/// Name of the service unless `--name` is given
+pub const DEFAULT_SERVICE_NAME: &str = "ytm";
+
+/// Completes once the service is asked to stop
+pub type StopSignal = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+type Runner = Box<dyn FnOnce(StopSignal) -> Result<()> + Send>;
+
+/// Handed from [`run`] to the thread the service control manager calls
+/// back on
+static RUNNER: Mutex<Option<Runner>> = Mutex::new(None);
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Register a service starting with Windows and serving with the settings
+/// of the `config` file
+pub fn install(name: &str, config: &Path) -> Result<()> {
+    let config = std::path::absolute(config)?;
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )
+    .context("failed to connect to the service control manager, run as administrator")?;
+
+    let info = ServiceInfo {
+        name: name.into(),
+        display_name: format!("YouTube Memories ({})", name).into(),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: std::env::current_exe()?,
+        launch_arguments: vec![
+            "--config".into(),
+            config.into_os_string(),
+            "service".into(),
+            "run".into(),
+            "--name".into(),
+            name.into(),
+        ],
+        dependencies: vec![],
+        account_name: None,
+        account_password: None,
+    };
+
+    let service = manager
+        .create_service(&info, ServiceAccess::CHANGE_CONFIG)
+        .with_context(|| format!("failed to create service `{}`", name))?;
+
+    service.set_description("Serves a YouTube watch history")?;
+
+    Ok(())
+}
+
+/// Stop the service when it runs and delete it
+pub fn uninstall(name: &str) -> Result<()> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+        .context("failed to connect to the service control manager, run as administrator")?;
+    let service = manager
+        .open_service(
+            name,
+            ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+        )
+        .with_context(|| format!("failed to open service `{}`", name))?;
+
+    if service.query_status()?.current_state != ServiceState::Stopped {
+        service.stop()?;
+    }
+
+    service.delete()?;
+
+    Ok(())
+}
+
+/// Hand the thread over to the service control manager, `serve` runs until
+/// the service is stopped. Only works when started by the manager
+pub fn run<F>(name: &str, serve: F) -> Result<()>
+where
+    F: FnOnce(StopSignal) -> Result<()> + Send + 'static,
+{
+    *RUNNER.lock().unwrap() = Some(Box::new(serve));
+
+    service_dispatcher::start(name, ffi_service_main)
+        .context("failed to reach the service control manager, `service run` is started by it")
+}
+
+fn service_main(arguments: Vec<OsString>) {
+    if let Err(e) = run_service(arguments) {
+        log::error!("service failed. {:?}", e);
+    }
+}
+
+fn run_service(arguments: Vec<OsString>) -> Result<()> {
+    let Some(serve) = RUNNER.lock().unwrap().take() else {
+        return Ok(());
+    };
+
+    let name = arguments
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| DEFAULT_SERVICE_NAME.into());
+    let (tx, rx) = oneshot::channel();
+    let mut tx = Some(tx);
+
+    let status = service_control_handler::register(&name, move |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            if let Some(tx) = tx.take() {
+                let _ = tx.send(());
+            }
+
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    })?;
+
+    status.set_service_status(service_status(
+        ServiceState::Running,
+        ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+        0,
+    ))?;
+
+    let res = serve(Box::pin(async {
+        let _ = rx.await;
+    }));
+
+    status.set_service_status(service_status(
+        ServiceState::Stopped,
+        ServiceControlAccept::empty(),
+        if res.is_ok() { 0 } else { 1 },
+    ))?;
+
+    res
+}
+
+fn service_status(state: ServiceState, accept: ServiceControlAccept, code: u32) -> ServiceStatus {
+    ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: state,
+        controls_accepted: accept,
+        exit_code: ServiceExitCode::Win32(code),
+        checkpoint: 0,
+        wait_hint: Duration::default(),
+        process_id: None,
+    }
+}
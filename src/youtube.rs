@@ -1,14 +1,43 @@
-use anyhow::Result;
-use once_cell::sync::Lazy;
-use regex::Regex;
+use bytes::Bytes;
+#[cfg(feature = "player")]
+use {
+    crate::{IFRAME_API_URL, LOCAL_WIDGET_API_PATH, utils::fetch_url, vault::Vault},
+    anyhow::{Context, Result},
+    once_cell::sync::Lazy,
+    regex::Regex,
+    tokio::time::{Duration, sleep},
+};
 
-use crate::{IFRAME_API_URL, LOCAL_WIDGET_API_PATH, utils::fetch_url};
+static IFRAME_API_FIXTURE: &[u8] = include_bytes!("../assets/youtube/iframe_api.js");
+static WIDGETAPI_FIXTURE: &[u8] = include_bytes!("../assets/youtube/www-widgetapi.js");
+
+/// Serve the bundled fixture scripts instead of fetching them from YouTube
+/// when set to `1` or `true`
+pub const YOUTUBE_FIXTURE_ENV: &str = "YTM_YOUTUBE_FIXTURE";
+
+/// Seconds clients are told to wait while `www-widgetapi.js` is missing
+pub const WIDGETAPI_RETRY_AFTER: u64 = 30;
 
 /// YouTube
 #[derive(Clone, Debug)]
 pub struct YouTube {
-    pub iframe_api_script: String,
-    pub widgetapi_script: String,
+    pub iframe_api_script: Bytes,
+    /// `None` until the download succeeds, retried in the background
+    pub widgetapi_script: Option<Bytes>,
+    /// Where `www-widgetapi.js` is downloaded from
+    pub widgetapi_url: String,
+}
+
+impl YouTube {
+    /// Bundled offline stand-in scripts, for tests and environments without
+    /// network access. The player does nothing
+    pub fn fixture() -> Self {
+        Self {
+            iframe_api_script: Bytes::from_static(IFRAME_API_FIXTURE),
+            widgetapi_script: Some(Bytes::from_static(WIDGETAPI_FIXTURE)),
+            widgetapi_url: String::new(),
+        }
+    }
 }
 
 /// Load YouTube components
@@ -16,12 +45,22 @@ pub struct YouTube {
 /// Retrieve YouTube Iframe API script once and serve it locally for the rest
 /// of the program's lifetime, reducing outbound network requests
 ///
-/// This function will panic if cannot extract `www-widgetapi.js` URL from
-/// `iframe_api`, which is most likely due to the `iframe_api` structure has
-/// been changed from the YouTube side
+/// Fails when `www-widgetapi.js` URL cannot be extracted from `iframe_api`,
+/// which is most likely due to the `iframe_api` structure has been changed
+/// from the YouTube side. A failed `www-widgetapi.js` download is only
+/// logged, see [`retry_widgetapi`]
+///
+/// Bundled fixtures are returned instead when [`YOUTUBE_FIXTURE_ENV`] is set
+#[cfg(feature = "player")]
 pub async fn load_youtube_components() -> Result<YouTube> {
     static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"var scriptUrl = '(.*?)';"#).unwrap());
 
+    if std::env::var(YOUTUBE_FIXTURE_ENV).is_ok_and(|v| v == "1" || v == "true") {
+        log::warn!("Serving bundled YouTube fixture scripts, the player is disabled");
+
+        return Ok(YouTube::fixture());
+    }
+
     log::debug!("Retrieve `iframe_api` script");
 
     let iframe_api_script = fetch_url(IFRAME_API_URL).await?;
@@ -33,7 +72,7 @@ pub async fn load_youtube_components() -> Result<YouTube> {
         .captures(&iframe_api_script)
         .and_then(|caps| caps.get(1))
         .map(|m| m.as_str().replace(r"\/", "/"))
-        .expect("Cannot extract `www-widgetapi.js` URL from `frame_api`, which is most likely due to the `iframe_api` structure has been changed from the YouTube side");
+        .context("Cannot extract `www-widgetapi.js` URL from `iframe_api`, which is most likely due to the `iframe_api` structure has been changed from the YouTube side")?;
 
     log::debug!("Modify `iframe_api` script");
 
@@ -47,13 +86,51 @@ pub async fn load_youtube_components() -> Result<YouTube> {
 
     log::debug!("Retrieve `www-widgetapi.js` script");
 
-    let widgetapi_script = fetch_url(&origin_url).await?;
-    let widgetapi_script = String::from_utf8(widgetapi_script.to_vec())?;
+    let widgetapi_script = fetch_url(&origin_url)
+        .await
+        .inspect_err(|e| log::warn!("failed to retrieve `www-widgetapi.js`, retrying. {:?}", e))
+        .ok();
 
     let yt = YouTube {
-        iframe_api_script,
+        iframe_api_script: Bytes::from(iframe_api_script),
         widgetapi_script,
+        widgetapi_url: origin_url,
     };
 
     Ok(yt)
 }
+
+/// Download `www-widgetapi.js` again until it succeeds, backing off from 5
+/// seconds up to 5 minutes. Returns right away when it is already there
+#[cfg(feature = "player")]
+pub async fn retry_widgetapi(vault: Vault) {
+    let mut backoff = Duration::from_secs(5);
+
+    loop {
+        let url = {
+            let state = vault.state.lock().unwrap();
+
+            match &state.youtube {
+                Some(v) if v.widgetapi_script.is_none() => v.widgetapi_url.clone(),
+                _ => return,
+            }
+        };
+
+        sleep(backoff).await;
+
+        match fetch_url(&url).await {
+            Ok(script) => {
+                if let Some(youtube) = &mut vault.state.lock().unwrap().youtube {
+                    youtube.widgetapi_script = Some(script);
+                }
+
+                log::info!("Retrieved `www-widgetapi.js`, the player is available");
+
+                return;
+            }
+            Err(e) => log::warn!("failed to retrieve `www-widgetapi.js`. {:?}", e),
+        }
+
+        backoff = (backoff * 2).min(Duration::from_secs(5 * 60));
+    }
+}
@@ -0,0 +1,1640 @@
+use chrono::{DateTime, Duration, Utc};
+use http_body_util::BodyExt;
+use hyper::{Request, StatusCode, body::Bytes};
+use tokio::{net::TcpListener, sync::oneshot};
+use ytm::{
+    auth::{User, hash_password},
+    demo::{DemoOptions, generate, to_v1_json},
+    schema::{
+        Channel, MetadataTable, WatchEntry, load_metadata_from_file, load_metadata_from_paths,
+        load_metadata_from_reader,
+    },
+    server::Server,
+    service::{DATASET_VERSION_HEADER, UiOptions},
+    youtube::YouTube,
+};
+
+fn metadata() -> MetadataTable {
+    let options = DemoOptions {
+        entries: 500,
+        seed: Some(1),
+        ..Default::default()
+    };
+
+    MetadataTable::from_entries(generate(&options))
+}
+
+/// Watch of `Video <id>`
+fn watch(id: &str, time: DateTime<Utc>) -> WatchEntry {
+    WatchEntry {
+        id: id.to_owned(),
+        title: format!("Video {id}"),
+        channel: Channel {
+            id: "UC".into(),
+            name: "Channel".into(),
+        },
+        time,
+        products: Vec::new(),
+        ad: false,
+    }
+}
+
+/// `data` of a JSON envelope
+fn data(body: &[u8]) -> serde_json::Value {
+    let mut envelope: serde_json::Value = serde_json::from_slice(body).unwrap();
+
+    envelope["data"].take()
+}
+
+async fn server() -> Server {
+    Server::builder()
+        .metadata(metadata())
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap()
+}
+
+async fn get(server: &Server, uri: &str) -> (StatusCode, Bytes) {
+    let req = Request::get(uri).body(Bytes::new()).unwrap();
+    let res = server.handler().run(req).await.unwrap();
+    let status = res.status();
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+
+    (status, body)
+}
+
+#[tokio::test]
+async fn in_process_requests() {
+    let server = server().await;
+    let total_count = metadata().total_count();
+
+    let (status, body) = get(&server, "/api/videos/count").await;
+    let count = data(&body);
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(count["count"], total_count);
+
+    let (status, body) = get(&server, "/?limit=5").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(String::from_utf8_lossy(&body).contains("</html>"));
+
+    let (status, body) = get(&server, "/iframe_api").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, YouTube::fixture().iframe_api_script);
+
+    let (status, _) = get(&server, "/p/missing/").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn ephemeral_port() {
+    let server = server().await;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = oneshot::channel::<()>();
+
+    let handle = tokio::spawn(server.serve_with_listener(listener, async {
+        let _ = rx.await;
+    }));
+
+    let res = reqwest::get(format!("http://{addr}/api/info"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let info = data(&res.bytes().await.unwrap());
+    assert_eq!(info["profile"], "default");
+
+    tx.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn dataset_version() {
+    let server = server().await;
+    let version = async |server: &Server| {
+        let req = Request::get("/api/info").body(Bytes::new()).unwrap();
+        let res = server.handler().run(req).await.unwrap();
+
+        res.headers()[DATASET_VERSION_HEADER]
+            .to_str()
+            .unwrap()
+            .to_owned()
+    };
+
+    assert_eq!(version(&server).await, "1");
+
+    let previous = server
+        .vault()
+        .state
+        .lock()
+        .unwrap()
+        .replace_profile("default", metadata());
+    assert!(previous.is_some());
+    assert_eq!(version(&server).await, "2");
+}
+
+#[tokio::test]
+async fn ingest_history() {
+    let server = Server::builder()
+        .metadata(metadata())
+        .youtube(YouTube::fixture())
+        .ingest_token("secret")
+        .build()
+        .await
+        .unwrap();
+    let post = async |token: &str, body: &'static str| {
+        let req = Request::post("/api/history")
+            .header("authorization", format!("Bearer {token}"))
+            .body(Bytes::from_static(body.as_bytes()))
+            .unwrap();
+
+        server.handler().run(req).await.unwrap().status()
+    };
+    let body = r#"{"id": "dQw4w9WgXcQ", "title": "Never Gonna Give You Up", "channel": "Rick Astley", "timestamp": "2024-01-01T12:00:00Z"}"#;
+    let total_count = metadata().total_count();
+
+    assert_eq!(post("wrong", body).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        post("secret", r#"{"id": "x"}"#).await,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(post("secret", body).await, StatusCode::CREATED);
+    assert_eq!(post("secret", body).await, StatusCode::OK);
+
+    let (_, body) = get(&server, "/api/videos/count").await;
+    let count = data(&body);
+    assert_eq!(count["count"], total_count + 1);
+}
+
+#[tokio::test]
+async fn missing_widgetapi() {
+    let youtube = YouTube {
+        widgetapi_script: None,
+        ..YouTube::fixture()
+    };
+    let server = Server::builder()
+        .metadata(metadata())
+        .youtube(youtube)
+        .build()
+        .await
+        .unwrap();
+
+    let req = Request::get("/www-widgetapi.js")
+        .body(Bytes::new())
+        .unwrap();
+    let res = server.handler().run(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(res.headers()["retry-after"], "30");
+
+    let (status, _) = get(&server, "/iframe_api").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn player_state() {
+    let server = server().await;
+    let run = async |method: &str, cookie: &str, body: &'static str| {
+        let req = Request::builder()
+            .method(method)
+            .uri("/api/player/state")
+            .header("cookie", cookie)
+            .body(Bytes::from_static(body.as_bytes()))
+            .unwrap();
+
+        server.handler().run(req).await.unwrap()
+    };
+
+    let res = run("GET", "", "").await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let set_cookie = res.headers()["set-cookie"].to_str().unwrap();
+    let cookie = set_cookie.split(';').next().unwrap().to_owned();
+
+    let body = r#"{"video_id": "dQw4w9WgXcQ", "time": 42.5, "playing": true}"#;
+    assert_eq!(
+        run("PUT", &cookie, body).await.status(),
+        StatusCode::NO_CONTENT
+    );
+
+    let res = run("GET", &cookie, "").await;
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+    let state = data(&body);
+    assert_eq!(state["video_id"], "dQw4w9WgXcQ");
+    assert_eq!(state["time"], 42.5);
+
+    assert_eq!(run("GET", "", "").await.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn external_frontend() {
+    let table = metadata();
+    let id = table.data()[0].id.clone();
+    let server = Server::builder()
+        .metadata(table)
+        .youtube(YouTube::fixture())
+        .ui(UiOptions {
+            external_frontend: "https://yewtu.be/".parse().unwrap(),
+            ..Default::default()
+        })
+        .build()
+        .await
+        .unwrap();
+
+    let (status, body) = get(&server, &format!("/api/videos/timeline?id={}", id)).await;
+    let timeline = data(&body);
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        timeline["url"],
+        format!("https://yewtu.be/watch?v={}", id).as_str()
+    );
+
+    let (_, body) = get(&server, "/api/stats/top?kind=videos&n=1").await;
+    let top = data(&body);
+    assert!(
+        top[0]["url"]
+            .as_str()
+            .unwrap()
+            .starts_with("https://yewtu.be/watch?v=")
+    );
+
+    let (_, body) = get(&server, "/?limit=5").await;
+    assert!(String::from_utf8_lossy(&body).contains("href=\"https://yewtu.be/watch?v="));
+}
+
+#[tokio::test]
+async fn relevance_order() {
+    let now = Utc::now();
+    let watch = |id: &str, days: i64| watch(id, now - Duration::days(days));
+    let entries = (0..10)
+        .map(|i| watch("old", 700 + i))
+        .chain([watch("new", 1), watch("new", 3)])
+        .chain([watch("last", 0)]);
+    let server = Server::builder()
+        .metadata(MetadataTable::from_entries(entries))
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+
+    let (_, body) = get(&server, "/export.md?order=relevance").await;
+    let body = String::from_utf8_lossy(&body);
+    let position = |id: &str| body.find(&format!("Video {id}")).unwrap();
+    assert!(position("new") < position("last"));
+    assert!(position("last") < position("old"));
+}
+
+#[tokio::test]
+async fn resurface() {
+    let now = Utc::now();
+    let watch = |id: &str, days: i64| watch(id, now - Duration::days(days));
+    let entries = [
+        // three different days, two years ago
+        watch("favourite", 730),
+        watch("favourite", 760),
+        watch("favourite", 800),
+        // one night on repeat
+        watch("loop", 730),
+        watch("loop", 730),
+        watch("loop", 730),
+        // still watched
+        watch("current", 700),
+        watch("current", 710),
+        watch("current", 0),
+    ];
+    let server = Server::builder()
+        .metadata(MetadataTable::from_entries(entries))
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+
+    let (status, body) = get(&server, "/api/resurface").await;
+    let list = data(&body);
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(list.as_array().unwrap().len(), 1);
+    assert_eq!(list[0]["id"], "favourite");
+    assert_eq!(list[0]["watch_days"], 3);
+
+    let (status, body) = get(&server, "/resurface?idle_days=30&min_days=1").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(String::from_utf8_lossy(&body).contains("Video loop"));
+
+    for query in [
+        "idle_days=-1",
+        "idle_days=9223372036854775807",
+        "idle_days=year",
+    ] {
+        let (status, _) = get(&server, &format!("/api/resurface?{query}")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{query}");
+    }
+}
+
+#[tokio::test]
+async fn duplicates() {
+    let now = Utc::now();
+    let mut reupload = watch("reupload", now);
+    reupload.title = "VIDEO ORIGINAL! [Official Video]".to_owned();
+    let entries = || {
+        [
+            watch("original", now - Duration::days(2)),
+            watch("original", now - Duration::days(1)),
+            reupload.clone(),
+            watch("other", now),
+        ]
+    };
+    let build = |ui: UiOptions, table: MetadataTable| {
+        Server::builder()
+            .metadata(table)
+            .youtube(YouTube::fixture())
+            .ui(ui)
+            .build()
+    };
+
+    let server = build(UiOptions::default(), MetadataTable::from_entries(entries()))
+        .await
+        .unwrap();
+    let (status, _) = get(&server, "/api/duplicates").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let ui = UiOptions {
+        duplicates: true,
+        ..Default::default()
+    };
+    let server = build(ui.clone(), MetadataTable::from_entries(entries()))
+        .await
+        .unwrap();
+    let (status, body) = get(&server, "/api/duplicates").await;
+    let groups = data(&body);
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(groups.as_array().unwrap().len(), 1);
+    assert_eq!(groups[0]["watch_count"], 3);
+    assert_eq!(groups[0]["videos"][0]["id"], "original");
+
+    let (status, body) = get(&server, "/duplicates").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(String::from_utf8_lossy(&body).contains("VIDEO ORIGINAL!"));
+
+    let mut table = MetadataTable::from_entries(entries());
+    assert_eq!(table.merge_duplicates(), 1);
+    assert_eq!(table.total_count(), 2);
+    assert_eq!(table.get("original").unwrap().watch_count, 3);
+
+    let server = build(ui, table).await.unwrap();
+    let (_, body) = get(&server, "/api/duplicates").await;
+    assert_eq!(data(&body), serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn channel_export() {
+    let now = Utc::now();
+    let mut other = watch("other", now);
+    other.channel = Channel {
+        id: "UCother".into(),
+        name: "Other".into(),
+    };
+    let entries = [
+        watch("first", now - Duration::days(2)),
+        watch("second", now - Duration::days(1)),
+        watch("first", now),
+        other,
+    ];
+    let server = Server::builder()
+        .metadata(MetadataTable::from_entries(entries))
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+
+    let (status, body) = get(&server, "/channel/UC/export.csv").await;
+    let body = String::from_utf8_lossy(&body);
+    let lines = body.lines().collect::<Vec<&str>>();
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(lines[0], "watched_at,id,title,channel_id,channel_name");
+    assert_eq!(lines.len(), 4);
+    assert!(lines[1].contains(",first,") && lines[3].contains(",first,"));
+    assert!(!body.contains("other"));
+
+    for uri in [
+        "/channel/UCmissing/export.csv",
+        "/channel/export.csv",
+        "/channel//export.csv",
+    ] {
+        let (status, _) = get(&server, uri).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn api_pagination() {
+    let server = server().await;
+    let req = Request::get("/api/videos?limit=5&page=2")
+        .body(Bytes::new())
+        .unwrap();
+    let res = server.handler().run(req).await.unwrap();
+    let link = res.headers()["link"].to_str().unwrap().to_owned();
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+    let envelope: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(envelope["data"].as_array().unwrap().len(), 5);
+    assert_eq!(envelope["pagination"]["current_page"], 2);
+    assert_eq!(envelope["meta"]["dataset_version"], 1);
+    assert!(link.contains(r#"</api/videos?limit=5&page=1>; rel="prev""#));
+    assert!(link.contains(r#"</api/videos?limit=5&page=3>; rel="next""#));
+    assert!(link.contains(r#"rel="first""#) && link.contains(r#"rel="last""#));
+
+    let (_, body) = get(&server, "/api/videos/count").await;
+    let envelope: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(envelope["pagination"].is_null());
+}
+
+#[tokio::test]
+async fn api_schema() {
+    let server = server().await;
+
+    let (status, body) = get(&server, "/api/schema").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(data(&body).as_array().unwrap().contains(&"filter".into()));
+
+    let (status, body) = get(&server, "/api/schema/filter").await;
+    let schema: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(schema["title"], "MetadataFilter");
+    assert!(schema["properties"]["channel_id"].is_object());
+    assert!(schema["properties"]["order"].is_object());
+
+    let (_, body) = get(&server, "/api/schema/videos").await;
+    let schema: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(schema["type"], "array");
+
+    let (status, _) = get(&server, "/api/schema/unknown").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn fuzzy_search() {
+    let now = Utc::now();
+    let entry = |id: &str, title: &str, channel: &str| WatchEntry {
+        title: title.to_owned(),
+        channel: Channel {
+            id: format!("UC{id}").into(),
+            name: channel.into(),
+        },
+        ..watch(id, now)
+    };
+    let entries = [
+        entry("a", "Why are there tides", "Veritasium"),
+        entry("b", "Veritasum tribute", "Someone"),
+        entry("c", "Cooking pasta", "Kitchen"),
+        WatchEntry {
+            time: now - Duration::days(1),
+            ..entry("d", "Veritasium best of", "Someone")
+        },
+    ];
+    let server = Server::builder()
+        .metadata(MetadataTable::from_entries(entries))
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+
+    let (_, body) = get(&server, "/api/videos?channel_name=veritasum").await;
+    assert_eq!(data(&body).as_array().unwrap().len(), 0);
+
+    let (_, body) = get(&server, "/api/videos?channel_name=veritasum&fuzzy=1").await;
+    let videos = data(&body);
+    assert_eq!(videos.as_array().unwrap().len(), 1);
+    assert_eq!(videos[0]["id"], "a");
+
+    let (_, body) = get(&server, "/api/videos?title=veritasium&fuzzy=1").await;
+    let videos = data(&body);
+    assert_eq!(videos.as_array().unwrap().len(), 2);
+    assert_eq!(videos[0]["id"], "d");
+    assert_eq!(videos[1]["id"], "b");
+
+    let (_, body) = get(&server, "/api/videos?title=pizza&fuzzy=1").await;
+    assert_eq!(data(&body).as_array().unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn combined_search() {
+    let now = Utc::now();
+    let entry = |id: &str, title: &str, channel: &str, days: i64| WatchEntry {
+        title: title.to_owned(),
+        channel: Channel {
+            id: format!("UC{id}").into(),
+            name: channel.into(),
+        },
+        ..watch(id, now - Duration::days(days))
+    };
+    let entries = [
+        entry("a", "Lofi beats", "Chillhop", 0),
+        entry("b", "Rainy night", "Lofi Girl", 1),
+        entry("c", "Lofi Girl live", "Lofi Girl", 2),
+        entry("d", "Cooking pasta", "Kitchen", 3),
+    ];
+    let server = Server::builder()
+        .metadata(MetadataTable::from_entries(entries))
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+    let ids = |body: &[u8]| {
+        data(body)
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v["id"].as_str().unwrap().to_owned())
+            .collect::<Vec<String>>()
+    };
+
+    let (_, body) = get(&server, "/api/videos?search=lofi").await;
+    assert_eq!(ids(&body), ["c", "a", "b"]);
+
+    let (_, body) = get(&server, "/api/videos?search=lofi&title=live").await;
+    assert_eq!(ids(&body), ["c"]);
+
+    let (_, body) = get(&server, "/api/videos?search=chilhop&fuzzy=1").await;
+    assert_eq!(ids(&body), ["a"]);
+}
+
+#[tokio::test]
+async fn case_and_whole_word() {
+    let now = Utc::now();
+    let entry = |id: &str, title: &str| WatchEntry {
+        title: title.to_owned(),
+        ..watch(id, now)
+    };
+    let entries = [
+        entry("a", "Let's go!"),
+        entry("b", "Google I/O keynote"),
+        entry("c", "How AI works"),
+        entry("d", "Email tips and tricks"),
+    ];
+    let server = Server::builder()
+        .metadata(MetadataTable::from_entries(entries))
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+    let count = |body: &[u8]| data(body).as_array().unwrap().len();
+
+    let (_, body) = get(&server, "/api/videos?title=go").await;
+    assert_eq!(count(&body), 2);
+
+    let (_, body) = get(&server, "/api/videos?title=go&whole_word=1").await;
+    assert_eq!(data(&body)[0]["id"], "a");
+    assert_eq!(count(&body), 1);
+
+    let (_, body) = get(&server, "/api/videos?title=ai").await;
+    assert_eq!(count(&body), 2);
+
+    let (_, body) = get(&server, "/api/videos?title=AI&case_sensitive=1").await;
+    assert_eq!(data(&body)[0]["id"], "c");
+    assert_eq!(count(&body), 1);
+
+    let (_, body) = get(&server, "/api/videos?title=ai&case_sensitive=1").await;
+    assert_eq!(count(&body), 1);
+
+    let (_, body) = get(&server, "/api/videos?title=ai&whole_word=1").await;
+    assert_eq!(count(&body), 1);
+}
+
+#[tokio::test]
+async fn timeline_zoom() {
+    let start = "2015-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+    let entries = (0..3650).map(|i| watch(&format!("v{}", i % 7), start + Duration::days(i)));
+    let server = Server::builder()
+        .metadata(MetadataTable::from_entries(entries))
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+
+    let (status, body) = get(&server, "/api/timeline?width=400").await;
+    let timeline = data(&body);
+    let buckets = timeline["buckets"].as_array().unwrap();
+    let sum = buckets
+        .iter()
+        .map(|v| v["count"].as_u64().unwrap())
+        .sum::<u64>();
+    assert_eq!(status, StatusCode::OK);
+    assert!(buckets.len() <= 100);
+    assert_eq!(timeline["bucket_seconds"], 91 * 24 * 60 * 60);
+    assert_eq!(timeline["total"], 3650);
+    assert_eq!(sum, 3650);
+
+    let uri = "/api/timeline?width=400&from=2020-03-01T00:00:00Z&to=2020-03-08T00:00:00Z";
+    let (_, body) = get(&server, uri).await;
+    let timeline = data(&body);
+    assert_eq!(timeline["bucket_seconds"], 60 * 60 * 3);
+    assert_eq!(timeline["buckets"].as_array().unwrap().len(), 56);
+    assert_eq!(timeline["total"], 7);
+
+    let (_, body) = get(&server, "/api/timeline?id=v0").await;
+    assert_eq!(data(&body)["total"], 522);
+
+    let (status, _) = get(&server, "/api/timeline?id=missing").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn query_timeout() {
+    let options = DemoOptions {
+        entries: 100_000,
+        seed: Some(1),
+        ..Default::default()
+    };
+    let server = Server::builder()
+        .metadata(MetadataTable::from_entries(generate(&options)))
+        .youtube(YouTube::fixture())
+        .ui(UiOptions {
+            query_timeout: 1,
+            ..Default::default()
+        })
+        .build()
+        .await
+        .unwrap();
+
+    let uri = "/api/videos?search=episdoe+chanel&fuzzy=1&order=alphabetical";
+    let (status, body) = get(&server, uri).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(String::from_utf8_lossy(&body).starts_with("query too expensive"));
+
+    let (status, _) = get(&server, "/api/videos?limit=5").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn years_cache_follows_replace() {
+    let server = server().await;
+    let (_, body) = get(&server, "/api/years").await;
+    assert!(!data(&body).as_array().unwrap().is_empty());
+
+    let now = Utc::now();
+    let previous = server
+        .vault()
+        .state
+        .lock()
+        .unwrap()
+        .replace_profile("default", MetadataTable::from_entries([watch("new", now)]));
+    assert!(previous.is_some());
+
+    let (_, body) = get(&server, "/api/years").await;
+    let years = data(&body);
+    assert_eq!(years.as_array().unwrap().len(), 1);
+    assert_eq!(years[0]["watch_count"], 1);
+
+    let (_, body) = get(&server, "/api/videos/count").await;
+    assert_eq!(data(&body)["count"], 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn concurrent_requests() {
+    let server = server().await;
+    let total_count = metadata().total_count();
+    let requests = (0..8).map(|i| {
+        let handler = server.handler();
+        let uri = match i % 2 {
+            0 => "/api/videos/count",
+            _ => "/api/player/state",
+        };
+        let req = Request::get(uri).body(Bytes::new()).unwrap();
+
+        tokio::spawn(async move { handler.run(req).await.unwrap() })
+    });
+
+    for (i, res) in futures::future::join_all(requests)
+        .await
+        .into_iter()
+        .enumerate()
+    {
+        let res = res.unwrap();
+
+        if i % 2 == 0 {
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(data(&body)["count"], total_count);
+        } else {
+            assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        }
+    }
+}
+
+#[tokio::test]
+async fn html_history() {
+    let cell = |content: &str| {
+        format!(
+            r#"<div class="outer-cell mdl-cell mdl-cell--12-col mdl-shadow--2dp"><div class="mdl-grid"><div class="header-cell mdl-cell mdl-cell--12-col"><p class="mdl-typography--title">YouTube<br></p></div><div class="content-cell mdl-cell mdl-cell--6-col mdl-typography--body-1">{content}</div><div class="content-cell mdl-cell mdl-cell--6-col mdl-typography--body-1 mdl-typography--text-right"></div><div class="content-cell mdl-cell mdl-cell--12-col mdl-typography--caption"><b>Products:</b><br>&emsp;YouTube<br><b>Why is this here?</b><br>&emsp;This activity was saved to your Google Account.<br></div></div></div>"#
+        )
+    };
+    let html = format!(
+        r#"<html><head><title>My Activity</title></head><body><div class="mdl-grid">{}{}{}{}</div></body></html>"#,
+        cell(
+            r#"Watched&nbsp;<a href="https://www.youtube.com/watch?v=dQw4w9WgXcQ">Never Gonna Give You Up</a><br><a href="https://www.youtube.com/channel/UCuAXFkgsw1L7xaCfnd5JJOw">Rick Astley</a><br>Jan 1, 2024, 1:00:00&#8239;PM CET<br>"#
+        ),
+        cell(
+            r#"Watched&nbsp;<a href="https://www.youtube.com/watch?v=dQw4w9WgXcQ">Never Gonna Give You Up</a><br><a href="https://www.youtube.com/channel/UCuAXFkgsw1L7xaCfnd5JJOw">Rick Astley</a><br>31 Dec 2023, 23:30:00 GMT+05:30<br>"#
+        ),
+        cell(
+            r#"Watched&nbsp;<a href="https://www.youtube.com/watch?v=9bZkp7q19f0">Tom &amp; Jerry</a><br>Feb 3, 2024, 8:15:00 AM UTC<br>"#
+        ),
+        cell(
+            r#"Watched&nbsp;<a href="https://www.youtube.com/watch?v=kJQP7kiw5Fk">Unreadable</a><br>3 février 2024, 08:15:00 UTC<br>"#
+        ),
+    );
+    let table = load_metadata_from_reader(std::io::Cursor::new(html)).unwrap();
+
+    assert_eq!(table.total_count(), 2);
+
+    let rick = table.get("dQw4w9WgXcQ").unwrap();
+    assert_eq!(rick.watch_count, 2);
+    assert_eq!(&*rick.channel.id, "UCuAXFkgsw1L7xaCfnd5JJOw");
+    assert_eq!(&*rick.channel.name, "Rick Astley");
+    assert_eq!(rick.watched_at.to_rfc3339(), "2023-12-31T18:00:00+00:00");
+    assert_eq!(
+        rick.last_watched_at.to_rfc3339(),
+        "2024-01-01T12:00:00+00:00"
+    );
+
+    let tom = table.get("9bZkp7q19f0").unwrap();
+    assert_eq!(&*tom.title, "Tom & Jerry");
+    assert_eq!(&*tom.channel.name, "-");
+}
+
+#[tokio::test]
+async fn invalid_limits() {
+    let server = server().await;
+
+    let (status, body) = get(&server, "/api/videos?limit=0").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body, "`limit` must be at least 1");
+
+    let (status, body) = get(&server, "/api/videos?page=0").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body, "`page` starts at 1");
+
+    let (status, body) = get(
+        &server,
+        "/api/videos/count?from=2024-02-01T00:00:00Z&to=2024-01-01T00:00:00Z",
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body, "`from` is after `to`");
+
+    for uri in ["/?limit=0", "/export.md?limit=0"] {
+        let (status, _) = get(&server, uri).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+    }
+
+    let (status, body) = get(&server, "/api/videos?limit=18446744073709551615&page=2").await;
+    let envelope: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(envelope["pagination"]["limit"], 1000);
+
+    let (status, body) = get(
+        &server,
+        "/api/sessions?limit=5000&page=18446744073709551615",
+    )
+    .await;
+    let envelope: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(envelope["pagination"]["limit"], 1000);
+
+    for (uri, message) in [
+        ("/api/sessions?limit=0", "`limit` must be at least 1"),
+        ("/sessions?page=0", "`page` starts at 1"),
+    ] {
+        let (status, body) = get(&server, uri).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+        assert_eq!(body, message);
+    }
+
+    for uri in [
+        "/quality?gap_days=9223372036854775807",
+        "/quality?gap_days=0",
+        "/quality?gap_days=week",
+        "/sessions?gap_minutes=9223372036854775807",
+        "/api/sessions?gap_minutes=0",
+        "/api/sessions?page=first",
+        "/api/stats/co-watch?gap_minutes=9223372036854775807",
+    ] {
+        let (status, _) = get(&server, uri).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+    }
+
+    let (status, _) = get(&server, "/quality?gap_days=36500").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn streamed_json() {
+    let load = |json: &str| load_metadata_from_reader(std::io::Cursor::new(json.to_owned()));
+    let entries = generate(&DemoOptions {
+        entries: 200,
+        seed: Some(1),
+        ..Default::default()
+    });
+    let json = serde_json::to_string_pretty(&to_v1_json(&entries)).unwrap();
+
+    let table = load(&json).unwrap();
+    assert_eq!(table.total_count_raw(), 200);
+    assert_eq!(
+        table.total_count(),
+        MetadataTable::from_entries(entries.clone()).total_count()
+    );
+
+    // entries without a readable time are skipped, not the whole file
+    let mut two = to_v1_json(&entries[..2]);
+    two[0]["time"] = "never".into();
+    assert_eq!(load(&two.to_string()).unwrap().total_count_raw(), 1);
+
+    // cut off or followed by more content
+    let cut = &json[..json.len() / 2];
+    assert!(load(&format!("{}]", cut.trim_end().trim_end_matches(','))).is_err());
+    assert!(load(&format!("{json} []")).is_err());
+    assert!(load(&json[..json.rfind(']').unwrap()]).is_err());
+}
+
+#[tokio::test]
+async fn zip_archive() {
+    use std::io::Write;
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    let json = include_bytes!("../data/v1-watch-history.json");
+    let expected = load_metadata_from_reader(std::io::Cursor::new(json))
+        .unwrap()
+        .total_count();
+    let archive = |entry: &str| {
+        let path = std::env::temp_dir().join(format!("ytm-{}.zip", std::process::id()));
+        let mut zip = ZipWriter::new(std::fs::File::create(&path).unwrap());
+
+        zip.start_file("Takeout/archive_browser.html", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"<html><body>Archive</body></html>").unwrap();
+        zip.start_file(entry, SimpleFileOptions::default()).unwrap();
+        zip.write_all(json).unwrap();
+        zip.finish().unwrap();
+
+        path
+    };
+
+    // English names are looked up, localized ones found by their content
+    for entry in [
+        "Takeout/YouTube and YouTube Music/history/watch-history.json",
+        "Takeout/YouTube und YouTube Music/Verlauf/Wiedergabeverlauf.json",
+    ] {
+        let path = archive(entry);
+        let table = load_metadata_from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let table = table.unwrap();
+        assert_eq!(table.total_count(), expected, "{entry}");
+        assert_eq!(table.source().unwrap().path, path);
+    }
+}
+
+#[tokio::test]
+async fn boundaries() {
+    let local = |day: &str| {
+        let time = chrono::NaiveDateTime::parse_from_str(day, "%Y-%m-%d %H:%M").unwrap();
+
+        time.and_local_timezone(chrono::Local).unwrap().to_utc()
+    };
+    let server = Server::builder()
+        .metadata(MetadataTable::from_entries([
+            watch("a", local("2024-01-01 00:00")),
+            watch("b", local("2024-06-01 12:00")),
+            watch("c", local("2025-01-01 00:00")),
+        ]))
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+    let count = async |query: &str| {
+        let (status, body) = get(&server, &format!("/api/videos/count?{query}")).await;
+        assert_eq!(status, StatusCode::OK, "{query}");
+
+        data(&body)["count"].clone()
+    };
+    let from = local("2024-01-01 00:00").to_rfc3339();
+    let to = local("2025-01-01 00:00").to_rfc3339();
+    let range = serde_urlencoded::to_string([("from", &from), ("to", &to)]).unwrap();
+
+    // watches right at the bounds are taken in unless asked otherwise
+    assert_eq!(count(&range).await, 3);
+    assert_eq!(count(&format!("{range}&boundaries=inclusive")).await, 3);
+    assert_eq!(count(&format!("{range}&boundaries=exclusive")).await, 1);
+    assert_eq!(count(&format!("{range}&boundaries=half_open")).await, 2);
+
+    let range = serde_urlencoded::to_string([("last_from", &from), ("last_to", &from)]).unwrap();
+    assert_eq!(count(&range).await, 1);
+    assert_eq!(count(&format!("{range}&boundaries=exclusive")).await, 0);
+
+    // dates and years end right before the midnight of the next one
+    assert_eq!(count("from=2024-01-01&to=2024-12-31").await, 2);
+    assert_eq!(count("to=2024-12-31&boundaries=exclusive").await, 2);
+    assert_eq!(count("from=2025-01-01").await, 1);
+    assert_eq!(count("period=2024").await, 2);
+
+    let (status, _) = get(&server, "/api/videos/count?boundaries=open").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn any_watch() {
+    let at = |day: &str| format!("{day}T12:00:00Z").parse::<DateTime<Utc>>().unwrap();
+    let server = Server::builder()
+        .metadata(MetadataTable::from_entries([
+            watch("a", at("2023-01-10")),
+            watch("a", at("2024-03-05")),
+            watch("a", at("2024-06-01")),
+            watch("b", at("2024-03-20")),
+        ]))
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+    let count = async |query: &str| {
+        let (status, body) = get(&server, &format!("/api/videos/count?{query}")).await;
+        assert_eq!(status, StatusCode::OK, "{query}");
+
+        data(&body)["count"].clone()
+    };
+    let march = "from=2024-03-01T00:00:00Z&to=2024-03-31T00:00:00Z";
+
+    // `a` was first watched long before, its rewatch in March only counts
+    // for `any_watch`
+    assert_eq!(count(march).await, 1);
+    assert_eq!(count(&format!("{march}&match=first_watch")).await, 1);
+    assert_eq!(count(&format!("{march}&match=any_watch")).await, 2);
+    assert_eq!(count(&format!("{march}&match=last_watch")).await, 1);
+    assert_eq!(count("period=2023&match=any_watch").await, 1);
+    assert_eq!(count("from=2024-06-01T00:00:00Z&match=last_watch").await, 1);
+
+    // a rewatch right at both bounds
+    let exact = "from=2024-03-05T12:00:00Z&to=2024-03-05T12:00:00Z&match=any_watch";
+    assert_eq!(count(exact).await, 1);
+    assert_eq!(count(&format!("{exact}&boundaries=exclusive")).await, 0);
+
+    let (status, _) = get(&server, "/api/videos/count?match=every_watch").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn watch_events() {
+    let at = |day: &str| format!("{day}T12:00:00Z").parse::<DateTime<Utc>>().unwrap();
+    let server = Server::builder()
+        .metadata(MetadataTable::from_entries([
+            watch("aaaaaaaaaaa", at("2023-01-10")),
+            watch("aaaaaaaaaaa", at("2024-03-05")),
+            watch("aaaaaaaaaaa", at("2024-03-06")),
+            watch("bbbbbbbbbbb", at("2024-03-20")),
+        ]))
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+    let events = async |query: &str| {
+        let (status, body) = get(&server, &format!("/api/videos?view=events&{query}")).await;
+        assert_eq!(status, StatusCode::OK, "{query}");
+
+        data(&body)
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| {
+                // first letter of the ID and day of the watch
+                let (id, time) = (v["id"].as_str().unwrap(), v["time"].as_str().unwrap());
+
+                format!("{}{}", &id[..1], &time[..10])
+            })
+            .collect::<Vec<String>>()
+    };
+
+    assert_eq!(
+        events("").await,
+        ["b2024-03-20", "a2024-03-06", "a2024-03-05", "a2023-01-10"]
+    );
+    assert_eq!(events("order=oldest&limit=1&page=2").await, ["a2024-03-05"]);
+
+    // bounds apply to each watch rather than the first one of the video
+    let march = "from=2024-03-01&to=2024-03-31";
+    assert_eq!(
+        events(march).await,
+        ["b2024-03-20", "a2024-03-06", "a2024-03-05"]
+    );
+    assert_eq!(events(&format!("{march}&channel_name=none")).await.len(), 0);
+
+    let (_, body) = get(&server, &format!("/api/videos/count?view=events&{march}")).await;
+    assert_eq!(data(&body)["count"], 3);
+
+    let (_, body) = get(&server, "/?view=events&limit=10").await;
+    let html = String::from_utf8_lossy(&body);
+    assert_eq!(html.matches("<tr>").count(), 5);
+
+    let (status, _) = get(&server, "/api/videos?view=rows").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn ad_impressions() {
+    let at = |hours| DateTime::<Utc>::UNIX_EPOCH + Duration::hours(hours);
+    let ad = |id: &str, time| WatchEntry {
+        ad: true,
+        ..watch(id, time)
+    };
+    // `b` was watched after its ad, only `c` is an ad alone
+    let json = to_v1_json(&[
+        watch("aaaaaaaaaaa", at(1)),
+        ad("bbbbbbbbbbb", at(2)),
+        watch("bbbbbbbbbbb", at(3)),
+        ad("ccccccccccc", at(4)),
+        ad("ccccccccccc", at(5)),
+    ]);
+    let table = load_metadata_from_reader(std::io::Cursor::new(json.to_string())).unwrap();
+    assert_eq!(table.ads_count(), 1);
+    assert!(
+        table
+            .data()
+            .iter()
+            .find(|v| v.id == "ccccccccccc")
+            .unwrap()
+            .ad
+    );
+
+    let server = Server::builder()
+        .metadata(table)
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+    let count = async |query: &str| {
+        let (status, body) = get(&server, &format!("/api/videos/count?{query}")).await;
+        assert_eq!(status, StatusCode::OK, "{query}");
+
+        data(&body)["count"].clone()
+    };
+
+    assert_eq!(count("").await, 2);
+    assert_eq!(count("ads=1").await, 3);
+    assert_eq!(count("ads=1&from=1970-01-01T04:00:00Z").await, 1);
+    assert_eq!(count("from=1970-01-01T04:00:00Z").await, 0);
+
+    let (status, body) = get(&server, "/").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(String::from_utf8_lossy(&body).contains("Videos only ever shown as ads"));
+}
+
+#[tokio::test]
+async fn merged_exports() {
+    let at = |hours| DateTime::<Utc>::UNIX_EPOCH + Duration::hours(hours);
+    let dir = std::env::temp_dir().join(format!("ytm-exports-{}", std::process::id()));
+    let write = |name: &str, entries: &[WatchEntry]| {
+        let path = dir.join(name);
+        std::fs::write(&path, to_v1_json(entries).to_string()).unwrap();
+
+        path
+    };
+
+    std::fs::create_dir_all(&dir).unwrap();
+    // the newer export repeats the first watch of `a`
+    let old = write(
+        "2023.json",
+        &[watch("aaaaaaaaaaa", at(1)), watch("bbbbbbbbbbb", at(2))],
+    );
+    let new = write(
+        "2024.json",
+        &[watch("aaaaaaaaaaa", at(1)), watch("aaaaaaaaaaa", at(3))],
+    );
+    let other = dir.join("notes.json");
+    std::fs::write(&other, r#"{"notes": []}"#).unwrap();
+
+    let from_dir = load_metadata_from_paths(std::slice::from_ref(&dir), false);
+    let from_files = load_metadata_from_paths(&[old, new], false);
+    let unrecognized = load_metadata_from_paths(&[other], false);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    for table in [from_dir.unwrap(), from_files.unwrap()] {
+        assert_eq!(table.total_count_raw(), 3);
+        assert_eq!(table.total_count(), 2);
+
+        let a = table.data().iter().find(|v| v.id == "aaaaaaaaaaa").unwrap();
+        assert_eq!(a.watch_count, 2);
+        assert_eq!(a.watch_timeline, [at(1), at(3)]);
+    }
+
+    // files passed by name must hold a history, unlike the ones of a directory
+    assert!(unrecognized.is_err());
+}
+
+#[tokio::test]
+async fn lenient_load() {
+    let entries = generate(&DemoOptions {
+        entries: 3,
+        seed: Some(1),
+        ..Default::default()
+    });
+    let mut json = to_v1_json(&entries);
+    json[1]["subtitles"] = "not a list".into();
+    let json = serde_json::to_string_pretty(&json).unwrap();
+    let line = json.lines().position(|v| v == "  },").unwrap() + 2;
+    let path = std::env::temp_dir().join(format!("ytm-lenient-{}.json", std::process::id()));
+    std::fs::write(&path, json).unwrap();
+
+    let strict = load_metadata_from_paths(std::slice::from_ref(&path), false);
+    let lenient = load_metadata_from_paths(std::slice::from_ref(&path), true);
+    std::fs::remove_file(&path).unwrap();
+
+    let message = format!("{:#}", strict.unwrap_err());
+    assert!(
+        message.contains(&format!("invalid entry 2 at line {line}")),
+        "{message}"
+    );
+
+    // the entry is skipped and reported, the ones around it are kept
+    let table = lenient.unwrap();
+    assert_eq!(table.total_count_raw(), 2);
+
+    let server = Server::builder()
+        .metadata(table)
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+    let (status, body) = get(&server, "/api/info").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let report = &data(&body)["source"]["parse_report"];
+    assert_eq!(report["skipped"], 1);
+    assert_eq!(report["examples"][0]["index"], 2);
+    assert_eq!(report["examples"][0]["line"], line);
+
+    let (_, body) = get(&server, "/about").await;
+    assert!(String::from_utf8_lossy(&body).contains("1 malformed entries skipped"));
+}
+
+#[tokio::test]
+async fn date_only_bounds() {
+    let local = |day: &str| {
+        let time = chrono::NaiveDateTime::parse_from_str(day, "%Y-%m-%d %H:%M").unwrap();
+
+        time.and_local_timezone(chrono::Local).unwrap().to_utc()
+    };
+    let server = Server::builder()
+        .metadata(MetadataTable::from_entries([
+            watch("a", local("2024-01-01 00:30")),
+            watch("b", local("2024-01-31 23:30")),
+            watch("c", local("2024-02-01 00:30")),
+        ]))
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+    let count = async |query: &str| {
+        let (status, body) = get(&server, &format!("/api/videos/count?{query}")).await;
+        assert_eq!(status, StatusCode::OK, "{query}");
+
+        data(&body)["count"].clone()
+    };
+
+    // `to` takes in the whole day
+    assert_eq!(count("from=2024-01-01&to=2024-01-31").await, 2);
+    assert_eq!(count("to=2024-01-31").await, 2);
+    assert_eq!(count("from=2024-02-01").await, 1);
+    assert_eq!(count("last_to=2024-01-01").await, 1);
+
+    let (status, body) = get(&server, "/api/videos/count?from=2024-13-01").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(String::from_utf8_lossy(&body).contains("YYYY-MM-DD"));
+}
+
+#[tokio::test]
+async fn diff_default_profile_only() {
+    let now = Utc::now();
+    let server = Server::builder()
+        .metadata(MetadataTable::from_entries([
+            watch("a", now),
+            watch("b", now),
+        ]))
+        .profile("other", MetadataTable::from_entries([watch("c", now)]))
+        .diff_base(MetadataTable::from_entries([watch("a", now)]))
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+
+    let (status, body) = get(&server, "/diff").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(String::from_utf8_lossy(&body).contains("Video b"));
+
+    let (status, _) = get(&server, "/p/other/diff").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn max_body() {
+    let server = Server::builder()
+        .metadata(metadata())
+        .youtube(YouTube::fixture())
+        .ui(UiOptions {
+            max_body: 16,
+            ..Default::default()
+        })
+        .build()
+        .await
+        .unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = oneshot::channel::<()>();
+
+    let handle = tokio::spawn(server.serve_with_listener(listener, async {
+        let _ = rx.await;
+    }));
+
+    let client = reqwest::Client::new();
+    let post = async |body: &'static str| {
+        client
+            .post(format!("http://{addr}/api/links"))
+            .body(body)
+            .send()
+            .await
+            .unwrap()
+            .status()
+    };
+
+    assert_ne!(
+        post("search=a").await,
+        reqwest::StatusCode::PAYLOAD_TOO_LARGE
+    );
+    assert_eq!(
+        post("search=a-rather-long-query").await,
+        reqwest::StatusCode::PAYLOAD_TOO_LARGE
+    );
+
+    tx.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn signed_preferences() {
+    let server = Server::builder()
+        .metadata(metadata())
+        .youtube(YouTube::fixture())
+        .prefs_key("secret")
+        .build()
+        .await
+        .unwrap();
+    let run = async |uri: &str, cookie: &str| {
+        let req = Request::get(uri)
+            .header("cookie", cookie)
+            .body(Bytes::new())
+            .unwrap();
+
+        server.handler().run(req).await.unwrap()
+    };
+
+    let res = run("/?limit=50&view=events", "").await;
+    let set_cookie = res.headers()["set-cookie"].to_str().unwrap();
+    let cookie = set_cookie.split(';').next().unwrap().to_owned();
+
+    // remembered settings don't need to be stored again
+    let res = run("/?limit=50&view=events", &cookie).await;
+    assert!(!res.headers().contains_key("set-cookie"));
+
+    // a tampered cookie is ignored
+    let res = run("/?limit=25", &cookie.replace("limit=50", "limit=25")).await;
+    assert!(res.headers().contains_key("set-cookie"));
+}
+
+#[tokio::test]
+async fn logs_access() {
+    let run = async |server: &Server, uri: &str, headers: &[(&str, &str)]| {
+        let mut req = Request::get(uri);
+        for (k, v) in headers {
+            req = req.header(*k, *v);
+        }
+
+        server
+            .handler()
+            .run(req.body(Bytes::new()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    };
+
+    // no login and no token, nobody may read them
+    let server = server().await;
+    assert_eq!(run(&server, "/logs", &[]).await, StatusCode::NOT_FOUND);
+    assert_eq!(run(&server, "/api/logs", &[]).await, StatusCode::NOT_FOUND);
+
+    let server = Server::builder()
+        .metadata(metadata())
+        .youtube(YouTube::fixture())
+        .ingest_token("secret")
+        .build()
+        .await
+        .unwrap();
+    let bearer = [("authorization", "Bearer secret")];
+    assert_eq!(run(&server, "/api/logs", &bearer).await, StatusCode::OK);
+    assert_eq!(
+        run(&server, "/api/logs?level=loud", &bearer).await,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(run(&server, "/logs", &bearer).await, StatusCode::NOT_FOUND);
+    assert_eq!(
+        run(&server, "/api/logs", &[("authorization", "Bearer wrong")]).await,
+        StatusCode::NOT_FOUND
+    );
+
+    let password_hash = hash_password("pw").unwrap();
+    let server = Server::builder()
+        .metadata(metadata())
+        .youtube(YouTube::fixture())
+        .user(User::new("admin".to_owned(), password_hash.clone(), None).unwrap())
+        .user(
+            User::new(
+                "guest".to_owned(),
+                password_hash,
+                Some(vec!["default".to_owned()]),
+            )
+            .unwrap(),
+        )
+        .build()
+        .await
+        .unwrap();
+    let login = async |name: &str| {
+        let req = Request::post("/login")
+            .body(Bytes::from(format!("name={name}&password=pw")))
+            .unwrap();
+        let res = server.handler().run(req).await.unwrap();
+        let set_cookie = res.headers()["set-cookie"].to_str().unwrap();
+
+        set_cookie.split(';').next().unwrap().to_owned()
+    };
+
+    let admin = login("admin").await;
+    assert_eq!(
+        run(&server, "/logs", &[("cookie", &admin)]).await,
+        StatusCode::OK
+    );
+    let guest = login("guest").await;
+    assert_eq!(
+        run(&server, "/api/logs", &[("cookie", &guest)]).await,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn profiles() {
+    let now = Utc::now();
+    let password_hash = hash_password("pw").unwrap();
+    let server = Server::builder()
+        .metadata(MetadataTable::from_entries([
+            watch("a", now),
+            watch("b", now),
+        ]))
+        .profile("alice", MetadataTable::from_entries([watch("c", now)]))
+        .user(User::new("admin".to_owned(), password_hash.clone(), None).unwrap())
+        .user(
+            User::new(
+                "alice".to_owned(),
+                password_hash,
+                Some(vec!["alice".to_owned()]),
+            )
+            .unwrap(),
+        )
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+    let login = async |name: &str| {
+        let req = Request::post("/login")
+            .body(Bytes::from(format!("name={name}&password=pw")))
+            .unwrap();
+        let res = server.handler().run(req).await.unwrap();
+        let set_cookie = res.headers()["set-cookie"].to_str().unwrap();
+
+        set_cookie.split(';').next().unwrap().to_owned()
+    };
+    let count = async |cookie: &str, uri: &str| {
+        let req = Request::get(uri)
+            .header("cookie", cookie)
+            .body(Bytes::new())
+            .unwrap();
+        let res = server.handler().run(req).await.unwrap();
+        let status = res.status();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+
+        (status == StatusCode::OK).then(|| data(&body)["count"].clone())
+    };
+
+    let admin = login("admin").await;
+    assert_eq!(count(&admin, "/api/videos/count").await, Some(2.into()));
+    assert_eq!(
+        count(&admin, "/p/default/api/videos/count").await,
+        Some(2.into())
+    );
+    assert_eq!(
+        count(&admin, "/p/alice/api/videos/count").await,
+        Some(1.into())
+    );
+    assert_eq!(count(&admin, "/p/bob/api/videos/count").await, None);
+
+    // restricted users land on their first profile and can't see the others
+    let alice = login("alice").await;
+    assert_eq!(count(&alice, "/api/videos/count").await, Some(1.into()));
+    assert_eq!(
+        count(&alice, "/p/alice/api/videos/count").await,
+        Some(1.into())
+    );
+    assert_eq!(count(&alice, "/p/default/api/videos/count").await, None);
+
+    // unauthenticated requests are sent to the login page
+    let req = Request::get("/p/alice/").body(Bytes::new()).unwrap();
+    let res = server.handler().run(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+}
+
+#[tokio::test]
+async fn top_api() {
+    let time = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+    let on = |id: &str, channel: &str, s: &str| WatchEntry {
+        channel: Channel {
+            id: format!("UC{channel}").into(),
+            name: channel.into(),
+        },
+        ..watch(id, time(s))
+    };
+    let server = Server::builder()
+        .metadata(MetadataTable::from_entries([
+            on("aaaaaaaaaaa", "X", "2020-06-15T12:00:00Z"),
+            on("aaaaaaaaaaa", "X", "2020-06-16T12:00:00Z"),
+            on("bbbbbbbbbbb", "Y", "2020-06-17T12:00:00Z"),
+            on("ccccccccccc", "Y", "2021-06-15T12:00:00Z"),
+            on("ddddddddddd", "Y", "2021-06-16T12:00:00Z"),
+        ]))
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+    let top = async |query: &str| {
+        let (status, body) = get(&server, &format!("/api/stats/top?{query}")).await;
+        assert_eq!(status, StatusCode::OK, "{query}");
+
+        data(&body)
+    };
+
+    let list = top("kind=channels").await;
+    assert_eq!(list[0]["name"], "Y");
+    assert_eq!(list[0]["watch_count"], 3);
+    assert_eq!(list[1]["rank"], 2);
+
+    let list = top("kind=channels&year=2020").await;
+    assert_eq!(list[0]["name"], "X");
+    assert_eq!(list.as_array().unwrap().len(), 2);
+
+    let list = top("kind=videos&year=2020&n=1").await;
+    assert_eq!(list.as_array().unwrap().len(), 1);
+    assert_eq!(list[0]["id"], "aaaaaaaaaaa");
+    assert_eq!(list[0]["watch_count"], 2);
+
+    // `year` wins over `from` and `to`
+    let list = top("kind=videos&year=2021&from=2020-01-01&to=2020-12-31").await;
+    assert_eq!(list.as_array().unwrap().len(), 2);
+
+    let list = top("kind=videos&from=2020-06-16&to=2020-06-17").await;
+    assert_eq!(list.as_array().unwrap().len(), 2);
+
+    for query in [
+        "",
+        "kind=people",
+        "kind=videos&n=-1",
+        "kind=videos&year=x",
+        "kind=videos&year=2147483647",
+        "kind=channels&year=-2147483648",
+    ] {
+        let (status, _) = get(&server, &format!("/api/stats/top?{query}")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{query}");
+    }
+}
+
+#[tokio::test]
+async fn calendar_api() {
+    let time = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+    let server = Server::builder()
+        .metadata(MetadataTable::from_entries([
+            watch("aaaaaaaaaaa", time("2021-06-10T12:00:00Z")),
+            watch("aaaaaaaaaaa", time("2021-06-12T12:00:00Z")),
+            watch("bbbbbbbbbbb", time("2021-06-12T13:00:00Z")),
+        ]))
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+
+    let (status, body) = get(&server, "/api/calendar/2021/06").await;
+    let calendar = data(&body);
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(calendar["total"], 3);
+    assert_eq!(calendar["days"].as_array().unwrap().len(), 30);
+    assert_eq!(calendar["days"][11]["date"], "2021-06-12");
+    assert_eq!(calendar["days"][11]["count"], 2);
+    assert_eq!(calendar["days"][11]["first_count"], 1);
+
+    for uri in [
+        "/api/calendar/2021",
+        "/api/calendar/2021/13",
+        "/api/calendar/2021/x",
+        "/api/calendar/2021/06/01",
+    ] {
+        let (status, _) = get(&server, uri).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn short_links() {
+    let server = server().await;
+    let shorten = async |body: &'static str| {
+        let req = Request::post("/api/links")
+            .body(Bytes::from_static(body.as_bytes()))
+            .unwrap();
+        let res = server.handler().run(req).await.unwrap();
+        let status = res.status();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+
+        (status, body)
+    };
+    let location = async |path: &str| {
+        let req = Request::get(path).body(Bytes::new()).unwrap();
+        let res = server.handler().run(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::SEE_OTHER, "{path}");
+
+        res.headers()["location"].to_str().unwrap().to_owned()
+    };
+
+    let (status, body) = shorten("search=rick&limit=5").await;
+    assert_eq!(status, StatusCode::OK);
+    let target = location(data(&body)["path"].as_str().unwrap()).await;
+    assert!(target.starts_with("/?"), "{target}");
+    assert!(target.contains("search=rick"), "{target}");
+    assert!(target.contains("limit=5"), "{target}");
+
+    // control characters are re-encoded instead of ending up in a header
+    for body in ["title=a%0Ab", "title=a\nb"] {
+        let (status, body) = shorten(body).await;
+        assert_eq!(status, StatusCode::OK);
+        let target = location(data(&body)["path"].as_str().unwrap()).await;
+        assert!(target.contains("title=a%0Ab"), "{target}");
+    }
+
+    let (status, _) = get(&server, "/api/videos/count").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = shorten("limit=0").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = get(&server, "/s/unknown").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn failed_logins() {
+    let server = Server::builder()
+        .metadata(metadata())
+        .user(User::new("admin".to_owned(), hash_password("pw").unwrap(), None).unwrap())
+        .youtube(YouTube::fixture())
+        .build()
+        .await
+        .unwrap();
+    let login = async |body: &'static str| {
+        let req = Request::post("/login")
+            .body(Bytes::from_static(body.as_bytes()))
+            .unwrap();
+        let res = server.handler().run(req).await.unwrap();
+
+        (res.status(), res.headers().contains_key("set-cookie"))
+    };
+
+    for body in [
+        "name=admin&password=wrong",
+        "name=nobody&password=pw",
+        "name=admin",
+    ] {
+        assert_eq!(
+            login(body).await,
+            (StatusCode::UNAUTHORIZED, false),
+            "{body}"
+        );
+    }
+    assert_eq!(
+        login("name=admin&password=pw").await,
+        (StatusCode::SEE_OTHER, true)
+    );
+
+    // hashing runs on the blocking pool, other requests go on meanwhile
+    let attempts = (0..4).map(|_| login("name=nobody&password=pw"));
+    let (results, (status, _)) =
+        tokio::join!(futures::future::join_all(attempts), get(&server, "/login"));
+    assert!(results.iter().all(|v| v.0 == StatusCode::UNAUTHORIZED));
+    assert_eq!(status, StatusCode::OK);
+}